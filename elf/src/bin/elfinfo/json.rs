@@ -0,0 +1,192 @@
+//! Output as a JSON document, for scripts. Values are named the same way as
+//! in the text output, and numbers are written in decimal.
+
+use std::fmt;
+
+use crate::{names, report::Report};
+
+/// A JSON value. Objects keep their keys in the order they were added.
+enum Json {
+    Null,
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl From<u64> for Json {
+    fn from(value: u64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+impl Json {
+    fn write(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Number(value) => write!(f, "{}", value),
+            Self::String(value) => write_string(f, value),
+            Self::Array(values) if values.is_empty() => write!(f, "[]"),
+            Self::Array(values) => {
+                writeln!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    write!(f, "{:1$}", "", indent + 2)?;
+                    value.write(f, indent + 2)?;
+                    writeln!(f, "{}", if index + 1 < values.len() { "," } else { "" })?;
+                }
+                write!(f, "{:1$}]", "", indent)
+            }
+            Self::Object(fields) if fields.is_empty() => write!(f, "{{}}"),
+            Self::Object(fields) => {
+                writeln!(f, "{{")?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    write!(f, "{:1$}", "", indent + 2)?;
+                    write_string(f, key)?;
+                    write!(f, ": ")?;
+                    value.write(f, indent + 2)?;
+                    writeln!(f, "{}", if index + 1 < fields.len() { "," } else { "" })?;
+                }
+                write!(f, "{:1$}}}", "", indent)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for character in value.chars() {
+        match character {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            character if character.is_control() => write!(f, "\\u{:04x}", character as u32)?,
+            character => write!(f, "{}", character)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+pub fn render(report: &Report) -> String {
+    let header = Json::Object(vec![
+        ("class", names::class(&report.class).into()),
+        ("endianness", names::endianness(report.endianness).into()),
+        ("type", names::elf_type(report.elf_type).into()),
+        ("machine", names::machine(report.instruction_set).into()),
+        ("entry_point", report.entry_point.into()),
+    ]);
+
+    let program_headers = report
+        .program_headers
+        .iter()
+        .map(|header| {
+            Json::Object(vec![
+                ("type", names::segment_type(header.segment_type()).into()),
+                ("offset", header.p_offset().into()),
+                ("virtual_address", header.p_vaddr().into()),
+                ("file_size", header.p_filesz().into()),
+                ("memory_size", header.p_memsz().into()),
+                (
+                    "flags",
+                    names::segment_flags(header.flags()).trim_end().into(),
+                ),
+                ("alignment", header.alignment().into()),
+            ])
+        })
+        .collect();
+
+    let sections = report
+        .sections
+        .iter()
+        .enumerate()
+        .map(|(index, section)| {
+            let header = &section.header;
+            Json::Object(vec![
+                ("index", (index as u64).into()),
+                ("name", section.name.into()),
+                ("type", names::section_type(header.header_type()).into()),
+                ("address", header.address().into()),
+                ("offset", header.section_file_offset().into()),
+                ("size", header.section_size().into()),
+                ("entry_size", header.section_entry_size().into()),
+                ("flags", names::section_flags(header.flags()).into()),
+                ("link", header.section_link_index().map(u64::from).into()),
+                ("info", header.info().map(u64::from).into()),
+                ("alignment", header.address_alignment().into()),
+            ])
+        })
+        .collect();
+
+    let symbol_tables = report.symbol_tables.as_ref().map(|tables| {
+        let tables = tables.iter().map(|table| {
+            let symbols = table.symbols.iter().enumerate().map(|(index, symbol)| {
+                Json::Object(vec![
+                    ("index", (index as u64).into()),
+                    ("name", symbol.name().into()),
+                    ("value", symbol.value().into()),
+                    ("size", symbol.size().into()),
+                    ("type", names::symbol_type(symbol.symbol_type()).into()),
+                    ("binding", names::symbol_binding(symbol.binding()).into()),
+                    (
+                        "visibility",
+                        names::symbol_visibility(symbol.visibility()).into(),
+                    ),
+                    (
+                        "section",
+                        names::symbol_section_index(symbol.section_index()).into(),
+                    ),
+                ])
+            });
+            Json::Object(vec![
+                ("section", table.name.into()),
+                ("symbols", Json::Array(symbols.collect())),
+            ])
+        });
+        Json::Array(tables.collect())
+    });
+
+    let dynamic_entries = report.dynamic_entries.as_ref().map(|entries| {
+        let entries = entries.iter().map(|entry| {
+            Json::Object(vec![
+                ("tag", names::dynamic_tag(entry.tag()).into()),
+                ("value", entry.value().into()),
+                ("name", entry.name().into()),
+            ])
+        });
+        Json::Array(entries.collect())
+    });
+
+    let report = Json::Object(vec![
+        ("header", header),
+        ("program_headers", Json::Array(program_headers)),
+        ("sections", Json::Array(sections)),
+        ("symbol_tables", symbol_tables.unwrap_or(Json::Null)),
+        ("dynamic_entries", dynamic_entries.unwrap_or(Json::Null)),
+    ]);
+    format!("{}\n", report)
+}
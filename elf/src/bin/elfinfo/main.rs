@@ -0,0 +1,52 @@
+//! Prints the headers, program headers, sections, symbols and dynamic entries
+//! of an ELF file, in a layout like readelf's or as JSON.
+//!
+//! Usage: `elfinfo [--json] <file>`
+
+use std::{env, fs, process};
+
+use elf::file::ElfFile;
+
+use report::Report;
+
+mod json;
+mod names;
+mod report;
+mod text;
+
+const USAGE: &str = "Usage: elfinfo [--json] <file>";
+
+fn main() {
+    let mut json = false;
+    let mut path = None;
+    for argument in env::args().skip(1) {
+        match argument.as_str() {
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if path.is_none() && !argument.starts_with('-') => path = Some(argument),
+            _ => fail(2, USAGE),
+        }
+    }
+    let path = path.unwrap_or_else(|| fail(2, USAGE));
+
+    let file_bytes =
+        fs::read(&path).unwrap_or_else(|error| fail(1, &format!("elfinfo: {}: {}", path, error)));
+    let file = ElfFile::from_bytes(&file_bytes)
+        .unwrap_or_else(|error| fail(1, &format!("elfinfo: {}: {:?}", path, error)));
+    let report = Report::new(&file)
+        .unwrap_or_else(|error| fail(1, &format!("elfinfo: {}: {:?}", path, error)));
+
+    if json {
+        print!("{}", json::render(&report));
+    } else {
+        print!("{}", text::render(&report));
+    }
+}
+
+fn fail(code: i32, message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(code)
+}
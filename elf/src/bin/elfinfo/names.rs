@@ -0,0 +1,245 @@
+//! The names readelf uses for the values of header fields.
+
+use elf::{
+    elf64::{
+        dynamic::DynamicTag,
+        sections::{SectionHeaderFlags, SectionHeaderType},
+        symbols::{SymbolBinding, SymbolSectionIndex, SymbolType, SymbolVisibility},
+    },
+    ElfFileClass, ElfType, Endian, InstructionSet, ProgramHeaderFlags, ProgramSegmentType,
+};
+
+pub fn class(class: &ElfFileClass) -> &'static str {
+    match class {
+        ElfFileClass::Elf32 => "ELF32",
+        ElfFileClass::Elf64 => "ELF64",
+    }
+}
+
+pub fn endianness(endianness: Endian) -> &'static str {
+    match endianness {
+        Endian::Little => "little endian",
+        Endian::Big => "big endian",
+    }
+}
+
+pub fn elf_type(elf_type: &ElfType) -> &'static str {
+    match elf_type {
+        ElfType::Relocatable => "REL",
+        ElfType::Executable => "EXEC",
+        ElfType::Shared => "DYN",
+        ElfType::Core => "CORE",
+    }
+}
+
+pub fn elf_type_description(elf_type: &ElfType) -> &'static str {
+    match elf_type {
+        ElfType::Relocatable => "Relocatable file",
+        ElfType::Executable => "Executable file",
+        ElfType::Shared => "Shared object file",
+        ElfType::Core => "Core file",
+    }
+}
+
+pub fn machine(instruction_set: &InstructionSet) -> &'static str {
+    match instruction_set {
+        InstructionSet::NoSpecific => "None",
+        InstructionSet::Sparc => "Sparc",
+        InstructionSet::X86 => "Intel 80386",
+        InstructionSet::MIPS => "MIPS R3000",
+        InstructionSet::PowerPC => "PowerPC",
+        InstructionSet::ARM => "ARM",
+        InstructionSet::SuperH => "Renesas / SuperH SH",
+        InstructionSet::Ia64 => "Intel IA-64",
+        InstructionSet::X86_64 => "Advanced Micro Devices X86-64",
+        InstructionSet::AArch64 => "AArch64",
+        InstructionSet::RiscV => "RISC-V",
+    }
+}
+
+pub fn segment_type(segment_type: &ProgramSegmentType) -> String {
+    let name = match segment_type {
+        ProgramSegmentType::Null => "NULL",
+        ProgramSegmentType::Load => "LOAD",
+        ProgramSegmentType::Dynamic => "DYNAMIC",
+        ProgramSegmentType::Interpret => "INTERP",
+        ProgramSegmentType::Note => "NOTE",
+        ProgramSegmentType::SharedLibrary => "SHLIB",
+        ProgramSegmentType::ProgramHeader => "PHDR",
+        ProgramSegmentType::Tls => "TLS",
+        ProgramSegmentType::GnuEhFrame => "GNU_EH_FRAME",
+        ProgramSegmentType::GnuStack => "GNU_STACK",
+        ProgramSegmentType::GnuRelro => "GNU_RELRO",
+        ProgramSegmentType::GnuProperty => "GNU_PROPERTY",
+        ProgramSegmentType::OperatingSystemSpecific(value)
+        | ProgramSegmentType::ProcessorSpecific(value)
+        | ProgramSegmentType::Unknown(value) => return format!("{:#x}", value),
+    };
+    name.to_string()
+}
+
+/// Permissions of a segment, as `RWE` with a space for each one it lacks.
+pub fn segment_flags(flags: &ProgramHeaderFlags) -> String {
+    let flag = |set, letter| if set { letter } else { ' ' };
+    [
+        flag(flags.readable(), 'R'),
+        flag(flags.writable(), 'W'),
+        flag(flags.executable(), 'E'),
+    ]
+    .iter()
+    .collect()
+}
+
+pub fn section_type(header_type: &SectionHeaderType) -> String {
+    let name = match header_type {
+        SectionHeaderType::Null => "NULL",
+        SectionHeaderType::ProgramBits => "PROGBITS",
+        SectionHeaderType::SymbolTable => "SYMTAB",
+        SectionHeaderType::StringTable => "STRTAB",
+        SectionHeaderType::RelA => "RELA",
+        SectionHeaderType::Hash => "HASH",
+        SectionHeaderType::Dynamic => "DYNAMIC",
+        SectionHeaderType::Note => "NOTE",
+        SectionHeaderType::NoBits => "NOBITS",
+        SectionHeaderType::Rel => "REL",
+        SectionHeaderType::ShLib => "SHLIB",
+        SectionHeaderType::DynamicSymbols => "DYNSYM",
+        SectionHeaderType::InitArray => "INIT_ARRAY",
+        SectionHeaderType::FinishArray => "FINI_ARRAY",
+        SectionHeaderType::PreinitArray => "PREINIT_ARRAY",
+        SectionHeaderType::Group => "GROUP",
+        SectionHeaderType::SymbolTableSectionHeaderIndex => "SYMTAB SECTION INDICES",
+        SectionHeaderType::GnuHash => "GNU_HASH",
+        SectionHeaderType::OperatingSystemSpecific(0x6FFFFFFD) => "VERDEF",
+        SectionHeaderType::OperatingSystemSpecific(0x6FFFFFFE) => "VERNEED",
+        SectionHeaderType::OperatingSystemSpecific(0x6FFFFFFF) => "VERSYM",
+        SectionHeaderType::OperatingSystemSpecific(value)
+        | SectionHeaderType::ProcessorSpecific(value)
+        | SectionHeaderType::UserApplicationSpecific(value) => return format!("{:#x}", value),
+    };
+    name.to_string()
+}
+
+/// Section flags as readelf's key letters, e.g. `WA` for a writable section
+/// that is allocated at run time.
+pub fn section_flags(flags: SectionHeaderFlags) -> String {
+    let letters = [
+        (SectionHeaderFlags::WRITE, 'W'),
+        (SectionHeaderFlags::ALLOC, 'A'),
+        (SectionHeaderFlags::EXECUTABLE_INSTRUCTIONS, 'X'),
+        (SectionHeaderFlags::MERGE, 'M'),
+        (SectionHeaderFlags::STRINGS, 'S'),
+        (SectionHeaderFlags::INFO_LINK, 'I'),
+        (SectionHeaderFlags::LINK_ORDER, 'L'),
+        (SectionHeaderFlags::OS_NONCONFORMING, 'O'),
+        (SectionHeaderFlags::GROUP, 'G'),
+        (SectionHeaderFlags::TLS, 'T'),
+        (SectionHeaderFlags::COMPRESSED, 'C'),
+    ];
+    letters
+        .iter()
+        .filter(|(flag, _)| flags.contains(*flag))
+        .map(|(_, letter)| letter)
+        .collect()
+}
+
+pub fn symbol_type(symbol_type: SymbolType) -> String {
+    let name = match symbol_type {
+        SymbolType::NoType => "NOTYPE",
+        SymbolType::Object => "OBJECT",
+        SymbolType::Function => "FUNC",
+        SymbolType::Section => "SECTION",
+        SymbolType::File => "FILE",
+        SymbolType::Common => "COMMON",
+        SymbolType::ThreadLocalStorage => "TLS",
+        SymbolType::OperatingSystemSpecific(10) => "IFUNC",
+        SymbolType::OperatingSystemSpecific(value) | SymbolType::ProcessorSpecific(value) => {
+            return value.to_string()
+        }
+    };
+    name.to_string()
+}
+
+pub fn symbol_binding(binding: SymbolBinding) -> String {
+    let name = match binding {
+        SymbolBinding::Local => "LOCAL",
+        SymbolBinding::Global => "GLOBAL",
+        SymbolBinding::Weak => "WEAK",
+        SymbolBinding::OperatingSystemSpecific(10) => "UNIQUE",
+        SymbolBinding::OperatingSystemSpecific(value) | SymbolBinding::ProcessorSpecific(value) => {
+            return value.to_string()
+        }
+    };
+    name.to_string()
+}
+
+pub fn symbol_visibility(visibility: SymbolVisibility) -> &'static str {
+    match visibility {
+        SymbolVisibility::Default => "DEFAULT",
+        SymbolVisibility::Internal => "INTERNAL",
+        SymbolVisibility::Hidden => "HIDDEN",
+        SymbolVisibility::Protected => "PROTECTED",
+    }
+}
+
+pub fn symbol_section_index(section_index: SymbolSectionIndex) -> String {
+    match section_index {
+        SymbolSectionIndex::Undefined => "UND".to_string(),
+        SymbolSectionIndex::Absolute => "ABS".to_string(),
+        SymbolSectionIndex::Common => "COM".to_string(),
+        SymbolSectionIndex::Index(index) => index.to_string(),
+        SymbolSectionIndex::Reserved(value) => format!("{:#x}", value),
+    }
+}
+
+pub fn dynamic_tag(tag: DynamicTag) -> String {
+    let name = match tag {
+        DynamicTag::Null => "NULL",
+        DynamicTag::Needed => "NEEDED",
+        DynamicTag::PltRelocationsSize => "PLTRELSZ",
+        DynamicTag::PltGot => "PLTGOT",
+        DynamicTag::Hash => "HASH",
+        DynamicTag::StringTable => "STRTAB",
+        DynamicTag::SymbolTable => "SYMTAB",
+        DynamicTag::Rela => "RELA",
+        DynamicTag::RelaSize => "RELASZ",
+        DynamicTag::RelaEntrySize => "RELAENT",
+        DynamicTag::StringTableSize => "STRSZ",
+        DynamicTag::SymbolEntrySize => "SYMENT",
+        DynamicTag::Init => "INIT",
+        DynamicTag::Fini => "FINI",
+        DynamicTag::SharedObjectName => "SONAME",
+        DynamicTag::RPath => "RPATH",
+        DynamicTag::Symbolic => "SYMBOLIC",
+        DynamicTag::Rel => "REL",
+        DynamicTag::RelSize => "RELSZ",
+        DynamicTag::RelEntrySize => "RELENT",
+        DynamicTag::PltRelocationType => "PLTREL",
+        DynamicTag::Debug => "DEBUG",
+        DynamicTag::TextRelocations => "TEXTREL",
+        DynamicTag::JumpRelocations => "JMPREL",
+        DynamicTag::BindNow => "BIND_NOW",
+        DynamicTag::InitArray => "INIT_ARRAY",
+        DynamicTag::FiniArray => "FINI_ARRAY",
+        DynamicTag::InitArraySize => "INIT_ARRAYSZ",
+        DynamicTag::FiniArraySize => "FINI_ARRAYSZ",
+        DynamicTag::RunPath => "RUNPATH",
+        DynamicTag::Flags => "FLAGS",
+        DynamicTag::PreInitArray => "PREINIT_ARRAY",
+        DynamicTag::PreInitArraySize => "PREINIT_ARRAYSZ",
+        DynamicTag::SymbolTableSectionHeaderIndex => "SYMTAB_SHNDX",
+        DynamicTag::GnuHash => "GNU_HASH",
+        DynamicTag::VersionSymbols => "VERSYM",
+        DynamicTag::RelaCount => "RELACOUNT",
+        DynamicTag::RelCount => "RELCOUNT",
+        DynamicTag::Flags1 => "FLAGS_1",
+        DynamicTag::VersionDefinitions => "VERDEF",
+        DynamicTag::VersionDefinitionCount => "VERDEFNUM",
+        DynamicTag::VersionNeeded => "VERNEED",
+        DynamicTag::VersionNeededCount => "VERNEEDNUM",
+        DynamicTag::OperatingSystemSpecific(value)
+        | DynamicTag::ProcessorSpecific(value)
+        | DynamicTag::Unknown(value) => return format!("{:#x}", value),
+    };
+    name.to_string()
+}
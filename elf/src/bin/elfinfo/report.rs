@@ -0,0 +1,112 @@
+use elf::{
+    elf64::{
+        dynamic::DynamicEntry,
+        sections::{SectionHeader, SectionHeaderType},
+        symbols::{Elf64Symbol, SymbolIterator},
+        Elf64ProgramHeader,
+    },
+    file::ElfFile,
+    ElfFileClass, ElfParseError, ElfType, Endian, InstructionSet,
+};
+
+/// Everything `elfinfo` prints about a file, read up front so that the text
+/// and JSON output show the same data.
+pub struct Report<'a> {
+    pub class: ElfFileClass,
+    pub endianness: Endian,
+    pub elf_type: &'a ElfType,
+    pub instruction_set: &'a InstructionSet,
+    pub entry_point: u64,
+    pub program_headers: Vec<Elf64ProgramHeader>,
+    pub sections: Vec<Section<'a>>,
+    /// `None` for 32-bit files, whose symbols and dynamic entries the parser
+    /// doesn't read.
+    pub symbol_tables: Option<Vec<SymbolTable<'a>>>,
+    pub dynamic_entries: Option<Vec<DynamicEntry<'a>>>,
+}
+
+pub struct Section<'a> {
+    pub name: &'a str,
+    pub header: SectionHeader,
+}
+
+pub struct SymbolTable<'a> {
+    /// Name of the symbol table's section, e.g. ".dynsym".
+    pub name: &'a str,
+    pub symbols: Vec<Elf64Symbol<'a>>,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(file: &'a ElfFile<'a>) -> Result<Self, ElfParseError> {
+        let program_headers = match file.program_headers() {
+            Some(headers) => headers.collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+
+        let headers = file.section_headers()?.collect::<Result<Vec<_>, _>>()?;
+        let mut sections = Vec::with_capacity(headers.len());
+        if !headers.is_empty() {
+            let names = file.section_names()?;
+            for header in headers {
+                sections.push(Section {
+                    name: header.name(&names)?,
+                    header,
+                });
+            }
+        }
+
+        let (symbol_tables, dynamic_entries) = match file {
+            ElfFile::Elf32(_) => (None, None),
+            ElfFile::Elf64(file) => {
+                let mut symbol_tables = Vec::new();
+                if let Some(symbols) = file.dynamic_symbols()? {
+                    let header_type = SectionHeaderType::DynamicSymbols;
+                    symbol_tables.push(SymbolTable::new(&sections, header_type, symbols)?);
+                }
+                if let Some(symbols) = file.symbols()? {
+                    let header_type = SectionHeaderType::SymbolTable;
+                    symbol_tables.push(SymbolTable::new(&sections, header_type, symbols)?);
+                }
+
+                let dynamic_entries = match file.dynamic_entries()? {
+                    Some(entries) => entries.collect::<Result<_, _>>()?,
+                    None => Vec::new(),
+                };
+
+                (Some(symbol_tables), Some(dynamic_entries))
+            }
+        };
+
+        Ok(Self {
+            class: file.class(),
+            endianness: file.endianness(),
+            elf_type: file.elf_type(),
+            instruction_set: file.instruction_set(),
+            entry_point: file.entry_point(),
+            program_headers,
+            sections,
+            symbol_tables,
+            dynamic_entries,
+        })
+    }
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Read the symbols of the first section of `header_type`, which is the
+    /// table the parser returns `symbols` of.
+    fn new(
+        sections: &[Section<'a>],
+        header_type: SectionHeaderType,
+        symbols: SymbolIterator<'a>,
+    ) -> Result<Self, ElfParseError> {
+        let name = sections
+            .iter()
+            .find(|section| *section.header.header_type() == header_type)
+            .map_or("", |section| section.name);
+
+        Ok(Self {
+            name,
+            symbols: symbols.collect::<Result<_, _>>()?,
+        })
+    }
+}
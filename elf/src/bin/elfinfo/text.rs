@@ -0,0 +1,230 @@
+//! Output in the layout of `readelf -h -l -S -s -d --wide`.
+
+use std::fmt::{self, Write};
+
+use elf::elf64::dynamic::{DynamicEntry, DynamicFlags, DynamicTag};
+
+use crate::{names, report::Report};
+
+pub fn render(report: &Report) -> String {
+    let mut output = String::new();
+    write_report(&mut output, report).expect("Writing to a String can't fail");
+
+    // Columns are padded to line up, which leaves trailing spaces after empty
+    // names
+    let mut text = String::with_capacity(output.len());
+    for line in output.lines() {
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+fn write_report(out: &mut String, report: &Report) -> fmt::Result {
+    writeln!(out, "ELF Header:")?;
+    writeln!(
+        out,
+        "  Class:                             {}",
+        names::class(&report.class)
+    )?;
+    writeln!(
+        out,
+        "  Data:                              2's complement, {}",
+        names::endianness(report.endianness)
+    )?;
+    writeln!(
+        out,
+        "  Type:                              {} ({})",
+        names::elf_type(report.elf_type),
+        names::elf_type_description(report.elf_type)
+    )?;
+    writeln!(
+        out,
+        "  Machine:                           {}",
+        names::machine(report.instruction_set)
+    )?;
+    writeln!(
+        out,
+        "  Entry point address:               {:#x}",
+        report.entry_point
+    )?;
+    writeln!(
+        out,
+        "  Number of program headers:         {}",
+        report.program_headers.len()
+    )?;
+    writeln!(
+        out,
+        "  Number of section headers:         {}",
+        report.sections.len()
+    )?;
+
+    writeln!(out)?;
+    if report.program_headers.is_empty() {
+        writeln!(out, "There are no program headers in this file.")?;
+    } else {
+        writeln!(out, "Program Headers:")?;
+        writeln!(
+            out,
+            "  Type           Offset   VirtAddr           FileSiz  MemSiz   Flg Align"
+        )?;
+        for header in &report.program_headers {
+            writeln!(
+                out,
+                "  {:<14} {:#08x} {:#018x} {:#08x} {:#08x} {} {:#x}",
+                names::segment_type(header.segment_type()),
+                header.p_offset(),
+                header.p_vaddr(),
+                header.p_filesz(),
+                header.p_memsz(),
+                names::segment_flags(header.flags()),
+                header.alignment()
+            )?;
+        }
+    }
+
+    writeln!(out)?;
+    if report.sections.is_empty() {
+        writeln!(out, "There are no sections in this file.")?;
+    } else {
+        writeln!(out, "Section Headers:")?;
+        writeln!(
+            out,
+            "  [Nr] Name              Type            Address          Off    Size   ES Flg Lk Inf Al"
+        )?;
+        for (index, section) in report.sections.iter().enumerate() {
+            let header = &section.header;
+            writeln!(
+                out,
+                "  [{:>2}] {:<17} {:<15} {:016x} {:06x} {:06x} {:02x} {:>3} {:>2} {:>3} {:>2}",
+                index,
+                section.name,
+                names::section_type(header.header_type()),
+                header.address().unwrap_or(0),
+                header.section_file_offset(),
+                header.section_size(),
+                header.section_entry_size().unwrap_or(0),
+                names::section_flags(header.flags()),
+                header.section_link_index().unwrap_or(0),
+                header.info().unwrap_or(0),
+                header.address_alignment()
+            )?;
+        }
+    }
+
+    let (symbol_tables, dynamic_entries) = match (&report.symbol_tables, &report.dynamic_entries) {
+        (Some(symbol_tables), Some(dynamic_entries)) => (symbol_tables, dynamic_entries),
+        _ => {
+            writeln!(out)?;
+            return writeln!(
+                out,
+                "Symbols and dynamic entries of 32-bit files aren't supported."
+            );
+        }
+    };
+
+    for table in symbol_tables {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "Symbol table '{}' contains {} entries:",
+            table.name,
+            table.symbols.len()
+        )?;
+        writeln!(
+            out,
+            "   Num:    Value          Size Type    Bind   Vis      Ndx Name"
+        )?;
+        for (index, symbol) in table.symbols.iter().enumerate() {
+            writeln!(
+                out,
+                "{:>6}: {:016x} {:>5} {:<7} {:<6} {:<8} {:>3} {}",
+                index,
+                symbol.value(),
+                symbol.size(),
+                names::symbol_type(symbol.symbol_type()),
+                names::symbol_binding(symbol.binding()),
+                names::symbol_visibility(symbol.visibility()),
+                names::symbol_section_index(symbol.section_index()),
+                symbol.name()
+            )?;
+        }
+    }
+
+    writeln!(out)?;
+    if dynamic_entries.is_empty() {
+        writeln!(out, "There is no dynamic section in this file.")?;
+    } else {
+        writeln!(
+            out,
+            "Dynamic section contains {} entries:",
+            dynamic_entries.len()
+        )?;
+        writeln!(out, "  Tag        Type                         Name/Value")?;
+        for entry in dynamic_entries {
+            writeln!(
+                out,
+                " {:#018x} {:<20} {}",
+                u64::from(entry.tag()),
+                format!("({})", names::dynamic_tag(entry.tag())),
+                DynamicValue(entry)
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The value of a dynamic entry, described the way its tag interprets it.
+struct DynamicValue<'a, 'b>(&'b DynamicEntry<'a>);
+
+impl fmt::Display for DynamicValue<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let entry = self.0;
+        let name = entry.name().unwrap_or("");
+        match entry.tag() {
+            DynamicTag::Needed => write!(f, "Shared library: [{}]", name),
+            DynamicTag::SharedObjectName => write!(f, "Library soname: [{}]", name),
+            DynamicTag::RPath => write!(f, "Library rpath: [{}]", name),
+            DynamicTag::RunPath => write!(f, "Library runpath: [{}]", name),
+            DynamicTag::PltRelocationType => match entry.value() {
+                7 => write!(f, "RELA"),
+                17 => write!(f, "REL"),
+                value => write!(f, "{:#x}", value),
+            },
+            DynamicTag::PltRelocationsSize
+            | DynamicTag::RelaSize
+            | DynamicTag::RelaEntrySize
+            | DynamicTag::StringTableSize
+            | DynamicTag::SymbolEntrySize
+            | DynamicTag::RelSize
+            | DynamicTag::RelEntrySize
+            | DynamicTag::InitArraySize
+            | DynamicTag::FiniArraySize
+            | DynamicTag::PreInitArraySize => write!(f, "{} (bytes)", entry.value()),
+            DynamicTag::RelaCount
+            | DynamicTag::RelCount
+            | DynamicTag::VersionDefinitionCount
+            | DynamicTag::VersionNeededCount => write!(f, "{}", entry.value()),
+            DynamicTag::Flags => {
+                let flags = entry.flags().unwrap_or_else(DynamicFlags::empty);
+                let names = [
+                    (DynamicFlags::ORIGIN, "ORIGIN"),
+                    (DynamicFlags::SYMBOLIC, "SYMBOLIC"),
+                    (DynamicFlags::TEXTREL, "TEXTREL"),
+                    (DynamicFlags::BIND_NOW, "BIND_NOW"),
+                    (DynamicFlags::STATIC_TLS, "STATIC_TLS"),
+                ];
+                let mut separator = "";
+                for (flag, name) in names.iter() {
+                    if flags.contains(*flag) {
+                        write!(f, "{}{}", separator, name)?;
+                        separator = " ";
+                    }
+                }
+                Ok(())
+            }
+            _ => write!(f, "{:#x}", entry.value()),
+        }
+    }
+}
@@ -0,0 +1,843 @@
+use alloc::{borrow::Cow, vec, vec::Vec};
+use core::ops::Range;
+
+use super::{
+    elf64::{
+        sections::{SectionHeaderFlags, SectionHeaderType},
+        symbols::{Elf64Symbol, SymbolBinding, SYMBOL_ENTRY_SIZE},
+    },
+    ElfType, InstructionSet, ProgramHeaderFlags, ProgramSegmentType,
+};
+
+const HEADER_SIZE: u64 = 64;
+const PROGRAM_HEADER_ENTRY_SIZE: u64 = 56;
+const SECTION_HEADER_ENTRY_SIZE: u64 = 64;
+/// Section indexes from `SHN_LORESERVE` (0xFF00) up are reserved, and the
+/// builder doesn't write the extensions needed to go past it.
+const MAX_SECTIONS: usize = 0xFF00;
+
+/// What a segment added to an `ElfBuilder` holds.
+#[derive(Debug, Clone)]
+pub enum SegmentContents<'a> {
+    /// The segment spans these sections, by the indexes `add_section`
+    /// returned for them. The sections must have been added in address order,
+    /// and the segment starts at the address of the first one.
+    Sections(Range<u16>),
+    /// The segment holds its own data, followed by zeroes up to
+    /// `memory_size`.
+    Data {
+        address: u64,
+        data: Cow<'a, [u8]>,
+        memory_size: u64,
+    },
+    /// The segment describes no memory, e.g. a `GnuStack` segment that only
+    /// carries flags.
+    Empty,
+}
+
+#[derive(Debug)]
+struct BuilderSection<'a> {
+    name: &'a str,
+    header_type: SectionHeaderType,
+    flags: SectionHeaderFlags,
+    address: u64,
+    alignment: u64,
+    data: Cow<'a, [u8]>,
+    /// Size of a `NoBits` section, which has no data.
+    size: u64,
+    link: u32,
+    info: u32,
+    entry_size: u64,
+}
+
+#[derive(Debug)]
+struct BuilderSegment<'a> {
+    segment_type: ProgramSegmentType,
+    flags: ProgramHeaderFlags,
+    alignment: u64,
+    contents: SegmentContents<'a>,
+}
+
+/// Writes little endian ELF64 files.
+///
+/// Sections and segments are added in the order they appear in their header
+/// tables. `build` lays them out after the ELF header and the program header
+/// table, with every segment's file offset congruent to its address modulo
+/// its alignment, so that the result can be mapped page by page. A `.symtab`
+/// and `.strtab` are generated for the added symbols, and a `.shstrtab` for
+/// the section names, after the added sections.
+#[derive(Debug)]
+pub struct ElfBuilder<'a> {
+    elf_type: ElfType,
+    instruction_set: InstructionSet,
+    entry_point: u64,
+    sections: Vec<BuilderSection<'a>>,
+    segments: Vec<BuilderSegment<'a>>,
+    symbols: Vec<Elf64Symbol<'a>>,
+}
+
+impl<'a> ElfBuilder<'a> {
+    pub fn new(elf_type: ElfType, instruction_set: InstructionSet) -> Self {
+        Self {
+            elf_type,
+            instruction_set,
+            entry_point: 0,
+            sections: Vec::new(),
+            segments: Vec::new(),
+            symbols: Vec::new(),
+        }
+    }
+
+    pub fn set_entry_point(&mut self, entry_point: u64) {
+        self.entry_point = entry_point;
+    }
+
+    /// Add a section holding `data`, and return its index in the section
+    /// header table. Use `add_nobits_section` for sections without data.
+    ///
+    /// Panics if the section header table is full.
+    pub fn add_section(
+        &mut self,
+        name: &'a str,
+        header_type: SectionHeaderType,
+        flags: SectionHeaderFlags,
+        address: u64,
+        alignment: u64,
+        data: impl Into<Cow<'a, [u8]>>,
+    ) -> u16 {
+        let data = data.into();
+        self.push_section(BuilderSection {
+            name,
+            header_type,
+            flags,
+            address,
+            alignment,
+            size: data.len() as u64,
+            data,
+            link: 0,
+            info: 0,
+            entry_size: 0,
+        })
+    }
+
+    /// Add a `NoBits` section of `size` bytes, e.g. `.bss`, and return its
+    /// index in the section header table.
+    ///
+    /// Panics if the section header table is full.
+    pub fn add_nobits_section(
+        &mut self,
+        name: &'a str,
+        flags: SectionHeaderFlags,
+        address: u64,
+        alignment: u64,
+        size: u64,
+    ) -> u16 {
+        self.push_section(BuilderSection {
+            name,
+            header_type: SectionHeaderType::NoBits,
+            flags,
+            address,
+            alignment,
+            data: Cow::Borrowed(&[]),
+            size,
+            link: 0,
+            info: 0,
+            entry_size: 0,
+        })
+    }
+
+    /// Add a `Note` section holding a single note, and return its index in the
+    /// section header table.
+    ///
+    /// Panics if the section header table is full.
+    pub fn add_note(
+        &mut self,
+        name: &'a str,
+        owner: &str,
+        note_type: u32,
+        descriptor: &[u8],
+    ) -> u16 {
+        self.add_section(
+            name,
+            SectionHeaderType::Note,
+            SectionHeaderFlags::empty(),
+            0,
+            4,
+            note_bytes(owner, note_type, descriptor),
+        )
+    }
+
+    fn push_section(&mut self, section: BuilderSection<'a>) -> u16 {
+        // Index 0 is the null section, and the generated sections come last
+        assert!(
+            self.sections.len() + 4 < MAX_SECTIONS,
+            "Too many sections for the section header table"
+        );
+        self.sections.push(section);
+        self.sections.len() as u16
+    }
+
+    /// Add a segment, and return its index in the program header table.
+    ///
+    /// Panics if `alignment` isn't a power of 2.
+    pub fn add_segment(
+        &mut self,
+        segment_type: ProgramSegmentType,
+        flags: ProgramHeaderFlags,
+        alignment: u64,
+        contents: SegmentContents<'a>,
+    ) -> u16 {
+        assert!(
+            alignment.is_power_of_two(),
+            "Segment alignments must be powers of 2"
+        );
+        self.segments.push(BuilderSegment {
+            segment_type,
+            flags,
+            alignment,
+            contents,
+        });
+        (self.segments.len() - 1) as u16
+    }
+
+    /// Add a symbol to the `.symtab`. Local symbols are written before the
+    /// others, as the symbol table requires.
+    pub fn add_symbol(&mut self, symbol: Elf64Symbol<'a>) {
+        self.symbols.push(symbol);
+    }
+
+    /// Write the file.
+    pub fn build(&self) -> Vec<u8> {
+        let generated = self.generated_sections();
+        let sections: Vec<&BuilderSection> = self.sections.iter().chain(generated.iter()).collect();
+
+        let mut offset = HEADER_SIZE + PROGRAM_HEADER_ENTRY_SIZE * self.segments.len() as u64;
+        let mut section_offsets: Vec<u64> = Vec::new();
+        for (index, section) in sections.iter().enumerate() {
+            let section_offset = match self.first_section_of_segment(index as u16 + 1) {
+                // Later sections of a segment keep their distance from its
+                // first section
+                Some((first, _)) if first as usize != index + 1 => {
+                    let first_section = sections[first as usize - 1];
+                    section_offsets[first as usize - 1] + (section.address - first_section.address)
+                }
+                Some((_, alignment)) => {
+                    congruent_offset(offset, section.address, alignment.max(section.alignment))
+                }
+                None => align_up(offset, section.alignment),
+            };
+            section_offsets.push(section_offset);
+            offset = offset.max(section_offset + section.data.len() as u64);
+        }
+
+        let mut segment_offsets: Vec<u64> = Vec::new();
+        for segment in self.segments.iter() {
+            match &segment.contents {
+                SegmentContents::Data { address, data, .. } => {
+                    let segment_offset = congruent_offset(offset, *address, segment.alignment);
+                    segment_offsets.push(segment_offset);
+                    offset = segment_offset + data.len() as u64;
+                }
+                _ => segment_offsets.push(0),
+            }
+        }
+
+        let section_table_position = if sections.is_empty() {
+            0
+        } else {
+            align_up(offset, 8)
+        };
+        // The null section comes first
+        let section_count = if sections.is_empty() {
+            0
+        } else {
+            sections.len() + 1
+        };
+        let file_size = if sections.is_empty() {
+            offset
+        } else {
+            section_table_position + SECTION_HEADER_ENTRY_SIZE * section_count as u64
+        };
+        let mut file_bytes = vec![0; file_size as usize];
+
+        // e_ident: the magic number, 64-bit class, little endian, version 1
+        // and the System V ABI
+        put(&mut file_bytes, 0, &[0x7F, b'E', b'L', b'F', 2, 1, 1, 0]);
+        put_u16(&mut file_bytes, 16, u16::from(&self.elf_type));
+        put_u16(&mut file_bytes, 18, u16::from(&self.instruction_set));
+        put_u32(&mut file_bytes, 20, 1);
+        put_u64(&mut file_bytes, 24, self.entry_point);
+        if !self.segments.is_empty() {
+            put_u64(&mut file_bytes, 32, HEADER_SIZE);
+        }
+        put_u64(&mut file_bytes, 40, section_table_position);
+        put_u16(&mut file_bytes, 52, HEADER_SIZE as u16);
+        put_u16(&mut file_bytes, 54, PROGRAM_HEADER_ENTRY_SIZE as u16);
+        put_u16(&mut file_bytes, 56, self.segments.len() as u16);
+        put_u16(&mut file_bytes, 58, SECTION_HEADER_ENTRY_SIZE as u16);
+        put_u16(&mut file_bytes, 60, section_count as u16);
+        // .shstrtab is the last section
+        put_u16(&mut file_bytes, 62, sections.len() as u16);
+
+        for (index, segment) in self.segments.iter().enumerate() {
+            let (segment_offset, address, file_size, memory_size) = match &segment.contents {
+                SegmentContents::Sections(range) if !range.is_empty() => {
+                    let spanned = || {
+                        range.clone().map(|number| {
+                            let index = number as usize - 1;
+                            (sections[index], section_offsets[index])
+                        })
+                    };
+                    let (first, first_offset) = spanned().next().unwrap();
+                    let file_end = spanned()
+                        .filter(|(section, _)| section.header_type != SectionHeaderType::NoBits)
+                        .map(|(section, offset)| offset + section.data.len() as u64)
+                        .max()
+                        .unwrap_or(first_offset);
+                    let memory_end = spanned()
+                        .map(|(section, _)| section.address + section.size)
+                        .max()
+                        .unwrap();
+                    (
+                        first_offset,
+                        first.address,
+                        file_end - first_offset,
+                        memory_end - first.address,
+                    )
+                }
+                SegmentContents::Data {
+                    address,
+                    data,
+                    memory_size,
+                } => (
+                    segment_offsets[index],
+                    *address,
+                    data.len() as u64,
+                    (*memory_size).max(data.len() as u64),
+                ),
+                _ => (0, 0, 0, 0),
+            };
+
+            let entry = (HEADER_SIZE + PROGRAM_HEADER_ENTRY_SIZE * index as u64) as usize;
+            put_u32(&mut file_bytes, entry, u32::from(&segment.segment_type));
+            put_u32(&mut file_bytes, entry + 4, u32::from(&segment.flags));
+            put_u64(&mut file_bytes, entry + 8, segment_offset);
+            put_u64(&mut file_bytes, entry + 16, address);
+            // The physical address is the same as the virtual one
+            put_u64(&mut file_bytes, entry + 24, address);
+            put_u64(&mut file_bytes, entry + 32, file_size);
+            put_u64(&mut file_bytes, entry + 40, memory_size);
+            put_u64(&mut file_bytes, entry + 48, segment.alignment);
+
+            if let SegmentContents::Data { data, .. } = &segment.contents {
+                put(&mut file_bytes, segment_offset as usize, data);
+            }
+        }
+
+        // Names are in .shstrtab in section order, after its leading null byte
+        let mut name_offset = 1;
+        for (index, section) in sections.iter().enumerate() {
+            put(
+                &mut file_bytes,
+                section_offsets[index] as usize,
+                &section.data,
+            );
+
+            let entry =
+                (section_table_position + SECTION_HEADER_ENTRY_SIZE * (index as u64 + 1)) as usize;
+            put_u32(&mut file_bytes, entry, name_offset);
+            name_offset += section.name.len() as u32 + 1;
+            put_u32(&mut file_bytes, entry + 4, u32::from(&section.header_type));
+            put_u64(&mut file_bytes, entry + 8, section.flags.bits());
+            put_u64(&mut file_bytes, entry + 16, section.address);
+            put_u64(&mut file_bytes, entry + 24, section_offsets[index]);
+            put_u64(&mut file_bytes, entry + 32, section.size);
+            put_u32(&mut file_bytes, entry + 40, section.link);
+            put_u32(&mut file_bytes, entry + 44, section.info);
+            put_u64(&mut file_bytes, entry + 48, section.alignment);
+            put_u64(&mut file_bytes, entry + 56, section.entry_size);
+        }
+
+        file_bytes
+    }
+
+    /// The `.symtab`, `.strtab` and `.shstrtab` sections for the added
+    /// symbols and sections, in that order. `.shstrtab` holds the names of
+    /// every section in order, itself included.
+    fn generated_sections(&self) -> Vec<BuilderSection<'a>> {
+        let mut generated = Vec::new();
+        if self.sections.is_empty() && self.symbols.is_empty() {
+            return generated;
+        }
+
+        if !self.symbols.is_empty() {
+            let symbol_table_index = self.sections.len() as u32 + 1;
+            let locals = self
+                .symbols
+                .iter()
+                .filter(|symbol| symbol.binding() == SymbolBinding::Local);
+            let others = self
+                .symbols
+                .iter()
+                .filter(|symbol| symbol.binding() != SymbolBinding::Local);
+
+            let mut names = vec![0];
+            // The first entry is the undefined symbol
+            let mut entries = vec![0; SYMBOL_ENTRY_SIZE as usize];
+            for symbol in locals.clone().chain(others) {
+                let mut entry = [0; SYMBOL_ENTRY_SIZE as usize];
+                put_u32(&mut entry, 0, names.len() as u32);
+                entry[4] = (u8::from(symbol.binding()) << 4) | u8::from(symbol.symbol_type());
+                entry[5] = u8::from(symbol.visibility());
+                put_u16(&mut entry, 6, u16::from(symbol.section_index()));
+                put_u64(&mut entry, 8, symbol.value());
+                put_u64(&mut entry, 16, symbol.size());
+                entries.extend_from_slice(&entry);
+
+                names.extend_from_slice(symbol.name().as_bytes());
+                names.push(0);
+            }
+
+            generated.push(BuilderSection {
+                name: ".symtab",
+                header_type: SectionHeaderType::SymbolTable,
+                flags: SectionHeaderFlags::empty(),
+                address: 0,
+                alignment: 8,
+                size: entries.len() as u64,
+                data: Cow::Owned(entries),
+                link: symbol_table_index + 1,
+                // One greater than the index of the last local symbol
+                info: locals.count() as u32 + 1,
+                entry_size: SYMBOL_ENTRY_SIZE,
+            });
+            generated.push(BuilderSection {
+                name: ".strtab",
+                header_type: SectionHeaderType::StringTable,
+                flags: SectionHeaderFlags::empty(),
+                address: 0,
+                alignment: 1,
+                size: names.len() as u64,
+                data: Cow::Owned(names),
+                link: 0,
+                info: 0,
+                entry_size: 0,
+            });
+        }
+
+        let mut names = vec![0];
+        for section in self.sections.iter().chain(generated.iter()) {
+            names.extend_from_slice(section.name.as_bytes());
+            names.push(0);
+        }
+        names.extend_from_slice(b".shstrtab\0");
+        generated.push(BuilderSection {
+            name: ".shstrtab",
+            header_type: SectionHeaderType::StringTable,
+            flags: SectionHeaderFlags::empty(),
+            address: 0,
+            alignment: 1,
+            size: names.len() as u64,
+            data: Cow::Owned(names),
+            link: 0,
+            info: 0,
+            entry_size: 0,
+        });
+
+        generated
+    }
+
+    /// The first section and the alignment of the first segment that spans
+    /// the section at `index`, if any does.
+    fn first_section_of_segment(&self, index: u16) -> Option<(u16, u64)> {
+        self.segments
+            .iter()
+            .find_map(|segment| match &segment.contents {
+                SegmentContents::Sections(range) if range.contains(&index) => {
+                    Some((range.start, segment.alignment))
+                }
+                _ => None,
+            })
+    }
+}
+
+/// Encode a note, with its name and descriptor padded to 4 bytes. The name
+/// is written with its terminating null byte.
+pub fn note_bytes(owner: &str, note_type: u32, descriptor: &[u8]) -> Vec<u8> {
+    let name_size = owner.len() + 1;
+    let mut bytes = vec![0; 12];
+    put_u32(&mut bytes, 0, name_size as u32);
+    put_u32(&mut bytes, 4, descriptor.len() as u32);
+    put_u32(&mut bytes, 8, note_type);
+
+    bytes.extend_from_slice(owner.as_bytes());
+    bytes.resize(12 + align_up(name_size as u64, 4) as usize, 0);
+    bytes.extend_from_slice(descriptor);
+    bytes.resize(align_up(bytes.len() as u64, 4) as usize, 0);
+
+    bytes
+}
+
+/// The first offset from `offset` on that is congruent to `address` modulo
+/// `alignment`.
+fn congruent_offset(offset: u64, address: u64, alignment: u64) -> u64 {
+    let alignment = alignment.max(1);
+    let target = address % alignment;
+    let aligned = offset - offset % alignment + target;
+    if aligned >= offset {
+        aligned
+    } else {
+        aligned + alignment
+    }
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    let alignment = alignment.max(1);
+    value.div_ceil(alignment) * alignment
+}
+
+fn put(bytes: &mut [u8], offset: usize, value: &[u8]) {
+    bytes[offset..][..value.len()].copy_from_slice(value);
+}
+
+fn put_u16(bytes: &mut [u8], offset: usize, value: u16) {
+    put(bytes, offset, &value.to_le_bytes());
+}
+
+fn put_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    put(bytes, offset, &value.to_le_bytes());
+}
+
+fn put_u64(bytes: &mut [u8], offset: usize, value: u64) {
+    put(bytes, offset, &value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        elf64::{
+            symbols::{SymbolSectionIndex, SymbolType, SymbolVisibility},
+            Elf64File, Elf64ProgramHeader,
+        },
+        validate,
+    };
+
+    /// `mov eax, 60; xor edi, edi; syscall`, i.e. `exit(0)`.
+    const EXIT_CODE: [u8; 9] = [0xB8, 0x3C, 0, 0, 0, 0x31, 0xFF, 0x0F, 0x05];
+    const BUILD_ID: [u8; 4] = [0xDE, 0xAD, 0xBE, 0xEF];
+
+    fn symbol(
+        name: &str,
+        value: u64,
+        size: u64,
+        binding: SymbolBinding,
+        symbol_type: SymbolType,
+        section_index: u32,
+    ) -> Elf64Symbol<'_> {
+        Elf64Symbol::new(
+            name,
+            value,
+            size,
+            binding,
+            symbol_type,
+            SymbolVisibility::Default,
+            SymbolSectionIndex::Index(section_index),
+        )
+    }
+
+    fn executable() -> ElfBuilder<'static> {
+        let mut builder = ElfBuilder::new(ElfType::Executable, InstructionSet::X86_64);
+        builder.set_entry_point(0x401000);
+
+        let alloc = SectionHeaderFlags::ALLOC;
+        let text = builder.add_section(
+            ".text",
+            SectionHeaderType::ProgramBits,
+            alloc | SectionHeaderFlags::EXECUTABLE_INSTRUCTIONS,
+            0x401000,
+            16,
+            &EXIT_CODE[..],
+        );
+        let data = builder.add_section(
+            ".data",
+            SectionHeaderType::ProgramBits,
+            alloc | SectionHeaderFlags::WRITE,
+            0x402000,
+            8,
+            7u64.to_le_bytes().to_vec(),
+        );
+        let bss = builder.add_nobits_section(
+            ".bss",
+            alloc | SectionHeaderFlags::WRITE,
+            0x402008,
+            8,
+            0x18,
+        );
+        builder.add_note(".note.gnu.build-id", "GNU", 3, &BUILD_ID);
+
+        builder.add_segment(
+            ProgramSegmentType::Load,
+            ProgramHeaderFlags::new(true, false, true),
+            0x1000,
+            SegmentContents::Sections(text..data),
+        );
+        builder.add_segment(
+            ProgramSegmentType::Load,
+            ProgramHeaderFlags::new(false, true, true),
+            0x1000,
+            SegmentContents::Sections(data..bss + 1),
+        );
+        builder.add_segment(
+            ProgramSegmentType::GnuStack,
+            ProgramHeaderFlags::new(false, true, true),
+            16,
+            SegmentContents::Empty,
+        );
+
+        builder.add_symbol(symbol(
+            "_start",
+            0x401000,
+            9,
+            SymbolBinding::Global,
+            SymbolType::Function,
+            text as u32,
+        ));
+        builder.add_symbol(symbol(
+            "counter",
+            0x402000,
+            8,
+            SymbolBinding::Local,
+            SymbolType::Object,
+            data as u32,
+        ));
+        builder.add_symbol(symbol(
+            "buffer",
+            0x402008,
+            0x18,
+            SymbolBinding::Global,
+            SymbolType::Object,
+            bss as u32,
+        ));
+
+        builder
+    }
+
+    #[test]
+    fn build_executable_segments() {
+        let file_bytes = executable().build();
+        let elf_file =
+            Elf64File::from_bytes(&file_bytes).expect("The file should be correctly parsed");
+
+        assert_eq!(&ElfType::Executable, elf_file.elf_type());
+        assert_eq!(&InstructionSet::X86_64, elf_file.instruction_set());
+        assert_eq!(0x401000, elf_file.entry_point());
+
+        let headers: Vec<_> = elf_file
+            .program_headers()
+            .expect("Expected program headers")
+            .collect();
+        assert_eq!(
+            vec![
+                // The segments start on the page offsets of their addresses
+                Ok(Elf64ProgramHeader::new(
+                    ProgramSegmentType::Load,
+                    ProgramHeaderFlags::new(true, false, true),
+                    0x1000,
+                    0x401000,
+                    9,
+                    9,
+                    0x1000
+                )),
+                Ok(Elf64ProgramHeader::new(
+                    ProgramSegmentType::Load,
+                    ProgramHeaderFlags::new(false, true, true),
+                    0x2000,
+                    0x402000,
+                    8,
+                    0x20,
+                    0x1000
+                )),
+                Ok(Elf64ProgramHeader::new(
+                    ProgramSegmentType::GnuStack,
+                    ProgramHeaderFlags::new(false, true, true),
+                    0,
+                    0,
+                    0,
+                    0,
+                    16
+                )),
+            ],
+            headers
+        );
+        assert_eq!(
+            Ok(&EXIT_CODE[..]),
+            elf_file.virtual_address_data(0x401000, 9)
+        );
+        assert_eq!(Ok(false), elf_file.requests_executable_stack());
+        assert_eq!(Ok(Vec::new()), validate::validate(&elf_file));
+    }
+
+    #[test]
+    fn build_executable_sections() {
+        let file_bytes = executable().build();
+        let elf_file =
+            Elf64File::from_bytes(&file_bytes).expect("The file should be correctly parsed");
+        let section_names = elf_file.section_names().expect("Expected section names");
+
+        let sections: Vec<_> = elf_file
+            .section_headers()
+            .expect("Failed to read section headers")
+            .map(|section| section.expect("Failed to parse section header"))
+            .collect();
+        let names: Vec<_> = sections
+            .iter()
+            .map(|section| section.name(&section_names).unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                "",
+                ".text",
+                ".data",
+                ".bss",
+                ".note.gnu.build-id",
+                ".symtab",
+                ".strtab",
+                ".shstrtab"
+            ],
+            names
+        );
+
+        let bss = &sections[3];
+        assert_eq!(&SectionHeaderType::NoBits, bss.header_type());
+        assert_eq!(
+            SectionHeaderFlags::ALLOC | SectionHeaderFlags::WRITE,
+            bss.flags()
+        );
+        assert_eq!(Some(0x402008), bss.address());
+        assert_eq!(0x18, bss.section_size());
+        assert_eq!(8, bss.address_alignment());
+        assert_eq!(
+            Ok(&7u64.to_le_bytes()[..]),
+            elf_file.section_data(&sections[2])
+        );
+        assert_eq!(Ok(Some(&BUILD_ID[..])), elf_file.build_id());
+
+        let symbols: Vec<_> = elf_file
+            .symbols()
+            .expect("Failed to find symbol table")
+            .expect("Expected a symbol table")
+            .collect();
+        assert_eq!(
+            vec![
+                Ok(Elf64Symbol::new(
+                    "",
+                    0,
+                    0,
+                    SymbolBinding::Local,
+                    SymbolType::NoType,
+                    SymbolVisibility::Default,
+                    SymbolSectionIndex::Undefined
+                )),
+                // Local symbols come first
+                Ok(symbol(
+                    "counter",
+                    0x402000,
+                    8,
+                    SymbolBinding::Local,
+                    SymbolType::Object,
+                    2
+                )),
+                Ok(symbol(
+                    "_start",
+                    0x401000,
+                    9,
+                    SymbolBinding::Global,
+                    SymbolType::Function,
+                    1
+                )),
+                Ok(symbol(
+                    "buffer",
+                    0x402008,
+                    0x18,
+                    SymbolBinding::Global,
+                    SymbolType::Object,
+                    3
+                )),
+            ],
+            symbols
+        );
+        assert_eq!(Some(2), sections[5].info());
+        assert_eq!(
+            Some((0x402008, 0x18)),
+            elf_file
+                .lookup_symbol("buffer")
+                .unwrap()
+                .map(|symbol| (symbol.value(), symbol.size()))
+        );
+    }
+
+    #[test]
+    fn build_core_file_without_sections() {
+        let mut builder = ElfBuilder::new(ElfType::Core, InstructionSet::X86_64);
+        let memory = [0xAB; 0x30];
+        builder.add_segment(
+            ProgramSegmentType::Note,
+            ProgramHeaderFlags::new(false, false, false),
+            4,
+            SegmentContents::Data {
+                address: 0,
+                data: Cow::Owned(note_bytes("CORE", 1, &[1, 2, 3, 4, 5])),
+                memory_size: 0,
+            },
+        );
+        builder.add_segment(
+            ProgramSegmentType::Load,
+            ProgramHeaderFlags::new(false, true, true),
+            0x1000,
+            SegmentContents::Data {
+                address: 0x7000_0010,
+                data: Cow::Borrowed(&memory),
+                memory_size: 0x1000,
+            },
+        );
+        let file_bytes = builder.build();
+
+        let elf_file =
+            Elf64File::from_bytes(&file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(&ElfType::Core, elf_file.elf_type());
+        assert_eq!(0, elf_file.section_headers().unwrap().count());
+
+        let headers: Vec<_> = elf_file
+            .program_headers()
+            .expect("Expected program headers")
+            .map(|header| header.expect("Failed to parse program header"))
+            .collect();
+        let notes: Vec<_> = elf_file
+            .segment_notes(&headers[0])
+            .expect("Expected notes")
+            .map(|note| note.expect("Failed to parse note"))
+            .collect();
+        assert_eq!(1, notes.len());
+        assert_eq!("CORE", notes[0].name());
+        assert_eq!(1, notes[0].note_type());
+        assert_eq!(&[1, 2, 3, 4, 5], notes[0].descriptor());
+
+        assert_eq!(0x7000_0010, headers[1].p_vaddr());
+        assert_eq!(0x10, headers[1].p_offset() % 0x1000);
+        assert_eq!(0x1000, headers[1].p_memsz());
+        assert_eq!(Ok(&memory[..]), elf_file.segment_data(&headers[1]));
+    }
+
+    #[test]
+    fn encode_notes() {
+        // The name and descriptor are each padded to 4 bytes
+        assert_eq!(
+            vec![4, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, b'G', b'N', b'U', 0, 0xAA, 0xBB, 0, 0],
+            note_bytes("GNU", 3, &[0xAA, 0xBB])
+        );
+        assert_eq!(0x1000, congruent_offset(0x120, 0x401000, 0x1000));
+        assert_eq!(0x1120, congruent_offset(0x121, 0x402120, 0x1000));
+        assert_eq!(0x121, congruent_offset(0x121, 0x402121, 0x1000));
+    }
+}
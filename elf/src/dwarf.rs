@@ -0,0 +1,535 @@
+use core::{convert::TryFrom, fmt};
+
+use self::{info::UnitAttributes, line::LineProgram};
+use super::{byte_at, bytes_at, string_table::StringTable, ElfParseError, Endian};
+
+pub mod aranges;
+pub mod info;
+pub mod line;
+
+/// Forms of attribute values, i.e. how a value is encoded.
+const DW_FORM_ADDR: u64 = 0x01;
+const DW_FORM_BLOCK2: u64 = 0x03;
+const DW_FORM_BLOCK4: u64 = 0x04;
+const DW_FORM_DATA2: u64 = 0x05;
+const DW_FORM_DATA4: u64 = 0x06;
+const DW_FORM_DATA8: u64 = 0x07;
+const DW_FORM_STRING: u64 = 0x08;
+const DW_FORM_BLOCK: u64 = 0x09;
+const DW_FORM_BLOCK1: u64 = 0x0a;
+const DW_FORM_DATA1: u64 = 0x0b;
+const DW_FORM_FLAG: u64 = 0x0c;
+const DW_FORM_SDATA: u64 = 0x0d;
+const DW_FORM_STRP: u64 = 0x0e;
+const DW_FORM_UDATA: u64 = 0x0f;
+const DW_FORM_REF_ADDR: u64 = 0x10;
+const DW_FORM_REF1: u64 = 0x11;
+const DW_FORM_REF2: u64 = 0x12;
+const DW_FORM_REF4: u64 = 0x13;
+const DW_FORM_REF8: u64 = 0x14;
+const DW_FORM_REF_UDATA: u64 = 0x15;
+const DW_FORM_INDIRECT: u64 = 0x16;
+const DW_FORM_SEC_OFFSET: u64 = 0x17;
+const DW_FORM_EXPRLOC: u64 = 0x18;
+const DW_FORM_FLAG_PRESENT: u64 = 0x19;
+const DW_FORM_STRX: u64 = 0x1a;
+const DW_FORM_ADDRX: u64 = 0x1b;
+const DW_FORM_REF_SUP4: u64 = 0x1c;
+const DW_FORM_STRP_SUP: u64 = 0x1d;
+const DW_FORM_DATA16: u64 = 0x1e;
+const DW_FORM_LINE_STRP: u64 = 0x1f;
+const DW_FORM_REF_SIG8: u64 = 0x20;
+const DW_FORM_IMPLICIT_CONST: u64 = 0x21;
+const DW_FORM_LOCLISTX: u64 = 0x22;
+const DW_FORM_RNGLISTX: u64 = 0x23;
+const DW_FORM_REF_SUP8: u64 = 0x24;
+const DW_FORM_STRX1: u64 = 0x25;
+const DW_FORM_STRX2: u64 = 0x26;
+const DW_FORM_STRX3: u64 = 0x27;
+const DW_FORM_STRX4: u64 = 0x28;
+const DW_FORM_ADDRX1: u64 = 0x29;
+const DW_FORM_ADDRX2: u64 = 0x2a;
+const DW_FORM_ADDRX3: u64 = 0x2b;
+const DW_FORM_ADDRX4: u64 = 0x2c;
+
+/// The contents of the DWARF sections needed to map code addresses to
+/// source lines. Sections the file doesn't have are left empty.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DwarfSections<'a> {
+    pub debug_line: &'a [u8],
+    pub debug_aranges: &'a [u8],
+    pub debug_info: &'a [u8],
+    pub debug_abbrev: &'a [u8],
+    pub debug_str: &'a [u8],
+    pub debug_line_str: &'a [u8],
+}
+
+/// Maps code addresses to the source lines they were compiled from, through
+/// the line programs of a file's `.debug_line`.
+#[derive(Debug, Clone, Copy)]
+pub struct LineInfo<'a> {
+    sections: DwarfSections<'a>,
+    endianness: Endian,
+}
+
+impl<'a> LineInfo<'a> {
+    pub fn new(sections: DwarfSections<'a>, endianness: Endian) -> Self {
+        Self {
+            sections,
+            endianness,
+        }
+    }
+
+    /// The line programs of all compilation units, in the order they appear
+    /// in `.debug_line`.
+    pub fn line_programs(&self) -> LineProgramIterator<'a> {
+        LineProgramIterator {
+            sections: self.sections,
+            endianness: self.endianness,
+            offset: 0,
+        }
+    }
+
+    /// The source location `address` was compiled from.
+    ///
+    /// The compilation unit covering the address is looked up in
+    /// `.debug_aranges`, and only its line program is run. Files without
+    /// address ranges for the address, which linkers and compilers often
+    /// leave out, have every line program run until one covers it.
+    pub fn locate(&self, address: u64) -> Result<Option<SourceLocation<'a>>, ElfParseError> {
+        if let Some(unit_offset) =
+            aranges::find_unit(self.sections.debug_aranges, address, self.endianness)?
+        {
+            let unit = UnitAttributes::read(&self.sections, unit_offset, self.endianness)?;
+            if let Some(line_offset) = unit.line_offset() {
+                let program = LineProgram::parse(&self.sections, line_offset, self.endianness)?;
+                return program.locate(address, unit.compilation_directory());
+            }
+        }
+
+        for program in self.line_programs() {
+            if let Some(location) = program?.locate(address, None)? {
+                return Ok(Some(location));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+pub struct LineProgramIterator<'a> {
+    sections: DwarfSections<'a>,
+    endianness: Endian,
+    offset: usize,
+}
+
+impl<'a> Iterator for LineProgramIterator<'a> {
+    type Item = Result<LineProgram<'a>, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.sections.debug_line.len() {
+            return None;
+        }
+
+        match LineProgram::parse(&self.sections, self.offset as u64, self.endianness) {
+            Ok(program) => {
+                self.offset = program.end_offset();
+                Some(Ok(program))
+            }
+            Err(error) => {
+                // The next unit can't be found without this one's length
+                self.offset = self.sections.debug_line.len();
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// A line of a source file, as found by `LineInfo::locate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourceLocation<'a> {
+    directory: Option<&'a str>,
+    file: &'a str,
+    line: u64,
+    column: u64,
+}
+
+impl<'a> SourceLocation<'a> {
+    /// The directory the file's path is relative to, if known. Files whose
+    /// path is absolute have none.
+    pub fn directory(&self) -> Option<&'a str> {
+        self.directory
+    }
+
+    pub fn file(&self) -> &'a str {
+        self.file
+    }
+
+    /// The line number, starting at 1. Code that can't be attributed to a
+    /// line, e.g. compiler-generated code, is on line 0.
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    /// The column, starting at 1, or 0 if unknown.
+    pub fn column(&self) -> u64 {
+        self.column
+    }
+}
+
+/// Formats the location as `directory/file:line:column`, leaving out the
+/// parts that are unknown.
+impl fmt::Display for SourceLocation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(directory) = self.directory {
+            write!(f, "{}/", directory.trim_end_matches('/'))?;
+        }
+        write!(f, "{}:{}", self.file, self.line)?;
+        if self.column != 0 {
+            write!(f, ":{}", self.column)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a unit's offsets and lengths are 4 or 8 bytes long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DwarfFormat {
+    Dwarf32,
+    Dwarf64,
+}
+
+/// How the values of a unit are encoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct UnitEncoding {
+    format: DwarfFormat,
+    version: u16,
+    address_size: u8,
+}
+
+/// A decoded attribute value. References and indexes into tables the reader
+/// doesn't load are returned as `Unsigned`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AttributeValue<'a> {
+    Unsigned(u64),
+    Signed(i64),
+    String(&'a str),
+    Block(&'a [u8]),
+}
+
+/// Reads DWARF values from a section, moving past each one.
+#[derive(Debug, Clone)]
+struct DwarfReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    endianness: Endian,
+}
+
+impl<'a> DwarfReader<'a> {
+    fn new(data: &'a [u8], offset: usize, endianness: Endian) -> Self {
+        Self {
+            data,
+            offset,
+            endianness,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    fn u8(&mut self) -> Result<u8, ElfParseError> {
+        let value = byte_at(self.data, self.offset)?;
+        self.offset += 1;
+        Ok(value)
+    }
+
+    fn u16(&mut self) -> Result<u16, ElfParseError> {
+        let value = self.endianness.get_u16(self.data, self.offset)?;
+        self.offset += 2;
+        Ok(value)
+    }
+
+    fn u32(&mut self) -> Result<u32, ElfParseError> {
+        let value = self.endianness.get_u32(self.data, self.offset)?;
+        self.offset += 4;
+        Ok(value)
+    }
+
+    fn u64(&mut self) -> Result<u64, ElfParseError> {
+        let value = self.endianness.get_u64(self.data, self.offset)?;
+        self.offset += 8;
+        Ok(value)
+    }
+
+    /// An unsigned value of `size` bytes, e.g. an address.
+    fn sized(&mut self, size: usize) -> Result<u64, ElfParseError> {
+        match size {
+            1 => self.u8().map(u64::from),
+            2 => self.u16().map(u64::from),
+            3 => {
+                let bytes = self.bytes(3)?;
+                Ok(match self.endianness {
+                    Endian::Little => {
+                        u64::from(bytes[0]) | u64::from(bytes[1]) << 8 | u64::from(bytes[2]) << 16
+                    }
+                    Endian::Big => {
+                        u64::from(bytes[0]) << 16 | u64::from(bytes[1]) << 8 | u64::from(bytes[2])
+                    }
+                })
+            }
+            4 => self.u32().map(u64::from),
+            8 => self.u64(),
+            _ => Err(ElfParseError::FailedToParseValue),
+        }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], ElfParseError> {
+        let bytes = bytes_at(self.data, self.offset, length)?;
+        self.offset += length;
+        Ok(bytes)
+    }
+
+    fn uleb128(&mut self) -> Result<u64, ElfParseError> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= u64::from(byte & 0x7F) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn sleb128(&mut self) -> Result<i64, ElfParseError> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= i64::from(byte & 0x7F) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Ok(value);
+            }
+        }
+    }
+
+    /// A null-terminated string within the data.
+    fn string(&mut self) -> Result<&'a str, ElfParseError> {
+        let offset = u32::try_from(self.offset).unwrap_or(u32::MAX);
+        let string = StringTable::new(self.data).get(offset)?;
+        self.offset += string.len() + 1;
+        Ok(string)
+    }
+
+    /// The length that starts every unit, and the format the length's
+    /// encoding says the unit uses.
+    fn unit_length(&mut self) -> Result<(u64, DwarfFormat), ElfParseError> {
+        match self.u32()? {
+            0xFFFF_FFFF => Ok((self.u64()?, DwarfFormat::Dwarf64)),
+            length if length >= 0xFFFF_FFF0 => Err(ElfParseError::FailedToParseValue),
+            length => Ok((u64::from(length), DwarfFormat::Dwarf32)),
+        }
+    }
+
+    /// An offset into another section.
+    fn section_offset(&mut self, format: DwarfFormat) -> Result<u64, ElfParseError> {
+        match format {
+            DwarfFormat::Dwarf32 => self.u32().map(u64::from),
+            DwarfFormat::Dwarf64 => self.u64(),
+        }
+    }
+
+    /// Read a value of `form`. `implicit_const` is the value of an
+    /// `DW_FORM_implicit_const` attribute, which is stored in its
+    /// abbreviation instead of the data.
+    fn attribute(
+        &mut self,
+        form: u64,
+        implicit_const: i64,
+        encoding: UnitEncoding,
+        sections: &DwarfSections<'a>,
+    ) -> Result<AttributeValue<'a>, ElfParseError> {
+        let value = match form {
+            DW_FORM_ADDR => AttributeValue::Unsigned(self.sized(encoding.address_size.into())?),
+            DW_FORM_DATA1 | DW_FORM_REF1 | DW_FORM_FLAG | DW_FORM_STRX1 | DW_FORM_ADDRX1 => {
+                AttributeValue::Unsigned(self.sized(1)?)
+            }
+            DW_FORM_DATA2 | DW_FORM_REF2 | DW_FORM_STRX2 | DW_FORM_ADDRX2 => {
+                AttributeValue::Unsigned(self.sized(2)?)
+            }
+            DW_FORM_STRX3 | DW_FORM_ADDRX3 => AttributeValue::Unsigned(self.sized(3)?),
+            DW_FORM_DATA4 | DW_FORM_REF4 | DW_FORM_REF_SUP4 | DW_FORM_STRX4 | DW_FORM_ADDRX4 => {
+                AttributeValue::Unsigned(self.sized(4)?)
+            }
+            DW_FORM_DATA8 | DW_FORM_REF8 | DW_FORM_REF_SIG8 | DW_FORM_REF_SUP8 => {
+                AttributeValue::Unsigned(self.u64()?)
+            }
+            DW_FORM_DATA16 => AttributeValue::Block(self.bytes(16)?),
+            DW_FORM_UDATA | DW_FORM_REF_UDATA | DW_FORM_STRX | DW_FORM_ADDRX | DW_FORM_LOCLISTX
+            | DW_FORM_RNGLISTX => AttributeValue::Unsigned(self.uleb128()?),
+            DW_FORM_SDATA => AttributeValue::Signed(self.sleb128()?),
+            DW_FORM_FLAG_PRESENT => AttributeValue::Unsigned(1),
+            DW_FORM_IMPLICIT_CONST => AttributeValue::Signed(implicit_const),
+            DW_FORM_SEC_OFFSET | DW_FORM_STRP_SUP => {
+                AttributeValue::Unsigned(self.section_offset(encoding.format)?)
+            }
+            // DWARF 2 references are address sized
+            DW_FORM_REF_ADDR if encoding.version <= 2 => {
+                AttributeValue::Unsigned(self.sized(encoding.address_size.into())?)
+            }
+            DW_FORM_REF_ADDR => AttributeValue::Unsigned(self.section_offset(encoding.format)?),
+            DW_FORM_STRING => AttributeValue::String(self.string()?),
+            DW_FORM_STRP => {
+                let offset = self.section_offset(encoding.format)?;
+                AttributeValue::String(string_at(sections.debug_str, offset)?)
+            }
+            DW_FORM_LINE_STRP => {
+                let offset = self.section_offset(encoding.format)?;
+                AttributeValue::String(string_at(sections.debug_line_str, offset)?)
+            }
+            DW_FORM_BLOCK1 => {
+                let length = self.u8()?;
+                AttributeValue::Block(self.bytes(length.into())?)
+            }
+            DW_FORM_BLOCK2 => {
+                let length = self.u16()?;
+                AttributeValue::Block(self.bytes(length.into())?)
+            }
+            DW_FORM_BLOCK4 => {
+                let length = self.u32()?;
+                AttributeValue::Block(self.bytes(length as usize)?)
+            }
+            DW_FORM_BLOCK | DW_FORM_EXPRLOC => {
+                let length = self.uleb128()?;
+                AttributeValue::Block(self.bytes(length as usize)?)
+            }
+            DW_FORM_INDIRECT => {
+                let form = self.uleb128()?;
+                if form == DW_FORM_INDIRECT {
+                    return Err(ElfParseError::UnsupportedDwarfForm(form));
+                }
+                return self.attribute(form, implicit_const, encoding, sections);
+            }
+            _ => return Err(ElfParseError::UnsupportedDwarfForm(form)),
+        };
+
+        Ok(value)
+    }
+}
+
+/// The string at `offset` in a string section such as `.debug_str`.
+fn string_at(section: &[u8], offset: u64) -> Result<&str, ElfParseError> {
+    let offset = u32::try_from(offset).map_err(|_| ElfParseError::InvalidStringOffset(u32::MAX))?;
+    StringTable::new(section).get(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf64::Elf64File;
+    use alloc::string::ToString;
+
+    fn line_info(file_bytes: &[u8]) -> LineInfo<'_> {
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        elf_file
+            .line_info()
+            .expect("Failed to read debug sections")
+            .expect("Expected debug information")
+    }
+
+    #[test]
+    fn read_leb128() {
+        let data = [0xE5, 0x8E, 0x26, 0x7F, 0x80, 0x7F, 0x02];
+        let mut reader = DwarfReader::new(&data, 0, Endian::Little);
+        assert_eq!(Ok(624485), reader.uleb128());
+        assert_eq!(Ok(-1), reader.sleb128());
+        assert_eq!(Ok(-128), reader.sleb128());
+        assert_eq!(Ok(2), reader.sleb128());
+        assert!(reader.is_empty());
+        assert_eq!(
+            Err(ElfParseError::Truncated {
+                offset: 7,
+                needed: 1
+            }),
+            reader.uleb128()
+        );
+    }
+
+    #[test]
+    fn locate_addresses_in_dwarf_4() {
+        let file_bytes = include_bytes!("test_files/line_info_dwarf4.elf64");
+        let line_info = line_info(file_bytes);
+
+        // `sum += square(i)`, through .debug_aranges
+        let location = line_info
+            .locate(0x401130)
+            .expect("Failed to locate address")
+            .expect("Expected a source location");
+        assert_eq!("line_info.c", location.file());
+        assert_eq!(10, location.line());
+        assert_eq!(16, location.column());
+        // DWARF 4 files of the compilation directory get it from .debug_info
+        assert_eq!(Some("/root/crate/src/elf/test_files"), location.directory());
+        assert_eq!(
+            "/root/crate/src/elf/test_files/line_info.c:10:16",
+            location.to_string()
+        );
+
+        // Within the inlined function from the header
+        let location = line_info.locate(0x401110).unwrap().unwrap();
+        assert_eq!("line_info.h", location.file());
+        assert_eq!(3, location.line());
+
+        assert_eq!(Ok(None), line_info.locate(0x401167));
+        assert_eq!(Ok(None), line_info.locate(0x400000));
+    }
+
+    #[test]
+    fn locate_addresses_in_dwarf_5() {
+        let file_bytes = include_bytes!("test_files/line_info_dwarf5.elf64");
+        let line_info = line_info(file_bytes);
+
+        let location = line_info.locate(0x401130).unwrap().unwrap();
+        assert_eq!("line_info.c", location.file());
+        assert_eq!(Some("/root/crate/src/elf/test_files"), location.directory());
+        assert_eq!(10, location.line());
+        assert_eq!(16, location.column());
+
+        // The last row of `main`, right before the end of the sequence
+        let location = line_info.locate(0x401166).unwrap().unwrap();
+        assert_eq!(18, location.line());
+        assert_eq!(Ok(None), line_info.locate(0x401167));
+    }
+
+    #[test]
+    fn locate_addresses_without_aranges() {
+        let file_bytes = include_bytes!("test_files/line_info_dwarf5.elf64");
+        let mut line_info = line_info(file_bytes);
+        line_info.sections.debug_aranges = &[];
+
+        // Every line program is run instead
+        let location = line_info.locate(0x401152).unwrap().unwrap();
+        assert_eq!("line_info.c", location.file());
+        assert_eq!(17, location.line());
+        assert_eq!(1, line_info.line_programs().count());
+    }
+
+    #[test]
+    fn no_line_info_without_debug_sections() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        assert!(matches!(elf_file.line_info(), Ok(None)));
+    }
+}
@@ -0,0 +1,189 @@
+use core::ops::Range;
+
+use super::DwarfReader;
+use crate::{ElfParseError, Endian};
+
+/// A range of code addresses in `.debug_aranges`, and the offset of the
+/// compilation unit it belongs to in `.debug_info`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressRange {
+    unit_offset: u64,
+    addresses: Range<u64>,
+}
+
+impl AddressRange {
+    pub fn unit_offset(&self) -> u64 {
+        self.unit_offset
+    }
+
+    pub fn addresses(&self) -> Range<u64> {
+        self.addresses.clone()
+    }
+}
+
+/// Iterates over the address ranges of all sets in `.debug_aranges`.
+pub struct AddressRangeIterator<'a> {
+    reader: DwarfReader<'a>,
+    /// The current set's compilation unit, address size and end, or `None`
+    /// between sets.
+    set: Option<(u64, u8, usize)>,
+    failed: bool,
+}
+
+impl<'a> AddressRangeIterator<'a> {
+    pub fn new(data: &'a [u8], endianness: Endian) -> Self {
+        Self {
+            reader: DwarfReader::new(data, 0, endianness),
+            set: None,
+            failed: false,
+        }
+    }
+
+    fn step(&mut self) -> Result<Option<AddressRange>, ElfParseError> {
+        loop {
+            let (unit_offset, address_size, set_end) = match self.set {
+                Some(set) => set,
+                None => {
+                    if self.reader.is_empty() {
+                        return Ok(None);
+                    }
+                    self.read_set_header()?
+                }
+            };
+            if self.reader.offset >= set_end {
+                self.reader.offset = set_end;
+                self.set = None;
+                continue;
+            }
+
+            let start = self.reader.sized(address_size.into())?;
+            let length = self.reader.sized(address_size.into())?;
+            if start == 0 && length == 0 {
+                // The set's terminating entry
+                self.reader.offset = set_end;
+                self.set = None;
+                continue;
+            }
+
+            return Ok(Some(AddressRange {
+                unit_offset,
+                addresses: start..start.saturating_add(length),
+            }));
+        }
+    }
+
+    fn read_set_header(&mut self) -> Result<(u64, u8, usize), ElfParseError> {
+        let set_start = self.reader.offset;
+        let (length, format) = self.reader.unit_length()?;
+        let set_end = self.reader.offset.saturating_add(length as usize);
+
+        let version = self.reader.u16()?;
+        if version != 2 {
+            return Err(ElfParseError::UnsupportedDwarfVersion(version));
+        }
+        let unit_offset = self.reader.section_offset(format)?;
+        let address_size = self.reader.u8()?;
+        let segment_selector_size = self.reader.u8()?;
+        if segment_selector_size != 0 {
+            return Err(ElfParseError::UnsupportedDwarfForm(
+                segment_selector_size.into(),
+            ));
+        }
+
+        // The entries are aligned to twice the address size from the set's
+        // start
+        let tuple_size = 2 * usize::from(address_size.max(1));
+        let header_size = self.reader.offset - set_start;
+        self.reader.offset = set_start + header_size.div_ceil(tuple_size) * tuple_size;
+
+        let set = (unit_offset, address_size, set_end);
+        self.set = Some(set);
+        Ok(set)
+    }
+}
+
+impl Iterator for AddressRangeIterator<'_> {
+    type Item = Result<AddressRange, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        match self.step() {
+            Ok(range) => range.map(Ok),
+            Err(error) => {
+                self.failed = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// The offset in `.debug_info` of the compilation unit whose code contains
+/// `address`, according to `.debug_aranges`.
+pub fn find_unit(
+    data: &[u8],
+    address: u64,
+    endianness: Endian,
+) -> Result<Option<u64>, ElfParseError> {
+    for range in AddressRangeIterator::new(data, endianness) {
+        let range = range?;
+        if range.addresses.contains(&address) {
+            return Ok(Some(range.unit_offset));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf64::Elf64File;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn read_address_ranges() {
+        let file_bytes = include_bytes!("../test_files/line_info_dwarf5.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let section = elf_file
+            .section_by_name(".debug_aranges")
+            .unwrap()
+            .expect("Expected a .debug_aranges section");
+        let data = elf_file.section_data(&section).unwrap();
+
+        let ranges: Vec<_> = AddressRangeIterator::new(data, Endian::Little).collect();
+        assert_eq!(
+            vec![Ok(AddressRange {
+                unit_offset: 0,
+                addresses: 0x401106..0x401167
+            })],
+            ranges
+        );
+        assert_eq!(Ok(Some(0)), find_unit(data, 0x401166, Endian::Little));
+        assert_eq!(Ok(None), find_unit(data, 0x401167, Endian::Little));
+    }
+
+    #[test]
+    fn read_address_ranges_of_several_sets() {
+        // A 32-bit set with an entry for unit 0x10, then one without entries
+        #[rustfmt::skip]
+        let data = [
+            28, 0, 0, 0, 2, 0, 0x10, 0, 0, 0, 4, 0, 0, 0, 0, 0,
+            0x00, 0x10, 0, 0, 0x20, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            12, 0, 0, 0, 2, 0, 0x20, 0, 0, 0, 4, 0, 0, 0, 0, 0,
+        ];
+
+        let ranges: Vec<_> = AddressRangeIterator::new(&data, Endian::Little).collect();
+        assert_eq!(
+            vec![Ok(AddressRange {
+                unit_offset: 0x10,
+                addresses: 0x1000..0x1020
+            })],
+            ranges
+        );
+    }
+}
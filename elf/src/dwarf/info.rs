@@ -0,0 +1,186 @@
+use alloc::vec::Vec;
+
+use super::{AttributeValue, DwarfReader, DwarfSections, UnitEncoding, DW_FORM_IMPLICIT_CONST};
+use crate::{ElfParseError, Endian};
+
+/// Attributes of the unit's root entry.
+const DW_AT_STMT_LIST: u64 = 0x10;
+const DW_AT_COMP_DIR: u64 = 0x1b;
+
+/// Unit types of DWARF 5 unit headers.
+const DW_UT_TYPE: u8 = 0x02;
+const DW_UT_SKELETON: u8 = 0x04;
+const DW_UT_SPLIT_COMPILE: u8 = 0x05;
+const DW_UT_SPLIT_TYPE: u8 = 0x06;
+
+/// The attributes of a compilation unit in `.debug_info` that are needed to
+/// run its line program.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct UnitAttributes<'a> {
+    line_offset: Option<u64>,
+    compilation_directory: Option<&'a str>,
+}
+
+impl<'a> UnitAttributes<'a> {
+    /// Read the attributes of the root entry of the unit at `offset` in
+    /// `.debug_info`, whose abbreviation is looked up in `.debug_abbrev`.
+    pub fn read(
+        sections: &DwarfSections<'a>,
+        offset: u64,
+        endianness: Endian,
+    ) -> Result<Self, ElfParseError> {
+        let mut reader = DwarfReader::new(sections.debug_info, offset as usize, endianness);
+        let (_, format) = reader.unit_length()?;
+        let version = reader.u16()?;
+        let (address_size, abbreviation_offset) = match version {
+            2..=4 => {
+                let abbreviation_offset = reader.section_offset(format)?;
+                (reader.u8()?, abbreviation_offset)
+            }
+            5 => {
+                let unit_type = reader.u8()?;
+                let address_size = reader.u8()?;
+                let abbreviation_offset = reader.section_offset(format)?;
+                match unit_type {
+                    // The unit's DWO ID
+                    DW_UT_SKELETON | DW_UT_SPLIT_COMPILE => {
+                        reader.u64()?;
+                    }
+                    // The type's signature and offset
+                    DW_UT_TYPE | DW_UT_SPLIT_TYPE => {
+                        reader.u64()?;
+                        reader.section_offset(format)?;
+                    }
+                    _ => {}
+                }
+                (address_size, abbreviation_offset)
+            }
+            _ => return Err(ElfParseError::UnsupportedDwarfVersion(version)),
+        };
+        let encoding = UnitEncoding {
+            format,
+            version,
+            address_size,
+        };
+
+        let code = reader.uleb128()?;
+        if code == 0 {
+            return Ok(Self::default());
+        }
+
+        let mut attributes = Self::default();
+        let abbreviation =
+            abbreviation(sections.debug_abbrev, abbreviation_offset, code, endianness)?;
+        for (name, form, implicit_const) in abbreviation {
+            let value = reader.attribute(form, implicit_const, encoding, sections)?;
+            match (name, value) {
+                (DW_AT_STMT_LIST, AttributeValue::Unsigned(offset)) => {
+                    attributes.line_offset = Some(offset)
+                }
+                (DW_AT_COMP_DIR, AttributeValue::String(directory)) => {
+                    attributes.compilation_directory = Some(directory)
+                }
+                _ => {}
+            }
+        }
+
+        Ok(attributes)
+    }
+
+    /// Offset of the unit's line program in `.debug_line`.
+    pub fn line_offset(&self) -> Option<u64> {
+        self.line_offset
+    }
+
+    /// The directory the unit was compiled in, which relative paths of its
+    /// line program are relative to.
+    pub fn compilation_directory(&self) -> Option<&'a str> {
+        self.compilation_directory
+    }
+}
+
+/// The attribute names, forms and implicit constants of the abbreviation
+/// with `code`, in the abbreviation table at `offset` in `.debug_abbrev`.
+fn abbreviation(
+    data: &[u8],
+    offset: u64,
+    code: u64,
+    endianness: Endian,
+) -> Result<Vec<(u64, u64, i64)>, ElfParseError> {
+    let mut reader = DwarfReader::new(data, offset as usize, endianness);
+    loop {
+        let entry_code = reader.uleb128()?;
+        if entry_code == 0 {
+            return Err(ElfParseError::MissingDwarfAbbreviation(code));
+        }
+        let _tag = reader.uleb128()?;
+        let _has_children = reader.u8()?;
+
+        let mut attributes = Vec::new();
+        loop {
+            let name = reader.uleb128()?;
+            let form = reader.uleb128()?;
+            if name == 0 && form == 0 {
+                break;
+            }
+            let implicit_const = if form == DW_FORM_IMPLICIT_CONST {
+                reader.sleb128()?
+            } else {
+                0
+            };
+            attributes.push((name, form, implicit_const));
+        }
+
+        if entry_code == code {
+            return Ok(attributes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf64::Elf64File;
+
+    fn unit_attributes(file_bytes: &[u8]) -> Result<UnitAttributes<'_>, ElfParseError> {
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let sections = elf_file.line_info().unwrap().unwrap().sections;
+        UnitAttributes::read(&sections, 0, Endian::Little)
+    }
+
+    #[test]
+    fn read_compilation_unit_attributes() {
+        for file_bytes in [
+            &include_bytes!("../test_files/line_info_dwarf4.elf64")[..],
+            &include_bytes!("../test_files/line_info_dwarf5.elf64")[..],
+        ]
+        .iter()
+        {
+            let attributes = unit_attributes(file_bytes).expect("Failed to read unit attributes");
+            assert_eq!(Some(0), attributes.line_offset());
+            assert_eq!(
+                Some("/root/crate/src/elf/test_files"),
+                attributes.compilation_directory()
+            );
+        }
+    }
+
+    #[test]
+    fn find_abbreviation_by_code() {
+        // Code 1 has no attributes, code 2 a DW_AT_stmt_list sec_offset and
+        // an implicit constant
+        let data = [
+            1, 0x11, 0, 0, 0, 2, 0x11, 1, 0x10, 0x17, 0x3a, 0x21, 0x7f, 0, 0, 0,
+        ];
+        assert_eq!(Ok(Vec::new()), abbreviation(&data, 0, 1, Endian::Little));
+        assert_eq!(
+            Ok(vec![(0x10, 0x17, 0), (0x3a, 0x21, -1)]),
+            abbreviation(&data, 0, 2, Endian::Little)
+        );
+        assert_eq!(
+            Err(ElfParseError::MissingDwarfAbbreviation(3)),
+            abbreviation(&data, 0, 3, Endian::Little)
+        );
+    }
+}
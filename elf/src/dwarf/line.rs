@@ -0,0 +1,536 @@
+use alloc::vec::Vec;
+
+use super::{AttributeValue, DwarfReader, DwarfSections, SourceLocation, UnitEncoding};
+use crate::{bytes_at, ElfParseError, Endian};
+
+/// Content types of the directory and file entries of DWARF 5 line program
+/// headers.
+const DW_LNCT_PATH: u64 = 0x1;
+const DW_LNCT_DIRECTORY_INDEX: u64 = 0x2;
+
+/// Standard opcodes, below the header's opcode base.
+const DW_LNS_COPY: u8 = 0x01;
+const DW_LNS_ADVANCE_PC: u8 = 0x02;
+const DW_LNS_ADVANCE_LINE: u8 = 0x03;
+const DW_LNS_SET_FILE: u8 = 0x04;
+const DW_LNS_SET_COLUMN: u8 = 0x05;
+const DW_LNS_NEGATE_STMT: u8 = 0x06;
+const DW_LNS_CONST_ADD_PC: u8 = 0x08;
+const DW_LNS_FIXED_ADVANCE_PC: u8 = 0x09;
+
+/// Extended opcodes, which follow a 0 byte and their length.
+const DW_LNE_END_SEQUENCE: u8 = 0x01;
+const DW_LNE_SET_ADDRESS: u8 = 0x02;
+
+/// A file of a line program's file table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileEntry<'a> {
+    path: &'a str,
+    directory_index: u64,
+}
+
+impl<'a> FileEntry<'a> {
+    pub fn path(&self) -> &'a str {
+        self.path
+    }
+
+    pub fn directory_index(&self) -> u64 {
+        self.directory_index
+    }
+}
+
+/// The line program of a compilation unit in `.debug_line`, i.e. the
+/// bytecode that builds the unit's table of code addresses and the source
+/// lines they belong to, and the header it is interpreted with.
+///
+/// DWARF versions 2 to 5 are supported. VLIW operation indexes aren't, so
+/// the maximum number of operations per instruction is taken to be 1.
+#[derive(Debug)]
+pub struct LineProgram<'a> {
+    encoding: UnitEncoding,
+    end_offset: usize,
+    minimum_instruction_length: u8,
+    default_is_stmt: bool,
+    line_base: i8,
+    line_range: u8,
+    opcode_base: u8,
+    /// Number of operands of each standard opcode, from opcode 1 on.
+    standard_opcode_lengths: &'a [u8],
+    include_directories: Vec<&'a str>,
+    files: Vec<FileEntry<'a>>,
+    program: &'a [u8],
+    endianness: Endian,
+}
+
+impl<'a> LineProgram<'a> {
+    /// Parse the header of the line program at `offset` in `.debug_line`.
+    pub fn parse(
+        sections: &DwarfSections<'a>,
+        offset: u64,
+        endianness: Endian,
+    ) -> Result<Self, ElfParseError> {
+        let data = sections.debug_line;
+        let mut reader = DwarfReader::new(data, offset as usize, endianness);
+        let (unit_length, format) = reader.unit_length()?;
+        let end_offset = reader.offset.saturating_add(unit_length as usize);
+
+        let version = reader.u16()?;
+        if !(2..=5).contains(&version) {
+            return Err(ElfParseError::UnsupportedDwarfVersion(version));
+        }
+        let address_size = if version >= 5 {
+            let address_size = reader.u8()?;
+            let _segment_selector_size = reader.u8()?;
+            address_size
+        } else {
+            // `DW_LNE_set_address` carries its own size before DWARF 5
+            8
+        };
+        let encoding = UnitEncoding {
+            format,
+            version,
+            address_size,
+        };
+
+        let header_length = reader.section_offset(format)?;
+        let program_offset = reader.offset.saturating_add(header_length as usize);
+        let minimum_instruction_length = reader.u8()?;
+        if version >= 4 {
+            let _maximum_operations_per_instruction = reader.u8()?;
+        }
+        let default_is_stmt = reader.u8()? != 0;
+        let line_base = reader.u8()? as i8;
+        let line_range = reader.u8()?;
+        let opcode_base = reader.u8()?;
+        if line_range == 0 || opcode_base == 0 {
+            return Err(ElfParseError::InvalidLineProgramHeader);
+        }
+        let standard_opcode_lengths = reader.bytes(usize::from(opcode_base) - 1)?;
+
+        let mut include_directories = Vec::new();
+        let mut files = Vec::new();
+        if version >= 5 {
+            for entry in entry_table(&mut reader, encoding, sections)? {
+                include_directories.push(entry?.path);
+            }
+            for entry in entry_table(&mut reader, encoding, sections)? {
+                files.push(entry?);
+            }
+        } else {
+            loop {
+                let directory = reader.string()?;
+                if directory.is_empty() {
+                    break;
+                }
+                include_directories.push(directory);
+            }
+            loop {
+                let path = reader.string()?;
+                if path.is_empty() {
+                    break;
+                }
+                let directory_index = reader.uleb128()?;
+                let _modification_time = reader.uleb128()?;
+                let _length = reader.uleb128()?;
+                files.push(FileEntry {
+                    path,
+                    directory_index,
+                });
+            }
+        }
+
+        let program = bytes_at(
+            data,
+            program_offset,
+            end_offset.saturating_sub(program_offset),
+        )?;
+
+        Ok(Self {
+            encoding,
+            end_offset,
+            minimum_instruction_length,
+            default_is_stmt,
+            line_base,
+            line_range,
+            opcode_base,
+            standard_opcode_lengths,
+            include_directories,
+            files,
+            program,
+            endianness,
+        })
+    }
+
+    pub fn version(&self) -> u16 {
+        self.encoding.version
+    }
+
+    /// Offset of the next unit's line program in `.debug_line`.
+    pub(super) fn end_offset(&self) -> usize {
+        self.end_offset
+    }
+
+    /// The file with `index` in the program's file table. Indexes start at 1
+    /// before DWARF 5, and at 0 from then on.
+    pub fn file(&self, index: u64) -> Option<&FileEntry<'a>> {
+        let index = if self.encoding.version >= 5 {
+            index
+        } else {
+            index.checked_sub(1)?
+        };
+        self.files.get(index as usize)
+    }
+
+    /// The include directory with `index`. Before DWARF 5, index 0 is the
+    /// compilation directory, which only `.debug_info` records and must be
+    /// passed in.
+    pub fn directory(&self, index: u64, compilation_directory: Option<&'a str>) -> Option<&'a str> {
+        if self.encoding.version >= 5 {
+            self.include_directories.get(index as usize).copied()
+        } else if index == 0 {
+            compilation_directory
+        } else {
+            self.include_directories.get(index as usize - 1).copied()
+        }
+    }
+
+    /// Run the program, yielding the rows of its line table.
+    pub fn rows(&self) -> LineRows<'_, 'a> {
+        LineRows {
+            program: self,
+            reader: DwarfReader::new(self.program, 0, self.endianness),
+            state: LineRow::initial(self.default_is_stmt),
+            failed: false,
+        }
+    }
+
+    /// The source location of `address`, i.e. of the last row at or before it
+    /// within a sequence.
+    pub fn locate(
+        &self,
+        address: u64,
+        compilation_directory: Option<&'a str>,
+    ) -> Result<Option<SourceLocation<'a>>, ElfParseError> {
+        let mut previous: Option<LineRow> = None;
+        for row in self.rows() {
+            let row = row?;
+            if let Some(previous) = previous {
+                if previous.address <= address && address < row.address {
+                    return self.location(&previous, compilation_directory).map(Some);
+                }
+            }
+            previous = if row.end_sequence { None } else { Some(row) };
+        }
+
+        Ok(None)
+    }
+
+    fn location(
+        &self,
+        row: &LineRow,
+        compilation_directory: Option<&'a str>,
+    ) -> Result<SourceLocation<'a>, ElfParseError> {
+        let file = self
+            .file(row.file)
+            .ok_or(ElfParseError::InvalidLineProgramFile(row.file))?;
+        let directory = if file.path.starts_with('/') {
+            None
+        } else {
+            self.directory(file.directory_index, compilation_directory)
+        };
+
+        Ok(SourceLocation {
+            directory,
+            file: file.path,
+            line: row.line,
+            column: row.column,
+        })
+    }
+}
+
+/// Read a DWARF 5 directory or file table: the formats of its entries'
+/// fields, then the entries.
+fn entry_table<'a>(
+    reader: &mut DwarfReader<'a>,
+    encoding: UnitEncoding,
+    sections: &DwarfSections<'a>,
+) -> Result<Vec<Result<FileEntry<'a>, ElfParseError>>, ElfParseError> {
+    let format_count = reader.u8()?;
+    let mut formats = Vec::with_capacity(format_count.into());
+    for _ in 0..format_count {
+        formats.push((reader.uleb128()?, reader.uleb128()?));
+    }
+
+    let entry_count = reader.uleb128()?;
+    let mut entries = Vec::new();
+    for _ in 0..entry_count {
+        let mut entry = FileEntry {
+            path: "",
+            directory_index: 0,
+        };
+        for &(content_type, form) in &formats {
+            let value = reader.attribute(form, 0, encoding, sections)?;
+            match (content_type, value) {
+                (DW_LNCT_PATH, AttributeValue::String(path)) => entry.path = path,
+                (DW_LNCT_DIRECTORY_INDEX, AttributeValue::Unsigned(index)) => {
+                    entry.directory_index = index
+                }
+                _ => {}
+            }
+        }
+        entries.push(Ok(entry));
+    }
+
+    Ok(entries)
+}
+
+/// A row of a line table: the registers of the line program's state machine
+/// when a row was emitted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineRow {
+    address: u64,
+    file: u64,
+    line: u64,
+    column: u64,
+    is_stmt: bool,
+    end_sequence: bool,
+}
+
+impl LineRow {
+    fn initial(default_is_stmt: bool) -> Self {
+        Self {
+            address: 0,
+            file: 1,
+            line: 1,
+            column: 0,
+            is_stmt: default_is_stmt,
+            end_sequence: false,
+        }
+    }
+
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Index of the row's file in the program's file table.
+    pub fn file(&self) -> u64 {
+        self.file
+    }
+
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    pub fn column(&self) -> u64 {
+        self.column
+    }
+
+    /// Whether the row is a recommended breakpoint location, e.g. the start
+    /// of a statement.
+    pub fn is_stmt(&self) -> bool {
+        self.is_stmt
+    }
+
+    /// Whether the row marks the first address past the end of a sequence of
+    /// contiguous code, rather than the start of a line.
+    pub fn end_sequence(&self) -> bool {
+        self.end_sequence
+    }
+}
+
+pub struct LineRows<'p, 'a> {
+    program: &'p LineProgram<'a>,
+    reader: DwarfReader<'a>,
+    state: LineRow,
+    failed: bool,
+}
+
+impl LineRows<'_, '_> {
+    fn step(&mut self) -> Result<Option<LineRow>, ElfParseError> {
+        let program = self.program;
+        let minimum_instruction_length = u64::from(program.minimum_instruction_length);
+
+        while !self.reader.is_empty() {
+            let opcode = self.reader.u8()?;
+            if opcode >= program.opcode_base {
+                // Special opcodes advance both the address and the line
+                let adjusted = opcode - program.opcode_base;
+                let address_advance = u64::from(adjusted / program.line_range);
+                let line_advance =
+                    i64::from(program.line_base) + i64::from(adjusted % program.line_range);
+                self.state.address = self
+                    .state
+                    .address
+                    .wrapping_add(address_advance * minimum_instruction_length);
+                self.state.line = self.state.line.wrapping_add(line_advance as u64);
+                return Ok(Some(self.state));
+            }
+
+            match opcode {
+                0 => {
+                    let length = self.reader.uleb128()? as usize;
+                    let end = self.reader.offset.saturating_add(length);
+                    match self.reader.u8()? {
+                        DW_LNE_END_SEQUENCE => {
+                            let mut row = self.state;
+                            row.end_sequence = true;
+                            self.state = LineRow::initial(program.default_is_stmt);
+                            self.reader.offset = end;
+                            return Ok(Some(row));
+                        }
+                        DW_LNE_SET_ADDRESS => {
+                            self.state.address = self.reader.sized(length.saturating_sub(1))?
+                        }
+                        // Other extended opcodes don't affect the table's
+                        // addresses, files or lines
+                        _ => {}
+                    }
+                    self.reader.offset = end;
+                }
+                DW_LNS_COPY => return Ok(Some(self.state)),
+                DW_LNS_ADVANCE_PC => {
+                    let advance = self.reader.uleb128()?;
+                    self.state.address = self
+                        .state
+                        .address
+                        .wrapping_add(advance.wrapping_mul(minimum_instruction_length));
+                }
+                DW_LNS_ADVANCE_LINE => {
+                    let advance = self.reader.sleb128()?;
+                    self.state.line = self.state.line.wrapping_add(advance as u64);
+                }
+                DW_LNS_SET_FILE => self.state.file = self.reader.uleb128()?,
+                DW_LNS_SET_COLUMN => self.state.column = self.reader.uleb128()?,
+                DW_LNS_NEGATE_STMT => self.state.is_stmt = !self.state.is_stmt,
+                DW_LNS_CONST_ADD_PC => {
+                    let advance = u64::from((255 - program.opcode_base) / program.line_range);
+                    self.state.address = self
+                        .state
+                        .address
+                        .wrapping_add(advance * minimum_instruction_length);
+                }
+                DW_LNS_FIXED_ADVANCE_PC => {
+                    let advance = self.reader.u16()?;
+                    self.state.address = self.state.address.wrapping_add(advance.into());
+                }
+                // Other standard opcodes only set flags the table doesn't
+                // keep, so their operands are skipped
+                _ => {
+                    let operands = program.standard_opcode_lengths[usize::from(opcode) - 1];
+                    for _ in 0..operands {
+                        self.reader.uleb128()?;
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl Iterator for LineRows<'_, '_> {
+    type Item = Result<LineRow, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        match self.step() {
+            Ok(row) => row.map(Ok),
+            Err(error) => {
+                self.failed = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf64::Elf64File;
+
+    fn line_program(file_bytes: &[u8]) -> LineProgram<'_> {
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let line_info = elf_file
+            .line_info()
+            .expect("Failed to read debug sections")
+            .expect("Expected debug information");
+        let program = line_info
+            .line_programs()
+            .next()
+            .expect("Expected a line program");
+        program.expect("Failed to parse line program")
+    }
+
+    #[test]
+    fn parse_dwarf_4_line_program() {
+        let file_bytes = include_bytes!("../test_files/line_info_dwarf4.elf64");
+        let program = line_program(file_bytes);
+        assert_eq!(4, program.version());
+
+        assert_eq!(None, program.file(0));
+        assert_eq!(Some("line_info.c"), program.file(1).map(FileEntry::path));
+        assert_eq!(Some("line_info.h"), program.file(2).map(FileEntry::path));
+        assert_eq!(Some("/build"), program.directory(0, Some("/build")));
+
+        let rows: Vec<_> = program
+            .rows()
+            .map(|row| row.expect("Failed to run line program"))
+            .map(|row| (row.address(), row.file(), row.line(), row.end_sequence()))
+            .collect();
+        assert_eq!(18, rows.len());
+        assert_eq!((0x401106, 2, 2, false), rows[0]);
+        assert_eq!((0x401115, 1, 7, false), rows[3]);
+        assert_eq!((0x40113d, 1, 9, false), rows[9]);
+        assert_eq!((0x401167, 1, 18, true), rows[17]);
+    }
+
+    #[test]
+    fn parse_dwarf_5_line_program() {
+        let file_bytes = include_bytes!("../test_files/line_info_dwarf5.elf64");
+        let program = line_program(file_bytes);
+        assert_eq!(5, program.version());
+
+        // The file table starts with the primary source file
+        assert_eq!(Some("line_info.c"), program.file(0).map(FileEntry::path));
+        assert_eq!(Some("line_info.h"), program.file(1).map(FileEntry::path));
+        assert_eq!(Some(0), program.file(1).map(FileEntry::directory_index));
+        assert_eq!(
+            Some("/root/crate/src/elf/test_files"),
+            program.directory(0, None)
+        );
+
+        let rows: Vec<_> = program
+            .rows()
+            .map(|row| row.expect("Failed to run line program"))
+            .collect();
+        assert_eq!(18, rows.len());
+        assert_eq!(0x401106, rows[0].address());
+        assert_eq!(1, rows[0].file());
+        assert_eq!(2, rows[0].line());
+        assert_eq!(1, rows[0].column());
+        assert!(rows[0].is_stmt());
+        assert_eq!(0x401130, rows[7].address());
+        assert_eq!(10, rows[7].line());
+        assert!(rows[17].end_sequence());
+    }
+
+    #[test]
+    fn reject_unsupported_line_program_version() {
+        let file_bytes = include_bytes!("../test_files/line_info_dwarf5.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let mut sections = elf_file.line_info().unwrap().unwrap().sections;
+        let mut debug_line = sections.debug_line.to_vec();
+        debug_line[4] = 6;
+        sections.debug_line = &debug_line;
+
+        assert!(matches!(
+            LineProgram::parse(&sections, 0, Endian::Little),
+            Err(ElfParseError::UnsupportedDwarfVersion(6))
+        ));
+    }
+}
@@ -0,0 +1,805 @@
+use core::fmt::Debug;
+
+use self::sections::{Elf32SectionHeader, Elf32SectionHeaderIterator};
+
+use super::{
+    byte_at, bytes_at, check_table_bounds,
+    elf64::{sections::SectionHeaderType, Elf64ProgramHeader},
+    string_table::StringTable,
+    ElfFileClass, ElfParseError, ElfType, Endian, InstructionSet, ProgramHeaderFlags,
+    ProgramSegmentType,
+};
+
+pub mod sections;
+
+/// Size of the ELF header of a 32-bit file.
+const HEADER_SIZE: usize = 52;
+/// Size of a single entry in the program header table.
+const PROGRAM_HEADER_ENTRY_SIZE: u16 = 32;
+/// Size of a single entry in the section header table.
+const SECTION_HEADER_ENTRY_SIZE: u16 = 40;
+
+#[derive(Debug, PartialEq)]
+pub struct Elf32ProgramHeaderSummary {
+    table_position: u32,
+    entry_size: u16,
+    entry_count: u16,
+}
+
+impl Elf32ProgramHeaderSummary {
+    // TODO: is usize correct?
+    fn byte_offset(&self, entry_index: u16) -> Option<usize> {
+        if entry_index < self.entry_count {
+            Some(
+                (self.table_position as u64 + (self.entry_size as u64) * (entry_index as u64))
+                    as usize,
+            )
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Elf32SectionHeaderSummary {
+    table_position: u32,
+    entry_size: u16,
+    entry_count: u16,
+    names_index: u16,
+}
+
+impl Elf32SectionHeaderSummary {
+    fn byte_offset(&self, entry_index: u16) -> Option<usize> {
+        if entry_index < self.entry_count {
+            Some(
+                (self.table_position as u64 + (self.entry_size as u64) * (entry_index as u64))
+                    as usize,
+            )
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Elf32Header {
+    endianness: Endian,
+    header_version: u8,
+    os_abi: u8,
+    elf_type: ElfType,
+    instruction_set: InstructionSet,
+    elf_version: u32,
+    program_entry_position: u32,
+
+    /// Required for elf_type Executable, but not for Relocatable
+    program_header_summary: Option<Elf32ProgramHeaderSummary>,
+    section_header_summary: Elf32SectionHeaderSummary,
+}
+
+#[derive(PartialEq)]
+pub struct Elf32File<'a> {
+    file_bytes: &'a [u8],
+    header: Elf32Header,
+}
+
+impl<'a> Elf32File<'a> {
+    pub fn from_bytes(file_bytes: &'a [u8]) -> Result<Self, ElfParseError> {
+        let class = ElfFileClass::from_bytes(file_bytes)?;
+        if class != ElfFileClass::Elf32 {
+            return Err(ElfParseError::WrongElfClass);
+        }
+
+        let header = Self::parse_header(file_bytes)?;
+        Self::check_header_tables(file_bytes, &header)?;
+
+        Ok(Self { file_bytes, header })
+    }
+
+    fn parse_header(file_bytes: &'a [u8]) -> Result<Elf32Header, ElfParseError> {
+        if file_bytes.len() < HEADER_SIZE {
+            return Err(ElfParseError::Truncated {
+                offset: 0,
+                needed: HEADER_SIZE,
+            });
+        }
+
+        let endianness = Endian::from_byte(byte_at(file_bytes, 5)?)?;
+        let instruction_set = InstructionSet::try_from(endianness.get_u16(file_bytes, 18)?)?;
+        let elf_type = ElfType::try_from(endianness.get_u16(file_bytes, 16)?)?;
+        let program_entry_position = endianness.get_u32(file_bytes, 24)?;
+
+        Ok(Elf32Header {
+            endianness,
+            header_version: byte_at(file_bytes, 6)?,
+            os_abi: byte_at(file_bytes, 7)?,
+            elf_type,
+            instruction_set,
+            elf_version: endianness.get_u32(file_bytes, 20)?,
+            program_entry_position,
+            program_header_summary: Self::parse_program_header_summary(file_bytes, &endianness)?,
+            section_header_summary: Elf32SectionHeaderSummary {
+                table_position: endianness.get_u32(file_bytes, 32)?,
+                entry_size: endianness.get_u16(file_bytes, 46)?,
+                entry_count: endianness.get_u16(file_bytes, 48)?,
+                names_index: endianness.get_u16(file_bytes, 50)?,
+            },
+        })
+    }
+
+    fn parse_program_header_summary(
+        file_bytes: &'a [u8],
+        endianness: &Endian,
+    ) -> Result<Option<Elf32ProgramHeaderSummary>, ElfParseError> {
+        let table_position = endianness.get_u32(file_bytes, 28)?;
+
+        if table_position == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(Elf32ProgramHeaderSummary {
+                table_position,
+                entry_size: endianness.get_u16(file_bytes, 42)?,
+                entry_count: endianness.get_u16(file_bytes, 44)?,
+            }))
+        }
+    }
+
+    /// Make sure the program and section header tables lie within the file, so
+    /// iterating over them can't read past its end.
+    fn check_header_tables(file_bytes: &[u8], header: &Elf32Header) -> Result<(), ElfParseError> {
+        if let Some(summary) = &header.program_header_summary {
+            check_table_bounds(
+                file_bytes.len(),
+                summary.table_position as u64,
+                summary.entry_size,
+                summary.entry_count,
+                PROGRAM_HEADER_ENTRY_SIZE,
+            )?;
+        }
+
+        let summary = &header.section_header_summary;
+        check_table_bounds(
+            file_bytes.len(),
+            summary.table_position as u64,
+            summary.entry_size,
+            summary.entry_count,
+            SECTION_HEADER_ENTRY_SIZE,
+        )
+    }
+
+    pub(super) fn file_bytes(&self) -> &'a [u8] {
+        self.file_bytes
+    }
+
+    /// Virtual address of the first instruction to execute once the file is
+    /// loaded, or 0 if the file has no entry point.
+    pub fn entry_point(&self) -> u32 {
+        self.header.program_entry_position
+    }
+
+    pub fn elf_type(&self) -> &ElfType {
+        &self.header.elf_type
+    }
+
+    pub fn instruction_set(&self) -> &InstructionSet {
+        &self.header.instruction_set
+    }
+
+    /// Byte order of the file's multi-byte values.
+    pub fn endianness(&self) -> Endian {
+        self.header.endianness
+    }
+
+    pub fn program_headers(&self) -> Option<Elf32ProgramHeaderIterator<'_>> {
+        self.header
+            .program_header_summary
+            .as_ref()
+            .map(|header_summary| {
+                Elf32ProgramHeaderIterator::new(
+                    self.file_bytes,
+                    &self.header.endianness,
+                    header_summary,
+                )
+            })
+    }
+
+    pub fn section_headers(&self) -> Result<Elf32SectionHeaderIterator<'_>, ElfParseError> {
+        Elf32SectionHeaderIterator::new(
+            self.file_bytes,
+            self.header.endianness,
+            &self.header.section_header_summary,
+        )
+    }
+
+    /// Parse the section header at `index`, keeping its addresses, offsets
+    /// and sizes 32 bits wide. `SectionHeader::from` widens it.
+    pub fn section_header(&self, index: u16) -> Result<Elf32SectionHeader, ElfParseError> {
+        Elf32SectionHeader::parse(
+            self.file_bytes,
+            self.header.endianness,
+            &self.header.section_header_summary,
+            index,
+        )
+    }
+
+    /// Returns the bytes of the file that hold the contents of `section`.
+    /// `NoBits` sections occupy no space in the file, so their data is empty.
+    pub fn section_data(&self, section: &Elf32SectionHeader) -> Result<&'a [u8], ElfParseError> {
+        if *section.header_type() == SectionHeaderType::NoBits {
+            return Ok(&[]);
+        }
+
+        bytes_at(
+            self.file_bytes,
+            section.section_file_offset() as usize,
+            section.section_size() as usize,
+        )
+    }
+
+    /// Returns the bytes of the file that hold the initialized data of
+    /// `segment`, i.e. its first `p_filesz` bytes.
+    pub fn segment_data(&self, segment: &Elf32ProgramHeader) -> Result<&'a [u8], ElfParseError> {
+        bytes_at(
+            self.file_bytes,
+            segment.p_offset as usize,
+            segment.p_filesz as usize,
+        )
+    }
+
+    /// Returns the section header string table, which holds the names of all
+    /// sections in the file.
+    pub fn section_names(&self) -> Result<StringTable<'a>, ElfParseError> {
+        let names_index = self.header.section_header_summary.names_index;
+        // Index 0 is SHN_UNDEF, meaning the file has no section name table
+        if names_index == 0 {
+            return Err(ElfParseError::MissingStringTable);
+        }
+
+        let section = self
+            .section_header(names_index)
+            .map_err(|_| ElfParseError::MissingStringTable)?;
+        if *section.header_type() != SectionHeaderType::StringTable {
+            return Err(ElfParseError::MissingStringTable);
+        }
+
+        let data = self
+            .section_data(&section)
+            .map_err(|_| ElfParseError::MissingStringTable)?;
+
+        Ok(StringTable::new(data))
+    }
+
+    /// Find the first section whose name is `name`, e.g. ".text".
+    pub fn section_by_name(&self, name: &str) -> Result<Option<Elf32SectionHeader>, ElfParseError> {
+        let section_names = self.section_names()?;
+
+        for section in self.section_headers()? {
+            let section = section?;
+            if section.name(&section_names)? == name {
+                return Ok(Some(section));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl<'a> Debug for Elf32File<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self.header))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Elf32ProgramHeader {
+    segment_type: ProgramSegmentType,
+    flags: ProgramHeaderFlags,
+
+    /// The offset in the file that the data for this segment can be found.
+    p_offset: u32,
+
+    /// Where you should start to put this segment in virtual memory.
+    p_vaddr: u32,
+
+    /// Size of the segment in the file.
+    p_filesz: u32,
+
+    /// Size of the segment in memory. This can be 0. If the p_filesz and
+    /// p_memsz members differ, this indicates that the segment is padded with
+    /// zeros. All bytes in memory between the ending offset of the file size,
+    /// and the segment's virtual memory size are to be cleared with zeros.
+    p_memsz: u32,
+
+    /// Required alignment for this section. Must be a power of 2.
+    alignment: u32,
+}
+
+impl Elf32ProgramHeader {
+    pub fn new(
+        segment_type: ProgramSegmentType,
+        flags: ProgramHeaderFlags,
+        p_offset: u32,
+        p_vaddr: u32,
+        p_filesz: u32,
+        p_memsz: u32,
+        alignment: u32,
+    ) -> Self {
+        Self {
+            segment_type,
+            flags,
+            p_offset,
+            p_vaddr,
+            p_filesz,
+            p_memsz,
+            alignment,
+        }
+    }
+
+    pub fn segment_type(&self) -> &ProgramSegmentType {
+        &self.segment_type
+    }
+
+    pub fn flags(&self) -> &ProgramHeaderFlags {
+        &self.flags
+    }
+
+    pub fn p_offset(&self) -> u32 {
+        self.p_offset
+    }
+
+    pub fn p_vaddr(&self) -> u32 {
+        self.p_vaddr
+    }
+
+    pub fn p_filesz(&self) -> u32 {
+        self.p_filesz
+    }
+
+    pub fn p_memsz(&self) -> u32 {
+        self.p_memsz
+    }
+
+    pub fn alignment(&self) -> u32 {
+        self.alignment
+    }
+}
+
+impl From<Elf32ProgramHeader> for Elf64ProgramHeader {
+    fn from(header: Elf32ProgramHeader) -> Self {
+        Elf64ProgramHeader::new(
+            header.segment_type,
+            header.flags,
+            header.p_offset as u64,
+            header.p_vaddr as u64,
+            header.p_filesz as u64,
+            header.p_memsz as u64,
+            header.alignment as u64,
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Elf32ProgramHeaderIterator<'a> {
+    current_index: u16,
+    data: &'a [u8],
+    endianness: &'a Endian,
+    header_summary: &'a Elf32ProgramHeaderSummary,
+    program_header_entry_seen: bool,
+}
+
+impl<'a> Elf32ProgramHeaderIterator<'a> {
+    pub fn new(
+        data: &'a [u8],
+        endianness: &'a Endian,
+        header_summary: &'a Elf32ProgramHeaderSummary,
+    ) -> Self {
+        Self {
+            current_index: 0,
+            data,
+            endianness,
+            header_summary,
+            program_header_entry_seen: false,
+        }
+    }
+}
+
+impl<'a> Elf32ProgramHeaderIterator<'a> {
+    fn parse_program_header(
+        &self,
+        byte_offset: usize,
+    ) -> Result<Elf32ProgramHeader, ElfParseError> {
+        let header = Elf32ProgramHeader {
+            segment_type: ProgramSegmentType::from(
+                self.endianness.get_u32(self.data, byte_offset)?,
+            ),
+            flags: ProgramHeaderFlags::from(self.endianness.get_u32(self.data, byte_offset + 24)?),
+            p_offset: self.endianness.get_u32(self.data, byte_offset + 4)?,
+            p_vaddr: self.endianness.get_u32(self.data, byte_offset + 8)?,
+            p_filesz: self.endianness.get_u32(self.data, byte_offset + 16)?,
+            p_memsz: self.endianness.get_u32(self.data, byte_offset + 20)?,
+            alignment: self.endianness.get_u32(self.data, byte_offset + 28)?,
+        };
+
+        if !header.alignment.is_power_of_two() {
+            return Err(ElfParseError::InvalidProgramHeaderAlignment);
+        }
+
+        Ok(header)
+    }
+}
+
+impl<'a> Iterator for Elf32ProgramHeaderIterator<'a> {
+    type Item = Result<Elf32ProgramHeader, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let byte_offset = self.header_summary.byte_offset(self.current_index)?;
+        // Move past this entry even if it fails to parse, so a malformed entry
+        // can't stall the iterator
+        self.current_index += 1;
+
+        let header = match self.parse_program_header(byte_offset) {
+            Ok(header) => header,
+            Err(error) => return Some(Err(error)),
+        };
+
+        if header.segment_type == ProgramSegmentType::ProgramHeader {
+            if self.program_header_entry_seen {
+                return Some(Err(ElfParseError::MultipleProgramHeaderEntriesFound));
+            }
+
+            self.program_header_entry_seen = true;
+        }
+
+        Some(Ok(header))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf64::sections::{SectionHeader, SectionHeaderFlags};
+
+    #[test]
+    fn parse_hello_world_32_bit_executable() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf32");
+        let elf_file =
+            Elf32File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let expected_elf = Elf32File {
+            file_bytes,
+            header: Elf32Header {
+                endianness: Endian::Little,
+                header_version: 1,
+                os_abi: 0,
+                elf_type: ElfType::Executable,
+                instruction_set: InstructionSet::NoSpecific,
+                elf_version: 1,
+                program_entry_position: 0x401040,
+                program_header_summary: Some(Elf32ProgramHeaderSummary {
+                    table_position: 52,
+                    entry_size: 32,
+                    entry_count: 10,
+                }),
+                section_header_summary: Elf32SectionHeaderSummary {
+                    table_position: 13624,
+                    entry_size: 40,
+                    entry_count: 29,
+                    names_index: 28,
+                },
+            },
+        };
+
+        assert_eq!(expected_elf, elf_file);
+        assert_eq!(10, elf_file.program_headers().unwrap().count());
+
+        let mut headers = elf_file.program_headers().unwrap();
+        let expected_program_header = Elf32ProgramHeader::new(
+            ProgramSegmentType::ProgramHeader,
+            ProgramHeaderFlags::new(false, false, true),
+            0x34,
+            0x400034,
+            0x140,
+            0x140,
+            0x4,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf32ProgramHeader::new(
+            ProgramSegmentType::Interpret,
+            ProgramHeaderFlags::new(false, false, true),
+            0x2A8,
+            0x4002A8,
+            0x1C,
+            0x1C,
+            0x1,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf32ProgramHeader::new(
+            ProgramSegmentType::Load,
+            ProgramHeaderFlags::new(false, false, true),
+            0,
+            0x400000,
+            0x440,
+            0x440,
+            0x1000,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf32ProgramHeader::new(
+            ProgramSegmentType::Load,
+            ProgramHeaderFlags::new(true, false, true),
+            0x1000,
+            0x401000,
+            0x1BD,
+            0x1BD,
+            0x1000,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf32ProgramHeader::new(
+            ProgramSegmentType::Load,
+            ProgramHeaderFlags::new(false, false, true),
+            0x2000,
+            0x402000,
+            0x150,
+            0x150,
+            0x1000,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf32ProgramHeader::new(
+            ProgramSegmentType::Load,
+            ProgramHeaderFlags::new(false, true, true),
+            0x2E00,
+            0x403E00,
+            0x230,
+            0x238,
+            0x1000,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf32ProgramHeader::new(
+            ProgramSegmentType::Dynamic,
+            ProgramHeaderFlags::new(false, true, true),
+            0x2E10,
+            0x403E10,
+            0x1E0,
+            0x1E0,
+            0x8,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf32ProgramHeader::new(
+            ProgramSegmentType::Note,
+            ProgramHeaderFlags::new(false, false, true),
+            0x2C4,
+            0x4002C4,
+            0x20,
+            0x20,
+            0x4,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf32ProgramHeader::new(
+            ProgramSegmentType::GnuEhFrame,
+            ProgramHeaderFlags::new(false, false, true),
+            0x2010,
+            0x402010,
+            0x3C,
+            0x3C,
+            0x4,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf32ProgramHeader::new(
+            ProgramSegmentType::GnuStack,
+            ProgramHeaderFlags::new(false, true, true),
+            0,
+            0,
+            0,
+            0,
+            0x4,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        assert_eq!(0, headers.count());
+    }
+
+    #[test]
+    fn resolve_hello_world_32_bit_section_names() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf32");
+        let elf_file =
+            Elf32File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let section_names = elf_file
+            .section_names()
+            .expect("Failed to find section name table");
+
+        let expected_names = [
+            "",
+            ".interp",
+            ".note.ABI-tag",
+            ".hash",
+            ".gnu.hash",
+            ".dynsym",
+            ".dynstr",
+            ".gnu.version",
+            ".gnu.version_r",
+            ".rela.dyn",
+            ".rela.plt",
+            ".init",
+            ".plt",
+            ".text",
+            ".fini",
+            ".rodata",
+            ".eh_frame_hdr",
+            ".eh_frame",
+            ".init_array",
+            ".fini_array",
+            ".dynamic",
+            ".got",
+            ".got.plt",
+            ".data",
+            ".bss",
+            ".comment",
+            ".symtab",
+            ".strtab",
+            ".shstrtab",
+        ];
+        let section_headers = elf_file
+            .section_headers()
+            .expect("Failed to create section iterator");
+
+        assert_eq!(
+            expected_names.len(),
+            elf_file.section_headers().unwrap().count()
+        );
+        for (expected_name, section) in expected_names.iter().zip(section_headers) {
+            let section = section.expect("Failed to parse section header");
+            assert_eq!(
+                Ok(*expected_name),
+                section.name(&section_names),
+                "Unexpected section name"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_hello_world_32_bit_section_headers() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf32");
+        let elf_file =
+            Elf32File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        let expected = SectionHeader::new(
+            0,
+            SectionHeaderType::Null,
+            SectionHeaderFlags::empty(),
+            None,
+            0,
+            0,
+            None,
+            None,
+            0,
+            None,
+        );
+        let section_header = |index| elf_file.section_header(index).map(SectionHeader::from);
+        assert_eq!(Ok(expected), section_header(0));
+
+        let expected = SectionHeader::new(
+            0x81,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::ALLOC | SectionHeaderFlags::EXECUTABLE_INSTRUCTIONS,
+            Some(0x401040),
+            0x1040,
+            0x171,
+            None,
+            None,
+            16,
+            None,
+        );
+        assert_eq!(Ok(expected), section_header(13));
+
+        let symbol_table = elf_file
+            .section_header(26)
+            .expect("Failed to parse section header");
+        assert_eq!(SectionHeaderType::SymbolTable, *symbol_table.header_type());
+        assert_eq!(0x3044, symbol_table.section_file_offset());
+        assert_eq!(0x240, symbol_table.section_size());
+        assert_eq!(Some(27), symbol_table.section_link_index());
+        assert_eq!(Some(0x10), symbol_table.section_entry_size());
+
+        let bss = elf_file
+            .section_header(24)
+            .expect("Failed to parse section header");
+        assert_eq!(SectionHeaderType::NoBits, *bss.header_type());
+        assert_eq!(Ok(&[][..]), elf_file.section_data(&bss));
+
+        assert_eq!(
+            Err(ElfParseError::InvalidSectionIndex(29)),
+            elf_file.section_header(29)
+        );
+    }
+
+    #[test]
+    fn find_hello_world_32_bit_section_by_name() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf32");
+        let elf_file =
+            Elf32File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        let text = elf_file
+            .section_by_name(".text")
+            .expect("Failed to search sections")
+            .expect("Expected a .text section");
+        assert_eq!(SectionHeaderType::ProgramBits, *text.header_type());
+        assert_eq!(0x1040, text.section_file_offset());
+        assert_eq!(0x171, text.section_size());
+        assert_eq!(Ok(None), elf_file.section_by_name(".not_a_section"));
+
+        let interp = elf_file
+            .section_by_name(".interp")
+            .expect("Failed to search sections")
+            .expect("Expected an .interp section");
+        assert_eq!(
+            Ok(&b"/lib64/ld-linux-x86-64.so.2\0"[..]),
+            elf_file.section_data(&interp)
+        );
+    }
+}
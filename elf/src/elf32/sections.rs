@@ -0,0 +1,159 @@
+use core::convert::TryFrom;
+
+use crate::{
+    elf64::sections::{SectionHeader, SectionHeaderFlags, SectionHeaderType},
+    string_table::StringTable,
+    ElfParseError, Endian,
+};
+
+use super::Elf32SectionHeaderSummary;
+
+/// A 32-bit section header. Its fields have the same meaning as those of the
+/// 64-bit `SectionHeader`, but addresses, offsets and sizes are 32 bits wide.
+#[derive(Debug, PartialEq)]
+pub struct Elf32SectionHeader {
+    name_index: u32,
+    header_type: SectionHeaderType,
+    flags: SectionHeaderFlags,
+    address: Option<u32>,
+    section_file_offset: u32,
+    section_size: u32,
+    section_link_index: Option<u32>,
+    info: Option<u32>,
+    address_alignment: u32,
+    section_entry_size: Option<u32>,
+}
+
+impl Elf32SectionHeader {
+    /// Read the 40-byte entry at `index` of the section header table described
+    /// by `section_header_summary`.
+    pub(super) fn parse(
+        data: &[u8],
+        endianness: Endian,
+        section_header_summary: &Elf32SectionHeaderSummary,
+        index: u16,
+    ) -> Result<Self, ElfParseError> {
+        let byte_offset = section_header_summary
+            .byte_offset(index)
+            .ok_or(ElfParseError::InvalidSectionIndex(index))?;
+        let name_index = endianness.get_u32(data, byte_offset)?;
+        let header_type = SectionHeaderType::try_from(endianness.get_u32(data, byte_offset + 4)?)?;
+        let flags = endianness.get_u32(data, byte_offset + 8)?;
+        let address = match endianness.get_u32(data, byte_offset + 12)? {
+            0 => None,
+            value => Some(value),
+        };
+        let section_file_offset = endianness.get_u32(data, byte_offset + 16)?;
+        let section_size = endianness.get_u32(data, byte_offset + 20)?;
+        let section_link_index = match endianness.get_u32(data, byte_offset + 24)? {
+            0 => None,
+            value => Some(value),
+        };
+        let info = match endianness.get_u32(data, byte_offset + 28)? {
+            0 => None,
+            value => Some(value),
+        };
+        let address_alignment = endianness.get_u32(data, byte_offset + 32)?;
+        let section_entry_size = match endianness.get_u32(data, byte_offset + 36)? {
+            0 => None,
+            value => Some(value),
+        };
+
+        Ok(Elf32SectionHeader {
+            name_index,
+            header_type,
+            flags: SectionHeaderFlags::from_bits_truncate(flags as u64),
+            address,
+            section_file_offset,
+            section_size,
+            section_link_index,
+            info,
+            address_alignment,
+            section_entry_size,
+        })
+    }
+
+    /// Resolve this section's name through the section header string table.
+    /// See `Elf32File::section_names`.
+    pub fn name<'a>(&self, section_names: &StringTable<'a>) -> Result<&'a str, ElfParseError> {
+        section_names.get(self.name_index)
+    }
+
+    pub fn header_type(&self) -> &SectionHeaderType {
+        &self.header_type
+    }
+
+    pub fn section_file_offset(&self) -> u32 {
+        self.section_file_offset
+    }
+
+    pub fn section_size(&self) -> u32 {
+        self.section_size
+    }
+
+    pub fn section_link_index(&self) -> Option<u32> {
+        self.section_link_index
+    }
+
+    pub fn section_entry_size(&self) -> Option<u32> {
+        self.section_entry_size
+    }
+}
+
+impl From<Elf32SectionHeader> for SectionHeader {
+    fn from(header: Elf32SectionHeader) -> Self {
+        SectionHeader::new(
+            header.name_index,
+            header.header_type,
+            header.flags,
+            header.address.map(u64::from),
+            header.section_file_offset as u64,
+            header.section_size as u64,
+            header.section_link_index,
+            header.info,
+            header.address_alignment as u64,
+            header.section_entry_size.map(u64::from),
+        )
+    }
+}
+
+pub struct Elf32SectionHeaderIterator<'a> {
+    current_index: u16,
+    data: &'a [u8],
+    endianness: Endian,
+    section_header_summary: &'a Elf32SectionHeaderSummary,
+}
+
+impl<'a> Elf32SectionHeaderIterator<'a> {
+    pub fn new(
+        data: &'a [u8],
+        endianness: Endian,
+        section_header_summary: &'a Elf32SectionHeaderSummary,
+    ) -> Result<Self, ElfParseError> {
+        Ok(Self {
+            current_index: 0,
+            data,
+            endianness,
+            section_header_summary,
+        })
+    }
+}
+
+impl<'a> Iterator for Elf32SectionHeaderIterator<'a> {
+    type Item = Result<Elf32SectionHeader, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_index == self.section_header_summary.entry_count {
+            None
+        } else {
+            let header = Elf32SectionHeader::parse(
+                self.data,
+                self.endianness,
+                self.section_header_summary,
+                self.current_index,
+            );
+            self.current_index += 1;
+            Some(header)
+        }
+    }
+}
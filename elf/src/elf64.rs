@@ -0,0 +1,2384 @@
+use core::{convert::TryInto, fmt::Debug, ops::Range};
+
+use self::{
+    dynamic::{DynamicIterator, DynamicTag, DYNAMIC_ENTRY_SIZE},
+    hash::{GnuHashTable, SysvHashTable},
+    relocations::{RelIterator, RelaIterator, RELA_ENTRY_SIZE, REL_ENTRY_SIZE},
+    sections::{SectionHeader, SectionHeaderIterator, SectionHeaderType},
+    symbols::{Elf64Symbol, SymbolIterator, SYMBOL_ENTRY_SIZE},
+};
+
+use super::{
+    byte_at, bytes_at, check_table_bounds,
+    dwarf::{DwarfSections, LineInfo},
+    notes::{GnuNote, GnuPropertyIterator, Note, NoteIterator},
+    string_table::StringTable,
+    tls::TlsTemplate,
+    ElfFileClass, ElfParseError, ElfType, Endian, InstructionSet, ProgramHeaderFlags,
+    ProgramSegmentType,
+};
+
+pub mod dynamic;
+pub mod hash;
+pub mod relocations;
+pub mod sections;
+pub mod symbols;
+
+/// Size of the ELF header of a 64-bit file.
+const HEADER_SIZE: usize = 64;
+/// Size of a single entry in the program header table.
+const PROGRAM_HEADER_ENTRY_SIZE: u16 = 56;
+/// Size of a single entry in the section header table.
+const SECTION_HEADER_ENTRY_SIZE: u16 = 64;
+
+#[derive(Debug, PartialEq)]
+pub struct Elf64ProgramHeaderSummary {
+    table_position: u64,
+    entry_size: u16,
+    entry_count: u16,
+}
+
+impl Elf64ProgramHeaderSummary {
+    // TODO: is usize correct?
+    fn byte_offset(&self, entry_index: u16) -> Option<usize> {
+        if entry_index < self.entry_count {
+            Some((self.table_position + (self.entry_size as u64) * (entry_index as u64)) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Elf64SectionHeaderSummary {
+    table_position: u64,
+    entry_size: u16,
+    entry_count: u16,
+    names_index: u16,
+}
+
+impl Elf64SectionHeaderSummary {
+    fn byte_offset(&self, entry_index: u16) -> Option<usize> {
+        if entry_index < self.entry_count {
+            Some((self.table_position + (self.entry_size as u64) * (entry_index as u64)) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Elf64Header {
+    endianness: Endian,
+    header_version: u8,
+    os_abi: u8,
+    elf_type: ElfType,
+    instruction_set: InstructionSet,
+    elf_version: u32,
+    program_entry_position: u64,
+
+    /// Required for elf_type Executable, but not for Relocatable
+    program_header_summary: Option<Elf64ProgramHeaderSummary>,
+    section_header_summary: Elf64SectionHeaderSummary,
+}
+
+#[derive(PartialEq)]
+pub struct Elf64File<'a> {
+    file_bytes: &'a [u8],
+    header: Elf64Header,
+}
+
+impl<'a> Elf64File<'a> {
+    pub fn from_bytes(file_bytes: &'a [u8]) -> Result<Self, ElfParseError> {
+        let class = ElfFileClass::from_bytes(file_bytes)?;
+        if class != ElfFileClass::Elf64 {
+            return Err(ElfParseError::WrongElfClass);
+        }
+
+        let header = Self::parse_header(file_bytes)?;
+        Self::check_header_tables(file_bytes, &header)?;
+
+        Ok(Self { file_bytes, header })
+    }
+
+    fn parse_header(file_bytes: &'a [u8]) -> Result<Elf64Header, ElfParseError> {
+        if file_bytes.len() < HEADER_SIZE {
+            return Err(ElfParseError::Truncated {
+                offset: 0,
+                needed: HEADER_SIZE,
+            });
+        }
+
+        let endianness = Endian::from_byte(byte_at(file_bytes, 5)?)?;
+        let instruction_set = InstructionSet::try_from(endianness.get_u16(file_bytes, 18)?)?;
+        let elf_type = ElfType::try_from(endianness.get_u16(file_bytes, 16)?)?;
+        let program_entry_position = endianness.get_u64(file_bytes, 24)?;
+
+        Ok(Elf64Header {
+            endianness,
+            header_version: byte_at(file_bytes, 6)?,
+            os_abi: byte_at(file_bytes, 7)?,
+            elf_type,
+            instruction_set,
+            elf_version: endianness.get_u32(file_bytes, 20)?,
+            program_entry_position,
+            program_header_summary: Self::parse_program_header_summary(file_bytes, &endianness)?,
+            section_header_summary: Elf64SectionHeaderSummary {
+                table_position: endianness.get_u64(file_bytes, 40)?,
+                entry_size: endianness.get_u16(file_bytes, 58)?,
+                entry_count: endianness.get_u16(file_bytes, 60)?,
+                names_index: endianness.get_u16(file_bytes, 62)?,
+            },
+        })
+    }
+
+    fn parse_program_header_summary(
+        file_bytes: &'a [u8],
+        endianness: &Endian,
+    ) -> Result<Option<Elf64ProgramHeaderSummary>, ElfParseError> {
+        let table_position = endianness.get_u64(file_bytes, 32)?;
+
+        if table_position == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(Elf64ProgramHeaderSummary {
+                table_position,
+                entry_size: endianness.get_u16(file_bytes, 54)?,
+                entry_count: endianness.get_u16(file_bytes, 56)?,
+            }))
+        }
+    }
+
+    /// Make sure the program and section header tables lie within the file, so
+    /// iterating over them can't read past its end.
+    fn check_header_tables(file_bytes: &[u8], header: &Elf64Header) -> Result<(), ElfParseError> {
+        if let Some(summary) = &header.program_header_summary {
+            check_table_bounds(
+                file_bytes.len(),
+                summary.table_position,
+                summary.entry_size,
+                summary.entry_count,
+                PROGRAM_HEADER_ENTRY_SIZE,
+            )?;
+        }
+
+        let summary = &header.section_header_summary;
+        check_table_bounds(
+            file_bytes.len(),
+            summary.table_position,
+            summary.entry_size,
+            summary.entry_count,
+            SECTION_HEADER_ENTRY_SIZE,
+        )
+    }
+
+    pub(super) fn file_bytes(&self) -> &'a [u8] {
+        self.file_bytes
+    }
+
+    /// Virtual address of the first instruction to execute once the file is
+    /// loaded, or 0 if the file has no entry point.
+    pub fn entry_point(&self) -> u64 {
+        self.header.program_entry_position
+    }
+
+    pub fn elf_type(&self) -> &ElfType {
+        &self.header.elf_type
+    }
+
+    pub fn instruction_set(&self) -> &InstructionSet {
+        &self.header.instruction_set
+    }
+
+    /// Byte order of the file's multi-byte values.
+    pub fn endianness(&self) -> Endian {
+        self.header.endianness
+    }
+
+    pub fn program_headers(&self) -> Option<Elf64ProgramHeaderIterator<'_>> {
+        self.header
+            .program_header_summary
+            .as_ref()
+            .map(|header_summary| {
+                Elf64ProgramHeaderIterator::new(
+                    self.file_bytes,
+                    &self.header.endianness,
+                    header_summary,
+                )
+            })
+    }
+
+    pub fn section_headers(&self) -> Result<SectionHeaderIterator<'_>, ElfParseError> {
+        SectionHeaderIterator::new(
+            self.file_bytes,
+            self.header.endianness,
+            &self.header.section_header_summary,
+        )
+    }
+
+    /// Parse the section header at `index` in the section header table.
+    pub fn section_header(&self, index: u16) -> Result<SectionHeader, ElfParseError> {
+        SectionHeader::parse(
+            self.file_bytes,
+            self.header.endianness,
+            &self.header.section_header_summary,
+            index,
+        )
+    }
+
+    /// Returns the bytes of the file that hold the contents of `section`.
+    /// `NoBits` sections occupy no space in the file, so their data is empty.
+    pub fn section_data(&self, section: &SectionHeader) -> Result<&'a [u8], ElfParseError> {
+        if *section.header_type() == SectionHeaderType::NoBits {
+            return Ok(&[]);
+        }
+
+        bytes_at(
+            self.file_bytes,
+            section.section_file_offset() as usize,
+            section.section_size() as usize,
+        )
+    }
+
+    /// Returns the bytes of the file that hold the initialized data of
+    /// `segment`, i.e. its first `p_filesz` bytes.
+    pub fn segment_data(&self, segment: &Elf64ProgramHeader) -> Result<&'a [u8], ElfParseError> {
+        bytes_at(
+            self.file_bytes,
+            segment.p_offset as usize,
+            segment.p_filesz as usize,
+        )
+    }
+
+    /// Returns the section header string table, which holds the names of all
+    /// sections in the file.
+    pub fn section_names(&self) -> Result<StringTable<'a>, ElfParseError> {
+        let names_index = self.header.section_header_summary.names_index;
+        // Index 0 is SHN_UNDEF, meaning the file has no section name table
+        if names_index == 0 {
+            return Err(ElfParseError::MissingStringTable);
+        }
+
+        self.string_table(names_index as u32)
+    }
+
+    /// Returns the string table held by the section at `index`.
+    fn string_table(&self, index: u32) -> Result<StringTable<'a>, ElfParseError> {
+        let index: u16 = index
+            .try_into()
+            .map_err(|_| ElfParseError::MissingStringTable)?;
+        let section = self
+            .section_header(index)
+            .map_err(|_| ElfParseError::MissingStringTable)?;
+        if *section.header_type() != SectionHeaderType::StringTable {
+            return Err(ElfParseError::MissingStringTable);
+        }
+
+        let data = self
+            .section_data(&section)
+            .map_err(|_| ElfParseError::MissingStringTable)?;
+
+        Ok(StringTable::new(data))
+    }
+
+    /// Find the first section with the given type, along with its index in the
+    /// section header table.
+    fn find_section(
+        &self,
+        header_type: SectionHeaderType,
+    ) -> Result<Option<(u16, SectionHeader)>, ElfParseError> {
+        for (index, section) in self.section_headers()?.enumerate() {
+            let section = section?;
+            if *section.header_type() == header_type {
+                return Ok(Some((index as u16, section)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Iterate over the full symbol table (`SymbolTable`), if the file has
+    /// one. Stripped files only have `dynamic_symbols`.
+    pub fn symbols(&self) -> Result<Option<SymbolIterator<'a>>, ElfParseError> {
+        self.symbol_table(SectionHeaderType::SymbolTable)
+    }
+
+    /// Iterate over the minimal set of symbols needed for dynamic linking
+    /// (`DynamicSymbols`), if the file has them.
+    pub fn dynamic_symbols(&self) -> Result<Option<SymbolIterator<'a>>, ElfParseError> {
+        self.symbol_table(SectionHeaderType::DynamicSymbols)
+    }
+
+    fn symbol_table(
+        &self,
+        header_type: SectionHeaderType,
+    ) -> Result<Option<SymbolIterator<'a>>, ElfParseError> {
+        match self.find_section(header_type)? {
+            Some((table_index, table)) => self.symbol_table_at(table_index, &table).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Iterate over the symbols of `table`, the section at `table_index`.
+    fn symbol_table_at(
+        &self,
+        table_index: u16,
+        table: &SectionHeader,
+    ) -> Result<SymbolIterator<'a>, ElfParseError> {
+        let names = self.string_table(
+            table
+                .section_link_index()
+                .ok_or(ElfParseError::MissingStringTable)?,
+        )?;
+
+        // A SymbolTableSectionHeaderIndex section links back to the symbol
+        // table whose SHN_XINDEX entries it resolves
+        let mut extended_section_indexes = None;
+        for section in self.section_headers()? {
+            let section = section?;
+            if *section.header_type() == SectionHeaderType::SymbolTableSectionHeaderIndex
+                && section.section_link_index() == Some(table_index as u32)
+            {
+                extended_section_indexes = Some(self.section_data(&section)?);
+                break;
+            }
+        }
+
+        SymbolIterator::new(
+            self.section_data(table)?,
+            table.section_entry_size().unwrap_or(SYMBOL_ENTRY_SIZE),
+            self.header.endianness,
+            names,
+            extended_section_indexes,
+        )
+    }
+
+    /// Iterate over the symbols of the symbol table `section` links to, e.g.
+    /// the table a `RelA` section's relocations refer to.
+    pub fn linked_symbols(
+        &self,
+        section: &SectionHeader,
+    ) -> Result<SymbolIterator<'a>, ElfParseError> {
+        let table_index = section
+            .section_link_index()
+            .ok_or(ElfParseError::MissingSymbolTable)?;
+        let table_index: u16 = table_index
+            .try_into()
+            .map_err(|_| ElfParseError::MissingSymbolTable)?;
+        let table = self.section_header(table_index)?;
+        match table.header_type() {
+            SectionHeaderType::SymbolTable | SectionHeaderType::DynamicSymbols => {
+                self.symbol_table_at(table_index, &table)
+            }
+            _ => Err(ElfParseError::MissingSymbolTable),
+        }
+    }
+
+    /// Iterate over the relocations of a `RelA` section.
+    pub fn rela_entries(&self, section: &SectionHeader) -> Result<RelaIterator<'a>, ElfParseError> {
+        if *section.header_type() != SectionHeaderType::RelA {
+            return Err(ElfParseError::WrongSectionType);
+        }
+
+        RelaIterator::new(
+            self.section_data(section)?,
+            section.section_entry_size().unwrap_or(RELA_ENTRY_SIZE),
+            self.header.endianness,
+        )
+    }
+
+    /// Iterate over the relocations of a `Rel` section.
+    pub fn rel_entries(&self, section: &SectionHeader) -> Result<RelIterator<'a>, ElfParseError> {
+        if *section.header_type() != SectionHeaderType::Rel {
+            return Err(ElfParseError::WrongSectionType);
+        }
+
+        RelIterator::new(
+            self.section_data(section)?,
+            section.section_entry_size().unwrap_or(REL_ENTRY_SIZE),
+            self.header.endianness,
+        )
+    }
+
+    /// Iterate over the entries of the dynamic section, if the file has one.
+    ///
+    /// The dynamic section is found through its section header, or through
+    /// the `Dynamic` program header if the file has no section headers. Names
+    /// held by the entries are resolved through the dynamic string table.
+    pub fn dynamic_entries(&self) -> Result<Option<DynamicIterator<'a>>, ElfParseError> {
+        if let Some((_, section)) = self.find_section(SectionHeaderType::Dynamic)? {
+            let names = match section.section_link_index() {
+                Some(index) => Some(self.string_table(index)?),
+                None => None,
+            };
+
+            return DynamicIterator::new(
+                self.section_data(&section)?,
+                section.section_entry_size().unwrap_or(DYNAMIC_ENTRY_SIZE),
+                self.header.endianness,
+                names,
+            )
+            .map(Some);
+        }
+
+        let data = match self.find_segment(ProgramSegmentType::Dynamic)? {
+            Some(segment) => self.segment_data(&segment)?,
+            None => return Ok(None),
+        };
+
+        // Without section headers, the string table can only be found through
+        // the StringTable and StringTableSize entries
+        let mut names_address = None;
+        let mut names_size = None;
+        for entry in DynamicIterator::new(data, DYNAMIC_ENTRY_SIZE, self.header.endianness, None)? {
+            // Entries that name something fail to parse without a string table
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(ElfParseError::MissingStringTable) => continue,
+                Err(error) => return Err(error),
+            };
+            match entry.tag() {
+                DynamicTag::StringTable => names_address = Some(entry.value()),
+                DynamicTag::StringTableSize => names_size = Some(entry.value()),
+                _ => {}
+            }
+        }
+        let names = match (names_address, names_size) {
+            (Some(address), Some(size)) => {
+                Some(StringTable::new(self.virtual_address_data(address, size)?))
+            }
+            _ => None,
+        };
+
+        DynamicIterator::new(data, DYNAMIC_ENTRY_SIZE, self.header.endianness, names).map(Some)
+    }
+
+    /// Iterate over the names of the shared libraries this file depends on,
+    /// i.e. its `Needed` dynamic entries. Statically linked files have none.
+    pub fn needed_libraries(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<&'a str, ElfParseError>>, ElfParseError> {
+        Ok(self
+            .dynamic_entries()?
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| match entry {
+                Ok(entry) if entry.tag() == DynamicTag::Needed => entry.name().map(Ok),
+                Ok(_) => None,
+                Err(error) => Some(Err(error)),
+            }))
+    }
+
+    /// Find the first program header with the given type.
+    fn find_segment(
+        &self,
+        segment_type: ProgramSegmentType,
+    ) -> Result<Option<Elf64ProgramHeader>, ElfParseError> {
+        for header in self.program_headers().into_iter().flatten() {
+            let header = header?;
+            if *header.segment_type() == segment_type {
+                return Ok(Some(header));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Whether the `GnuStack` segment asks for an executable stack. Files
+    /// without a `GnuStack` segment get a non-executable stack.
+    pub fn requests_executable_stack(&self) -> Result<bool, ElfParseError> {
+        Ok(self
+            .find_segment(ProgramSegmentType::GnuStack)?
+            .is_some_and(|header| header.flags().executable()))
+    }
+
+    /// The initialization image of the file's thread-local storage, if it has
+    /// a `Tls` segment.
+    pub fn tls_template(&self) -> Result<Option<TlsTemplate>, ElfParseError> {
+        Ok(self
+            .find_segment(ProgramSegmentType::Tls)?
+            .map(|header| TlsTemplate::from(&header)))
+    }
+
+    /// The virtual address range of the `GnuRelro` segment, which should be
+    /// made read-only once relocations have been applied.
+    pub fn relro_range(&self) -> Result<Option<Range<u64>>, ElfParseError> {
+        Ok(self
+            .find_segment(ProgramSegmentType::GnuRelro)?
+            .map(|header| header.p_vaddr()..header.p_vaddr().saturating_add(header.p_memsz())))
+    }
+
+    /// Returns the `size` bytes of file data that are loaded at the virtual
+    /// address `address`. The whole range must lie within the file data of a
+    /// single `Load` segment.
+    pub fn virtual_address_data(&self, address: u64, size: u64) -> Result<&'a [u8], ElfParseError> {
+        let headers = self
+            .program_headers()
+            .ok_or(ElfParseError::UnmappedAddress(address))?;
+
+        for header in headers {
+            let header = header?;
+            if *header.segment_type() != ProgramSegmentType::Load || address < header.p_vaddr() {
+                continue;
+            }
+
+            let offset = address - header.p_vaddr();
+            match offset.checked_add(size) {
+                Some(end) if end <= header.p_filesz() => {
+                    return bytes_at(self.segment_data(&header)?, offset as usize, size as usize);
+                }
+                _ => continue,
+            }
+        }
+
+        Err(ElfParseError::UnmappedAddress(address))
+    }
+
+    /// Iterate over the notes held by a `Note` section.
+    pub fn section_notes(
+        &self,
+        section: &SectionHeader,
+    ) -> Result<NoteIterator<'a>, ElfParseError> {
+        if *section.header_type() != SectionHeaderType::Note {
+            return Err(ElfParseError::WrongSectionType);
+        }
+
+        Ok(NoteIterator::new(
+            self.section_data(section)?,
+            section.address_alignment(),
+            self.header.endianness,
+        ))
+    }
+
+    /// Iterate over the notes held by a `Note` segment.
+    pub fn segment_notes(
+        &self,
+        segment: &Elf64ProgramHeader,
+    ) -> Result<NoteIterator<'a>, ElfParseError> {
+        if *segment.segment_type() != ProgramSegmentType::Note {
+            return Err(ElfParseError::WrongSegmentType);
+        }
+
+        Ok(NoteIterator::new(
+            self.segment_data(segment)?,
+            segment.alignment(),
+            self.header.endianness,
+        ))
+    }
+
+    /// Returns the first value `matches` returns for a note of the file.
+    ///
+    /// Notes are read from the `Note` sections, or from the `Note` segments if
+    /// the file has no `Note` sections, so that each note is only seen once.
+    fn find_note<T>(
+        &self,
+        mut matches: impl FnMut(Note<'a>) -> Result<Option<T>, ElfParseError>,
+    ) -> Result<Option<T>, ElfParseError> {
+        let mut has_note_sections = false;
+        for section in self.section_headers()? {
+            let section = section?;
+            if *section.header_type() != SectionHeaderType::Note {
+                continue;
+            }
+
+            has_note_sections = true;
+            for note in self.section_notes(&section)? {
+                if let Some(value) = matches(note?)? {
+                    return Ok(Some(value));
+                }
+            }
+        }
+        if has_note_sections {
+            return Ok(None);
+        }
+
+        for header in self.program_headers().into_iter().flatten() {
+            let header = header?;
+            if *header.segment_type() != ProgramSegmentType::Note {
+                continue;
+            }
+
+            for note in self.segment_notes(&header)? {
+                if let Some(value) = matches(note?)? {
+                    return Ok(Some(value));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the build ID of the file, i.e. the descriptor of its
+    /// `NT_GNU_BUILD_ID` note, if it has one.
+    pub fn build_id(&self) -> Result<Option<&'a [u8]>, ElfParseError> {
+        self.find_note(|note| match note.gnu()? {
+            Some(GnuNote::BuildId(build_id)) => Ok(Some(build_id)),
+            _ => Ok(None),
+        })
+    }
+
+    /// Iterate over the properties of the file's `NT_GNU_PROPERTY_TYPE_0`
+    /// note, if it has one.
+    pub fn gnu_properties(&self) -> Result<Option<GnuPropertyIterator<'a>>, ElfParseError> {
+        self.find_note(|note| match note.gnu()? {
+            Some(GnuNote::Properties(properties)) => Ok(Some(properties)),
+            _ => Ok(None),
+        })
+    }
+
+    /// Find the definition of the global or weak symbol `name`.
+    ///
+    /// The symbol is looked up through the `GnuHash` table if the file has
+    /// one, or else through its `Hash` table. Both hash the dynamic symbols,
+    /// so files without them are scanned linearly, through the full symbol
+    /// table if there is one.
+    pub fn lookup_symbol(&self, name: &str) -> Result<Option<Elf64Symbol<'a>>, ElfParseError> {
+        if let Some((_, section)) = self.find_section(SectionHeaderType::GnuHash)? {
+            let table = GnuHashTable::new(self.section_data(&section)?, self.header.endianness)?;
+            return table.lookup(name, &self.linked_symbols(&section)?);
+        }
+
+        if let Some((_, section)) = self.find_section(SectionHeaderType::Hash)? {
+            let table = SysvHashTable::new(self.section_data(&section)?, self.header.endianness)?;
+            return table.lookup(name, &self.linked_symbols(&section)?);
+        }
+
+        let symbols = match self.symbols()? {
+            Some(symbols) => symbols,
+            None => match self.dynamic_symbols()? {
+                Some(symbols) => symbols,
+                None => return Ok(None),
+            },
+        };
+        for symbol in symbols {
+            let symbol = symbol?;
+            if hash::defines(&symbol, name) {
+                return Ok(Some(symbol));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The file's DWARF line tables, to map code addresses to source lines.
+    /// Returns `None` if the file has no `.debug_line` section, e.g. because
+    /// it wasn't built with debug information or was stripped.
+    pub fn line_info(&self) -> Result<Option<LineInfo<'a>>, ElfParseError> {
+        let section_data = |name| -> Result<&'a [u8], ElfParseError> {
+            match self.section_by_name(name)? {
+                Some(section) => self.section_data(&section),
+                None => Ok(&[]),
+            }
+        };
+
+        let debug_line = section_data(".debug_line")?;
+        if debug_line.is_empty() {
+            return Ok(None);
+        }
+        let sections = DwarfSections {
+            debug_line,
+            debug_aranges: section_data(".debug_aranges")?,
+            debug_info: section_data(".debug_info")?,
+            debug_abbrev: section_data(".debug_abbrev")?,
+            debug_str: section_data(".debug_str")?,
+            debug_line_str: section_data(".debug_line_str")?,
+        };
+
+        Ok(Some(LineInfo::new(sections, self.header.endianness)))
+    }
+
+    /// Find the first section whose name is `name`, e.g. ".text".
+    pub fn section_by_name(&self, name: &str) -> Result<Option<SectionHeader>, ElfParseError> {
+        let section_names = self.section_names()?;
+
+        for section in self.section_headers()? {
+            let section = section?;
+            if section.name(&section_names)? == name {
+                return Ok(Some(section));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl<'a> Debug for Elf64File<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self.header))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Elf64ProgramHeader {
+    segment_type: ProgramSegmentType,
+    flags: ProgramHeaderFlags,
+
+    /// The offset in the file that the data for this segment can be found.
+    p_offset: u64,
+
+    /// Where you should start to put this segment in virtual memory.
+    p_vaddr: u64,
+
+    /// Size of the segment in the file.
+    p_filesz: u64,
+
+    /// Size of the segment in memory. This can be 0. If the p_filesz and
+    /// p_memsz members differ, this indicates that the segment is padded with
+    /// zeros. All bytes in memory between the ending offset of the file size,
+    /// and the segment's virtual memory size are to be cleared with zeros.
+    p_memsz: u64,
+
+    /// Required alignment for this section. Must be a power of 2.
+    alignment: u64,
+}
+
+impl Elf64ProgramHeader {
+    pub fn new(
+        segment_type: ProgramSegmentType,
+        flags: ProgramHeaderFlags,
+        p_offset: u64,
+        p_vaddr: u64,
+        p_filesz: u64,
+        p_memsz: u64,
+        alignment: u64,
+    ) -> Self {
+        Self {
+            segment_type,
+            flags,
+            p_offset,
+            p_vaddr,
+            p_filesz,
+            p_memsz,
+            alignment,
+        }
+    }
+
+    pub fn segment_type(&self) -> &ProgramSegmentType {
+        &self.segment_type
+    }
+
+    pub fn flags(&self) -> &ProgramHeaderFlags {
+        &self.flags
+    }
+
+    pub fn p_offset(&self) -> u64 {
+        self.p_offset
+    }
+
+    pub fn p_vaddr(&self) -> u64 {
+        self.p_vaddr
+    }
+
+    pub fn p_filesz(&self) -> u64 {
+        self.p_filesz
+    }
+
+    pub fn p_memsz(&self) -> u64 {
+        self.p_memsz
+    }
+
+    pub fn alignment(&self) -> u64 {
+        self.alignment
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Elf64ProgramHeaderIterator<'a> {
+    current_index: u16,
+    data: &'a [u8],
+    endianness: &'a Endian,
+    header_summary: &'a Elf64ProgramHeaderSummary,
+    program_header_entry_seen: bool,
+}
+
+impl<'a> Elf64ProgramHeaderIterator<'a> {
+    pub fn new(
+        data: &'a [u8],
+        endianness: &'a Endian,
+        header_summary: &'a Elf64ProgramHeaderSummary,
+    ) -> Self {
+        Self {
+            current_index: 0,
+            data,
+            endianness,
+            header_summary,
+            program_header_entry_seen: false,
+        }
+    }
+}
+
+impl<'a> Elf64ProgramHeaderIterator<'a> {
+    fn parse_program_header(
+        &self,
+        byte_offset: usize,
+    ) -> Result<Elf64ProgramHeader, ElfParseError> {
+        let header = Elf64ProgramHeader {
+            segment_type: ProgramSegmentType::from(
+                self.endianness.get_u32(self.data, byte_offset)?,
+            ),
+            flags: ProgramHeaderFlags::from(self.endianness.get_u32(self.data, byte_offset + 4)?),
+            p_offset: self.endianness.get_u64(self.data, byte_offset + 8)?,
+            p_vaddr: self.endianness.get_u64(self.data, byte_offset + 16)?,
+            p_filesz: self.endianness.get_u64(self.data, byte_offset + 32)?,
+            p_memsz: self.endianness.get_u64(self.data, byte_offset + 40)?,
+            alignment: self.endianness.get_u64(self.data, byte_offset + 48)?,
+        };
+
+        if !header.alignment.is_power_of_two() {
+            return Err(ElfParseError::InvalidProgramHeaderAlignment);
+        }
+
+        Ok(header)
+    }
+}
+
+impl<'a> Iterator for Elf64ProgramHeaderIterator<'a> {
+    type Item = Result<Elf64ProgramHeader, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let byte_offset = self.header_summary.byte_offset(self.current_index)?;
+        // Move past this entry even if it fails to parse, so a malformed entry
+        // can't stall the iterator
+        self.current_index += 1;
+
+        let header = match self.parse_program_header(byte_offset) {
+            Ok(header) => header,
+            Err(error) => return Some(Err(error)),
+        };
+
+        if header.segment_type == ProgramSegmentType::ProgramHeader {
+            if self.program_header_entry_seen {
+                return Some(Err(ElfParseError::MultipleProgramHeaderEntriesFound));
+            }
+
+            self.program_header_entry_seen = true;
+        }
+
+        Some(Ok(header))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dynamic::*;
+    use super::relocations::*;
+    use super::sections::*;
+    use super::symbols::*;
+    use super::*;
+    use alloc::{vec, vec::Vec};
+
+    #[test]
+    fn parse_main_return_0_64_bit_relocatable() {
+        let file_bytes = include_bytes!("test_files/main_ret0.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let expected_elf = Elf64File {
+            file_bytes,
+            header: Elf64Header {
+                endianness: Endian::Little,
+                header_version: 1,
+                os_abi: 0,
+                elf_type: ElfType::Relocatable,
+                instruction_set: InstructionSet::X86_64,
+                elf_version: 1,
+                program_entry_position: 0,
+                program_header_summary: None,
+                section_header_summary: Elf64SectionHeaderSummary {
+                    table_position: 552,
+                    entry_size: 64,
+                    entry_count: 11,
+                    names_index: 1,
+                },
+            },
+        };
+
+        assert_eq!(expected_elf, elf_file);
+        assert_eq!(None, elf_file.program_headers());
+    }
+
+    #[test]
+    fn parse_hello_world_64_bit_executable() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let expected_elf = Elf64File {
+            file_bytes,
+            header: Elf64Header {
+                endianness: Endian::Little,
+                header_version: 1,
+                os_abi: 0,
+                elf_type: ElfType::Executable,
+                instruction_set: InstructionSet::X86_64,
+                elf_version: 1,
+                program_entry_position: 0x401040,
+                program_header_summary: Some(Elf64ProgramHeaderSummary {
+                    table_position: 64,
+                    entry_size: 56,
+                    entry_count: 11,
+                }),
+                section_header_summary: Elf64SectionHeaderSummary {
+                    table_position: 14520,
+                    entry_size: 64,
+                    entry_count: 29,
+                    names_index: 28,
+                },
+            },
+        };
+
+        assert_eq!(expected_elf, elf_file);
+        assert_eq!(11, elf_file.program_headers().unwrap().count());
+
+        let mut headers = elf_file.program_headers().unwrap();
+        let expected_program_header = Elf64ProgramHeader::new(
+            ProgramSegmentType::ProgramHeader,
+            ProgramHeaderFlags::new(false, false, true),
+            0x40,
+            0x400040,
+            0x268,
+            0x268,
+            0x8,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf64ProgramHeader::new(
+            ProgramSegmentType::Interpret,
+            ProgramHeaderFlags::new(false, false, true),
+            0x2A8,
+            0x4002A8,
+            0x1C,
+            0x1C,
+            0x1,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf64ProgramHeader::new(
+            ProgramSegmentType::Load,
+            ProgramHeaderFlags::new(false, false, true),
+            0,
+            0x400000,
+            0x440,
+            0x440,
+            0x1000,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf64ProgramHeader::new(
+            ProgramSegmentType::Load,
+            ProgramHeaderFlags::new(true, false, true),
+            0x1000,
+            0x401000,
+            0x1BD,
+            0x1BD,
+            0x1000,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf64ProgramHeader::new(
+            ProgramSegmentType::Load,
+            ProgramHeaderFlags::new(false, false, true),
+            0x2000,
+            0x402000,
+            0x150,
+            0x150,
+            0x1000,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf64ProgramHeader::new(
+            ProgramSegmentType::Load,
+            ProgramHeaderFlags::new(false, true, true),
+            0x2E00,
+            0x403E00,
+            0x230,
+            0x238,
+            0x1000,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf64ProgramHeader::new(
+            ProgramSegmentType::Dynamic,
+            ProgramHeaderFlags::new(false, true, true),
+            0x2E10,
+            0x403E10,
+            0x1E0,
+            0x1E0,
+            0x8,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf64ProgramHeader::new(
+            ProgramSegmentType::Note,
+            ProgramHeaderFlags::new(false, false, true),
+            0x2C4,
+            0x4002C4,
+            0x20,
+            0x20,
+            0x4,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf64ProgramHeader::new(
+            ProgramSegmentType::GnuEhFrame,
+            ProgramHeaderFlags::new(false, false, true),
+            0x2010,
+            0x402010,
+            0x3C,
+            0x3C,
+            0x4,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf64ProgramHeader::new(
+            ProgramSegmentType::GnuStack,
+            ProgramHeaderFlags::new(false, true, true),
+            0,
+            0,
+            0,
+            0,
+            0x10,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        let expected_program_header = Elf64ProgramHeader::new(
+            ProgramSegmentType::GnuRelro,
+            ProgramHeaderFlags::new(false, false, true),
+            0x2E00,
+            0x403E00,
+            0x200,
+            0x200,
+            0x1,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+
+        assert_eq!(0, headers.count());
+
+        assert_eq!(
+            29,
+            elf_file
+                .section_headers()
+                .expect("Failed to create section iterator")
+                .count()
+        );
+
+        let mut section_headers = elf_file
+            .section_headers()
+            .expect("Failed to create section iterator");
+
+        let expected = SectionHeader::new(
+            0,
+            SectionHeaderType::Null,
+            SectionHeaderFlags::empty(),
+            None,
+            0,
+            0,
+            None,
+            None,
+            0,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x1B,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::ALLOC,
+            Some(0x4002A8),
+            0x2A8,
+            0x1C,
+            None,
+            None,
+            1,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x23,
+            SectionHeaderType::Note,
+            SectionHeaderFlags::ALLOC,
+            Some(0x4002C4),
+            0x2C4,
+            0x20,
+            None,
+            None,
+            4,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x35,
+            SectionHeaderType::Hash,
+            SectionHeaderFlags::ALLOC,
+            Some(0x4002E8),
+            0x2E8,
+            0x24,
+            Some(5),
+            None,
+            8,
+            Some(0x4),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x31,
+            SectionHeaderType::GnuHash,
+            SectionHeaderFlags::ALLOC,
+            Some(0x400310),
+            0x310,
+            0x1C,
+            Some(5),
+            None,
+            8,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x3B,
+            SectionHeaderType::DynamicSymbols,
+            SectionHeaderFlags::ALLOC,
+            Some(0x400330),
+            0x330,
+            0x60,
+            Some(6),
+            Some(1),
+            8,
+            Some(0x18),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x43,
+            SectionHeaderType::StringTable,
+            SectionHeaderFlags::ALLOC,
+            Some(0x400390),
+            0x390,
+            0x3D,
+            None,
+            None,
+            1,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x4B,
+            SectionHeaderType::OperatingSystemSpecific(0x6FFFFFFF),
+            SectionHeaderFlags::ALLOC,
+            Some(0x4003CE),
+            0x3CE,
+            0x8,
+            Some(5),
+            None,
+            2,
+            Some(0x2),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x58,
+            SectionHeaderType::OperatingSystemSpecific(0x6FFFFFFE),
+            SectionHeaderFlags::ALLOC,
+            Some(0x4003D8),
+            0x3D8,
+            0x20,
+            Some(6),
+            Some(1),
+            8,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x67,
+            SectionHeaderType::RelA,
+            SectionHeaderFlags::ALLOC,
+            Some(0x4003F8),
+            0x3F8,
+            0x30,
+            Some(5),
+            None,
+            8,
+            Some(0x18),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x71,
+            SectionHeaderType::RelA,
+            SectionHeaderFlags::ALLOC | SectionHeaderFlags::INFO_LINK,
+            Some(0x400428),
+            0x428,
+            0x18,
+            Some(5),
+            Some(22),
+            8,
+            Some(0x18),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x7B,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::ALLOC | SectionHeaderFlags::EXECUTABLE_INSTRUCTIONS,
+            Some(0x401000),
+            0x1000,
+            0x17,
+            None,
+            None,
+            4,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x76,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::ALLOC | SectionHeaderFlags::EXECUTABLE_INSTRUCTIONS,
+            Some(0x401020),
+            0x1020,
+            0x20,
+            None,
+            None,
+            16,
+            Some(0x10),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x81,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::ALLOC | SectionHeaderFlags::EXECUTABLE_INSTRUCTIONS,
+            Some(0x401040),
+            0x1040,
+            0x171,
+            None,
+            None,
+            16,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x87,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::ALLOC | SectionHeaderFlags::EXECUTABLE_INSTRUCTIONS,
+            Some(0x4011B4),
+            0x11B4,
+            0x9,
+            None,
+            None,
+            4,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x8D,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::ALLOC,
+            Some(0x402000),
+            0x2000,
+            0x10,
+            None,
+            None,
+            4,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x95,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::ALLOC,
+            Some(0x402010),
+            0x2010,
+            0x3C,
+            None,
+            None,
+            4,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0xA3,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::ALLOC,
+            Some(0x402050),
+            0x2050,
+            0x100,
+            None,
+            None,
+            8,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0xAD,
+            SectionHeaderType::InitArray,
+            SectionHeaderFlags::WRITE | SectionHeaderFlags::ALLOC,
+            Some(0x403E00),
+            0x2E00,
+            0x8,
+            None,
+            None,
+            8,
+            Some(8),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0xB9,
+            SectionHeaderType::FinishArray,
+            SectionHeaderFlags::WRITE | SectionHeaderFlags::ALLOC,
+            Some(0x403E08),
+            0x2E08,
+            0x8,
+            None,
+            None,
+            8,
+            Some(0x8),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0xC5,
+            SectionHeaderType::Dynamic,
+            SectionHeaderFlags::WRITE | SectionHeaderFlags::ALLOC,
+            Some(0x403E10),
+            0x2E10,
+            0x1E0,
+            Some(6),
+            None,
+            8,
+            Some(0x10),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0xCE,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::WRITE | SectionHeaderFlags::ALLOC,
+            Some(0x403FF0),
+            0x2FF0,
+            0x10,
+            None,
+            None,
+            8,
+            Some(0x8),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0xD3,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::WRITE | SectionHeaderFlags::ALLOC,
+            Some(0x404000),
+            0x3000,
+            0x20,
+            None,
+            None,
+            8,
+            Some(0x8),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0xDC,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::WRITE | SectionHeaderFlags::ALLOC,
+            Some(0x404020),
+            0x3020,
+            0x10,
+            None,
+            None,
+            8,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0xE2,
+            SectionHeaderType::NoBits,
+            SectionHeaderFlags::WRITE | SectionHeaderFlags::ALLOC,
+            Some(0x404030),
+            0x3030,
+            0x8,
+            None,
+            None,
+            1,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0xE7,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::MERGE | SectionHeaderFlags::STRINGS,
+            None,
+            0x3030,
+            0x12,
+            None,
+            None,
+            1,
+            Some(0x1),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x1,
+            SectionHeaderType::SymbolTable,
+            SectionHeaderFlags::empty(),
+            None,
+            0x3048,
+            0x5B8,
+            Some(27),
+            Some(43),
+            8,
+            Some(0x18),
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x9,
+            SectionHeaderType::StringTable,
+            SectionHeaderFlags::empty(),
+            None,
+            0x3600,
+            0x1C4,
+            None,
+            None,
+            1,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        let expected = SectionHeader::new(
+            0x11,
+            SectionHeaderType::StringTable,
+            SectionHeaderFlags::empty(),
+            None,
+            0x37C4,
+            0xF0,
+            None,
+            None,
+            1,
+            None,
+        );
+        assert_eq!(
+            expected,
+            section_headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse section header")
+        );
+
+        assert_eq!(0, section_headers.count());
+    }
+
+    #[test]
+    fn resolve_hello_world_64_bit_section_names() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let section_names = elf_file
+            .section_names()
+            .expect("Failed to find section name table");
+
+        let expected_names = [
+            "",
+            ".interp",
+            ".note.ABI-tag",
+            ".hash",
+            ".gnu.hash",
+            ".dynsym",
+            ".dynstr",
+            ".gnu.version",
+            ".gnu.version_r",
+            ".rela.dyn",
+            ".rela.plt",
+            ".init",
+            ".plt",
+            ".text",
+            ".fini",
+            ".rodata",
+            ".eh_frame_hdr",
+            ".eh_frame",
+            ".init_array",
+            ".fini_array",
+            ".dynamic",
+            ".got",
+            ".got.plt",
+            ".data",
+            ".bss",
+            ".comment",
+            ".symtab",
+            ".strtab",
+            ".shstrtab",
+        ];
+        let section_headers = elf_file
+            .section_headers()
+            .expect("Failed to create section iterator");
+
+        for (expected_name, section) in expected_names.iter().zip(section_headers) {
+            let section = section.expect("Failed to parse section header");
+            assert_eq!(
+                Ok(*expected_name),
+                section.name(&section_names),
+                "Unexpected name for {:?}",
+                section
+            );
+        }
+    }
+
+    #[test]
+    fn find_hello_world_64_bit_section_by_name() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        let expected = SectionHeader::new(
+            0x81,
+            SectionHeaderType::ProgramBits,
+            SectionHeaderFlags::ALLOC | SectionHeaderFlags::EXECUTABLE_INSTRUCTIONS,
+            Some(0x401040),
+            0x1040,
+            0x171,
+            None,
+            None,
+            16,
+            None,
+        );
+        assert_eq!(Ok(Some(expected)), elf_file.section_by_name(".text"));
+        assert_eq!(Ok(None), elf_file.section_by_name(".not_a_section"));
+
+        let interp = elf_file
+            .section_by_name(".interp")
+            .expect("Failed to search sections")
+            .expect("Expected an .interp section");
+        assert_eq!(
+            Ok(&b"/lib64/ld-linux-x86-64.so.2\0"[..]),
+            elf_file.section_data(&interp)
+        );
+    }
+
+    #[test]
+    fn find_main_return_0_64_bit_section_by_name() {
+        let file_bytes = include_bytes!("test_files/main_ret0.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        let text = elf_file
+            .section_by_name(".text")
+            .expect("Failed to search sections")
+            .expect("Expected a .text section");
+        assert_eq!(SectionHeaderType::ProgramBits, *text.header_type());
+        assert_eq!(0x40, text.section_file_offset());
+        assert_eq!(0x41, text.section_size());
+    }
+
+    #[test]
+    fn invalid_section_names_index() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let mut elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        elf_file.header.section_header_summary.names_index = 0;
+        assert_eq!(
+            Err(ElfParseError::MissingStringTable),
+            elf_file.section_names()
+        );
+
+        elf_file.header.section_header_summary.names_index = 29;
+        assert_eq!(
+            Err(ElfParseError::MissingStringTable),
+            elf_file.section_by_name(".text")
+        );
+
+        // Section 13 is .text, which is not a string table
+        elf_file.header.section_header_summary.names_index = 13;
+        assert_eq!(
+            Err(ElfParseError::MissingStringTable),
+            elf_file.section_names()
+        );
+    }
+
+    #[test]
+    fn parse_main_return_0_64_bit_symbols() {
+        let file_bytes = include_bytes!("test_files/main_ret0.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        let mut symbols = elf_file
+            .symbols()
+            .expect("Failed to find symbol table")
+            .expect("Expected a symbol table");
+        let expected_symbols = [
+            Elf64Symbol::new(
+                "",
+                0,
+                0,
+                SymbolBinding::Local,
+                SymbolType::NoType,
+                SymbolVisibility::Default,
+                SymbolSectionIndex::Undefined,
+            ),
+            Elf64Symbol::new(
+                "main-ret0.c",
+                0,
+                0,
+                SymbolBinding::Local,
+                SymbolType::File,
+                SymbolVisibility::Default,
+                SymbolSectionIndex::Absolute,
+            ),
+            Elf64Symbol::new(
+                "hello",
+                0,
+                12,
+                SymbolBinding::Local,
+                SymbolType::Object,
+                SymbolVisibility::Default,
+                SymbolSectionIndex::Index(4),
+            ),
+            Elf64Symbol::new(
+                "",
+                0,
+                0,
+                SymbolBinding::Local,
+                SymbolType::Section,
+                SymbolVisibility::Default,
+                SymbolSectionIndex::Index(2),
+            ),
+            Elf64Symbol::new(
+                "",
+                0,
+                0,
+                SymbolBinding::Local,
+                SymbolType::Section,
+                SymbolVisibility::Default,
+                SymbolSectionIndex::Index(4),
+            ),
+            Elf64Symbol::new(
+                "main",
+                0,
+                65,
+                SymbolBinding::Global,
+                SymbolType::Function,
+                SymbolVisibility::Default,
+                SymbolSectionIndex::Index(2),
+            ),
+        ];
+
+        for expected in expected_symbols.iter() {
+            assert_eq!(
+                expected,
+                &symbols.next().unwrap().expect("Failed to parse symbol")
+            );
+        }
+        assert_eq!(0, symbols.count());
+
+        assert!(elf_file
+            .dynamic_symbols()
+            .expect("Failed to search for dynamic symbols")
+            .is_none());
+    }
+
+    #[test]
+    fn parse_hello_world_64_bit_symbols() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        let symbols = || {
+            elf_file
+                .symbols()
+                .expect("Failed to find symbol table")
+                .expect("Expected a symbol table")
+                .map(|symbol| symbol.expect("Failed to parse symbol"))
+        };
+        assert_eq!(61, symbols().count());
+
+        let start = symbols()
+            .find(|symbol| symbol.name() == "_start")
+            .expect("Expected a _start symbol");
+        assert_eq!(
+            Elf64Symbol::new(
+                "_start",
+                elf_file.header.program_entry_position,
+                43,
+                SymbolBinding::Global,
+                SymbolType::Function,
+                SymbolVisibility::Default,
+                SymbolSectionIndex::Index(13),
+            ),
+            start
+        );
+
+        let main = symbols()
+            .find(|symbol| symbol.name() == "main")
+            .expect("Expected a main symbol");
+        assert_eq!(0x401126, main.value());
+        assert_eq!(32, main.size());
+
+        let fini = symbols()
+            .find(|symbol| symbol.name() == "_fini")
+            .expect("Expected a _fini symbol");
+        assert_eq!(SymbolVisibility::Hidden, fini.visibility());
+        assert_eq!(SymbolSectionIndex::Index(14), fini.section_index());
+
+        let data_start = symbols()
+            .find(|symbol| symbol.name() == "data_start")
+            .expect("Expected a data_start symbol");
+        assert_eq!(SymbolBinding::Weak, data_start.binding());
+        assert_eq!(SymbolType::NoType, data_start.symbol_type());
+    }
+
+    #[test]
+    fn parse_hello_world_64_bit_dynamic_symbols() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        let mut symbols = elf_file
+            .dynamic_symbols()
+            .expect("Failed to find dynamic symbol table")
+            .expect("Expected a dynamic symbol table");
+        let expected_symbols = [
+            ("", SymbolBinding::Local, SymbolType::NoType),
+            ("puts", SymbolBinding::Global, SymbolType::Function),
+            (
+                "__libc_start_main",
+                SymbolBinding::Global,
+                SymbolType::Function,
+            ),
+            ("__gmon_start__", SymbolBinding::Weak, SymbolType::NoType),
+        ];
+
+        for (name, binding, symbol_type) in expected_symbols.iter() {
+            let symbol = symbols
+                .next()
+                .unwrap()
+                .expect("Failed to parse dynamic symbol");
+            assert_eq!(*name, symbol.name());
+            assert_eq!(*binding, symbol.binding());
+            assert_eq!(*symbol_type, symbol.symbol_type());
+            assert_eq!(SymbolSectionIndex::Undefined, symbol.section_index());
+        }
+        assert_eq!(0, symbols.count());
+    }
+
+    fn hello_world_dynamic_entries() -> [(DynamicTag, u64); 24] {
+        [
+            (DynamicTag::Needed, 24),
+            (DynamicTag::Init, 0x401000),
+            (DynamicTag::Fini, 0x4011B4),
+            (DynamicTag::InitArray, 0x403E00),
+            (DynamicTag::InitArraySize, 8),
+            (DynamicTag::FiniArray, 0x403E08),
+            (DynamicTag::FiniArraySize, 8),
+            (DynamicTag::Hash, 0x4002E8),
+            (DynamicTag::GnuHash, 0x400310),
+            (DynamicTag::StringTable, 0x400390),
+            (DynamicTag::SymbolTable, 0x400330),
+            (DynamicTag::StringTableSize, 61),
+            (DynamicTag::SymbolEntrySize, 24),
+            (DynamicTag::Debug, 0),
+            (DynamicTag::PltGot, 0x404000),
+            (DynamicTag::PltRelocationsSize, 24),
+            (DynamicTag::PltRelocationType, 7),
+            (DynamicTag::JumpRelocations, 0x400428),
+            (DynamicTag::Rela, 0x4003F8),
+            (DynamicTag::RelaSize, 48),
+            (DynamicTag::RelaEntrySize, 24),
+            (DynamicTag::VersionNeeded, 0x4003D8),
+            (DynamicTag::VersionNeededCount, 1),
+            (DynamicTag::VersionSymbols, 0x4003CE),
+        ]
+    }
+
+    #[test]
+    fn parse_hello_world_64_bit_dynamic_entries() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let entries = elf_file
+            .dynamic_entries()
+            .expect("Failed to find dynamic section")
+            .expect("Expected a dynamic section");
+
+        let expected = hello_world_dynamic_entries();
+        let entries: Vec<_> = entries
+            .map(|entry| entry.expect("Failed to parse dynamic entry"))
+            .collect();
+        assert_eq!(expected.len(), entries.len());
+        for ((tag, value), entry) in expected.iter().zip(entries.iter()) {
+            assert_eq!((*tag, *value), (entry.tag(), entry.value()));
+        }
+        assert_eq!(Some("libc.so.6"), entries[0].name());
+        assert_eq!(None, entries[1].name());
+
+        let needed: Vec<_> = elf_file
+            .needed_libraries()
+            .expect("Failed to find dynamic section")
+            .collect();
+        assert_eq!(vec![Ok("libc.so.6")], needed);
+    }
+
+    #[test]
+    fn parse_dynamic_entries_without_section_headers() {
+        let mut file_bytes = include_bytes!("test_files/hello_world.elf64").to_vec();
+        // Clear e_shnum, leaving only the Dynamic program header
+        file_bytes[60..62].copy_from_slice(&[0, 0]);
+        let elf_file =
+            Elf64File::from_bytes(&file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(0, elf_file.section_headers().unwrap().count());
+
+        let entries = elf_file
+            .dynamic_entries()
+            .expect("Failed to find dynamic section")
+            .expect("Expected a dynamic section");
+        assert_eq!(hello_world_dynamic_entries().len(), entries.count());
+
+        let needed: Vec<_> = elf_file
+            .needed_libraries()
+            .expect("Failed to find dynamic section")
+            .collect();
+        assert_eq!(vec![Ok("libc.so.6")], needed);
+    }
+
+    #[test]
+    fn parse_main_return_0_64_bit_dynamic_entries() {
+        let file_bytes = include_bytes!("test_files/main_ret0.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        assert!(matches!(elf_file.dynamic_entries(), Ok(None)));
+        assert_eq!(0, elf_file.needed_libraries().unwrap().count());
+    }
+
+    #[test]
+    fn resolve_virtual_addresses() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        assert_eq!(
+            Ok(&file_bytes[0x1040..0x1050]),
+            elf_file.virtual_address_data(0x401040, 0x10)
+        );
+        // .bss is past the file data of its segment
+        assert_eq!(
+            Err(ElfParseError::UnmappedAddress(0x404030)),
+            elf_file.virtual_address_data(0x404030, 8)
+        );
+        assert_eq!(
+            Err(ElfParseError::UnmappedAddress(0x1000)),
+            elf_file.virtual_address_data(0x1000, 1)
+        );
+    }
+
+    #[test]
+    fn parse_hello_world_64_bit_relocations() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        let section = elf_file
+            .section_by_name(".rela.dyn")
+            .expect("Failed to search sections")
+            .expect("Expected a .rela.dyn section");
+        let symbols = elf_file
+            .linked_symbols(&section)
+            .expect("Failed to find symbol table");
+        let relocations: Vec<_> = elf_file
+            .rela_entries(&section)
+            .expect("Failed to create relocation iterator")
+            .map(|relocation| relocation.expect("Failed to parse relocation"))
+            .collect();
+        assert_eq!(
+            vec![
+                Elf64Rela::new(0x403FF0, 2, RelocationType::GlobalData, 0),
+                Elf64Rela::new(0x403FF8, 3, RelocationType::GlobalData, 0),
+            ],
+            relocations
+        );
+        assert_eq!(
+            Ok("__libc_start_main"),
+            symbols.symbol(2).map(|symbol| symbol.name())
+        );
+        assert_eq!(
+            Ok("__gmon_start__"),
+            symbols.symbol(3).map(|symbol| symbol.name())
+        );
+        assert!(matches!(
+            symbols.symbol(4),
+            Err(ElfParseError::InvalidSymbolIndex(4))
+        ));
+
+        let section = elf_file
+            .section_by_name(".rela.plt")
+            .expect("Failed to search sections")
+            .expect("Expected a .rela.plt section");
+        let symbols = elf_file
+            .linked_symbols(&section)
+            .expect("Failed to find symbol table");
+        let mut relocations = elf_file
+            .rela_entries(&section)
+            .expect("Failed to create relocation iterator");
+        assert_eq!(
+            Some(Ok(Elf64Rela::new(0x404018, 1, RelocationType::JumpSlot, 0))),
+            relocations.next()
+        );
+        assert_eq!(None, relocations.next());
+        assert_eq!(Ok("puts"), symbols.symbol(1).map(|symbol| symbol.name()));
+
+        assert!(matches!(
+            elf_file.rel_entries(&section),
+            Err(ElfParseError::WrongSectionType)
+        ));
+    }
+
+    #[test]
+    fn parse_main_return_0_64_bit_relocations() {
+        let file_bytes = include_bytes!("test_files/main_ret0.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        let section = elf_file
+            .section_by_name(".rela.text")
+            .expect("Failed to search sections")
+            .expect("Expected a .rela.text section");
+        assert_eq!(Some(2), section.info());
+        let mut relocations = elf_file
+            .rela_entries(&section)
+            .expect("Failed to create relocation iterator");
+        assert_eq!(
+            Some(Ok(Elf64Rela::new(
+                0x21,
+                4,
+                RelocationType::Direct32Signed,
+                0
+            ))),
+            relocations.next()
+        );
+        assert_eq!(None, relocations.next());
+
+        let symbols = elf_file
+            .linked_symbols(&section)
+            .expect("Failed to find symbol table");
+        let symbol = symbols.symbol(4).expect("Failed to parse symbol");
+        assert_eq!(SymbolType::Section, symbol.symbol_type());
+
+        let text = elf_file
+            .section_by_name(".text")
+            .expect("Failed to search sections")
+            .expect("Expected a .text section");
+        assert!(matches!(
+            elf_file.linked_symbols(&text),
+            Err(ElfParseError::MissingSymbolTable)
+        ));
+    }
+
+    #[test]
+    fn parse_hello_world_64_bit_notes() {
+        use crate::notes::{AbiTag, AbiTagOs, NT_GNU_ABI_TAG};
+
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+
+        let section = elf_file
+            .section_by_name(".note.ABI-tag")
+            .expect("Failed to search sections")
+            .expect("Expected a .note.ABI-tag section");
+        let mut notes = elf_file
+            .section_notes(&section)
+            .expect("Failed to create note iterator");
+        let note = notes.next().unwrap().expect("Failed to parse note");
+        assert_eq!("GNU", note.name());
+        assert_eq!(NT_GNU_ABI_TAG, note.note_type());
+        let expected_tag = AbiTag {
+            os: AbiTagOs::Linux,
+            major: 3,
+            minor: 2,
+            patch: 0,
+        };
+        assert!(matches!(note.gnu(), Ok(Some(GnuNote::AbiTag(tag))) if tag == expected_tag));
+        assert!(notes.next().is_none());
+
+        assert_eq!(Ok(None), elf_file.build_id());
+        assert!(matches!(elf_file.gnu_properties(), Ok(None)));
+        assert!(matches!(
+            elf_file.section_notes(&elf_file.section_header(0).unwrap()),
+            Err(ElfParseError::WrongSectionType)
+        ));
+    }
+
+    #[test]
+    fn find_static_pie_build_id() {
+        let expected_build_id = [
+            0x5c, 0xaf, 0x40, 0x39, 0xec, 0x11, 0xfc, 0x3b, 0x45, 0xf3, 0xb6, 0xf5, 0xa0, 0x24,
+            0xe7, 0xd2, 0x8a, 0xdd, 0xac, 0xc0,
+        ];
+
+        let file_bytes = include_bytes!("test_files/static_pie.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(Ok(Some(&expected_build_id[..])), elf_file.build_id());
+
+        // Without section headers the note is found through its segment
+        let mut file_bytes: Vec<u8> = file_bytes.to_vec();
+        file_bytes[60..62].copy_from_slice(&[0, 0]);
+        let elf_file =
+            Elf64File::from_bytes(&file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(Ok(Some(&expected_build_id[..])), elf_file.build_id());
+    }
+
+    #[test]
+    fn parse_static_pie_gnu_properties() {
+        use crate::notes::{GnuProperty, X86Features, X86IsaLevels};
+
+        let file_bytes = include_bytes!("test_files/static_pie_v3.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let properties: Vec<_> = elf_file
+            .gnu_properties()
+            .expect("Failed to search notes")
+            .expect("Expected GNU properties")
+            .collect();
+        assert_eq!(
+            vec![
+                Ok(GnuProperty::X86Features(
+                    X86Features::IBT | X86Features::SHSTK
+                )),
+                Ok(GnuProperty::X86IsaNeeded(X86IsaLevels::V3)),
+            ],
+            properties
+        );
+    }
+
+    #[test]
+    fn find_64_bit_stack_and_relro_segments() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(Ok(false), elf_file.requests_executable_stack());
+        assert_eq!(Ok(Some(0x403E00..0x404000)), elf_file.relro_range());
+
+        // Set PF_X in the flags of the GnuStack program header, the 10th
+        let mut file_bytes: Vec<u8> = file_bytes.to_vec();
+        file_bytes[64 + 9 * 56 + 4] |= 1;
+        let elf_file =
+            Elf64File::from_bytes(&file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(Ok(true), elf_file.requests_executable_stack());
+
+        let file_bytes = include_bytes!("test_files/static_pie.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(Ok(Some(0x3F00..0x4000)), elf_file.relro_range());
+
+        // Relocatable files have no program headers at all
+        let file_bytes = include_bytes!("test_files/main_ret0.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(Ok(false), elf_file.requests_executable_stack());
+        assert_eq!(Ok(None), elf_file.relro_range());
+    }
+
+    #[test]
+    fn find_64_bit_tls_template() {
+        let file_bytes = include_bytes!("test_files/tls.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let template = elf_file
+            .tls_template()
+            .expect("Failed to search program headers")
+            .expect("Expected a Tls segment");
+        assert_eq!(TlsTemplate::new(0x3F00, 8, 0x40, 0x20), template);
+        // _start accesses the variables at %fs:-0x40 and %fs:-0x20
+        assert_eq!(0x40, template.data_offset());
+
+        let file_bytes = include_bytes!("test_files/main_ret0.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(Ok(None), elf_file.tls_template());
+    }
+
+    #[test]
+    fn lookup_64_bit_symbols() {
+        fn lookup(file_bytes: &[u8], name: &str) -> Option<(u64, u64)> {
+            let elf_file =
+                Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+            elf_file
+                .lookup_symbol(name)
+                .expect("Failed to look up symbol")
+                .map(|symbol| (symbol.value(), symbol.size()))
+        }
+
+        // Through .gnu.hash
+        let file_bytes = include_bytes!("test_files/shared_library.elf64");
+        assert_eq!(Some((0x1030, 18)), lookup(file_bytes, "add_one"));
+        assert_eq!(Some((0x1050, 13)), lookup(file_bytes, "times_answer"));
+        assert_eq!(Some((0x400C, 4)), lookup(file_bytes, "exported_counter"));
+        assert_eq!(Some((0x1070, 3)), lookup(file_bytes, "weak_function"));
+        assert_eq!(None, lookup(file_bytes, "imported_function"));
+        assert_eq!(None, lookup(file_bytes, "helper"));
+        assert_eq!(None, lookup(file_bytes, "missing"));
+
+        // Through .hash, after turning .gnu.hash into a ProgramData section.
+        // Section headers start at 13048, and .gnu.hash is the 4th.
+        let mut file_bytes = file_bytes.to_vec();
+        file_bytes[13048 + 3 * 64 + 4] = 1;
+        assert_eq!(Some((0x1030, 18)), lookup(&file_bytes, "add_one"));
+        assert_eq!(Some((0x4008, 4)), lookup(&file_bytes, "answer"));
+        assert_eq!(None, lookup(&file_bytes, "imported_function"));
+        assert_eq!(None, lookup(&file_bytes, "missing"));
+
+        // Through the full symbol table, after doing the same to .hash
+        file_bytes[13048 + 2 * 64 + 4] = 1;
+        assert_eq!(Some((0x1060, 11)), lookup(&file_bytes, "increment_counter"));
+        assert_eq!(None, lookup(&file_bytes, "helper"));
+        assert_eq!(None, lookup(&file_bytes, "imported_function"));
+
+        let file_bytes = include_bytes!("test_files/main_ret0.elf64");
+        assert_eq!(Some((0, 65)), lookup(file_bytes, "main"));
+    }
+}
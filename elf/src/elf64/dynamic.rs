@@ -0,0 +1,460 @@
+use bitflags::bitflags;
+use core::convert::TryInto;
+
+use crate::{string_table::StringTable, ElfParseError, Endian};
+
+/// Size of a single `Elf64_Dyn` entry in the dynamic section.
+pub const DYNAMIC_ENTRY_SIZE: u64 = 16;
+
+/// The `d_tag` of a dynamic entry, which determines how its value is
+/// interpreted. Tags whose value is an address hold a virtual address, and
+/// tags that name something hold an offset into the dynamic string table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DynamicTag {
+    /// Marks the end of the dynamic array.
+    Null,
+    /// Name of a shared object this object depends on.
+    Needed,
+    /// Total size in bytes of the relocation entries for the PLT.
+    PltRelocationsSize,
+    /// Address of the PLT and/or GOT.
+    PltGot,
+    /// Address of the symbol hash table.
+    Hash,
+    /// Address of the dynamic string table.
+    StringTable,
+    /// Address of the dynamic symbol table.
+    SymbolTable,
+    /// Address of a relocation table with explicit addends.
+    Rela,
+    /// Total size in bytes of the `Rela` relocation table.
+    RelaSize,
+    /// Size in bytes of a single `Rela` relocation entry.
+    RelaEntrySize,
+    /// Size in bytes of the dynamic string table.
+    StringTableSize,
+    /// Size in bytes of a single dynamic symbol table entry.
+    SymbolEntrySize,
+    /// Address of the initialization function.
+    Init,
+    /// Address of the termination function.
+    Fini,
+    /// Name of this shared object.
+    SharedObjectName,
+    /// Library search path, superseded by `RunPath`.
+    RPath,
+    /// Symbol resolution starts from this object rather than the executable.
+    Symbolic,
+    /// Address of a relocation table with implicit addends.
+    Rel,
+    /// Total size in bytes of the `Rel` relocation table.
+    RelSize,
+    /// Size in bytes of a single `Rel` relocation entry.
+    RelEntrySize,
+    /// Type of relocation entry used by the PLT, either `Rel` or `Rela`.
+    PltRelocationType,
+    /// Used for debugging, its contents are not specified.
+    Debug,
+    /// Relocations might modify a non-writable segment.
+    TextRelocations,
+    /// Address of the relocation entries for the PLT.
+    JumpRelocations,
+    /// All relocations must be processed before control is transferred to
+    /// the program.
+    BindNow,
+    /// Address of the array of initialization functions.
+    InitArray,
+    /// Address of the array of termination functions.
+    FiniArray,
+    /// Size in bytes of the `InitArray` array.
+    InitArraySize,
+    /// Size in bytes of the `FiniArray` array.
+    FiniArraySize,
+    /// Library search path.
+    RunPath,
+    /// Flags for this object, see `DynamicFlags`.
+    Flags,
+    /// Address of the array of pre-initialization functions.
+    PreInitArray,
+    /// Size in bytes of the `PreInitArray` array.
+    PreInitArraySize,
+    /// Address of the `SymbolTableSectionHeaderIndex` section of the dynamic
+    /// symbol table.
+    SymbolTableSectionHeaderIndex,
+    /// Address of the GNU-style symbol hash table.
+    GnuHash,
+    /// Address of the symbol version table.
+    VersionSymbols,
+    /// Number of `R_X86_64_RELATIVE` entries at the start of the `Rela`
+    /// relocation table.
+    RelaCount,
+    /// Number of relative entries at the start of the `Rel` relocation table.
+    RelCount,
+    /// GNU-specific flags for this object.
+    Flags1,
+    /// Address of the version definition table.
+    VersionDefinitions,
+    /// Number of entries in the version definition table.
+    VersionDefinitionCount,
+    /// Address of the version dependency table.
+    VersionNeeded,
+    /// Number of entries in the version dependency table.
+    VersionNeededCount,
+    /// Any other value in the inclusive range from 0x6000000D through
+    /// 0x6FFFFFFF, which is reserved for operating system-specific semantics.
+    OperatingSystemSpecific(u64),
+    /// Values in the inclusive range from 0x70000000 through 0x7FFFFFFF are
+    /// reserved for processor-specific semantics.
+    ProcessorSpecific(u64),
+    /// A tag this parser doesn't know about. Unknown entries can be ignored.
+    Unknown(u64),
+}
+
+impl From<u64> for DynamicTag {
+    fn from(value: u64) -> Self {
+        match value {
+            0 => Self::Null,
+            1 => Self::Needed,
+            2 => Self::PltRelocationsSize,
+            3 => Self::PltGot,
+            4 => Self::Hash,
+            5 => Self::StringTable,
+            6 => Self::SymbolTable,
+            7 => Self::Rela,
+            8 => Self::RelaSize,
+            9 => Self::RelaEntrySize,
+            10 => Self::StringTableSize,
+            11 => Self::SymbolEntrySize,
+            12 => Self::Init,
+            13 => Self::Fini,
+            14 => Self::SharedObjectName,
+            15 => Self::RPath,
+            16 => Self::Symbolic,
+            17 => Self::Rel,
+            18 => Self::RelSize,
+            19 => Self::RelEntrySize,
+            20 => Self::PltRelocationType,
+            21 => Self::Debug,
+            22 => Self::TextRelocations,
+            23 => Self::JumpRelocations,
+            24 => Self::BindNow,
+            25 => Self::InitArray,
+            26 => Self::FiniArray,
+            27 => Self::InitArraySize,
+            28 => Self::FiniArraySize,
+            29 => Self::RunPath,
+            30 => Self::Flags,
+            32 => Self::PreInitArray,
+            33 => Self::PreInitArraySize,
+            34 => Self::SymbolTableSectionHeaderIndex,
+            0x6FFFFEF5 => Self::GnuHash,
+            0x6FFFFFF0 => Self::VersionSymbols,
+            0x6FFFFFF9 => Self::RelaCount,
+            0x6FFFFFFA => Self::RelCount,
+            0x6FFFFFFB => Self::Flags1,
+            0x6FFFFFFC => Self::VersionDefinitions,
+            0x6FFFFFFD => Self::VersionDefinitionCount,
+            0x6FFFFFFE => Self::VersionNeeded,
+            0x6FFFFFFF => Self::VersionNeededCount,
+            0x6000000D..=0x6FFFFFFF => Self::OperatingSystemSpecific(value),
+            0x70000000..=0x7FFFFFFF => Self::ProcessorSpecific(value),
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+impl From<DynamicTag> for u64 {
+    fn from(tag: DynamicTag) -> Self {
+        match tag {
+            DynamicTag::Null => 0,
+            DynamicTag::Needed => 1,
+            DynamicTag::PltRelocationsSize => 2,
+            DynamicTag::PltGot => 3,
+            DynamicTag::Hash => 4,
+            DynamicTag::StringTable => 5,
+            DynamicTag::SymbolTable => 6,
+            DynamicTag::Rela => 7,
+            DynamicTag::RelaSize => 8,
+            DynamicTag::RelaEntrySize => 9,
+            DynamicTag::StringTableSize => 10,
+            DynamicTag::SymbolEntrySize => 11,
+            DynamicTag::Init => 12,
+            DynamicTag::Fini => 13,
+            DynamicTag::SharedObjectName => 14,
+            DynamicTag::RPath => 15,
+            DynamicTag::Symbolic => 16,
+            DynamicTag::Rel => 17,
+            DynamicTag::RelSize => 18,
+            DynamicTag::RelEntrySize => 19,
+            DynamicTag::PltRelocationType => 20,
+            DynamicTag::Debug => 21,
+            DynamicTag::TextRelocations => 22,
+            DynamicTag::JumpRelocations => 23,
+            DynamicTag::BindNow => 24,
+            DynamicTag::InitArray => 25,
+            DynamicTag::FiniArray => 26,
+            DynamicTag::InitArraySize => 27,
+            DynamicTag::FiniArraySize => 28,
+            DynamicTag::RunPath => 29,
+            DynamicTag::Flags => 30,
+            DynamicTag::PreInitArray => 32,
+            DynamicTag::PreInitArraySize => 33,
+            DynamicTag::SymbolTableSectionHeaderIndex => 34,
+            DynamicTag::GnuHash => 0x6FFFFEF5,
+            DynamicTag::VersionSymbols => 0x6FFFFFF0,
+            DynamicTag::RelaCount => 0x6FFFFFF9,
+            DynamicTag::RelCount => 0x6FFFFFFA,
+            DynamicTag::Flags1 => 0x6FFFFFFB,
+            DynamicTag::VersionDefinitions => 0x6FFFFFFC,
+            DynamicTag::VersionDefinitionCount => 0x6FFFFFFD,
+            DynamicTag::VersionNeeded => 0x6FFFFFFE,
+            DynamicTag::VersionNeededCount => 0x6FFFFFFF,
+            DynamicTag::OperatingSystemSpecific(value)
+            | DynamicTag::ProcessorSpecific(value)
+            | DynamicTag::Unknown(value) => value,
+        }
+    }
+}
+
+impl DynamicTag {
+    /// Whether the entry's value is an offset into the dynamic string table.
+    fn is_string(&self) -> bool {
+        matches!(
+            self,
+            Self::Needed | Self::SharedObjectName | Self::RPath | Self::RunPath
+        )
+    }
+}
+
+bitflags! {
+    /// Values of a `DynamicTag::Flags` entry. Source:
+    /// https://refspecs.linuxbase.org/elf/gabi4+/ch5.dynamic.html.
+    #[repr(transparent)]
+    pub struct DynamicFlags: u64 {
+        /// The object may reference the `$ORIGIN` substitution string.
+        const ORIGIN = 1;
+        /// Symbol resolution starts from this object rather than the
+        /// executable.
+        const SYMBOLIC = 1 << 1;
+        /// Relocations might modify a non-writable segment.
+        const TEXTREL = 1 << 2;
+        /// All relocations must be processed before control is transferred to
+        /// the program.
+        const BIND_NOW = 1 << 3;
+        /// The object uses the static thread-local storage model, so it can't
+        /// be loaded with `dlopen`.
+        const STATIC_TLS = 1 << 4;
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DynamicEntry<'a> {
+    tag: DynamicTag,
+    /// An integer, a virtual address, or an offset into the dynamic string
+    /// table, depending on `tag`.
+    value: u64,
+    /// The string `value` refers to, for tags that name something.
+    name: Option<&'a str>,
+}
+
+impl<'a> DynamicEntry<'a> {
+    pub fn new(tag: DynamicTag, value: u64, name: Option<&'a str>) -> Self {
+        Self { tag, value, name }
+    }
+
+    pub fn tag(&self) -> DynamicTag {
+        self.tag
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// The name held by `Needed`, `SharedObjectName`, `RPath` and `RunPath`
+    /// entries, resolved through the dynamic string table.
+    pub fn name(&self) -> Option<&'a str> {
+        self.name
+    }
+
+    /// The flags held by a `Flags` entry.
+    pub fn flags(&self) -> Option<DynamicFlags> {
+        match self.tag {
+            DynamicTag::Flags => Some(DynamicFlags::from_bits_truncate(self.value)),
+            _ => None,
+        }
+    }
+}
+
+/// Iterates over the entries of a dynamic section, up to the `Null` entry that
+/// terminates it.
+pub struct DynamicIterator<'a> {
+    current_index: usize,
+    entry_count: usize,
+    entry_size: usize,
+    data: &'a [u8],
+    endianness: Endian,
+    /// The dynamic string table. Only needed if the section has entries that
+    /// name something.
+    names: Option<StringTable<'a>>,
+}
+
+impl<'a> DynamicIterator<'a> {
+    pub fn new(
+        data: &'a [u8],
+        entry_size: u64,
+        endianness: Endian,
+        names: Option<StringTable<'a>>,
+    ) -> Result<Self, ElfParseError> {
+        if entry_size < DYNAMIC_ENTRY_SIZE {
+            return Err(ElfParseError::InvalidDynamicEntrySize(entry_size));
+        }
+
+        Ok(Self {
+            current_index: 0,
+            entry_count: (data.len() as u64 / entry_size) as usize,
+            entry_size: entry_size as usize,
+            data,
+            endianness,
+            names,
+        })
+    }
+
+    fn parse_entry(&self, byte_offset: usize) -> Result<DynamicEntry<'a>, ElfParseError> {
+        let tag = DynamicTag::from(self.endianness.get_u64(self.data, byte_offset)?);
+        let value = self.endianness.get_u64(self.data, byte_offset + 8)?;
+
+        let name = if tag.is_string() {
+            let names = self
+                .names
+                .as_ref()
+                .ok_or(ElfParseError::MissingStringTable)?;
+            let offset = value
+                .try_into()
+                .map_err(|_| ElfParseError::InvalidStringOffset(u32::MAX))?;
+            Some(names.get(offset)?)
+        } else {
+            None
+        };
+
+        Ok(DynamicEntry { tag, value, name })
+    }
+}
+
+impl<'a> Iterator for DynamicIterator<'a> {
+    type Item = Result<DynamicEntry<'a>, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_index >= self.entry_count {
+            return None;
+        }
+
+        let byte_offset = self.current_index * self.entry_size;
+        self.current_index += 1;
+
+        match self.parse_entry(byte_offset) {
+            Ok(entry) if entry.tag == DynamicTag::Null => {
+                // Entries after the terminator are ignored
+                self.current_index = self.entry_count;
+                None
+            }
+            entry => Some(entry),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dynamic_entries(entries: &[(u64, u64)]) -> [u8; 64] {
+        let mut data = [0; 64];
+        for (index, (tag, value)) in entries.iter().enumerate() {
+            let byte_offset = index * DYNAMIC_ENTRY_SIZE as usize;
+            data[byte_offset..][..8].copy_from_slice(&tag.to_le_bytes());
+            data[byte_offset + 8..][..8].copy_from_slice(&value.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn parse_dynamic_tags() {
+        assert_eq!(DynamicTag::Needed, DynamicTag::from(1));
+        assert_eq!(DynamicTag::RunPath, DynamicTag::from(29));
+        assert_eq!(DynamicTag::GnuHash, DynamicTag::from(0x6FFFFEF5));
+        assert_eq!(
+            DynamicTag::OperatingSystemSpecific(0x6FFFFE00),
+            DynamicTag::from(0x6FFFFE00)
+        );
+        assert_eq!(
+            DynamicTag::ProcessorSpecific(0x70000001),
+            DynamicTag::from(0x70000001)
+        );
+        assert_eq!(DynamicTag::Unknown(31), DynamicTag::from(31));
+    }
+
+    #[test]
+    fn encode_dynamic_tags() {
+        for &value in &[1, 29, 0x6FFFFEF5, 0x6FFFFFFB, 0x6FFFFE00, 0x70000001, 31] {
+            assert_eq!(value, u64::from(DynamicTag::from(value)));
+        }
+    }
+
+    #[test]
+    fn stop_at_null_entry() {
+        let data = dynamic_entries(&[(30, 0x9), (0, 0), (1, 1)]);
+        let mut entries = DynamicIterator::new(&data, DYNAMIC_ENTRY_SIZE, Endian::Little, None)
+            .expect("Failed to create dynamic entry iterator");
+
+        let flags = entries
+            .next()
+            .unwrap()
+            .expect("Failed to parse dynamic entry");
+        assert_eq!(DynamicTag::Flags, flags.tag());
+        assert_eq!(
+            Some(DynamicFlags::ORIGIN | DynamicFlags::BIND_NOW),
+            flags.flags()
+        );
+        assert_eq!(None, entries.next());
+        assert_eq!(None, entries.next());
+    }
+
+    #[test]
+    fn resolve_entry_names() {
+        let data = dynamic_entries(&[(1, 1), (14, 11), (29, 19)]);
+        let entries = DynamicIterator::new(
+            &data,
+            DYNAMIC_ENTRY_SIZE,
+            Endian::Little,
+            Some(StringTable::new(b"\0libc.so.6\0libx.so\0$ORIGIN\0")),
+        )
+        .expect("Failed to create dynamic entry iterator")
+        .map(|entry| entry.expect("Failed to parse dynamic entry"));
+
+        let expected = [
+            (DynamicTag::Needed, "libc.so.6"),
+            (DynamicTag::SharedObjectName, "libx.so"),
+            (DynamicTag::RunPath, "$ORIGIN"),
+        ];
+        for ((tag, name), entry) in expected.iter().zip(entries) {
+            assert_eq!(*tag, entry.tag());
+            assert_eq!(Some(*name), entry.name());
+        }
+    }
+
+    #[test]
+    fn missing_dynamic_string_table() {
+        let data = dynamic_entries(&[(1, 1)]);
+        let mut entries = DynamicIterator::new(&data, DYNAMIC_ENTRY_SIZE, Endian::Little, None)
+            .expect("Failed to create dynamic entry iterator");
+
+        assert_eq!(Some(Err(ElfParseError::MissingStringTable)), entries.next());
+    }
+
+    #[test]
+    fn invalid_dynamic_entry_size() {
+        assert!(matches!(
+            DynamicIterator::new(&[], 8, Endian::Little, None),
+            Err(ElfParseError::InvalidDynamicEntrySize(8))
+        ));
+    }
+}
@@ -0,0 +1,235 @@
+use crate::{
+    elf64::symbols::{Elf64Symbol, SymbolBinding, SymbolIterator, SymbolSectionIndex},
+    ElfParseError, Endian,
+};
+
+/// The hash function of `Hash` sections, from the System V ABI.
+pub fn sysv_hash(name: &[u8]) -> u32 {
+    let mut hash: u32 = 0;
+    for &byte in name {
+        hash = (hash << 4).wrapping_add(byte as u32);
+        let high = hash & 0xF000_0000;
+        if high != 0 {
+            hash ^= high >> 24;
+        }
+        hash &= !high;
+    }
+
+    hash
+}
+
+/// The hash function of `GnuHash` sections, Bernstein's djb2.
+pub fn gnu_hash(name: &[u8]) -> u32 {
+    name.iter().fold(5381u32, |hash, &byte| {
+        hash.wrapping_mul(33).wrapping_add(byte as u32)
+    })
+}
+
+/// Whether `symbol` is a definition of `name` that other files can link to.
+pub(super) fn defines(symbol: &Elf64Symbol, name: &str) -> bool {
+    symbol.name() == name
+        && symbol.binding() != SymbolBinding::Local
+        && symbol.section_index() != SymbolSectionIndex::Undefined
+}
+
+/// A System V symbol hash table, the contents of a `Hash` section.
+///
+/// The table starts with `nbucket` and `nchain` words, followed by `nbucket`
+/// buckets and `nchain` chain entries. A bucket holds the index of the first
+/// symbol whose name hashes to it, and the chain entry of each symbol holds the
+/// index of the next one, or 0 at the end of the chain.
+#[derive(Debug)]
+pub struct SysvHashTable<'a> {
+    data: &'a [u8],
+    endianness: Endian,
+    bucket_count: u32,
+    chain_count: u32,
+}
+
+impl<'a> SysvHashTable<'a> {
+    pub fn new(data: &'a [u8], endianness: Endian) -> Result<Self, ElfParseError> {
+        let bucket_count = endianness.get_u32(data, 0)?;
+        let chain_count = endianness.get_u32(data, 4)?;
+        let words = 2 + bucket_count as u64 + chain_count as u64;
+        if bucket_count == 0 || words * 4 > data.len() as u64 {
+            return Err(ElfParseError::InvalidHashTable);
+        }
+
+        Ok(Self {
+            data,
+            endianness,
+            bucket_count,
+            chain_count,
+        })
+    }
+
+    /// Find the definition of `name` in `symbols`, the symbol table the hash
+    /// table was built for.
+    pub fn lookup(
+        &self,
+        name: &str,
+        symbols: &SymbolIterator<'a>,
+    ) -> Result<Option<Elf64Symbol<'a>>, ElfParseError> {
+        let bucket = sysv_hash(name.as_bytes()) % self.bucket_count;
+        let mut index = self.word(2 + bucket)?;
+
+        // A chain can't be longer than the table, unless it has a cycle
+        for _ in 0..self.chain_count {
+            // Index 0 is the undefined symbol, which ends the chain
+            if index == 0 {
+                break;
+            }
+
+            let symbol = symbols.symbol(index)?;
+            if defines(&symbol, name) {
+                return Ok(Some(symbol));
+            }
+
+            if index >= self.chain_count {
+                return Err(ElfParseError::InvalidHashTable);
+            }
+            index = self.word(2 + self.bucket_count + index)?;
+        }
+
+        Ok(None)
+    }
+
+    fn word(&self, index: u32) -> Result<u32, ElfParseError> {
+        self.endianness.get_u32(self.data, index as usize * 4)
+    }
+}
+
+/// A GNU symbol hash table, the contents of a `GnuHash` section.
+///
+/// The table starts with a header of `nbuckets`, `symoffset`, `bloom_size` and
+/// `bloom_shift` words, followed by a bloom filter of `bloom_size` 64-bit
+/// words, `nbuckets` buckets and the chain. Only the symbols from `symoffset`
+/// onwards are hashed, sorted by bucket. A bucket holds the index of the first
+/// symbol in it, and the chain holds the hash of each symbol, with its lowest
+/// bit set on the last symbol of a bucket.
+#[derive(Debug)]
+pub struct GnuHashTable<'a> {
+    data: &'a [u8],
+    endianness: Endian,
+    bucket_count: u32,
+    symbol_offset: u32,
+    bloom_size: u32,
+    bloom_shift: u32,
+}
+
+impl<'a> GnuHashTable<'a> {
+    pub fn new(data: &'a [u8], endianness: Endian) -> Result<Self, ElfParseError> {
+        let bucket_count = endianness.get_u32(data, 0)?;
+        let symbol_offset = endianness.get_u32(data, 4)?;
+        let bloom_size = endianness.get_u32(data, 8)?;
+        let bloom_shift = endianness.get_u32(data, 12)?;
+        if bucket_count == 0 || !bloom_size.is_power_of_two() {
+            return Err(ElfParseError::InvalidHashTable);
+        }
+
+        Ok(Self {
+            data,
+            endianness,
+            bucket_count,
+            symbol_offset,
+            bloom_size,
+            bloom_shift,
+        })
+    }
+
+    /// Find the definition of `name` in `symbols`, the symbol table the hash
+    /// table was built for.
+    pub fn lookup(
+        &self,
+        name: &str,
+        symbols: &SymbolIterator<'a>,
+    ) -> Result<Option<Elf64Symbol<'a>>, ElfParseError> {
+        let hash = gnu_hash(name.as_bytes());
+
+        // The filter has two bits set for every hashed symbol. If either is
+        // clear, the symbol isn't in the table.
+        let bloom_word = self
+            .endianness
+            .get_u64(self.data, 16 + ((hash / 64) % self.bloom_size) as usize * 8)?;
+        let mask = (1 << (hash % 64)) | (1 << ((hash >> (self.bloom_shift % 32)) % 64));
+        if bloom_word & mask != mask {
+            return Ok(None);
+        }
+
+        let buckets_offset = 16 + self.bloom_size as usize * 8;
+        let chain_offset = buckets_offset + self.bucket_count as usize * 4;
+        let mut index = self.endianness.get_u32(
+            self.data,
+            buckets_offset + (hash % self.bucket_count) as usize * 4,
+        )?;
+        // Empty buckets hold 0
+        if index < self.symbol_offset || index == 0 {
+            return Ok(None);
+        }
+
+        loop {
+            let chain_hash = self.endianness.get_u32(
+                self.data,
+                chain_offset + (index - self.symbol_offset) as usize * 4,
+            )?;
+            // The lowest bit of the chain hash marks the end of the bucket
+            if chain_hash | 1 == hash | 1 {
+                let symbol = symbols.symbol(index)?;
+                if defines(&symbol, name) {
+                    return Ok(Some(symbol));
+                }
+            }
+            if chain_hash & 1 == 1 {
+                return Ok(None);
+            }
+
+            index += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_names() {
+        assert_eq!(0, sysv_hash(b""));
+        assert_eq!(0x07AA_6625, sysv_hash(b"add_one"));
+        assert_eq!(0x0779_05A6, sysv_hash(b"printf"));
+        assert_eq!(5381, gnu_hash(b""));
+        assert_eq!(0x1F1C_128F, gnu_hash(b"add_one"));
+        assert_eq!(0x156B_2BB8, gnu_hash(b"printf"));
+    }
+
+    #[test]
+    fn reject_invalid_hash_tables() {
+        let data = [0u8; 16];
+        assert!(matches!(
+            SysvHashTable::new(&data, Endian::Little),
+            Err(ElfParseError::InvalidHashTable)
+        ));
+        assert!(matches!(
+            GnuHashTable::new(&data[..8], Endian::Little),
+            Err(ElfParseError::Truncated { .. })
+        ));
+
+        // 1 bucket and 8 chain entries don't fit in 16 bytes
+        let mut data = [0u8; 16];
+        data[0] = 1;
+        data[4] = 8;
+        assert!(matches!(
+            SysvHashTable::new(&data, Endian::Little),
+            Err(ElfParseError::InvalidHashTable)
+        ));
+
+        // A bloom filter of 3 words
+        let mut data = [0u8; 16];
+        data[0] = 1;
+        data[8] = 3;
+        assert!(matches!(
+            GnuHashTable::new(&data, Endian::Little),
+            Err(ElfParseError::InvalidHashTable)
+        ));
+    }
+}
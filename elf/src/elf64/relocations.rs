@@ -0,0 +1,376 @@
+use crate::{ElfParseError, Endian};
+
+/// Size of a single `Elf64_Rela` entry in a `RelA` section.
+pub const RELA_ENTRY_SIZE: u64 = 24;
+/// Size of a single `Elf64_Rel` entry in a `Rel` section.
+pub const REL_ENTRY_SIZE: u64 = 16;
+
+/// x86_64 relocation types, as defined by the System V AMD64 psABI. In the
+/// descriptions, S is the value of the symbol, A is the addend, B is the base
+/// address the file was loaded at, P is the address of the storage unit being
+/// relocated, and G and GOT refer to the global offset table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelocationType {
+    /// R_X86_64_NONE: no relocation.
+    None,
+    /// R_X86_64_64: S + A, as 64 bits.
+    Direct64,
+    /// R_X86_64_PC32: S + A - P, as a signed 32-bit value.
+    PcRelative32,
+    /// R_X86_64_GOT32: G + A.
+    Got32,
+    /// R_X86_64_PLT32: L + A - P, where L is the address of the symbol's
+    /// procedure linkage table entry.
+    Plt32,
+    /// R_X86_64_COPY: copy the symbol's data from the shared object.
+    Copy,
+    /// R_X86_64_GLOB_DAT: S, written to a GOT entry.
+    GlobalData,
+    /// R_X86_64_JUMP_SLOT: S, written to a PLT GOT entry.
+    JumpSlot,
+    /// R_X86_64_RELATIVE: B + A.
+    Relative,
+    /// R_X86_64_GOTPCREL: G + GOT + A - P.
+    GotPcRelative,
+    /// R_X86_64_32: S + A, zero extended from 32 bits.
+    Direct32,
+    /// R_X86_64_32S: S + A, sign extended from 32 bits.
+    Direct32Signed,
+    /// R_X86_64_16: S + A, as 16 bits.
+    Direct16,
+    /// R_X86_64_PC16: S + A - P, as 16 bits.
+    PcRelative16,
+    /// R_X86_64_8: S + A, as 8 bits.
+    Direct8,
+    /// R_X86_64_PC8: S + A - P, as 8 bits.
+    PcRelative8,
+    /// R_X86_64_DTPMOD64: ID of the module containing the symbol.
+    DtpMod64,
+    /// R_X86_64_DTPOFF64: offset of the symbol in its module's TLS block.
+    DtpOff64,
+    /// R_X86_64_TPOFF64: offset of the symbol from the thread pointer.
+    TpOff64,
+    /// R_X86_64_TLSGD: PC-relative offset to a GD GOT entry.
+    TlsGd,
+    /// R_X86_64_TLSLD: PC-relative offset to an LD GOT entry.
+    TlsLd,
+    /// R_X86_64_DTPOFF32: offset of the symbol in its module's TLS block, as
+    /// 32 bits.
+    DtpOff32,
+    /// R_X86_64_GOTTPOFF: PC-relative offset to an IE GOT entry.
+    GotTpOff,
+    /// R_X86_64_TPOFF32: offset of the symbol from the thread pointer, as 32
+    /// bits.
+    TpOff32,
+    /// R_X86_64_PC64: S + A - P, as 64 bits.
+    PcRelative64,
+    /// R_X86_64_GOTOFF64: S + A - GOT.
+    GotOff64,
+    /// R_X86_64_GOTPC32: GOT + A - P.
+    GotPc32,
+    /// R_X86_64_SIZE32: Z + A, where Z is the size of the symbol, as 32 bits.
+    Size32,
+    /// R_X86_64_SIZE64: Z + A, where Z is the size of the symbol, as 64 bits.
+    Size64,
+    /// R_X86_64_IRELATIVE: the result of calling the resolver function at
+    /// B + A.
+    IndirectRelative,
+    /// R_X86_64_GOTPCRELX: G + GOT + A - P, relaxable by the linker.
+    GotPcRelativeRelaxable,
+    /// R_X86_64_REX_GOTPCRELX: G + GOT + A - P, relaxable by the linker.
+    RexGotPcRelativeRelaxable,
+    /// A relocation type this parser doesn't know about.
+    Unknown(u32),
+}
+
+impl From<u32> for RelocationType {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::None,
+            1 => Self::Direct64,
+            2 => Self::PcRelative32,
+            3 => Self::Got32,
+            4 => Self::Plt32,
+            5 => Self::Copy,
+            6 => Self::GlobalData,
+            7 => Self::JumpSlot,
+            8 => Self::Relative,
+            9 => Self::GotPcRelative,
+            10 => Self::Direct32,
+            11 => Self::Direct32Signed,
+            12 => Self::Direct16,
+            13 => Self::PcRelative16,
+            14 => Self::Direct8,
+            15 => Self::PcRelative8,
+            16 => Self::DtpMod64,
+            17 => Self::DtpOff64,
+            18 => Self::TpOff64,
+            19 => Self::TlsGd,
+            20 => Self::TlsLd,
+            21 => Self::DtpOff32,
+            22 => Self::GotTpOff,
+            23 => Self::TpOff32,
+            24 => Self::PcRelative64,
+            25 => Self::GotOff64,
+            26 => Self::GotPc32,
+            32 => Self::Size32,
+            33 => Self::Size64,
+            37 => Self::IndirectRelative,
+            41 => Self::GotPcRelativeRelaxable,
+            42 => Self::RexGotPcRelativeRelaxable,
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+/// A relocation entry with an explicit addend, from a `RelA` section.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Elf64Rela {
+    /// Location to apply the relocation at. In relocatable files this is an
+    /// offset from the start of the section being relocated, and in executable
+    /// and shared object files it is a virtual address.
+    offset: u64,
+    /// Index of the symbol the relocation refers to in the associated symbol
+    /// table, or 0 if it refers to no symbol.
+    symbol_index: u32,
+    relocation_type: RelocationType,
+    addend: i64,
+}
+
+impl Elf64Rela {
+    pub fn new(
+        offset: u64,
+        symbol_index: u32,
+        relocation_type: RelocationType,
+        addend: i64,
+    ) -> Self {
+        Self {
+            offset,
+            symbol_index,
+            relocation_type,
+            addend,
+        }
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn symbol_index(&self) -> u32 {
+        self.symbol_index
+    }
+
+    pub fn relocation_type(&self) -> RelocationType {
+        self.relocation_type
+    }
+
+    pub fn addend(&self) -> i64 {
+        self.addend
+    }
+}
+
+/// A relocation entry from a `Rel` section. Its addend is implicit, stored in
+/// the location being relocated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Elf64Rel {
+    /// Location to apply the relocation at, see `Elf64Rela::offset`.
+    offset: u64,
+    /// Index of the symbol the relocation refers to in the associated symbol
+    /// table, or 0 if it refers to no symbol.
+    symbol_index: u32,
+    relocation_type: RelocationType,
+}
+
+impl Elf64Rel {
+    pub fn new(offset: u64, symbol_index: u32, relocation_type: RelocationType) -> Self {
+        Self {
+            offset,
+            symbol_index,
+            relocation_type,
+        }
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn symbol_index(&self) -> u32 {
+        self.symbol_index
+    }
+
+    pub fn relocation_type(&self) -> RelocationType {
+        self.relocation_type
+    }
+}
+
+/// Split `r_info` into its symbol index and relocation type.
+fn split_info(info: u64) -> (u32, RelocationType) {
+    ((info >> 32) as u32, RelocationType::from(info as u32))
+}
+
+pub struct RelaIterator<'a> {
+    current_index: usize,
+    entry_count: usize,
+    entry_size: usize,
+    data: &'a [u8],
+    endianness: Endian,
+}
+
+impl<'a> RelaIterator<'a> {
+    pub fn new(data: &'a [u8], entry_size: u64, endianness: Endian) -> Result<Self, ElfParseError> {
+        if entry_size < RELA_ENTRY_SIZE {
+            return Err(ElfParseError::InvalidRelocationEntrySize(entry_size));
+        }
+
+        Ok(Self {
+            current_index: 0,
+            entry_count: (data.len() as u64 / entry_size) as usize,
+            entry_size: entry_size as usize,
+            data,
+            endianness,
+        })
+    }
+
+    fn parse_relocation(&self, byte_offset: usize) -> Result<Elf64Rela, ElfParseError> {
+        let (symbol_index, relocation_type) =
+            split_info(self.endianness.get_u64(self.data, byte_offset + 8)?);
+
+        Ok(Elf64Rela {
+            offset: self.endianness.get_u64(self.data, byte_offset)?,
+            symbol_index,
+            relocation_type,
+            addend: self.endianness.get_u64(self.data, byte_offset + 16)? as i64,
+        })
+    }
+}
+
+impl<'a> Iterator for RelaIterator<'a> {
+    type Item = Result<Elf64Rela, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_index == self.entry_count {
+            None
+        } else {
+            let relocation = self.parse_relocation(self.current_index * self.entry_size);
+            self.current_index += 1;
+            Some(relocation)
+        }
+    }
+}
+
+pub struct RelIterator<'a> {
+    current_index: usize,
+    entry_count: usize,
+    entry_size: usize,
+    data: &'a [u8],
+    endianness: Endian,
+}
+
+impl<'a> RelIterator<'a> {
+    pub fn new(data: &'a [u8], entry_size: u64, endianness: Endian) -> Result<Self, ElfParseError> {
+        if entry_size < REL_ENTRY_SIZE {
+            return Err(ElfParseError::InvalidRelocationEntrySize(entry_size));
+        }
+
+        Ok(Self {
+            current_index: 0,
+            entry_count: (data.len() as u64 / entry_size) as usize,
+            entry_size: entry_size as usize,
+            data,
+            endianness,
+        })
+    }
+
+    fn parse_relocation(&self, byte_offset: usize) -> Result<Elf64Rel, ElfParseError> {
+        let (symbol_index, relocation_type) =
+            split_info(self.endianness.get_u64(self.data, byte_offset + 8)?);
+
+        Ok(Elf64Rel {
+            offset: self.endianness.get_u64(self.data, byte_offset)?,
+            symbol_index,
+            relocation_type,
+        })
+    }
+}
+
+impl<'a> Iterator for RelIterator<'a> {
+    type Item = Result<Elf64Rel, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_index == self.entry_count {
+            None
+        } else {
+            let relocation = self.parse_relocation(self.current_index * self.entry_size);
+            self.current_index += 1;
+            Some(relocation)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rela_entries() {
+        let mut data = [0; 48];
+        data[..8].copy_from_slice(&0x403FF0u64.to_le_bytes());
+        data[8..16].copy_from_slice(&0x0000_0002_0000_0006u64.to_le_bytes());
+        data[24..32].copy_from_slice(&0x21u64.to_le_bytes());
+        data[32..40].copy_from_slice(&0x0000_0000_0000_0008u64.to_le_bytes());
+        data[40..48].copy_from_slice(&(-8i64).to_le_bytes());
+
+        let mut relocations = RelaIterator::new(&data, RELA_ENTRY_SIZE, Endian::Little)
+            .expect("Failed to create relocation iterator");
+        assert_eq!(
+            Some(Ok(Elf64Rela::new(
+                0x403FF0,
+                2,
+                RelocationType::GlobalData,
+                0
+            ))),
+            relocations.next()
+        );
+        assert_eq!(
+            Some(Ok(Elf64Rela::new(0x21, 0, RelocationType::Relative, -8))),
+            relocations.next()
+        );
+        assert_eq!(None, relocations.next());
+    }
+
+    #[test]
+    fn parse_rel_entries() {
+        let mut data = [0; 16];
+        data[..8].copy_from_slice(&0x1000u64.to_be_bytes());
+        data[8..16].copy_from_slice(&0x0000_0005_0000_0002u64.to_be_bytes());
+
+        let mut relocations = RelIterator::new(&data, REL_ENTRY_SIZE, Endian::Big)
+            .expect("Failed to create relocation iterator");
+        assert_eq!(
+            Some(Ok(Elf64Rel::new(0x1000, 5, RelocationType::PcRelative32))),
+            relocations.next()
+        );
+        assert_eq!(None, relocations.next());
+    }
+
+    #[test]
+    fn parse_unknown_relocation_type() {
+        assert_eq!(RelocationType::Unknown(0xFF), RelocationType::from(0xFF));
+        assert_eq!(
+            RelocationType::RexGotPcRelativeRelaxable,
+            RelocationType::from(42)
+        );
+    }
+
+    #[test]
+    fn invalid_relocation_entry_size() {
+        assert!(matches!(
+            RelaIterator::new(&[], REL_ENTRY_SIZE, Endian::Little),
+            Err(ElfParseError::InvalidRelocationEntrySize(16))
+        ));
+        assert!(matches!(
+            RelIterator::new(&[], 8, Endian::Little),
+            Err(ElfParseError::InvalidRelocationEntrySize(8))
+        ));
+    }
+}
@@ -1,6 +1,6 @@
-use core::convert::{TryFrom, TryInto};
+use core::convert::TryFrom;
 
-use crate::elf::{ElfParseError, Endian};
+use crate::{string_table::StringTable, ElfParseError, Endian};
 use bitflags::bitflags;
 
 use super::Elf64SectionHeaderSummary;
@@ -87,6 +87,9 @@ pub enum SectionHeaderType {
     /// entry must be `SHN_UNDEF` (0).
     /// Source: https://refspecs.linuxbase.org/elf/gabi4+/ch4.sheader.html
     SymbolTableSectionHeaderIndex,
+    /// The section holds a GNU-style symbol hash table, which adds a bloom
+    /// filter to the `Hash` table's buckets to reject missing symbols early.
+    GnuHash,
     /// Values in the range from 0x60000000 through 0x6fffffff inclusive are
     /// reserved for operating system-specific semantics.
     OperatingSystemSpecific(u32),
@@ -122,6 +125,7 @@ impl TryFrom<u32> for SectionHeaderType {
             16 => Ok(Self::PreinitArray),
             17 => Ok(Self::Group),
             18 => Ok(Self::SymbolTableSectionHeaderIndex),
+            0x6FFFFFF6 => Ok(Self::GnuHash),
             0x60000000..=0x6FFFFFFF => Ok(Self::OperatingSystemSpecific(value)),
             0x70000000..=0x7FFFFFFF => Ok(Self::ProcessorSpecific(value)),
             0x80000000..=0xFFFFFFFF => Ok(Self::UserApplicationSpecific(value)),
@@ -130,6 +134,34 @@ impl TryFrom<u32> for SectionHeaderType {
     }
 }
 
+impl From<&SectionHeaderType> for u32 {
+    fn from(header_type: &SectionHeaderType) -> Self {
+        match header_type {
+            SectionHeaderType::Null => 0,
+            SectionHeaderType::ProgramBits => 1,
+            SectionHeaderType::SymbolTable => 2,
+            SectionHeaderType::StringTable => 3,
+            SectionHeaderType::RelA => 4,
+            SectionHeaderType::Hash => 5,
+            SectionHeaderType::Dynamic => 6,
+            SectionHeaderType::Note => 7,
+            SectionHeaderType::NoBits => 8,
+            SectionHeaderType::Rel => 9,
+            SectionHeaderType::ShLib => 10,
+            SectionHeaderType::DynamicSymbols => 11,
+            SectionHeaderType::InitArray => 14,
+            SectionHeaderType::FinishArray => 15,
+            SectionHeaderType::PreinitArray => 16,
+            SectionHeaderType::Group => 17,
+            SectionHeaderType::SymbolTableSectionHeaderIndex => 18,
+            SectionHeaderType::GnuHash => 0x6FFFFFF6,
+            SectionHeaderType::OperatingSystemSpecific(value)
+            | SectionHeaderType::ProcessorSpecific(value)
+            | SectionHeaderType::UserApplicationSpecific(value) => *value,
+        }
+    }
+}
+
 bitflags! {
     /// Definitions sourced from
     /// https://refspecs.linuxbase.org/elf/gabi4+/ch4.sheader.html,
@@ -271,6 +303,7 @@ pub struct SectionHeader {
 }
 
 impl SectionHeader {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         name_index: u32,
         header_type: SectionHeaderType,
@@ -296,54 +329,36 @@ impl SectionHeader {
             section_entry_size,
         }
     }
-}
 
-pub struct SectionHeaderIterator<'a> {
-    current_index: u16,
-    data: &'a [u8],
-    endianness: Endian,
-    section_header_summary: &'a Elf64SectionHeaderSummary,
-}
-
-impl<'a> SectionHeaderIterator<'a> {
-    pub fn new(
-        data: &'a [u8],
+    /// Parse the section header at `index` in the section header table.
+    pub(super) fn parse(
+        data: &[u8],
         endianness: Endian,
-        section_header_summary: &'a Elf64SectionHeaderSummary,
+        section_header_summary: &Elf64SectionHeaderSummary,
+        index: u16,
     ) -> Result<Self, ElfParseError> {
-        Ok(Self {
-            current_index: 0,
-            data,
-            endianness,
-            section_header_summary,
-        })
-    }
-
-    fn parse_section_header(&self) -> Result<SectionHeader, ElfParseError> {
-        let byte_offset = self
-            .section_header_summary
-            .byte_offset(self.current_index)
-            .unwrap();
-        let name_index = self.endianness.get_u32(&self.data[byte_offset..])?;
-        let header_type =
-            SectionHeaderType::try_from(self.endianness.get_u32(&self.data[byte_offset + 4..])?)?;
-        let flags = self.endianness.get_u64(&self.data[byte_offset + 8..])?;
-        let address = match self.endianness.get_u64(&self.data[byte_offset + 16..])? {
+        let byte_offset = section_header_summary
+            .byte_offset(index)
+            .ok_or(ElfParseError::InvalidSectionIndex(index))?;
+        let name_index = endianness.get_u32(data, byte_offset)?;
+        let header_type = SectionHeaderType::try_from(endianness.get_u32(data, byte_offset + 4)?)?;
+        let flags = endianness.get_u64(data, byte_offset + 8)?;
+        let address = match endianness.get_u64(data, byte_offset + 16)? {
             0 => None,
             value => Some(value),
         };
-        let section_file_offset = self.endianness.get_u64(&self.data[byte_offset + 24..])?;
-        let section_size = self.endianness.get_u64(&self.data[byte_offset + 32..])?;
-        let section_link_index = match self.endianness.get_u32(&self.data[byte_offset + 40..])? {
+        let section_file_offset = endianness.get_u64(data, byte_offset + 24)?;
+        let section_size = endianness.get_u64(data, byte_offset + 32)?;
+        let section_link_index = match endianness.get_u32(data, byte_offset + 40)? {
             0 => None,
             value => Some(value),
         };
-        let info = match self.endianness.get_u32(&self.data[byte_offset + 44..])? {
+        let info = match endianness.get_u32(data, byte_offset + 44)? {
             0 => None,
             value => Some(value),
         };
-        let address_alignment = self.endianness.get_u64(&self.data[byte_offset + 48..])?;
-        let section_entry_size = match self.endianness.get_u64(&self.data[byte_offset + 56..])? {
+        let address_alignment = endianness.get_u64(data, byte_offset + 48)?;
+        let section_entry_size = match endianness.get_u64(data, byte_offset + 56)? {
             0 => None,
             value => Some(value),
         };
@@ -361,6 +376,70 @@ impl<'a> SectionHeaderIterator<'a> {
             section_entry_size,
         })
     }
+
+    /// Resolve this section's name through the section header string table.
+    /// See `Elf64File::section_names`.
+    pub fn name<'a>(&self, section_names: &StringTable<'a>) -> Result<&'a str, ElfParseError> {
+        section_names.get(self.name_index)
+    }
+
+    pub fn header_type(&self) -> &SectionHeaderType {
+        &self.header_type
+    }
+
+    pub fn flags(&self) -> SectionHeaderFlags {
+        self.flags
+    }
+
+    pub fn address(&self) -> Option<u64> {
+        self.address
+    }
+
+    pub fn section_file_offset(&self) -> u64 {
+        self.section_file_offset
+    }
+
+    pub fn section_size(&self) -> u64 {
+        self.section_size
+    }
+
+    pub fn section_link_index(&self) -> Option<u32> {
+        self.section_link_index
+    }
+
+    pub fn info(&self) -> Option<u32> {
+        self.info
+    }
+
+    pub fn address_alignment(&self) -> u64 {
+        self.address_alignment
+    }
+
+    pub fn section_entry_size(&self) -> Option<u64> {
+        self.section_entry_size
+    }
+}
+
+pub struct SectionHeaderIterator<'a> {
+    current_index: u16,
+    data: &'a [u8],
+    endianness: Endian,
+    section_header_summary: &'a Elf64SectionHeaderSummary,
+}
+
+impl<'a> SectionHeaderIterator<'a> {
+    pub fn new(
+        data: &'a [u8],
+        endianness: Endian,
+        section_header_summary: &'a Elf64SectionHeaderSummary,
+    ) -> Result<Self, ElfParseError> {
+        Ok(Self {
+            current_index: 0,
+            data,
+            endianness,
+            section_header_summary,
+        })
+    }
 }
 
 impl<'a> Iterator for SectionHeaderIterator<'a> {
@@ -370,7 +449,12 @@ impl<'a> Iterator for SectionHeaderIterator<'a> {
         if self.current_index == self.section_header_summary.entry_count {
             None
         } else {
-            let header = self.parse_section_header();
+            let header = SectionHeader::parse(
+                self.data,
+                self.endianness,
+                self.section_header_summary,
+                self.current_index,
+            );
             self.current_index += 1;
             Some(header)
         }
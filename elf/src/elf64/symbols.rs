@@ -0,0 +1,477 @@
+use core::convert::TryFrom;
+
+use crate::{byte_at, string_table::StringTable, ElfParseError, Endian};
+
+/// Size of a single `Elf64_Sym` entry in a symbol table.
+pub const SYMBOL_ENTRY_SIZE: u64 = 24;
+
+/// `st_shndx` value of a symbol that is undefined in this file.
+const SHN_UNDEF: u16 = 0;
+/// Start of the reserved range of section header indexes.
+const SHN_LORESERVE: u16 = 0xFF00;
+/// `st_shndx` value of a symbol with an absolute value that will not change
+/// because of relocation.
+const SHN_ABS: u16 = 0xFFF1;
+/// `st_shndx` value of a common symbol that has not yet been allocated.
+const SHN_COMMON: u16 = 0xFFF2;
+/// `st_shndx` escape value indicating that the actual section header index is
+/// too large to fit and is found in the associated
+/// `SymbolTableSectionHeaderIndex` section.
+const SHN_XINDEX: u16 = 0xFFFF;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolBinding {
+    /// Local symbols are not visible outside the object file containing their
+    /// definition.
+    Local,
+    /// Global symbols are visible to all object files being combined.
+    Global,
+    /// Weak symbols resemble global symbols, but their definitions have lower
+    /// precedence.
+    Weak,
+    /// Values in the inclusive range from 10 through 12 are reserved for
+    /// operating system-specific semantics, e.g. STB_GNU_UNIQUE.
+    OperatingSystemSpecific(u8),
+    /// Values in the inclusive range from 13 through 15 are reserved for
+    /// processor-specific semantics.
+    ProcessorSpecific(u8),
+}
+
+impl TryFrom<u8> for SymbolBinding {
+    type Error = ElfParseError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Local),
+            1 => Ok(Self::Global),
+            2 => Ok(Self::Weak),
+            10..=12 => Ok(Self::OperatingSystemSpecific(value)),
+            13..=15 => Ok(Self::ProcessorSpecific(value)),
+            _ => Err(ElfParseError::InvalidSymbolBinding(value)),
+        }
+    }
+}
+
+impl From<SymbolBinding> for u8 {
+    fn from(binding: SymbolBinding) -> Self {
+        match binding {
+            SymbolBinding::Local => 0,
+            SymbolBinding::Global => 1,
+            SymbolBinding::Weak => 2,
+            SymbolBinding::OperatingSystemSpecific(value)
+            | SymbolBinding::ProcessorSpecific(value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolType {
+    /// The symbol's type is not specified.
+    NoType,
+    /// The symbol is associated with a data object, such as a variable or an
+    /// array.
+    Object,
+    /// The symbol is associated with a function or other executable code.
+    Function,
+    /// The symbol is associated with a section. Symbol table entries of this
+    /// type exist primarily for relocation and normally have `Local` binding.
+    Section,
+    /// The symbol's name gives the name of the source file associated with the
+    /// object file.
+    File,
+    /// The symbol labels an uninitialized common block.
+    Common,
+    /// The symbol specifies a thread-local storage entity. Its value is an
+    /// offset into the TLS template rather than an address.
+    ThreadLocalStorage,
+    /// Values in the inclusive range from 10 through 12 are reserved for
+    /// operating system-specific semantics, e.g. STT_GNU_IFUNC.
+    OperatingSystemSpecific(u8),
+    /// Values in the inclusive range from 13 through 15 are reserved for
+    /// processor-specific semantics.
+    ProcessorSpecific(u8),
+}
+
+impl TryFrom<u8> for SymbolType {
+    type Error = ElfParseError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::NoType),
+            1 => Ok(Self::Object),
+            2 => Ok(Self::Function),
+            3 => Ok(Self::Section),
+            4 => Ok(Self::File),
+            5 => Ok(Self::Common),
+            6 => Ok(Self::ThreadLocalStorage),
+            10..=12 => Ok(Self::OperatingSystemSpecific(value)),
+            13..=15 => Ok(Self::ProcessorSpecific(value)),
+            _ => Err(ElfParseError::InvalidSymbolType(value)),
+        }
+    }
+}
+
+impl From<SymbolType> for u8 {
+    fn from(symbol_type: SymbolType) -> Self {
+        match symbol_type {
+            SymbolType::NoType => 0,
+            SymbolType::Object => 1,
+            SymbolType::Function => 2,
+            SymbolType::Section => 3,
+            SymbolType::File => 4,
+            SymbolType::Common => 5,
+            SymbolType::ThreadLocalStorage => 6,
+            SymbolType::OperatingSystemSpecific(value) | SymbolType::ProcessorSpecific(value) => {
+                value
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolVisibility {
+    /// Visibility is specified by the symbol's binding.
+    Default,
+    /// Processor-specific hidden class.
+    Internal,
+    /// The symbol is not visible to other components.
+    Hidden,
+    /// The symbol is visible to other components, but cannot be preempted.
+    Protected,
+}
+
+impl From<u8> for SymbolVisibility {
+    fn from(value: u8) -> Self {
+        match value & 0x3 {
+            0 => Self::Default,
+            1 => Self::Internal,
+            2 => Self::Hidden,
+            _ => Self::Protected,
+        }
+    }
+}
+
+impl From<SymbolVisibility> for u8 {
+    fn from(visibility: SymbolVisibility) -> Self {
+        match visibility {
+            SymbolVisibility::Default => 0,
+            SymbolVisibility::Internal => 1,
+            SymbolVisibility::Hidden => 2,
+            SymbolVisibility::Protected => 3,
+        }
+    }
+}
+
+/// The section a symbol is defined relative to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolSectionIndex {
+    /// The symbol is undefined in this file and must be resolved elsewhere.
+    Undefined,
+    /// The symbol has an absolute value that will not change because of
+    /// relocation.
+    Absolute,
+    /// The symbol labels a common block that has not yet been allocated.
+    Common,
+    /// The symbol is defined relative to the section at this index in the
+    /// section header table.
+    Index(u32),
+    /// Any other value in the reserved range from 0xFF00 through 0xFFFF.
+    Reserved(u16),
+}
+
+impl From<SymbolSectionIndex> for u16 {
+    /// Indexes from `SHN_LORESERVE` up become `SHN_XINDEX`, which says the
+    /// actual index is held by a `SymbolTableSectionHeaderIndex` section.
+    fn from(section_index: SymbolSectionIndex) -> Self {
+        match section_index {
+            SymbolSectionIndex::Undefined => SHN_UNDEF,
+            SymbolSectionIndex::Absolute => SHN_ABS,
+            SymbolSectionIndex::Common => SHN_COMMON,
+            SymbolSectionIndex::Index(index) if index < SHN_LORESERVE as u32 => index as u16,
+            SymbolSectionIndex::Index(_) => SHN_XINDEX,
+            SymbolSectionIndex::Reserved(value) => value,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Elf64Symbol<'a> {
+    /// The symbol's name, resolved through the symbol table's linked string
+    /// table.
+    name: &'a str,
+    /// The value of the symbol. In executable and shared object files this is
+    /// a virtual address, and in relocatable files it is an offset from the
+    /// start of the symbol's section.
+    value: u64,
+    /// Size of the object the symbol refers to, or 0 if it has no size or the
+    /// size is unknown.
+    size: u64,
+    binding: SymbolBinding,
+    symbol_type: SymbolType,
+    visibility: SymbolVisibility,
+    section_index: SymbolSectionIndex,
+}
+
+impl<'a> Elf64Symbol<'a> {
+    pub fn new(
+        name: &'a str,
+        value: u64,
+        size: u64,
+        binding: SymbolBinding,
+        symbol_type: SymbolType,
+        visibility: SymbolVisibility,
+        section_index: SymbolSectionIndex,
+    ) -> Self {
+        Self {
+            name,
+            value,
+            size,
+            binding,
+            symbol_type,
+            visibility,
+            section_index,
+        }
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn binding(&self) -> SymbolBinding {
+        self.binding
+    }
+
+    pub fn symbol_type(&self) -> SymbolType {
+        self.symbol_type
+    }
+
+    pub fn visibility(&self) -> SymbolVisibility {
+        self.visibility
+    }
+
+    pub fn section_index(&self) -> SymbolSectionIndex {
+        self.section_index
+    }
+}
+
+pub struct SymbolIterator<'a> {
+    current_index: u32,
+    entry_count: u32,
+    entry_size: usize,
+    data: &'a [u8],
+    endianness: Endian,
+    names: StringTable<'a>,
+    /// Contents of the `SymbolTableSectionHeaderIndex` section associated with
+    /// this symbol table, if there is one.
+    extended_section_indexes: Option<&'a [u8]>,
+}
+
+impl<'a> SymbolIterator<'a> {
+    pub fn new(
+        data: &'a [u8],
+        entry_size: u64,
+        endianness: Endian,
+        names: StringTable<'a>,
+        extended_section_indexes: Option<&'a [u8]>,
+    ) -> Result<Self, ElfParseError> {
+        if entry_size < SYMBOL_ENTRY_SIZE {
+            return Err(ElfParseError::InvalidSymbolEntrySize(entry_size));
+        }
+
+        Ok(Self {
+            current_index: 0,
+            entry_count: (data.len() as u64 / entry_size) as u32,
+            entry_size: entry_size as usize,
+            data,
+            endianness,
+            names,
+            extended_section_indexes,
+        })
+    }
+
+    /// Parse the symbol at `index` in the table, independently of the
+    /// iterator's position. Relocations refer to symbols this way.
+    pub fn symbol(&self, index: u32) -> Result<Elf64Symbol<'a>, ElfParseError> {
+        if index >= self.entry_count {
+            return Err(ElfParseError::InvalidSymbolIndex(index));
+        }
+
+        self.parse_symbol(index)
+    }
+
+    fn parse_symbol(&self, index: u32) -> Result<Elf64Symbol<'a>, ElfParseError> {
+        let byte_offset = index as usize * self.entry_size;
+
+        let name = self
+            .names
+            .get(self.endianness.get_u32(self.data, byte_offset)?)?;
+        let info = byte_at(self.data, byte_offset + 4)?;
+        let other = byte_at(self.data, byte_offset + 5)?;
+        let section_index =
+            self.section_index(index, self.endianness.get_u16(self.data, byte_offset + 6)?)?;
+
+        Ok(Elf64Symbol {
+            name,
+            value: self.endianness.get_u64(self.data, byte_offset + 8)?,
+            size: self.endianness.get_u64(self.data, byte_offset + 16)?,
+            binding: SymbolBinding::try_from(info >> 4)?,
+            symbol_type: SymbolType::try_from(info & 0xF)?,
+            visibility: SymbolVisibility::from(other),
+            section_index,
+        })
+    }
+
+    fn section_index(&self, index: u32, value: u16) -> Result<SymbolSectionIndex, ElfParseError> {
+        match value {
+            SHN_UNDEF => Ok(SymbolSectionIndex::Undefined),
+            SHN_ABS => Ok(SymbolSectionIndex::Absolute),
+            SHN_COMMON => Ok(SymbolSectionIndex::Common),
+            SHN_XINDEX => {
+                let extended_section_indexes = self
+                    .extended_section_indexes
+                    .ok_or(ElfParseError::MissingExtendedSectionIndexTable)?;
+                // One Elf32_Word per symbol table entry
+                let byte_offset = index as usize * 4;
+                let index = self
+                    .endianness
+                    .get_u32(extended_section_indexes, byte_offset)?;
+
+                Ok(SymbolSectionIndex::Index(index))
+            }
+            SHN_LORESERVE..=0xFFFF => Ok(SymbolSectionIndex::Reserved(value)),
+            _ => Ok(SymbolSectionIndex::Index(value as u32)),
+        }
+    }
+}
+
+impl<'a> Iterator for SymbolIterator<'a> {
+    type Item = Result<Elf64Symbol<'a>, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_index == self.entry_count {
+            None
+        } else {
+            let symbol = self.parse_symbol(self.current_index);
+            self.current_index += 1;
+            Some(symbol)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol_entry(name: u32, info: u8, other: u8, section_index: u16) -> [u8; 24] {
+        let mut entry = [0; 24];
+        entry[..4].copy_from_slice(&name.to_le_bytes());
+        entry[4] = info;
+        entry[5] = other;
+        entry[6..8].copy_from_slice(&section_index.to_le_bytes());
+        entry[8..16].copy_from_slice(&0x1000u64.to_le_bytes());
+        entry[16..24].copy_from_slice(&8u64.to_le_bytes());
+        entry
+    }
+
+    #[test]
+    fn parse_extended_section_index() {
+        let entry = symbol_entry(1, 0x11, 0, SHN_XINDEX);
+        let extended_section_indexes = 0x12345u32.to_le_bytes();
+        let mut symbols = SymbolIterator::new(
+            &entry,
+            SYMBOL_ENTRY_SIZE,
+            Endian::Little,
+            StringTable::new(b"\0counter\0"),
+            Some(&extended_section_indexes),
+        )
+        .expect("Failed to create symbol iterator");
+
+        let expected = Elf64Symbol::new(
+            "counter",
+            0x1000,
+            8,
+            SymbolBinding::Global,
+            SymbolType::Object,
+            SymbolVisibility::Default,
+            SymbolSectionIndex::Index(0x12345),
+        );
+        assert_eq!(Some(Ok(expected)), symbols.next());
+        assert_eq!(None, symbols.next());
+    }
+
+    #[test]
+    fn parse_extended_section_index_without_table() {
+        let entry = symbol_entry(1, 0x11, 0, SHN_XINDEX);
+        let mut symbols = SymbolIterator::new(
+            &entry,
+            SYMBOL_ENTRY_SIZE,
+            Endian::Little,
+            StringTable::new(b"\0counter\0"),
+            None,
+        )
+        .expect("Failed to create symbol iterator");
+
+        assert_eq!(
+            Some(Err(ElfParseError::MissingExtendedSectionIndexTable)),
+            symbols.next()
+        );
+    }
+
+    #[test]
+    fn parse_reserved_section_indexes() {
+        let mut data = [0; 48];
+        data[..24].copy_from_slice(&symbol_entry(0, 0x04, 0, SHN_ABS));
+        data[24..].copy_from_slice(&symbol_entry(0, 0x15, 2, SHN_COMMON));
+        let symbols = SymbolIterator::new(
+            &data,
+            SYMBOL_ENTRY_SIZE,
+            Endian::Little,
+            StringTable::new(b"\0"),
+            None,
+        )
+        .expect("Failed to create symbol iterator")
+        .map(|symbol| symbol.expect("Failed to parse symbol"));
+
+        let expected = [
+            (
+                SymbolBinding::Local,
+                SymbolType::File,
+                SymbolVisibility::Default,
+                SymbolSectionIndex::Absolute,
+            ),
+            (
+                SymbolBinding::Global,
+                SymbolType::Common,
+                SymbolVisibility::Hidden,
+                SymbolSectionIndex::Common,
+            ),
+        ];
+        for (expected, symbol) in expected.iter().zip(symbols) {
+            assert_eq!(
+                *expected,
+                (
+                    symbol.binding(),
+                    symbol.symbol_type(),
+                    symbol.visibility(),
+                    symbol.section_index()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_symbol_entry_size() {
+        assert!(matches!(
+            SymbolIterator::new(&[], 16, Endian::Little, StringTable::new(b"\0"), None),
+            Err(ElfParseError::InvalidSymbolEntrySize(16))
+        ));
+    }
+}
@@ -0,0 +1,336 @@
+use super::{
+    bytes_at,
+    elf32::{sections::Elf32SectionHeaderIterator, Elf32File, Elf32ProgramHeaderIterator},
+    elf64::{
+        sections::{SectionHeader, SectionHeaderIterator, SectionHeaderType},
+        Elf64File, Elf64ProgramHeader, Elf64ProgramHeaderIterator,
+    },
+    string_table::StringTable,
+    tls::TlsTemplate,
+    ElfFileClass, ElfParseError, ElfType, Endian, InstructionSet, ProgramSegmentType,
+};
+
+/// An ELF file of either class.
+///
+/// Header fields, program headers and section headers of 32-bit files are
+/// widened to their 64-bit form, so callers can handle both classes with the
+/// same code. Data returned by the file still borrows from the input bytes.
+#[derive(Debug, PartialEq)]
+pub enum ElfFile<'a> {
+    Elf32(Elf32File<'a>),
+    Elf64(Elf64File<'a>),
+}
+
+impl<'a> ElfFile<'a> {
+    pub fn from_bytes(file_bytes: &'a [u8]) -> Result<Self, ElfParseError> {
+        match ElfFileClass::from_bytes(file_bytes)? {
+            ElfFileClass::Elf32 => Elf32File::from_bytes(file_bytes).map(Self::Elf32),
+            ElfFileClass::Elf64 => Elf64File::from_bytes(file_bytes).map(Self::Elf64),
+        }
+    }
+
+    fn file_bytes(&self) -> &'a [u8] {
+        match self {
+            Self::Elf32(file) => file.file_bytes(),
+            Self::Elf64(file) => file.file_bytes(),
+        }
+    }
+
+    pub fn class(&self) -> ElfFileClass {
+        match self {
+            Self::Elf32(_) => ElfFileClass::Elf32,
+            Self::Elf64(_) => ElfFileClass::Elf64,
+        }
+    }
+
+    /// Virtual address of the first instruction to execute once the file is
+    /// loaded, or 0 if the file has no entry point.
+    pub fn entry_point(&self) -> u64 {
+        match self {
+            Self::Elf32(file) => file.entry_point() as u64,
+            Self::Elf64(file) => file.entry_point(),
+        }
+    }
+
+    pub fn elf_type(&self) -> &ElfType {
+        match self {
+            Self::Elf32(file) => file.elf_type(),
+            Self::Elf64(file) => file.elf_type(),
+        }
+    }
+
+    pub fn instruction_set(&self) -> &InstructionSet {
+        match self {
+            Self::Elf32(file) => file.instruction_set(),
+            Self::Elf64(file) => file.instruction_set(),
+        }
+    }
+
+    /// Byte order of the file's multi-byte values.
+    pub fn endianness(&self) -> Endian {
+        match self {
+            Self::Elf32(file) => file.endianness(),
+            Self::Elf64(file) => file.endianness(),
+        }
+    }
+
+    pub fn program_headers(&self) -> Option<ProgramHeaderIterator<'_>> {
+        match self {
+            Self::Elf32(file) => file.program_headers().map(ProgramHeaderIterator::Elf32),
+            Self::Elf64(file) => file.program_headers().map(ProgramHeaderIterator::Elf64),
+        }
+    }
+
+    pub fn section_headers(&self) -> Result<SectionIterator<'_>, ElfParseError> {
+        match self {
+            Self::Elf32(file) => file.section_headers().map(SectionIterator::Elf32),
+            Self::Elf64(file) => file.section_headers().map(SectionIterator::Elf64),
+        }
+    }
+
+    /// Parse the section header at `index`. A 32-bit file's header is widened
+    /// to the 64-bit layout.
+    pub fn section_header(&self, index: u16) -> Result<SectionHeader, ElfParseError> {
+        match self {
+            Self::Elf32(file) => file.section_header(index).map(SectionHeader::from),
+            Self::Elf64(file) => file.section_header(index),
+        }
+    }
+
+    /// Returns the bytes of the file that hold the contents of `section`.
+    /// `NoBits` sections occupy no space in the file, so their data is empty.
+    pub fn section_data(&self, section: &SectionHeader) -> Result<&'a [u8], ElfParseError> {
+        if *section.header_type() == SectionHeaderType::NoBits {
+            return Ok(&[]);
+        }
+
+        bytes_at(
+            self.file_bytes(),
+            section.section_file_offset() as usize,
+            section.section_size() as usize,
+        )
+    }
+
+    /// Returns the bytes of the file that hold the initialized data of
+    /// `segment`, i.e. its first `p_filesz` bytes.
+    pub fn segment_data(&self, segment: &Elf64ProgramHeader) -> Result<&'a [u8], ElfParseError> {
+        bytes_at(
+            self.file_bytes(),
+            segment.p_offset() as usize,
+            segment.p_filesz() as usize,
+        )
+    }
+
+    /// The initialization image of the file's thread-local storage, if it has
+    /// a `Tls` segment.
+    pub fn tls_template(&self) -> Result<Option<TlsTemplate>, ElfParseError> {
+        for header in self.program_headers().into_iter().flatten() {
+            let header = header?;
+            if *header.segment_type() == ProgramSegmentType::Tls {
+                return Ok(Some(TlsTemplate::from(&header)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the section header string table, which holds the names of all
+    /// sections in the file.
+    pub fn section_names(&self) -> Result<StringTable<'a>, ElfParseError> {
+        match self {
+            Self::Elf32(file) => file.section_names(),
+            Self::Elf64(file) => file.section_names(),
+        }
+    }
+
+    /// Find the first section whose name is `name`, e.g. ".text".
+    pub fn section_by_name(&self, name: &str) -> Result<Option<SectionHeader>, ElfParseError> {
+        match self {
+            Self::Elf32(file) => Ok(file.section_by_name(name)?.map(SectionHeader::from)),
+            Self::Elf64(file) => file.section_by_name(name),
+        }
+    }
+}
+
+/// Iterates over the program headers of either class of file, widening those
+/// of 32-bit files.
+pub enum ProgramHeaderIterator<'a> {
+    Elf32(Elf32ProgramHeaderIterator<'a>),
+    Elf64(Elf64ProgramHeaderIterator<'a>),
+}
+
+impl<'a> Iterator for ProgramHeaderIterator<'a> {
+    type Item = Result<Elf64ProgramHeader, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Elf32(headers) => headers
+                .next()
+                .map(|header| header.map(Elf64ProgramHeader::from)),
+            Self::Elf64(headers) => headers.next(),
+        }
+    }
+}
+
+/// Iterates over the section headers of either class of file, widening those
+/// of 32-bit files.
+pub enum SectionIterator<'a> {
+    Elf32(Elf32SectionHeaderIterator<'a>),
+    Elf64(SectionHeaderIterator<'a>),
+}
+
+impl<'a> Iterator for SectionIterator<'a> {
+    type Item = Result<SectionHeader, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Elf32(headers) => headers.next().map(|header| header.map(SectionHeader::from)),
+            Self::Elf64(headers) => headers.next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{elf64::sections::SectionHeaderFlags, ProgramHeaderFlags};
+
+    #[test]
+    fn parse_either_class() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf32");
+        let elf_file =
+            ElfFile::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        assert!(matches!(elf_file, ElfFile::Elf32(_)));
+        assert_eq!(ElfFileClass::Elf32, elf_file.class());
+        assert_eq!(0x401040, elf_file.entry_point());
+        assert_eq!(ElfType::Executable, *elf_file.elf_type());
+        assert_eq!(InstructionSet::NoSpecific, *elf_file.instruction_set());
+
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            ElfFile::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        assert!(matches!(elf_file, ElfFile::Elf64(_)));
+        assert_eq!(ElfFileClass::Elf64, elf_file.class());
+        assert_eq!(0x401040, elf_file.entry_point());
+        assert_eq!(ElfType::Executable, *elf_file.elf_type());
+        assert_eq!(InstructionSet::X86_64, *elf_file.instruction_set());
+
+        let file_bytes = include_bytes!("test_files/main_ret0.elf64");
+        let elf_file =
+            ElfFile::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(0, elf_file.entry_point());
+        assert_eq!(ElfType::Relocatable, *elf_file.elf_type());
+    }
+
+    #[test]
+    fn reject_invalid_file() {
+        assert_eq!(
+            Err(ElfParseError::NotValidElfFile),
+            ElfFile::from_bytes(b"\x7FELX")
+        );
+    }
+
+    #[test]
+    fn widen_32_bit_program_headers() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf32");
+        let elf_file =
+            ElfFile::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let mut headers = elf_file.program_headers().unwrap();
+
+        let expected_program_header = Elf64ProgramHeader::new(
+            ProgramSegmentType::ProgramHeader,
+            ProgramHeaderFlags::new(false, false, true),
+            0x34,
+            0x400034,
+            0x140,
+            0x140,
+            0x4,
+        );
+        assert_eq!(
+            expected_program_header,
+            headers
+                .next()
+                .unwrap()
+                .expect("Failed to parse program header")
+        );
+        assert_eq!(9, headers.count());
+
+        let interp = elf_file
+            .program_headers()
+            .unwrap()
+            .flatten()
+            .find(|header| *header.segment_type() == ProgramSegmentType::Interpret)
+            .expect("Expected an Interpret segment");
+        assert_eq!(
+            Ok(&b"/lib64/ld-linux-x86-64.so.2\0"[..]),
+            elf_file.segment_data(&interp)
+        );
+    }
+
+    #[test]
+    fn widen_32_bit_section_headers() {
+        let expected_text = || {
+            SectionHeader::new(
+                0x81,
+                SectionHeaderType::ProgramBits,
+                SectionHeaderFlags::ALLOC | SectionHeaderFlags::EXECUTABLE_INSTRUCTIONS,
+                Some(0x401040),
+                0x1040,
+                0x171,
+                None,
+                None,
+                16,
+                None,
+            )
+        };
+
+        // Both files were built from the same program, so their sections have
+        // the same names and .text is at the same place
+        let files: [&[u8]; 2] = [
+            include_bytes!("test_files/hello_world.elf32"),
+            include_bytes!("test_files/hello_world.elf64"),
+        ];
+        for file_bytes in files.iter() {
+            let elf_file =
+                ElfFile::from_bytes(file_bytes).expect("The file should be correctly parsed");
+            assert_eq!(29, elf_file.section_headers().unwrap().count());
+            assert_eq!(Ok(expected_text()), elf_file.section_header(13));
+            assert_eq!(Ok(Some(expected_text())), elf_file.section_by_name(".text"));
+
+            let section_names = elf_file
+                .section_names()
+                .expect("Failed to find section name table");
+            let bss = elf_file
+                .section_headers()
+                .unwrap()
+                .flatten()
+                .find(|section| section.name(&section_names) == Ok(".bss"))
+                .expect("Expected a .bss section");
+            assert_eq!(Ok(&[][..]), elf_file.section_data(&bss));
+        }
+    }
+
+    #[test]
+    fn find_tls_template_of_either_class() {
+        let file_bytes = include_bytes!("test_files/tls.elf32");
+        let elf_file =
+            ElfFile::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(
+            Ok(Some(TlsTemplate::new(0x804BFE0, 4, 0x30, 0x20))),
+            elf_file.tls_template()
+        );
+
+        let file_bytes = include_bytes!("test_files/tls.elf64");
+        let elf_file =
+            ElfFile::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(
+            Ok(Some(TlsTemplate::new(0x3F00, 8, 0x40, 0x20))),
+            elf_file.tls_template()
+        );
+
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            ElfFile::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(Ok(None), elf_file.tls_template());
+    }
+}
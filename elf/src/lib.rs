@@ -0,0 +1,725 @@
+//! Parsing and writing of ELF files.
+//!
+//! The crate only needs `core` and `alloc`, so the kernel uses it to load
+//! executables and read its own symbols. With the default `std` feature it
+//! builds for the host, where the `elfinfo` binary prints the contents of ELF
+//! files and the tests run under plain `cargo test`.
+
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+extern crate alloc;
+
+use core::{array::TryFromSliceError, convert::TryInto, fmt::Debug};
+
+pub mod builder;
+pub mod dwarf;
+pub mod elf32;
+pub mod elf64;
+pub mod file;
+pub mod notes;
+pub mod relocate;
+pub mod string_table;
+pub mod symbolize;
+pub mod tls;
+pub mod validate;
+
+#[derive(Debug, PartialEq)]
+pub enum ElfParseError {
+    NotValidElfFile,
+    InvalidClass,
+    WrongElfClass,
+    InvalidEndianness,
+    InvalidElfType,
+    InvalidInstructionSetValue,
+    FailedToParseValue,
+    /// The data ended before a value could be read. `offset` is where the read
+    /// started, and `needed` is the number of bytes it required.
+    Truncated {
+        offset: usize,
+        needed: usize,
+    },
+    /// A program or section header table extends past the end of the file.
+    TableOutOfBounds,
+    /// A program or section header table's entries are smaller than the
+    /// structure they must hold.
+    InvalidTableEntrySize(u16),
+
+    InvalidProgramSegmentType(u32),
+    InvalidProgramHeaderFlags(u32),
+    InvalidProgramHeaderAlignment,
+    MultipleProgramHeaderEntriesFound,
+    /// A segment was used as a type of segment it isn't.
+    WrongSegmentType,
+
+    InvalidSectionHeaderType(u32),
+    InvalidSectionIndex(u16),
+    /// A section was used as a type of section it isn't, e.g. reading
+    /// relocations from a section that isn't a relocation section.
+    WrongSectionType,
+    MissingStringTable,
+    InvalidStringOffset(u32),
+    UnterminatedString(u32),
+    InvalidUtf8String(u32),
+
+    InvalidSymbolBinding(u8),
+    InvalidSymbolType(u8),
+    InvalidSymbolEntrySize(u64),
+    MissingSymbolTable,
+    MissingExtendedSectionIndexTable,
+
+    InvalidDynamicEntrySize(u64),
+    InvalidRelocationEntrySize(u64),
+    InvalidSymbolIndex(u32),
+    /// A virtual address isn't backed by the file data of any `Load` segment.
+    UnmappedAddress(u64),
+
+    /// The owner name of the note at this offset isn't valid UTF-8.
+    InvalidNoteName(u64),
+    /// The descriptor of a note of this type is too small for its contents.
+    InvalidNoteDescriptor(u32),
+    /// A `Hash` or `GnuHash` section has no buckets, is too small for its
+    /// buckets and chains, or has a bloom filter whose size isn't a power of 2.
+    InvalidHashTable,
+
+    /// DWARF data of this version isn't supported.
+    UnsupportedDwarfVersion(u16),
+    /// A DWARF attribute or line table entry is encoded in a form that can't
+    /// be read.
+    UnsupportedDwarfForm(u64),
+    /// A compilation unit uses an abbreviation code that isn't defined in
+    /// `.debug_abbrev`.
+    MissingDwarfAbbreviation(u64),
+    /// A line program header has a line range or opcode base of 0.
+    InvalidLineProgramHeader,
+    /// A line table row refers to a file that isn't in the line program's
+    /// file table.
+    InvalidLineProgramFile(u64),
+}
+
+impl From<TryFromSliceError> for ElfParseError {
+    fn from(_: TryFromSliceError) -> Self {
+        Self::FailedToParseValue
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InstructionSet {
+    NoSpecific,
+    Sparc,
+    X86,
+    MIPS,
+    PowerPC,
+    ARM,
+    SuperH,
+    Ia64,
+    X86_64,
+    AArch64,
+    RiscV,
+}
+
+impl InstructionSet {
+    fn try_from(value: u16) -> Result<Self, ElfParseError> {
+        match value {
+            0 => Ok(Self::NoSpecific),
+            2 => Ok(Self::Sparc),
+            3 => Ok(Self::X86),
+            8 => Ok(Self::MIPS),
+            0x14 => Ok(Self::PowerPC),
+            0x28 => Ok(Self::ARM),
+            0x2A => Ok(Self::SuperH),
+            0x32 => Ok(Self::Ia64),
+            0x3E => Ok(Self::X86_64),
+            0xB7 => Ok(Self::AArch64),
+            0xFE => Ok(Self::RiscV),
+            _ => Err(ElfParseError::InvalidInstructionSetValue),
+        }
+    }
+}
+
+impl From<&InstructionSet> for u16 {
+    fn from(instruction_set: &InstructionSet) -> Self {
+        match instruction_set {
+            InstructionSet::NoSpecific => 0,
+            InstructionSet::Sparc => 2,
+            InstructionSet::X86 => 3,
+            InstructionSet::MIPS => 8,
+            InstructionSet::PowerPC => 0x14,
+            InstructionSet::ARM => 0x28,
+            InstructionSet::SuperH => 0x2A,
+            InstructionSet::Ia64 => 0x32,
+            InstructionSet::X86_64 => 0x3E,
+            InstructionSet::AArch64 => 0xB7,
+            InstructionSet::RiscV => 0xFE,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ElfType {
+    Relocatable,
+    Executable,
+    Shared,
+    Core,
+}
+
+impl ElfType {
+    fn try_from(value: u16) -> Result<Self, ElfParseError> {
+        match value {
+            1 => Ok(Self::Relocatable),
+            2 => Ok(Self::Executable),
+            3 => Ok(Self::Shared),
+            4 => Ok(Self::Core),
+            _ => Err(ElfParseError::InvalidElfType),
+        }
+    }
+}
+
+impl From<&ElfType> for u16 {
+    fn from(elf_type: &ElfType) -> Self {
+        match elf_type {
+            ElfType::Relocatable => 1,
+            ElfType::Executable => 2,
+            ElfType::Shared => 3,
+            ElfType::Core => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    fn from_byte(byte: u8) -> Result<Self, ElfParseError> {
+        match byte {
+            1 => Ok(Self::Little),
+            2 => Ok(Self::Big),
+            _ => Err(ElfParseError::InvalidEndianness),
+        }
+    }
+
+    fn get_u16(&self, bytes: &[u8], offset: usize) -> Result<u16, ElfParseError> {
+        let bytes = bytes_at(bytes, offset, 2)?.try_into()?;
+        match self {
+            Endian::Big => Ok(u16::from_be_bytes(bytes)),
+            Endian::Little => Ok(u16::from_le_bytes(bytes)),
+        }
+    }
+
+    fn get_u32(&self, bytes: &[u8], offset: usize) -> Result<u32, ElfParseError> {
+        let bytes = bytes_at(bytes, offset, 4)?.try_into()?;
+        match self {
+            Endian::Big => Ok(u32::from_be_bytes(bytes)),
+            Endian::Little => Ok(u32::from_le_bytes(bytes)),
+        }
+    }
+
+    fn get_u64(&self, bytes: &[u8], offset: usize) -> Result<u64, ElfParseError> {
+        let bytes = bytes_at(bytes, offset, 8)?.try_into()?;
+        match self {
+            Endian::Big => Ok(u64::from_be_bytes(bytes)),
+            Endian::Little => Ok(u64::from_le_bytes(bytes)),
+        }
+    }
+}
+
+/// Returns the byte at `offset`, or a `Truncated` error if `bytes` ends before
+/// then.
+fn byte_at(bytes: &[u8], offset: usize) -> Result<u8, ElfParseError> {
+    Ok(bytes_at(bytes, offset, 1)?[0])
+}
+
+/// Returns the `length` bytes of `bytes` starting at `offset`, or a
+/// `Truncated` error if `bytes` ends before then.
+fn bytes_at(bytes: &[u8], offset: usize, length: usize) -> Result<&[u8], ElfParseError> {
+    offset
+        .checked_add(length)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or(ElfParseError::Truncated {
+            offset,
+            needed: length,
+        })
+}
+
+/// Check that a header table of `entry_count` entries of `entry_size` bytes,
+/// starting at `table_position`, fits within a file of `file_length` bytes.
+/// Entries must be at least `min_entry_size` bytes to hold the structure they
+/// describe.
+fn check_table_bounds(
+    file_length: usize,
+    table_position: u64,
+    entry_size: u16,
+    entry_count: u16,
+    min_entry_size: u16,
+) -> Result<(), ElfParseError> {
+    if entry_count == 0 {
+        return Ok(());
+    }
+    if entry_size < min_entry_size {
+        return Err(ElfParseError::InvalidTableEntrySize(entry_size));
+    }
+
+    let table_size = entry_size as u64 * entry_count as u64;
+    match table_position.checked_add(table_size) {
+        Some(table_end) if table_end <= file_length as u64 => Ok(()),
+        _ => Err(ElfParseError::TableOutOfBounds),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ElfFileClass {
+    Elf32,
+    Elf64,
+}
+
+fn is_elf_file(file_bytes: &[u8]) -> bool {
+    // 0x7F followed by "ELF" in ASCII
+    file_bytes.starts_with(&[0x7F, 0x45, 0x4C, 0x46])
+}
+
+impl ElfFileClass {
+    pub fn from_bytes(file_bytes: &[u8]) -> Result<Self, ElfParseError> {
+        if !is_elf_file(file_bytes) {
+            return Err(ElfParseError::NotValidElfFile);
+        }
+
+        match file_bytes.get(4) {
+            Some(1) => Ok(Self::Elf32),
+            Some(2) => Ok(Self::Elf64),
+            _ => Err(ElfParseError::InvalidClass),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProgramSegmentType {
+    Null,
+    Load,
+    Dynamic,
+    Interpret,
+    Note,
+    SharedLibrary,
+    ProgramHeader,
+    /// The initialization image of thread-local storage, i.e. the `.tdata`
+    /// section followed by the zero-initialized `.tbss`.
+    Tls,
+    /// Location of the `.eh_frame_hdr` section, which indexes the unwind
+    /// tables.
+    GnuEhFrame,
+    /// Its flags are the permissions the stack needs. Its other fields are
+    /// unused.
+    GnuStack,
+    /// A range of a `Load` segment that is only written by relocation, so it
+    /// can be made read-only once relocations have been applied.
+    GnuRelro,
+    /// Location of the `NT_GNU_PROPERTY_TYPE_0` note.
+    GnuProperty,
+    /// Values in the range from 0x60000000 through 0x6fffffff inclusive are
+    /// reserved for operating system-specific semantics.
+    OperatingSystemSpecific(u32),
+    /// Values in the range from 0x70000000 through 0x7fffffff inclusive are
+    /// reserved for processor-specific semantics.
+    ProcessorSpecific(u32),
+    /// A value that isn't defined by the specification, nor in one of the
+    /// reserved ranges.
+    Unknown(u32),
+}
+
+impl From<u32> for ProgramSegmentType {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::Null,
+            1 => Self::Load,
+            2 => Self::Dynamic,
+            3 => Self::Interpret,
+            4 => Self::Note,
+            5 => Self::SharedLibrary,
+            6 => Self::ProgramHeader,
+            7 => Self::Tls,
+            0x6474E550 => Self::GnuEhFrame,
+            0x6474E551 => Self::GnuStack,
+            0x6474E552 => Self::GnuRelro,
+            0x6474E553 => Self::GnuProperty,
+            0x60000000..=0x6FFFFFFF => Self::OperatingSystemSpecific(value),
+            0x70000000..=0x7FFFFFFF => Self::ProcessorSpecific(value),
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+impl From<&ProgramSegmentType> for u32 {
+    fn from(segment_type: &ProgramSegmentType) -> Self {
+        match segment_type {
+            ProgramSegmentType::Null => 0,
+            ProgramSegmentType::Load => 1,
+            ProgramSegmentType::Dynamic => 2,
+            ProgramSegmentType::Interpret => 3,
+            ProgramSegmentType::Note => 4,
+            ProgramSegmentType::SharedLibrary => 5,
+            ProgramSegmentType::ProgramHeader => 6,
+            ProgramSegmentType::Tls => 7,
+            ProgramSegmentType::GnuEhFrame => 0x6474E550,
+            ProgramSegmentType::GnuStack => 0x6474E551,
+            ProgramSegmentType::GnuRelro => 0x6474E552,
+            ProgramSegmentType::GnuProperty => 0x6474E553,
+            ProgramSegmentType::OperatingSystemSpecific(value)
+            | ProgramSegmentType::ProcessorSpecific(value)
+            | ProgramSegmentType::Unknown(value) => *value,
+        }
+    }
+}
+
+// TODO: use bitflags for this struct
+#[derive(Debug, PartialEq)]
+pub struct ProgramHeaderFlags {
+    executable: bool,
+    writable: bool,
+    readable: bool,
+}
+
+impl ProgramHeaderFlags {
+    pub fn new(executable: bool, writable: bool, readable: bool) -> Self {
+        Self {
+            executable,
+            writable,
+            readable,
+        }
+    }
+
+    pub fn executable(&self) -> bool {
+        self.executable
+    }
+
+    pub fn writable(&self) -> bool {
+        self.writable
+    }
+
+    pub fn readable(&self) -> bool {
+        self.readable
+    }
+}
+
+impl From<u32> for ProgramHeaderFlags {
+    fn from(value: u32) -> Self {
+        Self {
+            executable: (value & 1) == 1,
+            writable: (value & 2) == 2,
+            readable: (value & 4) == 4,
+        }
+    }
+}
+
+impl From<&ProgramHeaderFlags> for u32 {
+    fn from(flags: &ProgramHeaderFlags) -> Self {
+        (flags.executable as u32) | ((flags.writable as u32) << 1) | ((flags.readable as u32) << 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::elf32::Elf32File;
+    use super::elf64::Elf64File;
+    use super::file::ElfFile;
+    use super::*;
+    use alloc::vec::Vec;
+
+    // TODO: How to create an executable ELF: use gcc docker image to compile a C
+    // program that is ELF formatted by default
+
+    #[test]
+    fn elf_file_class() {
+        let file_bytes = include_bytes!("test_files/main_ret0.elf64");
+        assert_eq!(
+            ElfFileClass::Elf64,
+            ElfFileClass::from_bytes(file_bytes).expect("Expected a valid Elf64 file")
+        );
+
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        assert_eq!(
+            ElfFileClass::Elf64,
+            ElfFileClass::from_bytes(file_bytes).expect("Expected a valid Elf64 file")
+        );
+
+        let file_bytes = include_bytes!("test_files/hello_world.elf32");
+        assert_eq!(
+            ElfFileClass::Elf32,
+            ElfFileClass::from_bytes(file_bytes).expect("Expected a valid Elf32 file")
+        );
+    }
+
+    /// Parse `file_bytes` as both ELF classes and read everything the parser
+    /// can reach from there. Errors are expected for malformed input; panics
+    /// and infinite loops are not.
+    fn parse_everything(file_bytes: &[u8]) {
+        if let Ok(elf_file) = Elf64File::from_bytes(file_bytes) {
+            if let Some(headers) = elf_file.program_headers() {
+                for header in headers.flatten() {
+                    let _ = elf_file.segment_data(&header);
+                }
+            }
+
+            let section_names = elf_file.section_names();
+            if let Ok(sections) = elf_file.section_headers() {
+                for section in sections.flatten() {
+                    let _ = elf_file.section_data(&section);
+                    if let Ok(section_names) = &section_names {
+                        let _ = section.name(section_names);
+                    }
+                }
+            }
+            let _ = elf_file.section_by_name(".text");
+
+            if let Ok(Some(symbols)) = elf_file.symbols() {
+                symbols.count();
+            }
+            if let Ok(Some(symbols)) = elf_file.dynamic_symbols() {
+                symbols.count();
+            }
+            if let Ok(Some(entries)) = elf_file.dynamic_entries() {
+                entries.count();
+            }
+            if let Ok(sections) = elf_file.section_headers() {
+                for section in sections.flatten() {
+                    if let Ok(relocations) = elf_file.rela_entries(&section) {
+                        relocations.count();
+                    }
+                    if let Ok(symbols) = elf_file.linked_symbols(&section) {
+                        let _ = symbols.symbol(1);
+                    }
+                    if let Ok(notes) = elf_file.section_notes(&section) {
+                        for note in notes.flatten() {
+                            if let Ok(Some(notes::GnuNote::Properties(properties))) = note.gnu() {
+                                properties.count();
+                            }
+                        }
+                    }
+                }
+            }
+            let _ = elf_file.build_id();
+            let _ = elf_file.lookup_symbol("main");
+        }
+
+        if let Ok(elf_file) = Elf32File::from_bytes(file_bytes) {
+            if let Some(headers) = elf_file.program_headers() {
+                headers.count();
+            }
+
+            let section_names = elf_file.section_names();
+            if let Ok(sections) = elf_file.section_headers() {
+                for section in sections.flatten() {
+                    let _ = elf_file.section_data(&section);
+                    if let Ok(section_names) = &section_names {
+                        let _ = section.name(section_names);
+                    }
+                }
+            }
+            let _ = elf_file.section_by_name(".text");
+        }
+
+        if let Ok(elf_file) = ElfFile::from_bytes(file_bytes) {
+            if let Some(headers) = elf_file.program_headers() {
+                for header in headers.flatten() {
+                    let _ = elf_file.segment_data(&header);
+                }
+            }
+            if let Ok(sections) = elf_file.section_headers() {
+                for section in sections.flatten() {
+                    let _ = elf_file.section_data(&section);
+                }
+            }
+        }
+    }
+
+    fn fixtures() -> [&'static [u8]; 8] {
+        [
+            include_bytes!("test_files/main_ret0.elf64"),
+            include_bytes!("test_files/hello_world.elf64"),
+            include_bytes!("test_files/hello_world.elf32"),
+            include_bytes!("test_files/static_pie.elf64"),
+            include_bytes!("test_files/static_pie_v3.elf64"),
+            include_bytes!("test_files/tls.elf64"),
+            include_bytes!("test_files/tls.elf32"),
+            include_bytes!("test_files/shared_library.elf64"),
+        ]
+    }
+
+    #[test]
+    fn program_segment_type_ranges() {
+        assert_eq!(ProgramSegmentType::GnuProperty, 0x6474E553.into());
+        assert_eq!(
+            ProgramSegmentType::OperatingSystemSpecific(0x6474E554),
+            0x6474E554.into()
+        );
+        assert_eq!(
+            ProgramSegmentType::ProcessorSpecific(0x70000001),
+            0x70000001.into()
+        );
+        assert_eq!(ProgramSegmentType::Unknown(0x80000000), 0x80000000.into());
+    }
+
+    #[test]
+    fn parse_truncated_fixtures() {
+        for file_bytes in fixtures().iter() {
+            // Every length through the headers, then a sample of the rest
+            let lengths = (0..256).chain((256..file_bytes.len()).step_by(37));
+            for length in lengths {
+                parse_everything(&file_bytes[..length]);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_bit_flipped_fixtures() {
+        // xorshift, so every run flips the same bits
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize
+        };
+
+        for file_bytes in fixtures().iter() {
+            for _ in 0..256 {
+                let mut mutated = Vec::from(*file_bytes);
+                for _ in 0..4 {
+                    // Favor the ELF header and the section header table at the
+                    // end of the file, where most of the parsed values are
+                    let offset = match random() % 3 {
+                        0 => random() % 64,
+                        1 => mutated.len() - 1 - random() % mutated.len().min(2048),
+                        _ => random() % mutated.len(),
+                    };
+                    mutated[offset] ^= 1 << (random() % 8);
+                }
+
+                parse_everything(&mutated);
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_header() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        assert_eq!(
+            Err(ElfParseError::Truncated {
+                offset: 0,
+                needed: 64
+            }),
+            Elf64File::from_bytes(&file_bytes[..40])
+        );
+
+        let file_bytes = include_bytes!("test_files/hello_world.elf32");
+        assert_eq!(
+            Err(ElfParseError::Truncated {
+                offset: 0,
+                needed: 52
+            }),
+            Elf32File::from_bytes(&file_bytes[..40])
+        );
+        assert_eq!(
+            Err(ElfParseError::InvalidClass),
+            Elf32File::from_bytes(&file_bytes[..4])
+        );
+    }
+
+    #[test]
+    fn wrong_elf_class() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        assert_eq!(
+            Err(ElfParseError::WrongElfClass),
+            Elf32File::from_bytes(file_bytes)
+        );
+
+        let file_bytes = include_bytes!("test_files/hello_world.elf32");
+        assert_eq!(
+            Err(ElfParseError::WrongElfClass),
+            Elf64File::from_bytes(file_bytes)
+        );
+    }
+
+    #[test]
+    fn header_table_out_of_bounds() {
+        // The section header table is at the very end of the file
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        assert_eq!(
+            Err(ElfParseError::TableOutOfBounds),
+            Elf64File::from_bytes(&file_bytes[..file_bytes.len() - 1])
+        );
+
+        let file_bytes = include_bytes!("test_files/hello_world.elf32");
+        assert_eq!(
+            Err(ElfParseError::TableOutOfBounds),
+            Elf32File::from_bytes(&file_bytes[..file_bytes.len() - 1])
+        );
+
+        // Program header entry count
+        let mut file_bytes = Vec::from(&include_bytes!("test_files/hello_world.elf64")[..]);
+        file_bytes[56..58].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        assert_eq!(
+            Err(ElfParseError::TableOutOfBounds),
+            Elf64File::from_bytes(&file_bytes)
+        );
+
+        // Section header table position
+        let mut file_bytes = Vec::from(&include_bytes!("test_files/hello_world.elf64")[..]);
+        file_bytes[40..48].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(
+            Err(ElfParseError::TableOutOfBounds),
+            Elf64File::from_bytes(&file_bytes)
+        );
+    }
+
+    #[test]
+    fn header_table_entry_too_small() {
+        let mut file_bytes = Vec::from(&include_bytes!("test_files/hello_world.elf64")[..]);
+        file_bytes[54..56].copy_from_slice(&8u16.to_le_bytes());
+        assert_eq!(
+            Err(ElfParseError::InvalidTableEntrySize(8)),
+            Elf64File::from_bytes(&file_bytes)
+        );
+
+        let mut file_bytes = Vec::from(&include_bytes!("test_files/hello_world.elf32")[..]);
+        file_bytes[46..48].copy_from_slice(&20u16.to_le_bytes());
+        assert_eq!(
+            Err(ElfParseError::InvalidTableEntrySize(20)),
+            Elf32File::from_bytes(&file_bytes)
+        );
+    }
+
+    #[test]
+    fn section_data_out_of_bounds() {
+        // Point .symtab (section 26) past the end of the file
+        let mut file_bytes = Vec::from(&include_bytes!("test_files/hello_world.elf64")[..]);
+        let symtab_offset = 0x38B8 + 26 * 64 + 24;
+        file_bytes[symtab_offset..symtab_offset + 8].copy_from_slice(&0xFFFF_FFFFu64.to_le_bytes());
+
+        let elf_file =
+            Elf64File::from_bytes(&file_bytes).expect("The file should be correctly parsed");
+        assert!(matches!(
+            elf_file.symbols(),
+            Err(ElfParseError::Truncated {
+                offset: 0xFFFF_FFFF,
+                needed: 0x5B8
+            })
+        ));
+    }
+
+    #[test]
+    fn malformed_program_header_does_not_stall_iterator() {
+        // Give the first program header an alignment that isn't a power of two
+        let mut file_bytes = Vec::from(&include_bytes!("test_files/hello_world.elf64")[..]);
+        file_bytes[64 + 48..64 + 56].copy_from_slice(&3u64.to_le_bytes());
+
+        let elf_file =
+            Elf64File::from_bytes(&file_bytes).expect("The file should be correctly parsed");
+        let mut headers = elf_file.program_headers().unwrap();
+        assert_eq!(
+            Some(Err(ElfParseError::InvalidProgramHeaderAlignment)),
+            headers.next()
+        );
+        assert_eq!(10, headers.count());
+    }
+}
@@ -0,0 +1,415 @@
+use bitflags::bitflags;
+use core::str;
+
+use super::{bytes_at, ElfParseError, Endian};
+
+/// Size of the `namesz`, `descsz` and `type` words that start every note.
+const NOTE_HEADER_SIZE: usize = 12;
+
+/// Owner name of the notes defined by the GNU toolchain.
+pub const GNU_NOTE_OWNER: &str = "GNU";
+
+/// The OS and minimum kernel version the file was built for.
+pub const NT_GNU_ABI_TAG: u32 = 1;
+/// A unique identifier of the build that produced the file.
+pub const NT_GNU_BUILD_ID: u32 = 3;
+/// An array of GNU properties, e.g. which x86 features the code supports.
+pub const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+
+/// The x86 control-flow protection features all of the file's code is
+/// compatible with.
+const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc000_0002;
+/// The x86-64 ISA levels the file's code needs.
+const GNU_PROPERTY_X86_ISA_1_NEEDED: u32 = 0xc000_8002;
+
+/// A single entry of a `Note` section or segment. Notes carry information
+/// about the file that doesn't fit anywhere else, identified by the name of
+/// their owner and a type that is specific to that owner.
+#[derive(Debug, PartialEq)]
+pub struct Note<'a> {
+    name: &'a str,
+    note_type: u32,
+    descriptor: &'a [u8],
+    endianness: Endian,
+    alignment: usize,
+}
+
+impl<'a> Note<'a> {
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn note_type(&self) -> u32 {
+        self.note_type
+    }
+
+    pub fn descriptor(&self) -> &'a [u8] {
+        self.descriptor
+    }
+
+    /// Decode the descriptor of a note owned by GNU. Returns `None` for notes
+    /// of other owners and for GNU notes whose type isn't supported.
+    pub fn gnu(&self) -> Result<Option<GnuNote<'a>>, ElfParseError> {
+        if self.name != GNU_NOTE_OWNER {
+            return Ok(None);
+        }
+
+        match self.note_type {
+            NT_GNU_ABI_TAG => {
+                if self.descriptor.len() < 16 {
+                    return Err(ElfParseError::InvalidNoteDescriptor(self.note_type));
+                }
+                let word = |index: usize| self.endianness.get_u32(self.descriptor, index * 4);
+                Ok(Some(GnuNote::AbiTag(AbiTag {
+                    os: AbiTagOs::from(word(0)?),
+                    major: word(1)?,
+                    minor: word(2)?,
+                    patch: word(3)?,
+                })))
+            }
+            NT_GNU_BUILD_ID => Ok(Some(GnuNote::BuildId(self.descriptor))),
+            NT_GNU_PROPERTY_TYPE_0 => Ok(Some(GnuNote::Properties(GnuPropertyIterator {
+                data: self.descriptor,
+                endianness: self.endianness,
+                alignment: self.alignment,
+                offset: 0,
+            }))),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The decoded descriptor of a note owned by GNU.
+#[derive(Debug)]
+pub enum GnuNote<'a> {
+    AbiTag(AbiTag),
+    /// The build ID bytes, usually a 20-byte SHA-1 hash. It's displayed as
+    /// lowercase hex, e.g. in crash reports.
+    BuildId(&'a [u8]),
+    Properties(GnuPropertyIterator<'a>),
+}
+
+/// The descriptor of an `NT_GNU_ABI_TAG` note: the file needs at least
+/// version `major.minor.patch` of the kernel `os`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AbiTag {
+    pub os: AbiTagOs,
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AbiTagOs {
+    Linux,
+    Hurd,
+    Solaris,
+    FreeBsd,
+    Unknown(u32),
+}
+
+impl From<u32> for AbiTagOs {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::Linux,
+            1 => Self::Hurd,
+            2 => Self::Solaris,
+            3 => Self::FreeBsd,
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+bitflags! {
+    /// Values of a `GNU_PROPERTY_X86_FEATURE_1_AND` property. Source:
+    /// https://gitlab.com/x86-psABIs/x86-64-ABI.
+    #[repr(transparent)]
+    pub struct X86Features: u32 {
+        /// Indirect branches only land on `endbr64` instructions.
+        const IBT = 1;
+        /// Return addresses are checked against a shadow stack.
+        const SHSTK = 1 << 1;
+    }
+}
+
+bitflags! {
+    /// Values of a `GNU_PROPERTY_X86_ISA_1_NEEDED` property. Source:
+    /// https://gitlab.com/x86-psABIs/x86-64-ABI.
+    #[repr(transparent)]
+    pub struct X86IsaLevels: u32 {
+        /// The original x86-64 instructions, including SSE and SSE2.
+        const BASELINE = 1;
+        /// Adds CMPXCHG16B, LAHF/SAHF, POPCNT, SSE3, SSSE3, SSE4.1 and SSE4.2.
+        const V2 = 1 << 1;
+        /// Adds AVX, AVX2, BMI1, BMI2, F16C, FMA, LZCNT, MOVBE and XSAVE.
+        const V3 = 1 << 2;
+        /// Adds AVX512F, AVX512BW, AVX512CD, AVX512DQ and AVX512VL.
+        const V4 = 1 << 3;
+    }
+}
+
+/// A single property of an `NT_GNU_PROPERTY_TYPE_0` note.
+#[derive(Debug, PartialEq)]
+pub enum GnuProperty<'a> {
+    /// Control-flow protection features the code is compatible with. They
+    /// only need to be enabled if the whole process supports them, so files
+    /// that have them still run without them.
+    X86Features(X86Features),
+    /// ISA levels the code needs, so the file can't run without them.
+    X86IsaNeeded(X86IsaLevels),
+    Other {
+        property_type: u32,
+        data: &'a [u8],
+    },
+}
+
+/// Iterates over the properties of an `NT_GNU_PROPERTY_TYPE_0` note. Each
+/// property is a `pr_type` word and a `pr_datasz` word followed by its data,
+/// padded to the note's alignment.
+#[derive(Debug)]
+pub struct GnuPropertyIterator<'a> {
+    data: &'a [u8],
+    endianness: Endian,
+    alignment: usize,
+    offset: usize,
+}
+
+impl<'a> GnuPropertyIterator<'a> {
+    fn parse_property(&self) -> Result<(GnuProperty<'a>, usize), ElfParseError> {
+        let property_type = self.endianness.get_u32(self.data, self.offset)?;
+        let data_size = self.endianness.get_u32(self.data, self.offset + 4)? as usize;
+        let data = bytes_at(self.data, self.offset + 8, data_size)?;
+
+        let feature_word = || {
+            if data.len() < 4 {
+                return Err(ElfParseError::InvalidNoteDescriptor(NT_GNU_PROPERTY_TYPE_0));
+            }
+            self.endianness.get_u32(data, 0)
+        };
+        let property = match property_type {
+            GNU_PROPERTY_X86_FEATURE_1_AND => {
+                GnuProperty::X86Features(X86Features::from_bits_truncate(feature_word()?))
+            }
+            GNU_PROPERTY_X86_ISA_1_NEEDED => {
+                GnuProperty::X86IsaNeeded(X86IsaLevels::from_bits_truncate(feature_word()?))
+            }
+            _ => GnuProperty::Other {
+                property_type,
+                data,
+            },
+        };
+
+        Ok((
+            property,
+            align_up(self.offset + 8 + data_size, self.alignment),
+        ))
+    }
+}
+
+impl<'a> Iterator for GnuPropertyIterator<'a> {
+    type Item = Result<GnuProperty<'a>, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.data.len() {
+            return None;
+        }
+
+        match self.parse_property() {
+            Ok((property, next_offset)) => {
+                self.offset = next_offset;
+                Some(Ok(property))
+            }
+            Err(error) => {
+                // The size of a malformed property can't be trusted, so there
+                // is no way to find the next one
+                self.offset = self.data.len();
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Iterates over the notes in the data of a `Note` section or segment.
+///
+/// Each note is a `namesz`, a `descsz` and a `type` word, followed by the
+/// null-terminated owner name and the descriptor, each padded to the
+/// alignment of the section or segment. Notes are 4-byte aligned, except
+/// GNU property notes in 64-bit files, which are 8-byte aligned.
+#[derive(Debug)]
+pub struct NoteIterator<'a> {
+    data: &'a [u8],
+    endianness: Endian,
+    alignment: usize,
+    offset: usize,
+}
+
+impl<'a> NoteIterator<'a> {
+    /// `alignment` is the `sh_addralign` or `p_align` of the notes. Any value
+    /// other than 8 is treated as 4.
+    pub fn new(data: &'a [u8], alignment: u64, endianness: Endian) -> Self {
+        Self {
+            data,
+            endianness,
+            alignment: if alignment == 8 { 8 } else { 4 },
+            offset: 0,
+        }
+    }
+
+    fn parse_note(&self) -> Result<(Note<'a>, usize), ElfParseError> {
+        let name_size = self.endianness.get_u32(self.data, self.offset)? as usize;
+        let descriptor_size = self.endianness.get_u32(self.data, self.offset + 4)? as usize;
+        let note_type = self.endianness.get_u32(self.data, self.offset + 8)?;
+
+        let name_offset = self.offset + NOTE_HEADER_SIZE;
+        let name = bytes_at(self.data, name_offset, name_size)?;
+        // The size includes the terminator, which some producers leave out
+        let name = match name.split_last() {
+            Some((0, name)) => name,
+            _ => name,
+        };
+        let name =
+            str::from_utf8(name).map_err(|_| ElfParseError::InvalidNoteName(self.offset as u64))?;
+
+        let descriptor_offset = align_up(name_offset + name_size, self.alignment);
+        let descriptor = bytes_at(self.data, descriptor_offset, descriptor_size)?;
+
+        let note = Note {
+            name,
+            note_type,
+            descriptor,
+            endianness: self.endianness,
+            alignment: self.alignment,
+        };
+        Ok((
+            note,
+            align_up(descriptor_offset + descriptor_size, self.alignment),
+        ))
+    }
+}
+
+impl<'a> Iterator for NoteIterator<'a> {
+    type Item = Result<Note<'a>, ElfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.data.len() {
+            return None;
+        }
+
+        match self.parse_note() {
+            Ok((note, next_offset)) => {
+                self.offset = next_offset;
+                Some(Ok(note))
+            }
+            Err(error) => {
+                // The sizes of a malformed note can't be trusted, so there is
+                // no way to find the next one
+                self.offset = self.data.len();
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+fn align_up(offset: usize, alignment: usize) -> usize {
+    (offset + alignment - 1) & !(alignment - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An `NT_GNU_PROPERTY_TYPE_0` note of a file that needs x86-64-v2 and is
+    /// compatible with IBT and SHSTK, 8-byte aligned as in 64-bit files.
+    const PROPERTY_NOTE: [u8; 48] = [
+        4, 0, 0, 0, // namesz
+        32, 0, 0, 0, // descsz
+        5, 0, 0, 0, // type
+        b'G', b'N', b'U', 0, // name
+        0x02, 0x80, 0x00, 0xc0, // GNU_PROPERTY_X86_ISA_1_NEEDED
+        4, 0, 0, 0, // pr_datasz
+        3, 0, 0, 0, // baseline and v2
+        0, 0, 0, 0, // padding
+        0x02, 0x00, 0x00, 0xc0, // GNU_PROPERTY_X86_FEATURE_1_AND
+        4, 0, 0, 0, // pr_datasz
+        3, 0, 0, 0, // IBT and SHSTK
+        0, 0, 0, 0, // padding
+    ];
+
+    #[test]
+    fn parse_notes() {
+        let data = [
+            4, 0, 0, 0, 3, 0, 0, 0, 3, 0, 0, 0, b'G', b'N', b'U', 0, 0xAB, 0xCD, 0xEF, 0, //
+            6, 0, 0, 0, 0, 0, 0, 0, 0x42, 0, 0, 0, b'O', b'w', b'n', b'e', b'r', 0, 0, 0,
+        ];
+        let mut notes = NoteIterator::new(&data, 4, Endian::Little);
+
+        let note = notes.next().unwrap().expect("Failed to parse note");
+        assert_eq!("GNU", note.name());
+        assert_eq!(NT_GNU_BUILD_ID, note.note_type());
+        assert_eq!(&[0xAB, 0xCD, 0xEF][..], note.descriptor());
+        assert!(matches!(
+            note.gnu(),
+            Ok(Some(GnuNote::BuildId(&[0xAB, 0xCD, 0xEF])))
+        ));
+
+        let note = notes.next().unwrap().expect("Failed to parse note");
+        assert_eq!("Owner", note.name());
+        assert_eq!(0x42, note.note_type());
+        assert!(note.descriptor().is_empty());
+        assert!(matches!(note.gnu(), Ok(None)));
+
+        assert!(notes.next().is_none());
+    }
+
+    #[test]
+    fn parse_gnu_properties() {
+        let note = NoteIterator::new(&PROPERTY_NOTE, 8, Endian::Little)
+            .next()
+            .unwrap()
+            .expect("Failed to parse note");
+        let mut properties = match note.gnu() {
+            Ok(Some(GnuNote::Properties(properties))) => properties,
+            other => panic!("Expected GNU properties, got {:?}", other),
+        };
+
+        assert_eq!(
+            Some(Ok(GnuProperty::X86IsaNeeded(
+                X86IsaLevels::BASELINE | X86IsaLevels::V2
+            ))),
+            properties.next()
+        );
+        assert_eq!(
+            Some(Ok(GnuProperty::X86Features(
+                X86Features::IBT | X86Features::SHSTK
+            ))),
+            properties.next()
+        );
+        assert_eq!(None, properties.next());
+    }
+
+    #[test]
+    fn reject_truncated_notes() {
+        // The descriptor is cut off after the name
+        let mut notes = NoteIterator::new(&PROPERTY_NOTE[..24], 8, Endian::Little);
+        assert_eq!(
+            Some(Err(ElfParseError::Truncated {
+                offset: 16,
+                needed: 32
+            })),
+            notes.next()
+        );
+        assert_eq!(None, notes.next());
+
+        let abi_tag = [
+            4, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, b'G', b'N', b'U', 0, 0, 0, 0, 0,
+        ];
+        let note = NoteIterator::new(&abi_tag, 4, Endian::Little)
+            .next()
+            .unwrap()
+            .expect("Failed to parse note");
+        assert!(matches!(
+            note.gnu(),
+            Err(ElfParseError::InvalidNoteDescriptor(NT_GNU_ABI_TAG))
+        ));
+    }
+}
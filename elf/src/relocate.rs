@@ -0,0 +1,312 @@
+use core::convert::TryFrom;
+
+use super::elf64::relocations::{Elf64Rel, Elf64Rela, RelocationType};
+
+#[derive(Debug, PartialEq)]
+pub enum RelocationError {
+    /// The relocation type can't be applied by `apply_rela` or `apply_rel`.
+    UnsupportedRelocation(RelocationType),
+    /// The symbol resolver found no address for the symbol at this index.
+    UnresolvedSymbol(u32),
+    /// The relocated value doesn't fit in the field at this address.
+    Overflow(u64),
+    /// The address isn't part of the memory being relocated.
+    UnmappedAddress(u64),
+}
+
+/// Memory that relocations are applied to, addressed the same way as the
+/// relocations' places.
+pub trait RelocationTarget {
+    fn read(&mut self, address: u64, bytes: &mut [u8]) -> Result<(), RelocationError>;
+    fn write(&mut self, address: u64, bytes: &[u8]) -> Result<(), RelocationError>;
+}
+
+/// Apply a relocation with an explicit addend.
+///
+/// The relocation's place is `base + offset`, and `base` is also the B of
+/// `Relative` relocations. `resolve_symbol` returns the address of the
+/// symbol at the given index of the relocation's symbol table. It is only
+/// called for relocations that refer to a symbol.
+pub fn apply_rela(
+    relocation: &Elf64Rela,
+    base: u64,
+    target: &mut impl RelocationTarget,
+    resolve_symbol: impl FnMut(u32) -> Option<u64>,
+) -> Result<(), RelocationError> {
+    apply(
+        relocation.relocation_type(),
+        base.wrapping_add(relocation.offset()),
+        relocation.symbol_index(),
+        relocation.addend(),
+        base,
+        target,
+        resolve_symbol,
+    )
+}
+
+/// Apply a relocation whose addend is stored at its place. See `apply_rela`.
+pub fn apply_rel(
+    relocation: &Elf64Rel,
+    base: u64,
+    target: &mut impl RelocationTarget,
+    resolve_symbol: impl FnMut(u32) -> Option<u64>,
+) -> Result<(), RelocationError> {
+    let relocation_type = relocation.relocation_type();
+    let place = base.wrapping_add(relocation.offset());
+
+    let addend = match relocation_type {
+        RelocationType::PcRelative32 | RelocationType::Plt32 | RelocationType::Direct32Signed => {
+            let mut bytes = [0; 4];
+            target.read(place, &mut bytes)?;
+            i32::from_le_bytes(bytes) as i64
+        }
+        RelocationType::Direct32 => {
+            let mut bytes = [0; 4];
+            target.read(place, &mut bytes)?;
+            u32::from_le_bytes(bytes) as i64
+        }
+        RelocationType::None => 0,
+        _ => {
+            let mut bytes = [0; 8];
+            target.read(place, &mut bytes)?;
+            i64::from_le_bytes(bytes)
+        }
+    };
+
+    apply(
+        relocation_type,
+        place,
+        relocation.symbol_index(),
+        addend,
+        base,
+        target,
+        resolve_symbol,
+    )
+}
+
+fn apply(
+    relocation_type: RelocationType,
+    place: u64,
+    symbol_index: u32,
+    addend: i64,
+    base: u64,
+    target: &mut impl RelocationTarget,
+    mut resolve_symbol: impl FnMut(u32) -> Option<u64>,
+) -> Result<(), RelocationError> {
+    let mut symbol = || match symbol_index {
+        // Index 0 is the undefined symbol, whose value is 0
+        0 => Ok(0),
+        index => resolve_symbol(index).ok_or(RelocationError::UnresolvedSymbol(index)),
+    };
+    let overflow = || RelocationError::Overflow(place);
+
+    match relocation_type {
+        RelocationType::None => Ok(()),
+        RelocationType::Direct64 => {
+            let value = symbol()?.wrapping_add(addend as u64);
+            target.write(place, &value.to_le_bytes())
+        }
+        RelocationType::GlobalData | RelocationType::JumpSlot => {
+            target.write(place, &symbol()?.to_le_bytes())
+        }
+        RelocationType::Relative => {
+            let value = base.wrapping_add(addend as u64);
+            target.write(place, &value.to_le_bytes())
+        }
+        RelocationType::PcRelative64 => {
+            let value = symbol()?.wrapping_add(addend as u64).wrapping_sub(place);
+            target.write(place, &value.to_le_bytes())
+        }
+        // Without a PLT of our own, calls go straight to the symbol
+        RelocationType::PcRelative32 | RelocationType::Plt32 => {
+            let value = symbol()? as i128 + addend as i128 - place as i128;
+            let value = i32::try_from(value).map_err(|_| overflow())?;
+            target.write(place, &value.to_le_bytes())
+        }
+        RelocationType::Direct32 => {
+            let value = symbol()? as i128 + addend as i128;
+            let value = u32::try_from(value).map_err(|_| overflow())?;
+            target.write(place, &value.to_le_bytes())
+        }
+        RelocationType::Direct32Signed => {
+            // The value is sign extended when used, so it must equal its own
+            // sign extension as a 64-bit address
+            let value = symbol()?.wrapping_add(addend as u64) as i64;
+            let value = i32::try_from(value).map_err(|_| overflow())?;
+            target.write(place, &value.to_le_bytes())
+        }
+        other => Err(RelocationError::UnsupportedRelocation(other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUFFER_ADDRESS: u64 = 0x1000;
+
+    /// 32 bytes of memory at `BUFFER_ADDRESS`.
+    struct Buffer([u8; 32]);
+
+    impl Buffer {
+        fn range(&self, address: u64, length: usize) -> Result<usize, RelocationError> {
+            match address.checked_sub(BUFFER_ADDRESS) {
+                Some(start) if start as usize + length <= self.0.len() => Ok(start as usize),
+                _ => Err(RelocationError::UnmappedAddress(address)),
+            }
+        }
+    }
+
+    impl RelocationTarget for Buffer {
+        fn read(&mut self, address: u64, bytes: &mut [u8]) -> Result<(), RelocationError> {
+            let start = self.range(address, bytes.len())?;
+            bytes.copy_from_slice(&self.0[start..][..bytes.len()]);
+            Ok(())
+        }
+
+        fn write(&mut self, address: u64, bytes: &[u8]) -> Result<(), RelocationError> {
+            let start = self.range(address, bytes.len())?;
+            self.0[start..][..bytes.len()].copy_from_slice(bytes);
+            Ok(())
+        }
+    }
+
+    fn resolve(index: u32) -> Option<u64> {
+        match index {
+            1 => Some(0x2000),
+            2 => Some(0xFFFF_FFFF_8000_0000),
+            _ => None,
+        }
+    }
+
+    fn u64_at(buffer: &Buffer, offset: usize) -> u64 {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&buffer.0[offset..][..8]);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn u32_at(buffer: &Buffer, offset: usize) -> u32 {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&buffer.0[offset..][..4]);
+        u32::from_le_bytes(bytes)
+    }
+
+    #[test]
+    fn apply_64_bit_relocations() {
+        let mut buffer = Buffer([0; 32]);
+        let relocations = [
+            Elf64Rela::new(0, 0, RelocationType::Relative, 0x40),
+            Elf64Rela::new(8, 1, RelocationType::Direct64, 0x10),
+            Elf64Rela::new(16, 1, RelocationType::GlobalData, 0x10),
+            Elf64Rela::new(24, 2, RelocationType::JumpSlot, 0),
+        ];
+        for relocation in relocations.iter() {
+            apply_rela(relocation, BUFFER_ADDRESS, &mut buffer, resolve)
+                .expect("Failed to apply relocation");
+        }
+
+        assert_eq!(0x1040, u64_at(&buffer, 0));
+        assert_eq!(0x2010, u64_at(&buffer, 8));
+        // The addend doesn't apply to GOT entries
+        assert_eq!(0x2000, u64_at(&buffer, 16));
+        assert_eq!(0xFFFF_FFFF_8000_0000, u64_at(&buffer, 24));
+    }
+
+    #[test]
+    fn apply_32_bit_relocations() {
+        let mut buffer = Buffer([0; 32]);
+        let relocations = [
+            Elf64Rela::new(0, 1, RelocationType::PcRelative32, -4),
+            Elf64Rela::new(4, 1, RelocationType::Plt32, -4),
+            Elf64Rela::new(8, 1, RelocationType::Direct32, 0),
+            Elf64Rela::new(12, 2, RelocationType::Direct32Signed, 0x10),
+        ];
+        for relocation in relocations.iter() {
+            apply_rela(relocation, BUFFER_ADDRESS, &mut buffer, resolve)
+                .expect("Failed to apply relocation");
+        }
+
+        assert_eq!(0x2000 - 4 - 0x1000, u32_at(&buffer, 0));
+        assert_eq!(0x2000 - 4 - 0x1004, u32_at(&buffer, 4));
+        assert_eq!(0x2000, u32_at(&buffer, 8));
+        assert_eq!(0x8000_0010, u32_at(&buffer, 12));
+    }
+
+    #[test]
+    fn apply_relocation_with_implicit_addend() {
+        let mut buffer = Buffer([0; 32]);
+        buffer.0[..8].copy_from_slice(&0x40u64.to_le_bytes());
+        buffer.0[8..12].copy_from_slice(&(-4i32).to_le_bytes());
+
+        apply_rel(
+            &Elf64Rel::new(0, 0, RelocationType::Relative),
+            BUFFER_ADDRESS,
+            &mut buffer,
+            resolve,
+        )
+        .expect("Failed to apply relocation");
+        apply_rel(
+            &Elf64Rel::new(8, 1, RelocationType::PcRelative32),
+            BUFFER_ADDRESS,
+            &mut buffer,
+            resolve,
+        )
+        .expect("Failed to apply relocation");
+
+        assert_eq!(0x1040, u64_at(&buffer, 0));
+        assert_eq!(0x2000 - 4 - 0x1008, u32_at(&buffer, 8));
+    }
+
+    #[test]
+    fn reject_invalid_relocations() {
+        let mut buffer = Buffer([0; 32]);
+
+        assert_eq!(
+            Err(RelocationError::UnresolvedSymbol(3)),
+            apply_rela(
+                &Elf64Rela::new(0, 3, RelocationType::Direct64, 0),
+                BUFFER_ADDRESS,
+                &mut buffer,
+                resolve
+            )
+        );
+        // The symbol is more than 2GiB away from the place
+        assert_eq!(
+            Err(RelocationError::Overflow(0x1000)),
+            apply_rela(
+                &Elf64Rela::new(0, 2, RelocationType::PcRelative32, 0),
+                BUFFER_ADDRESS,
+                &mut buffer,
+                resolve
+            )
+        );
+        assert_eq!(
+            Err(RelocationError::Overflow(0x1000)),
+            apply_rela(
+                &Elf64Rela::new(0, 2, RelocationType::Direct32, 0),
+                BUFFER_ADDRESS,
+                &mut buffer,
+                resolve
+            )
+        );
+        assert_eq!(
+            Err(RelocationError::UnsupportedRelocation(RelocationType::Copy)),
+            apply_rela(
+                &Elf64Rela::new(0, 1, RelocationType::Copy, 0),
+                BUFFER_ADDRESS,
+                &mut buffer,
+                resolve
+            )
+        );
+        assert_eq!(
+            Err(RelocationError::UnmappedAddress(0x1020)),
+            apply_rela(
+                &Elf64Rela::new(0x20, 0, RelocationType::Relative, 0),
+                BUFFER_ADDRESS,
+                &mut buffer,
+                resolve
+            )
+        );
+        assert_eq!([0; 32], buffer.0);
+    }
+}
@@ -0,0 +1,67 @@
+use core::str;
+
+use super::ElfParseError;
+
+/// A view over the contents of a `StringTable` section. String tables hold
+/// null-terminated character sequences, and other structures in the file
+/// refer to a string by its byte offset into the table. The first byte of a
+/// string table is always null, so offset 0 refers to an empty string.
+#[derive(Debug, PartialEq)]
+pub struct StringTable<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StringTable<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Returns the null-terminated string starting at `offset`, without its
+    /// terminator.
+    pub fn get(&self, offset: u32) -> Result<&'a str, ElfParseError> {
+        let bytes = self
+            .data
+            .get(offset as usize..)
+            .ok_or(ElfParseError::InvalidStringOffset(offset))?;
+        let length = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or(ElfParseError::UnterminatedString(offset))?;
+
+        str::from_utf8(&bytes[..length]).map_err(|_| ElfParseError::InvalidUtf8String(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_string_at_offset() {
+        let table = StringTable::new(b"\0.text\0.data\0");
+        assert_eq!(Ok(""), table.get(0));
+        assert_eq!(Ok(".text"), table.get(1));
+        assert_eq!(Ok("text"), table.get(2));
+        assert_eq!(Ok(".data"), table.get(7));
+        assert_eq!(Ok(""), table.get(12));
+    }
+
+    #[test]
+    fn get_string_past_end_of_table() {
+        let table = StringTable::new(b"\0.text\0");
+        assert_eq!(Err(ElfParseError::InvalidStringOffset(8)), table.get(8));
+        assert_eq!(Err(ElfParseError::UnterminatedString(7)), table.get(7));
+    }
+
+    #[test]
+    fn get_unterminated_string() {
+        let table = StringTable::new(b"\0.text");
+        assert_eq!(Err(ElfParseError::UnterminatedString(1)), table.get(1));
+    }
+
+    #[test]
+    fn get_invalid_utf8_string() {
+        let table = StringTable::new(b"\0\xC3\x28\0");
+        assert_eq!(Err(ElfParseError::InvalidUtf8String(1)), table.get(1));
+    }
+}
@@ -0,0 +1,298 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::{
+    elf64::{
+        symbols::{SymbolSectionIndex, SymbolType},
+        Elf64File,
+    },
+    ElfParseError,
+};
+
+/// A function of a file, by the address range of its code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FunctionSymbol<'a> {
+    name: &'a str,
+    address: u64,
+    size: u64,
+}
+
+impl<'a> FunctionSymbol<'a> {
+    /// The function's name as it appears in the symbol table, which may be
+    /// mangled. See `demangle`.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Resolves code addresses of a file to the functions containing them,
+/// through the function symbols of its `.symtab`.
+#[derive(Debug, Default)]
+pub struct SymbolMap<'a> {
+    /// Sorted by address.
+    functions: Vec<FunctionSymbol<'a>>,
+}
+
+impl<'a> SymbolMap<'a> {
+    /// Collect the defined function symbols of `elf_file`. A file without a
+    /// `.symtab`, e.g. a stripped one, gives an empty map.
+    pub fn new(elf_file: &Elf64File<'a>) -> Result<Self, ElfParseError> {
+        let mut functions = Vec::new();
+        if let Some(symbols) = elf_file.symbols()? {
+            for symbol in symbols {
+                let symbol = symbol?;
+                if symbol.symbol_type() == SymbolType::Function
+                    && symbol.section_index() != SymbolSectionIndex::Undefined
+                {
+                    functions.push(FunctionSymbol {
+                        name: symbol.name(),
+                        address: symbol.value(),
+                        size: symbol.size(),
+                    });
+                }
+            }
+        }
+        functions.sort_unstable_by_key(|function| function.address);
+
+        Ok(Self { functions })
+    }
+
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    /// The function whose code contains `address`, and the offset of the
+    /// address from the function's start. Functions without a size only
+    /// contain their first address.
+    pub fn resolve(&self, address: u64) -> Option<(FunctionSymbol<'a>, u64)> {
+        let index = match self
+            .functions
+            .binary_search_by_key(&address, |function| function.address)
+        {
+            Ok(index) => index,
+            Err(0) => return None,
+            Err(index) => index - 1,
+        };
+        // Aliases share an address, and any of them may have been found
+        let function = self.functions[..=index]
+            .iter()
+            .rev()
+            .take_while(|function| function.address == self.functions[index].address)
+            .max_by_key(|function| function.size)?;
+
+        let offset = address - function.address;
+        if offset < function.size.max(1) {
+            Some((*function, offset))
+        } else {
+            None
+        }
+    }
+}
+
+/// Formats a symbol name mangled with Rust's legacy scheme, e.g.
+/// `_ZN10my_rust_os4main17h0123456789abcdefE`, as its path without the
+/// trailing hash, i.e. `my_rust_os::main`. Other names are formatted as
+/// they are.
+///
+/// Formatting doesn't allocate, so it can be used while panicking.
+pub fn demangle(name: &str) -> Demangled<'_> {
+    Demangled(name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Demangled<'a>(&'a str);
+
+impl fmt::Display for Demangled<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match legacy_path(self.0) {
+            Some(path) => {
+                let mut identifiers = PathIdentifiers { rest: path }.peekable();
+                let mut first = true;
+                while let Some(identifier) = identifiers.next() {
+                    if identifiers.peek().is_none() && is_hash(identifier) {
+                        break;
+                    }
+                    if !first {
+                        f.write_str("::")?;
+                    }
+                    write_identifier(f, identifier)?;
+                    first = false;
+                }
+                Ok(())
+            }
+            None => f.write_str(self.0),
+        }
+    }
+}
+
+/// The length-prefixed identifiers between `_ZN` and `E`, if `name` is
+/// well-formed.
+fn legacy_path(name: &str) -> Option<&str> {
+    let path = name.strip_prefix("_ZN")?.strip_suffix('E')?;
+    let mut identifiers = PathIdentifiers { rest: path };
+    while identifiers.next().is_some() {}
+    if identifiers.rest.is_empty() && !path.is_empty() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+struct PathIdentifiers<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for PathIdentifiers<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let digits = self
+            .rest
+            .bytes()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        let length: usize = self.rest[..digits].parse().ok()?;
+        // A crafted length can overflow, which ends the path like any other
+        // malformed one
+        let end = digits.checked_add(length)?;
+        let identifier = self.rest.get(digits..end)?;
+        self.rest = &self.rest[end..];
+        Some(identifier)
+    }
+}
+
+/// Whether `identifier` is the `h` and 16 hex digits the compiler appends
+/// to every path.
+fn is_hash(identifier: &str) -> bool {
+    identifier.len() == 17
+        && identifier.starts_with('h')
+        && identifier[1..].bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+/// Write an identifier with its `$`-escapes and `..` path separators
+/// decoded.
+fn write_identifier(f: &mut fmt::Formatter, identifier: &str) -> fmt::Result {
+    // A leading underscore only keeps identifiers from starting with `$`
+    let mut rest = match identifier.strip_prefix("_$") {
+        Some(_) => &identifier[1..],
+        None => identifier,
+    };
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("..") {
+            f.write_str("::")?;
+            rest = after;
+        } else if let Some(escape) = rest
+            .strip_prefix('$')
+            .and_then(|after| Some(&after[..after.find('$')?]))
+        {
+            match escape_character(escape) {
+                Some(character) => write!(f, "{}", character)?,
+                None => write!(f, "${}$", escape)?,
+            }
+            rest = &rest[escape.len() + 2..];
+        } else {
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|&(_, character)| character == '$' || character == '.')
+                .map_or(rest.len(), |(end, _)| end);
+            f.write_str(&rest[..end])?;
+            rest = &rest[end..];
+        }
+    }
+
+    Ok(())
+}
+
+fn escape_character(escape: &str) -> Option<char> {
+    match escape {
+        "SP" => Some('@'),
+        "BP" => Some('*'),
+        "RF" => Some('&'),
+        "LT" => Some('<'),
+        "GT" => Some('>'),
+        "LP" => Some('('),
+        "RP" => Some(')'),
+        "C" => Some(','),
+        _ => {
+            let code = u32::from_str_radix(escape.strip_prefix('u')?, 16).ok()?;
+            core::char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn resolve_function_addresses() {
+        let file_bytes = include_bytes!("test_files/hello_world.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let symbols = SymbolMap::new(&elf_file).expect("Failed to read symbols");
+        assert!(!symbols.is_empty());
+
+        // main is at 0x401126 and 32 bytes long
+        let (function, offset) = symbols
+            .resolve(0x40112A)
+            .expect("Expected main to contain the address");
+        assert_eq!("main", function.name());
+        assert_eq!(0x401126, function.address());
+        assert_eq!(32, function.size());
+        assert_eq!(4, offset);
+        // The address right after main is past its end
+        assert_eq!(None, symbols.resolve(0x401146));
+
+        // frame_dummy has no size, so only its own address resolves to it
+        assert_eq!(
+            Some("frame_dummy"),
+            symbols
+                .resolve(0x401120)
+                .map(|(function, _)| function.name())
+        );
+        assert_eq!(None, symbols.resolve(0x401121));
+        assert_eq!(None, symbols.resolve(0));
+        assert_eq!(None, symbols.resolve(u64::MAX));
+    }
+
+    #[test]
+    fn demangle_legacy_names() {
+        assert_eq!(
+            "my_rust_os::backtrace::walk",
+            demangle("_ZN10my_rust_os9backtrace4walk17h0123456789abcdefE").to_string()
+        );
+        assert_eq!(
+            "core::ptr::drop_in_place<alloc::vec::Vec<u8>>",
+            demangle(
+                "_ZN4core3ptr46drop_in_place$LT$alloc..vec..Vec$LT$u8$GT$$GT$17h1b2c3d4e5f607182E"
+            )
+            .to_string()
+        );
+        assert_eq!(
+            "<my_rust_os::Foo as core::fmt::Display>::fmt",
+            demangle("_ZN54_$LT$my_rust_os..Foo$u20$as$u20$core..fmt..Display$GT$3fmt17h00000000deadbeefE")
+                .to_string()
+        );
+        // Names that aren't mangled, or not well-formed, are left alone
+        assert_eq!("main", demangle("main").to_string());
+        assert_eq!("_ZN4core3pt", demangle("_ZN4core3pt").to_string());
+        assert_eq!("_ZN4core9ptrE", demangle("_ZN4core9ptrE").to_string());
+        let overflowing = "_ZN4core18446744073709551615ptrE";
+        assert_eq!(overflowing, demangle(overflowing).to_string());
+    }
+}
@@ -0,0 +1,26 @@
+// Built with: gcc -c -O2 -ffreestanding -fno-pic -mno-red-zone -mgeneral-regs-only -fno-stack-protector -fno-asynchronous-unwind-tables kernel_module.c -o kernel_module.elf64
+
+// Exported by the kernel
+extern void kernel_record(long value);
+extern long kernel_counter;
+
+static long initialized = 0x1234;
+static long zeroed[4];
+const char message[] = "module";
+long *pointer = &initialized;
+
+static __attribute__((noinline)) long sum(void) {
+    return zeroed[0] + zeroed[1] + zeroed[2] + zeroed[3];
+}
+
+int module_init(void) {
+    zeroed[2] = message[0];
+    kernel_record(initialized + sum());
+    kernel_counter += *pointer;
+    return 0;
+}
+
+void module_exit(void) {
+    kernel_record(-1);
+    kernel_counter = 0;
+}
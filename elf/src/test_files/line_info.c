@@ -0,0 +1,18 @@
+// Built with:
+// gcc -g -gdwarf-4 -O0 -no-pie -o line_info_dwarf4.elf64 line_info.c
+// gcc -g -gdwarf-5 -O0 -no-pie -o line_info_dwarf5.elf64 line_info.c
+#include "line_info.h"
+
+int sum_of_squares(int count)
+{
+    int sum = 0;
+    for (int i = 1; i <= count; i++) {
+        sum += square(i);
+    }
+    return sum;
+}
+
+int main(void)
+{
+    return sum_of_squares(3) == 14 ? 0 : 1;
+}
@@ -0,0 +1,29 @@
+// Built with: gcc -shared -nostdlib -fPIC -O2 -Wl,--hash-style=both -o shared_library.elf64 shared_library.c
+int exported_counter = 1;
+int answer = 42;
+
+__attribute__((noinline)) static int helper(int value) {
+    return value * 2;
+}
+
+int add_one(int value) {
+    return helper(value) / 2 + 1;
+}
+
+int times_answer(int value) {
+    return value * answer;
+}
+
+void increment_counter(void) {
+    exported_counter += 1;
+}
+
+__attribute__((weak)) int weak_function(void) {
+    return 0;
+}
+
+extern int imported_function(void);
+
+int call_imported(void) {
+    return imported_function();
+}
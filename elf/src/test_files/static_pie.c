@@ -0,0 +1,11 @@
+// Built with: gcc -static-pie -nostdlib -fPIE -O2 -o static_pie.elf64 static_pie.c
+// and: gcc -static-pie -nostdlib -fPIE -O2 -march=x86-64-v3 -fcf-protection \
+//     -Wl,-z,x86-64-v3 -o static_pie_v3.elf64 static_pie.c
+static long value = 42;
+long *pointer = &value;
+
+void _start(void) {
+    *pointer += 1;
+    for (;;) {
+    }
+}
@@ -0,0 +1,10 @@
+// Built with: gcc -static-pie -nostdlib -fPIE -O2 -fno-stack-protector -o tls.elf64 tls.c
+// and: gcc -m32 -static -no-pie -nostdlib -O2 -fno-stack-protector -o tls.elf32 tls.c
+__thread long counter = 7;
+__thread long scratch[4] __attribute__((aligned(32)));
+
+void _start(void) {
+    counter += scratch[0] + 1;
+    for (;;) {
+    }
+}
@@ -0,0 +1,115 @@
+use super::elf64::Elf64ProgramHeader;
+
+/// Size of the thread control block that follows the TLS data of a thread.
+/// Its first word points to itself, so that `%fs:0` holds the thread pointer,
+/// and it is large enough for the stack protector canary gcc reads from
+/// `%fs:0x28`.
+pub const TCB_SIZE: u64 = 0x30;
+
+/// The initialization image of a file's thread-local storage, described by
+/// its `Tls` segment. Every thread gets a copy of the first `file_size` bytes
+/// (`.tdata`), followed by zeroes up to `memory_size` (`.tbss`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TlsTemplate {
+    address: u64,
+    file_size: u64,
+    memory_size: u64,
+    alignment: u64,
+}
+
+impl TlsTemplate {
+    pub fn new(address: u64, file_size: u64, memory_size: u64, alignment: u64) -> Self {
+        Self {
+            address,
+            file_size,
+            memory_size,
+            // 0 and 1 both mean the data needs no alignment
+            alignment: alignment.max(1),
+        }
+    }
+
+    /// Virtual address of the initialization image in the loaded file.
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Size of the initialized data, i.e. `.tdata`.
+    pub fn file_size(&self) -> u64 {
+        self.file_size
+    }
+
+    /// Size of the whole TLS data, i.e. `.tdata` and `.tbss`.
+    pub fn memory_size(&self) -> u64 {
+        self.memory_size
+    }
+
+    pub fn alignment(&self) -> u64 {
+        self.alignment
+    }
+
+    /// Distance from the thread pointer back to the start of the TLS data.
+    /// In the x86_64 variant II layout the data ends at the thread pointer,
+    /// padded to its alignment. The linker computes the `%fs` offsets of
+    /// thread-local variables the same way.
+    pub fn data_offset(&self) -> u64 {
+        align_up(self.memory_size, self.alignment)
+    }
+
+    /// Offset of the thread pointer from the start of a thread's TLS block,
+    /// which must be aligned to `block_alignment`. The TLS data comes before
+    /// the thread pointer, and the thread control block starts at it.
+    pub fn thread_pointer_offset(&self) -> u64 {
+        align_up(self.data_offset(), self.block_alignment())
+    }
+
+    /// Size of a thread's TLS block, including the thread control block.
+    pub fn block_size(&self) -> u64 {
+        self.thread_pointer_offset() + TCB_SIZE
+    }
+
+    pub fn block_alignment(&self) -> u64 {
+        // The thread control block holds a pointer
+        self.alignment.max(8)
+    }
+}
+
+impl From<&Elf64ProgramHeader> for TlsTemplate {
+    fn from(header: &Elf64ProgramHeader) -> Self {
+        Self::new(
+            header.p_vaddr(),
+            header.p_filesz(),
+            header.p_memsz(),
+            header.alignment(),
+        )
+    }
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    value.div_ceil(alignment) * alignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variant_2_block_layout() {
+        // A long in .tdata and a 32-byte aligned array of 4 longs in .tbss
+        let template = TlsTemplate::new(0x3F00, 8, 0x40, 0x20);
+        assert_eq!(0x40, template.data_offset());
+        assert_eq!(0x40, template.thread_pointer_offset());
+        assert_eq!(0x40 + TCB_SIZE, template.block_size());
+        assert_eq!(0x20, template.block_alignment());
+
+        // The block is padded at its start to keep the thread pointer aligned
+        let template = TlsTemplate::new(0, 4, 0x14, 4);
+        assert_eq!(0x14, template.data_offset());
+        assert_eq!(0x18, template.thread_pointer_offset());
+        assert_eq!(8, template.block_alignment());
+
+        let template = TlsTemplate::new(0, 0, 0, 0);
+        assert_eq!(1, template.alignment());
+        assert_eq!(0, template.thread_pointer_offset());
+        assert_eq!(TCB_SIZE, template.block_size());
+    }
+}
@@ -0,0 +1,287 @@
+use alloc::vec::Vec;
+
+use super::{
+    elf64::{
+        sections::{SectionHeader, SectionHeaderType},
+        Elf64File, Elf64ProgramHeader,
+    },
+    ElfParseError, ElfType, InstructionSet, ProgramSegmentType,
+};
+
+const PAGE_SIZE: u64 = 4096;
+
+/// How serious a finding is. The loader refuses files with `Fatal` findings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The file is unusual, but it can still be loaded safely.
+    Warning,
+    /// The file can't be loaded correctly or safely.
+    Fatal,
+}
+
+/// A problem `validate` found in a file. Segments and sections are identified
+/// by their index in the program or section header table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Finding {
+    /// The file's `e_machine` isn't `InstructionSet::X86_64`.
+    WrongInstructionSet,
+    /// Two `Load` segments map overlapping virtual address ranges.
+    OverlappingSegments(u16, u16),
+    /// Two `Load` segments share a page, but differ in whether they are
+    /// writable or executable, so the page can't have the permissions of both.
+    ConflictingPagePermissions(u16, u16),
+    /// The entry point isn't inside an executable `Load` segment.
+    EntryPointNotExecutable(u64),
+    /// A `Load` segment is both writable and executable.
+    WritableAndExecutable(u16),
+    /// A segment's `p_vaddr` and `p_offset` aren't congruent modulo its
+    /// alignment, so it can't be mapped page by page from the file.
+    MisalignedSegment(u16),
+    /// A section's contents extend past the end of the file.
+    SectionOutOfBounds(u16),
+}
+
+impl Finding {
+    pub fn severity(&self) -> Severity {
+        match self {
+            // Sections aren't needed to load the file
+            Self::SectionOutOfBounds(_) => Severity::Warning,
+            _ => Severity::Fatal,
+        }
+    }
+}
+
+/// Check a file for structural problems and insecure segment permissions,
+/// beyond what is needed to parse it. Files without findings have sound
+/// segments for the loader, and sections that lie within the file.
+pub fn validate(elf_file: &Elf64File) -> Result<Vec<Finding>, ElfParseError> {
+    let mut findings = Vec::new();
+
+    if *elf_file.instruction_set() != InstructionSet::X86_64 {
+        findings.push(Finding::WrongInstructionSet);
+    }
+
+    let mut segments: Vec<(u16, Elf64ProgramHeader)> = Vec::new();
+    for (index, header) in elf_file.program_headers().into_iter().flatten().enumerate() {
+        segments.push((index as u16, header?));
+    }
+    let loads = || {
+        segments
+            .iter()
+            .filter(|(_, header)| *header.segment_type() == ProgramSegmentType::Load)
+    };
+
+    for (index, header) in segments.iter() {
+        if !is_aligned(header) {
+            findings.push(Finding::MisalignedSegment(*index));
+        }
+    }
+
+    for (index, header) in loads() {
+        let flags = header.flags();
+        if flags.writable() && flags.executable() {
+            findings.push(Finding::WritableAndExecutable(*index));
+        }
+    }
+
+    for (first_index, first) in loads() {
+        for (second_index, second) in loads().filter(|(index, _)| index > first_index) {
+            if overlaps(first, second) {
+                findings.push(Finding::OverlappingSegments(*first_index, *second_index));
+            } else if shares_page(first, second) && !same_permissions(first, second) {
+                findings.push(Finding::ConflictingPagePermissions(
+                    *first_index,
+                    *second_index,
+                ));
+            }
+        }
+    }
+
+    // Shared libraries that aren't also executables have no entry point
+    let entry_point = elf_file.entry_point();
+    let has_entry_point = match elf_file.elf_type() {
+        ElfType::Executable => true,
+        ElfType::Shared => entry_point != 0,
+        _ => false,
+    };
+    if has_entry_point
+        && !loads().any(|(_, header)| header.flags().executable() && contains(header, entry_point))
+    {
+        findings.push(Finding::EntryPointNotExecutable(entry_point));
+    }
+
+    let file_length = elf_file.file_bytes().len() as u64;
+    for (index, section) in elf_file.section_headers()?.enumerate() {
+        if !fits_in_file(&section?, file_length) {
+            findings.push(Finding::SectionOutOfBounds(index as u16));
+        }
+    }
+
+    Ok(findings)
+}
+
+/// Segments must start at the same offset into a page in memory as in the
+/// file. Alignments of 0 and 1 mean the segment needs no alignment.
+fn is_aligned(header: &Elf64ProgramHeader) -> bool {
+    let alignment = header.alignment().max(1);
+    header.p_vaddr() % alignment == header.p_offset() % alignment
+}
+
+fn overlaps(first: &Elf64ProgramHeader, second: &Elf64ProgramHeader) -> bool {
+    if first.p_memsz() == 0 || second.p_memsz() == 0 {
+        return false;
+    }
+
+    let first_end = first.p_vaddr().saturating_add(first.p_memsz());
+    let second_end = second.p_vaddr().saturating_add(second.p_memsz());
+    first.p_vaddr() < second_end && second.p_vaddr() < first_end
+}
+
+/// Segments that aren't page aligned can end and start on the same page.
+fn shares_page(first: &Elf64ProgramHeader, second: &Elf64ProgramHeader) -> bool {
+    if first.p_memsz() == 0 || second.p_memsz() == 0 {
+        return false;
+    }
+
+    let pages = |header: &Elf64ProgramHeader| {
+        let last = header.p_vaddr().saturating_add(header.p_memsz() - 1);
+        (header.p_vaddr() / PAGE_SIZE, last / PAGE_SIZE)
+    };
+    let (first_start, first_end) = pages(first);
+    let (second_start, second_end) = pages(second);
+    first_start <= second_end && second_start <= first_end
+}
+
+fn same_permissions(first: &Elf64ProgramHeader, second: &Elf64ProgramHeader) -> bool {
+    first.flags().writable() == second.flags().writable()
+        && first.flags().executable() == second.flags().executable()
+}
+
+fn contains(header: &Elf64ProgramHeader, address: u64) -> bool {
+    address >= header.p_vaddr() && address - header.p_vaddr() < header.p_memsz()
+}
+
+fn fits_in_file(section: &SectionHeader, file_length: u64) -> bool {
+    // NoBits sections occupy no space in the file
+    if *section.header_type() == SectionHeaderType::NoBits {
+        return true;
+    }
+
+    match section
+        .section_file_offset()
+        .checked_add(section.section_size())
+    {
+        Some(end) => end <= file_length,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// Offset of the program header at `index` in hello_world.elf64.
+    fn program_header(index: usize) -> usize {
+        64 + index * 56
+    }
+
+    fn findings(file_bytes: &[u8]) -> Vec<Finding> {
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        validate(&elf_file).expect("Failed to validate file")
+    }
+
+    #[test]
+    fn validate_fixtures() {
+        let fixtures: [&[u8]; 4] = [
+            include_bytes!("test_files/main_ret0.elf64"),
+            include_bytes!("test_files/hello_world.elf64"),
+            include_bytes!("test_files/static_pie.elf64"),
+            include_bytes!("test_files/tls.elf64"),
+        ];
+        for file_bytes in fixtures.iter() {
+            assert_eq!(Vec::<Finding>::new(), findings(file_bytes));
+        }
+    }
+
+    #[test]
+    fn find_wrong_instruction_set() {
+        let mut file_bytes = include_bytes!("test_files/hello_world.elf64").to_vec();
+        // e_machine of EM_386
+        file_bytes[18] = 3;
+        assert_eq!(vec![Finding::WrongInstructionSet], findings(&file_bytes));
+    }
+
+    #[test]
+    fn find_insecure_and_misplaced_segments() {
+        let mut file_bytes = include_bytes!("test_files/hello_world.elf64").to_vec();
+        // Make the text segment writable
+        file_bytes[program_header(3) + 4] |= 2;
+        // Grow the read-only data segment at 0x402000 into the data segment
+        // at 0x403E00
+        file_bytes[program_header(4) + 40..][..8].copy_from_slice(&0x2000u64.to_le_bytes());
+        // Move the data segment's file data off its page offset
+        file_bytes[program_header(5) + 8..][..8].copy_from_slice(&0x2E08u64.to_le_bytes());
+
+        assert_eq!(
+            vec![
+                Finding::MisalignedSegment(5),
+                Finding::WritableAndExecutable(3),
+                Finding::OverlappingSegments(4, 5),
+            ],
+            findings(&file_bytes)
+        );
+        assert!(findings(&file_bytes)
+            .iter()
+            .all(|finding| finding.severity() == Severity::Fatal));
+    }
+
+    #[test]
+    fn find_conflicting_permissions_on_shared_page() {
+        // Move the segment at `index` onto the page where the data segment at
+        // 0x403E00 starts
+        let move_to_data_page = |index: usize| {
+            let mut file_bytes = include_bytes!("test_files/hello_world.elf64").to_vec();
+            file_bytes[program_header(index) + 16..][..8]
+                .copy_from_slice(&0x403000u64.to_le_bytes());
+            file_bytes
+        };
+
+        // The text segment, along with the entry point
+        let mut file_bytes = move_to_data_page(3);
+        file_bytes[24..32].copy_from_slice(&0x403040u64.to_le_bytes());
+        assert_eq!(
+            vec![Finding::ConflictingPagePermissions(3, 5)],
+            findings(&file_bytes)
+        );
+        // The read-only data segment
+        assert_eq!(
+            vec![Finding::ConflictingPagePermissions(4, 5)],
+            findings(&move_to_data_page(4))
+        );
+    }
+
+    #[test]
+    fn find_entry_point_outside_executable_segment() {
+        let mut file_bytes = include_bytes!("test_files/hello_world.elf64").to_vec();
+        // The start of the read-only segment with the ELF header
+        file_bytes[24..32].copy_from_slice(&0x400000u64.to_le_bytes());
+        assert_eq!(
+            vec![Finding::EntryPointNotExecutable(0x400000)],
+            findings(&file_bytes)
+        );
+    }
+
+    #[test]
+    fn find_section_outside_file() {
+        let mut file_bytes = include_bytes!("test_files/hello_world.elf64").to_vec();
+        // Section headers start at 14520, and .text is the 14th
+        let text = 14520 + 13 * 64;
+        file_bytes[text + 32..][..8].copy_from_slice(&0x10000u64.to_le_bytes());
+
+        let findings = findings(&file_bytes);
+        assert_eq!(vec![Finding::SectionOutOfBounds(13)], findings);
+        assert_eq!(Severity::Warning, findings[0].severity());
+    }
+}
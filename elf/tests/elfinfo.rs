@@ -0,0 +1,99 @@
+//! Compares the output of `elfinfo` for the test files with the expected
+//! output in `tests/golden`. Run with `UPDATE_GOLDEN=1` to rewrite the
+//! expected output after an intended change, and review the diff.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+const TEST_FILES: &[&str] = &[
+    "hello_world.elf32",
+    "hello_world.elf64",
+    "kernel_module.elf64",
+    "line_info_dwarf4.elf64",
+    "line_info_dwarf5.elf64",
+    "main_ret0.elf64",
+    "shared_library.elf64",
+    "static_pie.elf64",
+    "static_pie_v3.elf64",
+    "tls.elf32",
+    "tls.elf64",
+];
+
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn elfinfo(arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_elfinfo"))
+        .args(arguments)
+        .output()
+        .expect("Failed to run elfinfo")
+}
+
+fn check_golden(test_file: &str, json: bool) {
+    let path = manifest_dir().join("src/test_files").join(test_file);
+    let path = path.to_str().expect("Test file paths should be UTF-8");
+    let output = if json {
+        elfinfo(&["--json", path])
+    } else {
+        elfinfo(&[path])
+    };
+    assert!(
+        output.status.success(),
+        "elfinfo failed on {}: {}",
+        test_file,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let actual = String::from_utf8(output.stdout).expect("elfinfo should print UTF-8");
+
+    let extension = if json { "json" } else { "txt" };
+    let golden: PathBuf = manifest_dir()
+        .join("tests/golden")
+        .join(format!("{}.{}", test_file, extension));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden, &actual).expect("Failed to write the expected output");
+        return;
+    }
+
+    let expected = fs::read_to_string(&golden)
+        .unwrap_or_else(|error| panic!("Failed to read {}: {}", golden.display(), error));
+    assert!(
+        actual == expected,
+        "Output for {} differs from {}:\n{}",
+        test_file,
+        golden.display(),
+        actual
+    );
+}
+
+#[test]
+fn text_output() {
+    for test_file in TEST_FILES {
+        check_golden(test_file, false);
+    }
+}
+
+#[test]
+fn json_output() {
+    for test_file in TEST_FILES {
+        check_golden(test_file, true);
+    }
+}
+
+#[test]
+fn reject_file_that_is_not_elf() {
+    let path = manifest_dir().join("Cargo.toml");
+    let output = elfinfo(&[path.to_str().unwrap()]);
+    assert_eq!(Some(1), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("NotValidElfFile"));
+}
+
+#[test]
+fn reject_missing_file_argument() {
+    let output = elfinfo(&["--json"]);
+    assert_eq!(Some(2), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Usage: elfinfo"));
+}
@@ -1,7 +1,9 @@
+use crate::memory::{self, mapping, GlobalFrameAllocator};
+use alloc::alloc::Layout;
+use spin::Mutex;
 use x86_64::{
-    structures::paging::{
-        mapper::MapToError, FrameAllocator, Mapper, Page, PageTableFlags, Size4KiB,
-    },
+    registers::control::Cr3,
+    structures::paging::{mapper::MapToError, OffsetPageTable, PageTableFlags, Size4KiB},
     VirtAddr,
 };
 
@@ -15,28 +17,47 @@ use fixed_size_block::FixedSizeBlockAllocator;
 static ALLOCATOR: Locked<FixedSizeBlockAllocator> = Locked::new(FixedSizeBlockAllocator::new());
 
 pub const HEAP_START: usize = 0x_4444_4444_0000;
+/// Size of the heap that `init_heap` maps up front.
 pub const HEAP_SIZE: usize = 100 * 1024; // 100 KiB
+/// Size of the virtual address range reserved for the heap, which gets mapped
+/// as the heap grows. It stays within the level 4 entry of `HEAP_START`, so
+/// page tables that copied the kernel's level 4 entries see the heap grow.
+pub const HEAP_MAX_SIZE: usize = 1 << 30; // 1 GiB
+/// The heap grows by multiples of this size, so that small allocations don't
+/// each have to map a page.
+const HEAP_GROWTH: usize = 64 * 1024; // 64 KiB
 
-pub fn init_heap(
-    mapper: &mut impl Mapper<Size4KiB>,
-    frame_allocator: &mut impl FrameAllocator<Size4KiB>,
-) -> Result<(), MapToError<Size4KiB>> {
-    let page_range = {
-        let heap_start = VirtAddr::new(HEAP_START as u64);
-        let heap_end = heap_start + HEAP_SIZE - 1u64;
-        let heap_start_page = Page::containing_address(heap_start);
-        let heap_end_page = Page::containing_address(heap_end);
-        Page::range_inclusive(heap_start_page, heap_end_page)
-    };
+/// The end of the mapped part of the heap, and where the physical memory is
+/// mapped to map more of it.
+static HEAP_MEMORY: Mutex<Option<HeapMemory>> = Mutex::new(None);
+
+struct HeapMemory {
+    end: usize,
+    physical_memory_offset: VirtAddr,
+}
 
-    for page in page_range {
-        let frame = frame_allocator
-            .allocate_frame()
-            .ok_or(MapToError::FrameAllocationFailed)?;
-        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
-        unsafe { mapper.map_to(page, frame, flags, frame_allocator)?.flush() };
+/// Map the start of the heap and hand it to the global allocator, which maps
+/// more of it when it runs out of memory. Frames come from
+/// `memory::GlobalFrameAllocator`, so `memory::init_frame_allocator` must be
+/// called first.
+pub fn init_heap(mapper: &mut OffsetPageTable) -> Result<(), MapToError<Size4KiB>> {
+    let heap_start = VirtAddr::new(HEAP_START as u64);
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+    let mapped_size = mapping::map_allocated_range(
+        mapper,
+        heap_start,
+        HEAP_SIZE as u64,
+        flags,
+        &mut GlobalFrameAllocator,
+    )?;
+    if mapped_size < HEAP_SIZE as u64 {
+        return Err(MapToError::FrameAllocationFailed);
     }
 
+    *HEAP_MEMORY.lock() = Some(HeapMemory {
+        end: HEAP_START + HEAP_SIZE,
+        physical_memory_offset: mapper.phys_offset(),
+    });
     unsafe {
         ALLOCATOR.lock().init(HEAP_START, HEAP_SIZE);
     }
@@ -44,6 +65,46 @@ pub fn init_heap(
     Ok(())
 }
 
+/// Map pages after the end of the heap, enough for an allocation of `layout`
+/// that the heap had no room for.
+///
+/// Returns the start and size of the newly mapped memory, or `None` if the
+/// heap wasn't initialized or it couldn't map any pages, because the reserved
+/// range or the frame allocator ran out.
+fn grow_heap(layout: Layout) -> Option<(usize, usize)> {
+    let mut heap_memory = HEAP_MEMORY.lock();
+    let heap_memory = heap_memory.as_mut()?;
+    let start = heap_memory.end;
+    let size = align_up(layout.size() + layout.align(), HEAP_GROWTH)
+        .min(HEAP_START + HEAP_MAX_SIZE - start);
+
+    // The heap is mapped in the kernel's part of every page table, so it can
+    // be mapped through the active one
+    let physical_memory_offset = heap_memory.physical_memory_offset;
+    let (level_4_frame, _) = Cr3::read();
+    let mut mapper = unsafe {
+        let level_4_table = memory::page_table_at(level_4_frame, physical_memory_offset);
+        OffsetPageTable::new(level_4_table, physical_memory_offset)
+    };
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+    let heap_end = VirtAddr::new(start as u64);
+    // Pages that were mapped before running out of frames are still usable.
+    // The reserved range is only mapped here, so any other error is a bug.
+    let mapped_size = mapping::map_allocated_range(
+        &mut mapper,
+        heap_end,
+        size as u64,
+        flags,
+        &mut GlobalFrameAllocator,
+    )
+    .expect("Failed to map the heap's reserved range") as usize;
+    if mapped_size == 0 {
+        return None;
+    }
+    heap_memory.end += mapped_size;
+    Some((start, mapped_size))
+}
+
 /// Align the given address upwards to the given alignment
 ///
 /// Requires `align` to be a power of 2
@@ -1,6 +1,6 @@
 use super::{linked_list, Locked};
 use alloc::alloc::{GlobalAlloc, Layout};
-use core::mem;
+use core::{mem, ptr};
 
 struct ListNode {
     next: Option<&'static mut ListNode>,
@@ -36,8 +36,22 @@ impl FixedSizeBlockAllocator {
         self.fallback_allocator.init(heap_start, heap_size);
     }
 
+    /// Allocate from the fallback allocator, growing the heap until it has
+    /// room for `layout`.
     fn fallback_alloc(&mut self, layout: Layout) -> *mut u8 {
-        unsafe { self.fallback_allocator.allocate(layout) }
+        loop {
+            let ptr = unsafe { self.fallback_allocator.allocate(layout) };
+            if !ptr.is_null() {
+                return ptr;
+            }
+
+            // The new memory merges with a free region at the old end of the
+            // heap, if there is one
+            match super::grow_heap(layout) {
+                Some((start, size)) => unsafe { self.fallback_allocator.extend(start, size) },
+                None => return ptr::null_mut(),
+            }
+        }
     }
 
     /// Choose an appropriate block size for the given Layout.alloc
@@ -58,6 +58,15 @@ impl LinkedListAllocator {
         self.add_free_region(heap_start, heap_size);
     }
 
+    /// Add memory to the heap, e.g. memory that was mapped after the end of
+    /// the heap as it grew.
+    ///
+    /// This function is unsafe because the caller must guarantee that the
+    /// given memory is valid and unused.
+    pub unsafe fn extend(&mut self, start: usize, size: usize) {
+        self.add_free_region(start, size);
+    }
+
     pub unsafe fn allocate(&mut self, layout: Layout) -> *mut u8 {
         let (size, align) = Self::size_align(layout);
 
@@ -0,0 +1,226 @@
+use bootloader::{bootinfo::MemoryRegionType, BootInfo};
+use conquer_once::spin::OnceCell;
+use core::{arch::asm, slice};
+use x86_64::{
+    registers::control::Cr3,
+    structures::paging::{OffsetPageTable, Translate},
+    VirtAddr,
+};
+
+use crate::{
+    elf::{
+        dwarf::{LineInfo, SourceLocation},
+        elf64::Elf64File,
+        symbolize::{demangle, FunctionSymbol, SymbolMap},
+    },
+    memory, println, serial_println,
+};
+
+/// Stack walks stop after this many frames, in case the chain of frame
+/// pointers loops.
+const MAX_FRAMES: usize = 64;
+
+struct KernelImage {
+    symbols: SymbolMap<'static>,
+    line_info: Option<LineInfo<'static>>,
+    physical_memory_offset: VirtAddr,
+}
+
+static KERNEL_IMAGE: OnceCell<KernelImage> = OnceCell::uninit();
+
+/// Read the kernel's symbol table, so that backtraces can name the kernel
+/// functions their return addresses are in, and the source lines if the
+/// kernel was built with debug information. Must be called after the heap
+/// is initialized.
+///
+/// The bootloader leaves the kernel's ELF file in physical memory, in the
+/// memory map's `Kernel` region, and maps the kernel's segments from it. Its
+/// `.symtab` and DWARF sections aren't part of any segment, so it is read
+/// from there through the physical memory mapping. Backtraces show bare
+/// addresses if the file can't be found or was stripped.
+pub fn init(boot_info: &'static BootInfo) {
+    let physical_memory_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let region = match boot_info
+        .memory_map
+        .iter()
+        .find(|region| region.region_type == MemoryRegionType::Kernel)
+    {
+        Some(region) => region,
+        None => return,
+    };
+
+    let file_bytes = unsafe {
+        slice::from_raw_parts(
+            (physical_memory_offset + region.range.start_addr()).as_ptr::<u8>(),
+            (region.range.end_addr() - region.range.start_addr()) as usize,
+        )
+    };
+    let file = match Elf64File::from_bytes(file_bytes) {
+        Ok(file) => file,
+        Err(error) => {
+            serial_println!("Failed to read the kernel's ELF file: {:?}", error);
+            return;
+        }
+    };
+    let symbols = match SymbolMap::new(&file) {
+        Ok(symbols) => symbols,
+        Err(error) => {
+            serial_println!("Failed to read the kernel's symbols: {:?}", error);
+            return;
+        }
+    };
+    let line_info = file.line_info().unwrap_or_else(|error| {
+        serial_println!("Failed to read the kernel's line tables: {:?}", error);
+        None
+    });
+
+    KERNEL_IMAGE
+        .try_init_once(|| KernelImage {
+            symbols,
+            line_info,
+            physical_memory_offset,
+        })
+        .expect("backtrace::init should only be called once");
+}
+
+/// The kernel function whose code contains `address`, and the offset of the
+/// address from its start.
+pub fn resolve(address: u64) -> Option<(FunctionSymbol<'static>, u64)> {
+    KERNEL_IMAGE.get()?.symbols.resolve(address)
+}
+
+/// The source line of the kernel code at `address`, if the kernel was built
+/// with debug information.
+pub fn locate(address: u64) -> Option<SourceLocation<'static>> {
+    KERNEL_IMAGE.get()?.line_info?.locate(address).ok()?
+}
+
+/// The frame pointer of the calling function. The kernel is built with
+/// frame pointers forced on, so it points at the caller's saved frame pointer,
+/// followed by its return address.
+#[inline(always)]
+pub fn frame_pointer() -> u64 {
+    let frame_pointer: u64;
+    unsafe { asm!("mov {}, rbp", out(reg) frame_pointer, options(nomem, nostack)) };
+    frame_pointer
+}
+
+/// Walk the chain of saved frame pointers from `frame_pointer`, yielding the
+/// return address of every frame.
+///
+/// The walk stops at a null or misaligned frame pointer, one that doesn't
+/// point further up the stack than the last one, or one that isn't mapped,
+/// so a corrupted stack ends the backtrace early instead of faulting. Before
+/// `init`, mappings can't be checked and no frames are walked.
+pub fn frames(frame_pointer: u64) -> Frames {
+    Frames {
+        frame_pointer,
+        previous: 0,
+        count: 0,
+    }
+}
+
+pub struct Frames {
+    frame_pointer: u64,
+    previous: u64,
+    count: usize,
+}
+
+impl Iterator for Frames {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame_pointer = self.frame_pointer;
+        if frame_pointer == 0
+            || frame_pointer % 8 != 0
+            || frame_pointer <= self.previous
+            || self.count >= MAX_FRAMES
+            || !is_frame_mapped(frame_pointer)
+        {
+            return None;
+        }
+
+        let (saved_frame_pointer, return_address) = unsafe {
+            let frame = frame_pointer as *const u64;
+            (*frame, *frame.add(1))
+        };
+        if return_address == 0 {
+            return None;
+        }
+
+        self.previous = frame_pointer;
+        self.frame_pointer = saved_frame_pointer;
+        self.count += 1;
+        Some(return_address)
+    }
+}
+
+/// Whether both words of the frame at `frame_pointer`, the saved frame
+/// pointer and the return address, are mapped in the active page table. Frame
+/// pointers are only 8-byte aligned, so the return address can be on the next
+/// page.
+fn is_frame_mapped(frame_pointer: u64) -> bool {
+    let physical_memory_offset = match KERNEL_IMAGE.get() {
+        Some(image) => image.physical_memory_offset,
+        None => return false,
+    };
+
+    let (level_4_frame, _) = Cr3::read();
+    let mapper = unsafe {
+        OffsetPageTable::new(
+            memory::page_table_at(level_4_frame, physical_memory_offset),
+            physical_memory_offset,
+        )
+    };
+    let is_mapped = |address| {
+        VirtAddr::try_new(address).is_ok_and(|address| mapper.translate_addr(address).is_some())
+    };
+    is_mapped(frame_pointer) && frame_pointer.checked_add(8).is_some_and(is_mapped)
+}
+
+/// Print a backtrace to both the VGA buffer and the serial interface.
+///
+/// `instruction_pointer` is printed as the first frame if given, e.g. the
+/// faulting instruction of an exception, followed by the return addresses of
+/// the frames from `frame_pointer` on. Frames in code with line tables are
+/// followed by their source location.
+pub fn print(instruction_pointer: Option<u64>, frame_pointer: u64) {
+    println!("Backtrace:");
+    serial_println!("Backtrace:");
+
+    let addresses = instruction_pointer
+        .into_iter()
+        .map(|address| (address, address))
+        // A return address follows the call, which may be the last
+        // instruction of its function, so it is resolved one byte earlier
+        .chain(frames(frame_pointer).map(|address| (address, address - 1)));
+    for (index, (address, lookup_address)) in addresses.enumerate() {
+        match resolve(lookup_address) {
+            Some((function, offset)) => {
+                let offset = offset + (address - lookup_address);
+                println!(
+                    "{:>4}: {:#x} - {}+{:#x}",
+                    index,
+                    address,
+                    demangle(function.name()),
+                    offset
+                );
+                serial_println!(
+                    "{:>4}: {:#x} - {}+{:#x}",
+                    index,
+                    address,
+                    demangle(function.name()),
+                    offset
+                );
+            }
+            None => {
+                println!("{:>4}: {:#x} - <unknown>", index, address);
+                serial_println!("{:>4}: {:#x} - <unknown>", index, address);
+            }
+        }
+        if let Some(location) = locate(lookup_address) {
+            println!("{:>10} {}", "at", location);
+            serial_println!("{:>10} {}", "at", location);
+        }
+    }
+}
@@ -0,0 +1,459 @@
+use alloc::{borrow::Cow, collections::BTreeMap, vec, vec::Vec};
+use spin::Mutex;
+use x86_64::{
+    structures::{
+        idt::InterruptStackFrame,
+        paging::{mapper::TranslateResult, OffsetPageTable, PageTableFlags, Translate},
+    },
+    VirtAddr,
+};
+
+use super::{
+    builder::{note_bytes, ElfBuilder, SegmentContents},
+    loader::LoadedImage,
+    ElfType, InstructionSet, ProgramHeaderFlags, ProgramSegmentType,
+};
+use crate::memory;
+
+const PAGE_SIZE: u64 = 4096;
+
+/// The user process that is running, whose core is dumped when it faults.
+static USER_PROCESS: Mutex<Option<UserProcess>> = Mutex::new(None);
+
+/// Owner name of the notes that describe a process in a core file.
+pub const CORE_NOTE_OWNER: &str = "CORE";
+
+/// The signal, IDs and registers of a thread, as a Linux `elf_prstatus`.
+pub const NT_PRSTATUS: u32 = 1;
+/// The name and state of the process, as a Linux `elf_prpsinfo`.
+pub const NT_PRPSINFO: u32 = 3;
+
+const PRSTATUS_SIZE: usize = 336;
+const PRSTATUS_REGISTERS_OFFSET: usize = 112;
+const PRPSINFO_SIZE: usize = 136;
+const PRPSINFO_NAME_OFFSET: usize = 40;
+const PRPSINFO_NAME_SIZE: usize = 16;
+const PRPSINFO_ARGUMENTS_OFFSET: usize = 56;
+const PRPSINFO_ARGUMENTS_SIZE: usize = 80;
+
+/// The signal a process is reported to have died of, by its Linux number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    /// Invalid opcode.
+    Illegal,
+    /// Breakpoint or debug exception.
+    Trap,
+    /// Divide error or x87/SIMD floating point exception.
+    FloatingPoint,
+    /// Page fault or general protection fault.
+    Segmentation,
+}
+
+impl Signal {
+    pub fn number(&self) -> u32 {
+        match self {
+            Signal::Illegal => 4,
+            Signal::Trap => 5,
+            Signal::FloatingPoint => 8,
+            Signal::Segmentation => 11,
+        }
+    }
+}
+
+/// The registers of a thread, in the order of a Linux `user_regs_struct`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Registers {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub rbp: u64,
+    pub rbx: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rax: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub orig_rax: u64,
+    pub rip: u64,
+    pub cs: u64,
+    pub rflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+    pub fs_base: u64,
+    pub gs_base: u64,
+    pub ds: u64,
+    pub es: u64,
+    pub fs: u64,
+    pub gs: u64,
+}
+
+impl Registers {
+    fn to_words(&self) -> [u64; 27] {
+        [
+            self.r15,
+            self.r14,
+            self.r13,
+            self.r12,
+            self.rbp,
+            self.rbx,
+            self.r11,
+            self.r10,
+            self.r9,
+            self.r8,
+            self.rax,
+            self.rcx,
+            self.rdx,
+            self.rsi,
+            self.rdi,
+            self.orig_rax,
+            self.rip,
+            self.cs,
+            self.rflags,
+            self.rsp,
+            self.ss,
+            self.fs_base,
+            self.gs_base,
+            self.ds,
+            self.es,
+            self.fs,
+            self.gs,
+        ]
+    }
+}
+
+/// Takes the registers the CPU pushed on an interrupt. The general purpose
+/// registers aren't part of the frame and are left zeroed.
+impl From<&InterruptStackFrame> for Registers {
+    fn from(stack_frame: &InterruptStackFrame) -> Self {
+        Self {
+            rip: stack_frame.instruction_pointer.as_u64(),
+            cs: stack_frame.code_segment,
+            rflags: stack_frame.cpu_flags,
+            rsp: stack_frame.stack_pointer.as_u64(),
+            ss: stack_frame.stack_segment,
+            orig_rax: u64::MAX,
+            ..Self::default()
+        }
+    }
+}
+
+/// The process a core file is written for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrashedProcess<'a> {
+    pub pid: u32,
+    /// The program's name. Only its first 15 bytes are recorded.
+    pub name: &'a str,
+    pub signal: Signal,
+    /// The registers of the faulting thread.
+    pub registers: Registers,
+}
+
+/// A loaded image that runs as a user process.
+#[derive(Debug)]
+pub struct UserProcess {
+    pub pid: u32,
+    pub name: &'static str,
+    pub image: LoadedImage,
+    /// Where the full physical memory is mapped, to read the image's memory
+    /// through.
+    pub physical_memory_offset: VirtAddr,
+}
+
+/// Set the user process that is entered next, whose core the fault handlers
+/// dump if it faults. Returns the process that was set before.
+pub fn set_user_process(process: Option<UserProcess>) -> Option<UserProcess> {
+    core::mem::replace(&mut *USER_PROCESS.lock(), process)
+}
+
+/// Write a core file for the user process that faulted with `registers`, as
+/// called by the fault handlers. Returns `None` for faults of the kernel, or
+/// if no user process was set with `set_user_process`.
+pub fn user_fault_core(registers: Registers, signal: Signal) -> Option<Vec<u8>> {
+    // Code segments of user mode have privilege level 3
+    if registers.cs & 3 != 3 {
+        return None;
+    }
+    // The kernel doesn't hold the lock while user code runs
+    let user_process = USER_PROCESS.lock();
+    let user_process = user_process.as_ref()?;
+    let process = CrashedProcess {
+        pid: user_process.pid,
+        name: user_process.name,
+        signal,
+        registers,
+    };
+
+    Some(core_dump(
+        &user_process.image,
+        user_process.physical_memory_offset,
+        &process,
+    ))
+}
+
+/// A run of contiguous pages of a process, with the same flags.
+#[derive(Debug, PartialEq)]
+pub struct MemoryRegion {
+    pub start: VirtAddr,
+    pub flags: PageTableFlags,
+    pub data: Vec<u8>,
+}
+
+/// Write a core file for a process that crashed in `image`, which `gdb` can
+/// open together with the image's executable.
+///
+/// The file has a `Note` segment with the process's `NT_PRSTATUS` and
+/// `NT_PRPSINFO` notes, followed by a `Load` segment for every run of pages
+/// of the image's segments, stack and initial TLS block that are mapped with
+/// the same flags. Their contents are read through the image's page table,
+/// which the full physical memory must be mapped for at
+/// `physical_memory_offset`.
+///
+/// TLS blocks from `allocate_tls_block` aren't tracked by the image and are
+/// left out.
+pub fn core_dump(
+    image: &LoadedImage,
+    physical_memory_offset: VirtAddr,
+    process: &CrashedProcess,
+) -> Vec<u8> {
+    let level_4_table =
+        unsafe { memory::page_table_at(image.level_4_frame(), physical_memory_offset) };
+    let mapper = unsafe { OffsetPageTable::new(level_4_table, physical_memory_offset) };
+
+    let mut pages = BTreeMap::new();
+    let ranges = image
+        .segments()
+        .iter()
+        .map(|segment| segment.pages())
+        .chain(Some(image.stack().pages()))
+        .chain(image.tls_block().map(|block| block.pages()));
+    for range in ranges {
+        for page in range {
+            if let TranslateResult::Mapped { frame, flags, .. } =
+                mapper.translate(page.start_address())
+            {
+                pages.insert(page, (frame.start_address(), flags));
+            }
+        }
+    }
+
+    let mut regions: Vec<MemoryRegion> = Vec::new();
+    for (page, (frame_address, flags)) in pages {
+        let address = physical_memory_offset + frame_address.as_u64();
+        let contents = unsafe { &*address.as_ptr::<[u8; PAGE_SIZE as usize]>() };
+        match regions.last_mut() {
+            Some(region)
+                if region.flags == flags
+                    && region.start + region.data.len() as u64 == page.start_address() =>
+            {
+                region.data.extend_from_slice(contents)
+            }
+            _ => regions.push(MemoryRegion {
+                start: page.start_address(),
+                flags,
+                data: contents.to_vec(),
+            }),
+        }
+    }
+
+    core_file(&regions, process)
+}
+
+/// Write a core file holding `regions` for `process`.
+pub fn core_file(regions: &[MemoryRegion], process: &CrashedProcess) -> Vec<u8> {
+    let mut notes = note_bytes(CORE_NOTE_OWNER, NT_PRSTATUS, &prstatus(process));
+    notes.extend(note_bytes(CORE_NOTE_OWNER, NT_PRPSINFO, &prpsinfo(process)));
+
+    let mut builder = ElfBuilder::new(ElfType::Core, InstructionSet::X86_64);
+    builder.add_segment(
+        ProgramSegmentType::Note,
+        ProgramHeaderFlags::new(false, false, false),
+        4,
+        SegmentContents::Data {
+            address: 0,
+            data: Cow::Owned(notes),
+            memory_size: 0,
+        },
+    );
+    for region in regions {
+        builder.add_segment(
+            ProgramSegmentType::Load,
+            ProgramHeaderFlags::new(
+                !region.flags.contains(PageTableFlags::NO_EXECUTE),
+                region.flags.contains(PageTableFlags::WRITABLE),
+                true,
+            ),
+            PAGE_SIZE,
+            SegmentContents::Data {
+                address: region.start.as_u64(),
+                data: Cow::Borrowed(&region.data),
+                memory_size: region.data.len() as u64,
+            },
+        );
+    }
+
+    builder.build()
+}
+
+/// Encode the `elf_prstatus` of the process's faulting thread. The thread
+/// is reported as the process's only one, with its ID as the process ID.
+fn prstatus(process: &CrashedProcess) -> Vec<u8> {
+    let mut bytes = vec![0; PRSTATUS_SIZE];
+    let signal = process.signal.number();
+    // si_signo and pr_cursig
+    bytes[0..4].copy_from_slice(&signal.to_le_bytes());
+    bytes[12..14].copy_from_slice(&(signal as u16).to_le_bytes());
+    // pr_pid, pr_pgrp and pr_sid
+    for &offset in &[32, 40, 44] {
+        bytes[offset..offset + 4].copy_from_slice(&process.pid.to_le_bytes());
+    }
+
+    for (index, word) in process.registers.to_words().iter().enumerate() {
+        let offset = PRSTATUS_REGISTERS_OFFSET + index * 8;
+        bytes[offset..offset + 8].copy_from_slice(&word.to_le_bytes());
+    }
+
+    bytes
+}
+
+/// Encode the `elf_prpsinfo` of the process, with its name as both the
+/// program name and its arguments.
+fn prpsinfo(process: &CrashedProcess) -> Vec<u8> {
+    let mut bytes = vec![0; PRPSINFO_SIZE];
+    // pr_sname, the state letter of a dead process
+    bytes[1] = b'X';
+    bytes[24..28].copy_from_slice(&process.pid.to_le_bytes());
+
+    // Both strings are null terminated within their fields
+    let name = process.name.as_bytes();
+    let length = name.len().min(PRPSINFO_NAME_SIZE - 1);
+    bytes[PRPSINFO_NAME_OFFSET..][..length].copy_from_slice(&name[..length]);
+    let length = name.len().min(PRPSINFO_ARGUMENTS_SIZE - 1);
+    bytes[PRPSINFO_ARGUMENTS_OFFSET..][..length].copy_from_slice(&name[..length]);
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::elf64::Elf64File;
+
+    fn process() -> CrashedProcess<'static> {
+        CrashedProcess {
+            pid: 7,
+            name: "a_rather_long_program_name",
+            signal: Signal::Segmentation,
+            registers: Registers {
+                rax: 0x1111,
+                rip: 0x401000,
+                rsp: 0x7FFF_FFFF_EF00,
+                cs: 0x23,
+                ss: 0x1B,
+                ..Registers::default()
+            },
+        }
+    }
+
+    #[test_case]
+    fn encode_prstatus() {
+        let bytes = prstatus(&process());
+        assert_eq!(PRSTATUS_SIZE, bytes.len());
+        assert_eq!(&11u32.to_le_bytes(), &bytes[0..4]);
+        assert_eq!(&11u16.to_le_bytes(), &bytes[12..14]);
+        assert_eq!(&7u32.to_le_bytes(), &bytes[32..36]);
+
+        let register = |index: usize| {
+            let offset = PRSTATUS_REGISTERS_OFFSET + index * 8;
+            u64::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+                bytes[offset + 4],
+                bytes[offset + 5],
+                bytes[offset + 6],
+                bytes[offset + 7],
+            ])
+        };
+        assert_eq!(0x1111, register(10));
+        assert_eq!(0x401000, register(16));
+        assert_eq!(0x23, register(17));
+        assert_eq!(0x7FFF_FFFF_EF00, register(19));
+        assert_eq!(0x1B, register(20));
+    }
+
+    #[test_case]
+    fn encode_prpsinfo() {
+        let bytes = prpsinfo(&process());
+        assert_eq!(PRPSINFO_SIZE, bytes.len());
+        assert_eq!(b'X', bytes[1]);
+        // The name is cut to fit its field with the null byte
+        assert_eq!(
+            b"a_rather_long_p\0",
+            &bytes[PRPSINFO_NAME_OFFSET..PRPSINFO_NAME_OFFSET + PRPSINFO_NAME_SIZE]
+        );
+        assert_eq!(
+            b"a_rather_long_program_name\0",
+            &bytes[PRPSINFO_ARGUMENTS_OFFSET..PRPSINFO_ARGUMENTS_OFFSET + 27]
+        );
+    }
+
+    #[test_case]
+    fn write_core_file() {
+        let present = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+        let regions = [
+            MemoryRegion {
+                start: VirtAddr::new(0x401000),
+                flags: present,
+                data: vec![0xCC; 0x1000],
+            },
+            MemoryRegion {
+                start: VirtAddr::new(0x7FFF_FFFF_E000),
+                flags: present | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE,
+                data: vec![0x55; 0x1000],
+            },
+        ];
+        let file_bytes = core_file(&regions, &process());
+
+        let elf_file =
+            Elf64File::from_bytes(&file_bytes).expect("The file should be correctly parsed");
+        assert_eq!(&ElfType::Core, elf_file.elf_type());
+        let headers: Vec<_> = elf_file
+            .program_headers()
+            .expect("Expected program headers")
+            .map(|header| header.expect("Failed to parse program header"))
+            .collect();
+        assert_eq!(3, headers.len());
+
+        let notes: Vec<_> = elf_file
+            .segment_notes(&headers[0])
+            .expect("Expected notes")
+            .map(|note| note.expect("Failed to parse note"))
+            .collect();
+        assert_eq!(2, notes.len());
+        assert_eq!(CORE_NOTE_OWNER, notes[0].name());
+        assert_eq!(NT_PRSTATUS, notes[0].note_type());
+        assert_eq!(&prstatus(&process())[..], notes[0].descriptor());
+        assert_eq!(NT_PRPSINFO, notes[1].note_type());
+
+        assert_eq!(&ProgramSegmentType::Load, headers[1].segment_type());
+        assert_eq!(
+            &ProgramHeaderFlags::new(true, false, true),
+            headers[1].flags()
+        );
+        assert_eq!(0x401000, headers[1].p_vaddr());
+        assert_eq!(0, headers[1].p_offset() % PAGE_SIZE);
+        assert_eq!(Ok(&regions[0].data[..]), elf_file.segment_data(&headers[1]));
+        assert_eq!(
+            &ProgramHeaderFlags::new(false, true, true),
+            headers[2].flags()
+        );
+        assert_eq!(0x7FFF_FFFF_E000, headers[2].p_vaddr());
+        assert_eq!(Ok(&regions[1].data[..]), elf_file.segment_data(&headers[2]));
+    }
+}
@@ -0,0 +1,950 @@
+use alloc::vec::Vec;
+use core::ptr;
+use x86_64::{
+    registers::model_specific::FsBase,
+    structures::paging::{
+        mapper::{MapToError, TranslateResult},
+        page::{PageRange, PageRangeInclusive},
+        page_table::PageTableEntry,
+        FrameAllocator, FrameDeallocator, Mapper, OffsetPageTable, Page, PageTable, PageTableFlags,
+        PageTableIndex, PhysFrame, Size4KiB, Translate,
+    },
+    VirtAddr,
+};
+
+use super::{
+    elf64::{
+        sections::{SectionHeader, SectionHeaderFlags, SectionHeaderType},
+        symbols::{SymbolBinding, SymbolIterator, SymbolSectionIndex},
+        Elf64File, Elf64ProgramHeader,
+    },
+    module::{MODULE_AREA_END, MODULE_AREA_START},
+    notes::{GnuProperty, X86IsaLevels},
+    relocate::{self, RelocationError, RelocationTarget},
+    tls::TlsTemplate,
+    validate::{self, Finding, Severity},
+    ElfParseError, ElfType, ProgramSegmentType,
+};
+use crate::{
+    allocator::{HEAP_MAX_SIZE, HEAP_START},
+    memory, random,
+};
+
+const PAGE_SIZE: u64 = 4096;
+
+/// Static-PIE executables are loaded at a random base within this range of
+/// the lower half of the address space.
+const RANDOM_BASE_START: u64 = 0x0000_1000_0000_0000;
+const RANDOM_BASE_END: u64 = 0x0000_7000_0000_0000;
+/// Number of random bases to try before giving up on finding one whose range
+/// doesn't overlap the kernel's mappings.
+const RANDOM_BASE_ATTEMPTS: usize = 16;
+
+/// The stack is mapped right below this address, which leaves the last page
+/// of the lower half unmapped.
+const STACK_TOP: u64 = 0x0000_7FFF_FFFF_F000;
+const STACK_PAGES: u64 = 16;
+/// TLS blocks are mapped upwards from this address, up to a guard page below
+/// the stack.
+const TLS_AREA_START: u64 = 0x0000_7FFF_0000_0000;
+const TLS_AREA_END: u64 = STACK_TOP - (STACK_PAGES + 1) * PAGE_SIZE;
+
+/// The end of the lower half of the address space, which images are loaded
+/// into.
+const LOWER_HALF_END: u64 = 0x0000_8000_0000_0000;
+/// Ranges of the lower half that segments may not overlap: the kernel heap's
+/// reserved range, the kernel module area, and the TLS blocks and stack that
+/// are mapped for the image up to the end of the lower half.
+const RESERVED_RANGES: [(u64, u64); 3] = [
+    (HEAP_START as u64, (HEAP_START + HEAP_MAX_SIZE) as u64),
+    (MODULE_AREA_START, MODULE_AREA_END),
+    (TLS_AREA_START, LOWER_HALF_END),
+];
+
+/// ISA levels whose instructions the kernel enables for loaded images.
+const SUPPORTED_ISA_LEVELS: X86IsaLevels = X86IsaLevels::BASELINE;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Parse(ElfParseError),
+    /// `validate` found a fatal problem with the file.
+    Invalid(Finding),
+    /// Only `ElfType::Executable` files and static-PIE `ElfType::Shared`
+    /// files with program headers can be loaded.
+    NotExecutable,
+    /// The file must be loaded by a dynamic linker, which isn't supported.
+    InterpreterRequired,
+    /// Only `ElfType::Relocatable` files can be loaded as kernel modules.
+    NotRelocatable,
+    /// The module section at this index is thread-local, both writable and
+    /// executable, or needs more than page alignment.
+    UnsupportedSection(u16),
+    /// The module's `module_init` function returned this nonzero value.
+    ModuleInitFailed(i32),
+    /// The file's GNU properties say it needs these ISA levels, which aren't
+    /// enabled.
+    UnsupportedIsaLevels(X86IsaLevels),
+    /// No random base was found whose address range is free in the kernel's
+    /// page table.
+    NoFreeAddressRange,
+    /// A segment's file size is larger than its memory size.
+    InvalidSegmentSize,
+    /// A segment's memory range is not a valid virtual address range.
+    InvalidSegmentAddress(u64),
+    /// The segment at this address lies outside the lower half, or overlaps
+    /// a range the kernel reserves, e.g. for its heap, for kernel modules or
+    /// for the image's stack.
+    ReservedAddress(u64),
+    FrameAllocationFailed,
+    Mapping(MapToError<Size4KiB>),
+    /// A page that was mapped for an earlier segment could not be found in
+    /// the new page table.
+    PageNotMapped(Page),
+    Relocation(RelocationError),
+}
+
+impl From<ElfParseError> for LoadError {
+    fn from(error: ElfParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<RelocationError> for LoadError {
+    fn from(error: RelocationError) -> Self {
+        Self::Relocation(error)
+    }
+}
+
+impl From<MapToError<Size4KiB>> for LoadError {
+    fn from(error: MapToError<Size4KiB>) -> Self {
+        Self::Mapping(error)
+    }
+}
+
+/// The pages a `Load` segment was mapped to, and the flags they were mapped
+/// with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadedSegment {
+    pages: PageRangeInclusive,
+    flags: PageTableFlags,
+}
+
+impl LoadedSegment {
+    pub(super) fn new(pages: PageRangeInclusive, flags: PageTableFlags) -> Self {
+        Self { pages, flags }
+    }
+
+    pub fn pages(&self) -> PageRangeInclusive {
+        self.pages
+    }
+
+    pub fn flags(&self) -> PageTableFlags {
+        self.flags
+    }
+}
+
+/// An executable mapped into its own address space, ready to be entered.
+///
+/// The address space isn't a `memory::address_space::AddressSpace`, which
+/// leaves every level 4 entry the kernel uses to the kernel. Executables are
+/// linked at fixed addresses like `0x400000`, within the level 4 entry of the
+/// kernel's own code, so the image gets private copies of the page tables on
+/// the way to its pages instead. `unload` tells them apart from the kernel's
+/// page tables by comparing the two.
+#[derive(Debug)]
+pub struct LoadedImage {
+    entry_point: VirtAddr,
+    /// Difference between the addresses the image was loaded at and the
+    /// addresses in the file. Always 0 for `Executable` files.
+    base: u64,
+    level_4_frame: PhysFrame,
+    segments: Vec<LoadedSegment>,
+    stack: LoadedSegment,
+    /// Pages of the `GnuRelro` segment that were made read-only after
+    /// relocation. They keep the flags of their `Load` segment in `segments`.
+    relro_pages: Option<PageRange>,
+    /// The file's TLS initialization image, at its address in the image.
+    tls_template: Option<TlsTemplate>,
+    /// The TLS block of the image's initial thread.
+    tls_block: Option<TlsBlock>,
+    /// Where the next TLS block may be mapped.
+    next_tls_address: u64,
+}
+
+impl LoadedImage {
+    pub fn base(&self) -> u64 {
+        self.base
+    }
+
+    pub fn entry_point(&self) -> VirtAddr {
+        self.entry_point
+    }
+
+    /// The frame holding the image's level 4 page table, to be written to CR3
+    /// when switching to the image's address space.
+    pub fn level_4_frame(&self) -> PhysFrame {
+        self.level_4_frame
+    }
+
+    pub fn segments(&self) -> &[LoadedSegment] {
+        &self.segments
+    }
+
+    pub fn stack(&self) -> LoadedSegment {
+        self.stack
+    }
+
+    /// The initial stack pointer, i.e. the end of the stack's pages.
+    pub fn stack_top(&self) -> VirtAddr {
+        self.stack.pages.end.start_address() + PAGE_SIZE
+    }
+
+    pub fn relro_pages(&self) -> Option<PageRange> {
+        self.relro_pages
+    }
+
+    pub fn tls_template(&self) -> Option<TlsTemplate> {
+        self.tls_template
+    }
+
+    /// The TLS block of the image's initial thread, if the file uses
+    /// thread-local storage. Blocks for other threads are mapped with
+    /// `allocate_tls_block`.
+    pub fn tls_block(&self) -> Option<TlsBlock> {
+        self.tls_block
+    }
+}
+
+/// A thread's block of thread-local storage in a loaded image's address
+/// space, laid out as in the x86_64 variant II: the thread's copy of the TLS
+/// data, followed by the thread control block at the thread pointer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TlsBlock {
+    pages: PageRangeInclusive,
+    thread_pointer: VirtAddr,
+}
+
+impl TlsBlock {
+    pub fn pages(&self) -> PageRangeInclusive {
+        self.pages
+    }
+
+    /// The FS base while the block's thread runs. Thread-local variables are
+    /// accessed at negative offsets from it.
+    pub fn thread_pointer(&self) -> VirtAddr {
+        self.thread_pointer
+    }
+
+    /// Point the FS base at the block's thread pointer, through the FS_BASE
+    /// MSR. Must be called on every switch to the block's thread, since the
+    /// FS base isn't part of the address space.
+    pub fn activate(&self) {
+        FsBase::write(self.thread_pointer);
+    }
+}
+
+/// Load an executable into a fresh address space.
+///
+/// A new level 4 page table is created that shares the kernel's mappings from
+/// `kernel_mapper`, and each `Load` segment is mapped into it at `p_vaddr`.
+/// The segment's first `p_filesz` bytes are copied from the file and the rest
+/// of its `p_memsz` bytes are zero-filled. Page tables on the path to a
+/// segment's pages are copied first, so loading never modifies the kernel's
+/// own page tables.
+///
+/// Static-PIE executables, i.e. `Shared` files without an `Interpret`
+/// segment, are loaded at a random base instead, and their relocations are
+/// applied for them.
+///
+/// A zeroed stack is mapped below `STACK_TOP`, which is only executable if
+/// the file requests it through its `GnuStack` segment. Once relocations have
+/// been applied, the pages of the `GnuRelro` segment are made read-only, and
+/// the initial thread's TLS block is mapped if the file has a `Tls` segment.
+///
+/// Files with fatal `validate` findings, e.g. writable and executable
+/// segments or segments that share a page with different permissions, and files with segments outside the lower half or in a range the
+/// kernel reserves, are refused before anything is mapped. If loading fails
+/// later, the frames that were allocated for the image are deallocated again.
+/// Once the image isn't needed anymore, it is torn down with `unload`.
+pub fn load(
+    elf_file: &Elf64File,
+    kernel_mapper: &mut OffsetPageTable,
+    frame_allocator: &mut (impl FrameAllocator<Size4KiB> + FrameDeallocator<Size4KiB>),
+) -> Result<LoadedImage, LoadError> {
+    let program_headers = elf_file.program_headers().ok_or(LoadError::NotExecutable)?;
+
+    if let Some(finding) = validate::validate(elf_file)?
+        .into_iter()
+        .find(|finding| finding.severity() == Severity::Fatal)
+    {
+        return Err(LoadError::Invalid(finding));
+    }
+    check_isa_levels(elf_file)?;
+
+    let physical_memory_offset = kernel_mapper.phys_offset();
+    let kernel_level_4_table: &PageTable = kernel_mapper.level_4_table();
+
+    let base = match elf_file.elf_type() {
+        ElfType::Executable => 0,
+        ElfType::Shared => random_base(elf_file, kernel_level_4_table)?,
+        _ => return Err(LoadError::NotExecutable),
+    };
+
+    let mut loads = Vec::new();
+    for header in program_headers {
+        let header = header?;
+        if *header.segment_type() != ProgramSegmentType::Load || header.p_memsz() == 0 {
+            continue;
+        }
+
+        let segment = LoadedSegment {
+            pages: segment_pages(&header, base)?,
+            flags: page_flags(&header),
+        };
+        loads.push((header, segment));
+    }
+
+    let level_4_frame = memory::allocate_zeroed_frame(frame_allocator, physical_memory_offset)
+        .ok_or(LoadError::FrameAllocationFailed)?;
+    let level_4_table = unsafe { memory::page_table_at(level_4_frame, physical_memory_offset) };
+    for (entry, kernel_entry) in level_4_table.iter_mut().zip(kernel_level_4_table.iter()) {
+        *entry = kernel_entry.clone();
+    }
+    let mut mapper = unsafe { OffsetPageTable::new(level_4_table, physical_memory_offset) };
+
+    let image = map_image(
+        elf_file,
+        &loads,
+        base,
+        level_4_frame,
+        &mut mapper,
+        kernel_level_4_table,
+        frame_allocator,
+    );
+    if image.is_err() {
+        // Nothing else knows about the new page table
+        unsafe {
+            memory::deallocate_page_table(
+                level_4_frame,
+                Some(kernel_level_4_table),
+                4,
+                physical_memory_offset,
+                frame_allocator,
+            )
+        };
+    }
+
+    image
+}
+
+/// Map the `loads` segments of a file and its stack and TLS block into the
+/// page table of `mapper`, and relocate and protect them.
+fn map_image(
+    elf_file: &Elf64File,
+    loads: &[(Elf64ProgramHeader, LoadedSegment)],
+    base: u64,
+    level_4_frame: PhysFrame,
+    mapper: &mut OffsetPageTable,
+    kernel_level_4_table: &PageTable,
+    frame_allocator: &mut impl FrameAllocator<Size4KiB>,
+) -> Result<LoadedImage, LoadError> {
+    let physical_memory_offset = mapper.phys_offset();
+    let mut segments: Vec<LoadedSegment> = Vec::new();
+    for (header, segment) in loads {
+        let file_data = elf_file.segment_data(header)?;
+
+        for page in segment.pages {
+            let frame = match segments
+                .iter()
+                .find(|loaded| contains_page(loaded.pages, page))
+            {
+                // Segments that aren't page aligned can share a page with the
+                // previous segment. `validate` refuses segments that do so
+                // with different permissions, so the page keeps its flags.
+                Some(_) => mapper
+                    .translate_page(page)
+                    .map_err(|_| LoadError::PageNotMapped(page))?,
+                None => map_zeroed_page(
+                    mapper,
+                    kernel_level_4_table,
+                    page,
+                    segment.flags,
+                    frame_allocator,
+                )?,
+            };
+
+            copy_file_data(header, base, file_data, page, frame, physical_memory_offset);
+        }
+
+        segments.push(*segment);
+    }
+
+    let stack = map_stack(elf_file, mapper, kernel_level_4_table, frame_allocator)?;
+
+    let entry_point = base.wrapping_add(elf_file.entry_point());
+    let mut image = LoadedImage {
+        entry_point: VirtAddr::try_new(entry_point)
+            .map_err(|_| LoadError::InvalidSegmentAddress(entry_point))?,
+        base,
+        level_4_frame,
+        segments,
+        stack,
+        relro_pages: None,
+        tls_template: elf_file.tls_template()?.map(|template| {
+            TlsTemplate::new(
+                base.wrapping_add(template.address()),
+                template.file_size(),
+                template.memory_size(),
+                template.alignment(),
+            )
+        }),
+        tls_block: None,
+        next_tls_address: TLS_AREA_START,
+    };
+
+    if *elf_file.elf_type() == ElfType::Shared {
+        // A static-PIE executable defines every symbol it uses
+        relocate(&image, elf_file, physical_memory_offset, |_| None)?;
+    }
+    image.relro_pages = protect_relro(elf_file, base, mapper)?;
+    // The TLS data is copied from the image, so it must be relocated first
+    image.tls_block = map_tls_block(&mut image, mapper, kernel_level_4_table, frame_allocator)?;
+
+    Ok(image)
+}
+
+/// Tear down a loaded image, giving the frames of its segments, stack and
+/// TLS blocks and its page tables to `frame_deallocator`. The page tables it
+/// shares with the kernel's page table of `kernel_mapper` are left alone.
+///
+/// This function is unsafe because the caller must guarantee that the
+/// image's page table isn't active, and that nothing still uses its memory.
+pub unsafe fn unload(
+    image: LoadedImage,
+    kernel_mapper: &mut OffsetPageTable,
+    frame_deallocator: &mut impl FrameDeallocator<Size4KiB>,
+) {
+    let physical_memory_offset = kernel_mapper.phys_offset();
+    memory::deallocate_page_table(
+        image.level_4_frame,
+        Some(kernel_mapper.level_4_table()),
+        4,
+        physical_memory_offset,
+        frame_deallocator,
+    );
+}
+
+/// Map a TLS block for a new thread of `image`, holding a fresh copy of the
+/// image's TLS data. Returns `None` if the image's file has no `Tls` segment.
+pub fn allocate_tls_block(
+    image: &mut LoadedImage,
+    kernel_mapper: &mut OffsetPageTable,
+    frame_allocator: &mut impl FrameAllocator<Size4KiB>,
+) -> Result<Option<TlsBlock>, LoadError> {
+    let physical_memory_offset = kernel_mapper.phys_offset();
+    let level_4_table =
+        unsafe { memory::page_table_at(image.level_4_frame, physical_memory_offset) };
+    let mut mapper = unsafe { OffsetPageTable::new(level_4_table, physical_memory_offset) };
+
+    map_tls_block(
+        image,
+        &mut mapper,
+        kernel_mapper.level_4_table(),
+        frame_allocator,
+    )
+}
+
+/// Map a TLS block at the image's next free TLS address, through `mapper`
+/// for the image's page table. The initialized part of the TLS data is
+/// copied from the image, and the rest of the block is zeroed except for the
+/// thread control block's pointer to itself.
+fn map_tls_block(
+    image: &mut LoadedImage,
+    mapper: &mut OffsetPageTable,
+    kernel_level_4_table: &PageTable,
+    frame_allocator: &mut impl FrameAllocator<Size4KiB>,
+) -> Result<Option<TlsBlock>, LoadError> {
+    let template = match image.tls_template {
+        Some(template) => template,
+        None => return Ok(None),
+    };
+
+    let alignment = template.block_alignment().max(PAGE_SIZE);
+    let start = image.next_tls_address.next_multiple_of(alignment);
+    let end = start
+        .checked_add(template.block_size())
+        .filter(|&end| end <= TLS_AREA_END)
+        .ok_or(LoadError::NoFreeAddressRange)?;
+    let pages = Page::range_inclusive(
+        Page::containing_address(VirtAddr::new(start)),
+        Page::containing_address(VirtAddr::new(end - 1)),
+    );
+
+    let flags = PageTableFlags::PRESENT
+        | PageTableFlags::USER_ACCESSIBLE
+        | PageTableFlags::WRITABLE
+        | PageTableFlags::NO_EXECUTE;
+    for page in pages {
+        map_zeroed_page(mapper, kernel_level_4_table, page, flags, frame_allocator)?;
+    }
+
+    let thread_pointer = start + template.thread_pointer_offset();
+    let data_start = thread_pointer - template.data_offset();
+    for offset in 0..template.file_size() {
+        let byte = unsafe { *image_byte(mapper, template.address() + offset)? };
+        unsafe { *image_byte(mapper, data_start + offset)? = byte };
+    }
+    for (offset, byte) in thread_pointer.to_le_bytes().iter().enumerate() {
+        unsafe { *image_byte(mapper, thread_pointer + offset as u64)? = *byte };
+    }
+
+    // Leave an unmapped guard page between blocks
+    image.next_tls_address = pages.end.start_address().as_u64() + 2 * PAGE_SIZE;
+
+    Ok(Some(TlsBlock {
+        pages,
+        thread_pointer: VirtAddr::new(thread_pointer),
+    }))
+}
+
+/// Returns a pointer to the byte mapped at `address` by `mapper`, through
+/// the physical memory mapping.
+fn image_byte(mapper: &OffsetPageTable, address: u64) -> Result<*mut u8, LoadError> {
+    let address =
+        VirtAddr::try_new(address).map_err(|_| LoadError::InvalidSegmentAddress(address))?;
+    let physical_address = mapper
+        .translate_addr(address)
+        .ok_or(LoadError::PageNotMapped(Page::containing_address(address)))?;
+
+    Ok((mapper.phys_offset() + physical_address.as_u64()).as_mut_ptr())
+}
+
+/// Map the stack's pages below `STACK_TOP`, writable and only executable if
+/// the file requests an executable stack.
+fn map_stack(
+    elf_file: &Elf64File,
+    mapper: &mut OffsetPageTable,
+    kernel_level_4_table: &PageTable,
+    frame_allocator: &mut impl FrameAllocator<Size4KiB>,
+) -> Result<LoadedSegment, LoadError> {
+    let mut flags =
+        PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE | PageTableFlags::WRITABLE;
+    if !elf_file.requests_executable_stack()? {
+        flags |= PageTableFlags::NO_EXECUTE;
+    }
+
+    let top = Page::containing_address(VirtAddr::new(STACK_TOP));
+    // Segments can't overlap the stack, since it's in a reserved range
+    let stack = LoadedSegment {
+        pages: Page::range_inclusive(top - STACK_PAGES, top - 1),
+        flags,
+    };
+
+    for page in stack.pages {
+        map_zeroed_page(mapper, kernel_level_4_table, page, flags, frame_allocator)?;
+    }
+
+    Ok(stack)
+}
+
+/// Remove write access from the pages of the file's `GnuRelro` segment, for
+/// an image loaded at `base`. Pages the range only partly covers at its end
+/// stay writable, since the rest of them may hold writable data.
+fn protect_relro(
+    elf_file: &Elf64File,
+    base: u64,
+    mapper: &mut OffsetPageTable,
+) -> Result<Option<PageRange>, LoadError> {
+    let range = match elf_file.relro_range()? {
+        Some(range) => range,
+        None => return Ok(None),
+    };
+    let virtual_address = |address: u64| {
+        base.checked_add(address)
+            .and_then(|address| VirtAddr::try_new(address).ok())
+            .ok_or(LoadError::InvalidSegmentAddress(range.start))
+    };
+    let pages = Page::range(
+        Page::containing_address(virtual_address(range.start)?),
+        Page::containing_address(virtual_address(range.end)?),
+    );
+
+    for page in pages {
+        let flags = match mapper.translate(page.start_address()) {
+            TranslateResult::Mapped { flags, .. } => flags,
+            _ => return Err(LoadError::PageNotMapped(page)),
+        };
+        // The new table isn't active, so there is nothing to flush
+        unsafe { mapper.update_flags(page, flags - PageTableFlags::WRITABLE) }
+            .map_err(|_| LoadError::PageNotMapped(page))?
+            .ignore();
+    }
+
+    Ok(Some(pages))
+}
+
+/// Check that every ISA level the file's GNU properties say it needs is
+/// enabled. Control-flow protection features like IBT and SHSTK only mark
+/// code as compatible with them, so files that have them load either way.
+fn check_isa_levels(elf_file: &Elf64File) -> Result<(), LoadError> {
+    for property in elf_file.gnu_properties()?.into_iter().flatten() {
+        if let GnuProperty::X86IsaNeeded(levels) = property? {
+            let unsupported = levels - SUPPORTED_ISA_LEVELS;
+            if !unsupported.is_empty() {
+                return Err(LoadError::UnsupportedIsaLevels(unsupported));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Choose a random base for a static-PIE executable, aligned to its largest
+/// segment alignment, such that none of its segments overlap the level 4
+/// entries the kernel uses.
+fn random_base(elf_file: &Elf64File, kernel_level_4_table: &PageTable) -> Result<u64, LoadError> {
+    let mut start = u64::MAX;
+    let mut end = 0;
+    let mut alignment = PAGE_SIZE;
+    for header in elf_file.program_headers().ok_or(LoadError::NotExecutable)? {
+        let header = header?;
+        match header.segment_type() {
+            ProgramSegmentType::Interpret => return Err(LoadError::InterpreterRequired),
+            ProgramSegmentType::Load if header.p_memsz() > 0 => {
+                let segment_end = header
+                    .p_vaddr()
+                    .checked_add(header.p_memsz())
+                    .ok_or(LoadError::InvalidSegmentAddress(header.p_vaddr()))?;
+                start = start.min(header.p_vaddr());
+                end = end.max(segment_end);
+                alignment = alignment.max(header.alignment());
+            }
+            _ => {}
+        }
+    }
+    if start >= end {
+        return Err(LoadError::NotExecutable);
+    }
+
+    // RANDOM_BASE_START is a power of two, so it's aligned to every smaller
+    // alignment
+    if alignment > RANDOM_BASE_START {
+        return Err(LoadError::InvalidSegmentAddress(start));
+    }
+    let slot_count = RANDOM_BASE_END
+        .checked_sub(end)
+        .and_then(|last_base| last_base.checked_sub(RANDOM_BASE_START))
+        .map(|range| range / alignment + 1)
+        .ok_or(LoadError::InvalidSegmentAddress(end))?;
+
+    for _ in 0..RANDOM_BASE_ATTEMPTS {
+        let base = RANDOM_BASE_START + (random::random_u64() % slot_count) * alignment;
+        let first_page = Page::<Size4KiB>::containing_address(VirtAddr::new(base + start));
+        let last_page = Page::<Size4KiB>::containing_address(VirtAddr::new(base + end - 1));
+        let overlaps_kernel = (u16::from(first_page.p4_index())..=u16::from(last_page.p4_index()))
+            .any(|index| !kernel_level_4_table[index as usize].is_unused());
+        if !overlaps_kernel {
+            return Ok(base);
+        }
+    }
+
+    Err(LoadError::NoFreeAddressRange)
+}
+
+/// Apply the dynamic relocations of `elf_file` to `image`, the result of
+/// loading it.
+///
+/// Relocations are read from the file's allocated `RelA` and `Rel` sections.
+/// Symbols defined in the file are relocated along with the image, and
+/// `resolve_symbol` is asked for the address of every other symbol by name.
+/// Undefined weak symbols it doesn't know about resolve to 0. The image is
+/// written through the physical memory mapping, so relocating still works
+/// after `load` made the `GnuRelro` pages read-only.
+pub fn relocate(
+    image: &LoadedImage,
+    elf_file: &Elf64File,
+    physical_memory_offset: VirtAddr,
+    mut resolve_symbol: impl FnMut(&str) -> Option<u64>,
+) -> Result<(), LoadError> {
+    let level_4_table =
+        unsafe { memory::page_table_at(image.level_4_frame, physical_memory_offset) };
+    let mut memory = ImageMemory {
+        image,
+        mapper: unsafe { OffsetPageTable::new(level_4_table, physical_memory_offset) },
+    };
+
+    for section in elf_file.section_headers()? {
+        let section = section?;
+        if !section.flags().contains(SectionHeaderFlags::ALLOC) {
+            continue;
+        }
+
+        match section.header_type() {
+            SectionHeaderType::RelA => {
+                let symbols = relocation_symbols(elf_file, &section)?;
+                for relocation in elf_file.rela_entries(&section)? {
+                    relocate::apply_rela(&relocation?, image.base, &mut memory, |index| {
+                        resolve(image, symbols.as_ref(), index, &mut resolve_symbol)
+                    })?;
+                }
+            }
+            SectionHeaderType::Rel => {
+                let symbols = relocation_symbols(elf_file, &section)?;
+                for relocation in elf_file.rel_entries(&section)? {
+                    relocate::apply_rel(&relocation?, image.base, &mut memory, |index| {
+                        resolve(image, symbols.as_ref(), index, &mut resolve_symbol)
+                    })?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// The symbol table the relocations in `section` refer to. Relocation
+/// sections that only hold `Relative` relocations may not have one.
+fn relocation_symbols<'a>(
+    elf_file: &Elf64File<'a>,
+    section: &SectionHeader,
+) -> Result<Option<SymbolIterator<'a>>, LoadError> {
+    match section.section_link_index() {
+        Some(_) => Ok(Some(elf_file.linked_symbols(section)?)),
+        None => Ok(None),
+    }
+}
+
+fn resolve(
+    image: &LoadedImage,
+    symbols: Option<&SymbolIterator>,
+    index: u32,
+    resolve_symbol: &mut impl FnMut(&str) -> Option<u64>,
+) -> Option<u64> {
+    let symbol = symbols?.symbol(index).ok()?;
+    match symbol.section_index() {
+        SymbolSectionIndex::Undefined => match resolve_symbol(symbol.name()) {
+            Some(address) => Some(address),
+            None if symbol.binding() == SymbolBinding::Weak => Some(0),
+            None => None,
+        },
+        SymbolSectionIndex::Absolute => Some(symbol.value()),
+        _ => Some(image.base.wrapping_add(symbol.value())),
+    }
+}
+
+/// The memory of a loaded image, accessed through the image's own page table.
+/// Only the image's segments can be read or written.
+struct ImageMemory<'a> {
+    image: &'a LoadedImage,
+    mapper: OffsetPageTable<'a>,
+}
+
+impl<'a> ImageMemory<'a> {
+    /// Returns a pointer to the byte at `address` in the image, through the
+    /// physical memory mapping so that read-only pages can be written.
+    fn byte(&self, address: u64) -> Result<*mut u8, RelocationError> {
+        let page = VirtAddr::try_new(address)
+            .map(Page::containing_address)
+            .map_err(|_| RelocationError::UnmappedAddress(address))?;
+        if !self
+            .image
+            .segments
+            .iter()
+            .any(|segment| contains_page(segment.pages, page))
+        {
+            return Err(RelocationError::UnmappedAddress(address));
+        }
+
+        let physical_address = self
+            .mapper
+            .translate_addr(VirtAddr::new(address))
+            .ok_or(RelocationError::UnmappedAddress(address))?;
+
+        Ok((self.mapper.phys_offset() + physical_address.as_u64()).as_mut_ptr())
+    }
+}
+
+impl<'a> RelocationTarget for ImageMemory<'a> {
+    fn read(&mut self, address: u64, bytes: &mut [u8]) -> Result<(), RelocationError> {
+        for (offset, byte) in bytes.iter_mut().enumerate() {
+            *byte = unsafe { *self.byte(address.wrapping_add(offset as u64))? };
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, address: u64, bytes: &[u8]) -> Result<(), RelocationError> {
+        // Check the whole range first, so a relocation is applied completely
+        // or not at all
+        for offset in 0..bytes.len() {
+            self.byte(address.wrapping_add(offset as u64))?;
+        }
+        for (offset, byte) in bytes.iter().enumerate() {
+            unsafe { *self.byte(address.wrapping_add(offset as u64))? = *byte };
+        }
+
+        Ok(())
+    }
+}
+
+fn segment_pages(header: &Elf64ProgramHeader, base: u64) -> Result<PageRangeInclusive, LoadError> {
+    if header.p_filesz() > header.p_memsz() {
+        return Err(LoadError::InvalidSegmentSize);
+    }
+
+    let segment_start = base
+        .checked_add(header.p_vaddr())
+        .ok_or(LoadError::InvalidSegmentAddress(header.p_vaddr()))?;
+    let start = VirtAddr::try_new(segment_start)
+        .map_err(|_| LoadError::InvalidSegmentAddress(header.p_vaddr()))?;
+    let end = segment_start
+        .checked_add(header.p_memsz() - 1)
+        .and_then(|end| VirtAddr::try_new(end).ok())
+        .ok_or(LoadError::InvalidSegmentAddress(header.p_vaddr()))?;
+    let pages = Page::range_inclusive(
+        Page::containing_address(start),
+        Page::containing_address(end),
+    );
+
+    let pages_start = pages.start.start_address().as_u64();
+    let pages_end = pages.end.start_address().as_u64() + PAGE_SIZE;
+    if pages_end > LOWER_HALF_END
+        || RESERVED_RANGES
+            .iter()
+            .any(|&(reserved_start, reserved_end)| {
+                pages_start < reserved_end && reserved_start < pages_end
+            })
+    {
+        return Err(LoadError::ReservedAddress(header.p_vaddr()));
+    }
+
+    Ok(pages)
+}
+
+/// Segments are always mapped user accessible, writable only if the segment
+/// is writable, and non-executable unless the segment is executable.
+fn page_flags(header: &Elf64ProgramHeader) -> PageTableFlags {
+    let mut flags = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+    if header.flags().writable() {
+        flags |= PageTableFlags::WRITABLE;
+    }
+    if !header.flags().executable() {
+        flags |= PageTableFlags::NO_EXECUTE;
+    }
+
+    flags
+}
+
+fn contains_page(pages: PageRangeInclusive, page: Page) -> bool {
+    pages.start <= page && page <= pages.end
+}
+
+/// Map `page` to a new zeroed frame, after giving `mapper` its own copies of
+/// the page tables on the way to it that it still shares with the kernel.
+fn map_zeroed_page(
+    mapper: &mut OffsetPageTable,
+    kernel_level_4_table: &PageTable,
+    page: Page,
+    flags: PageTableFlags,
+    frame_allocator: &mut impl FrameAllocator<Size4KiB>,
+) -> Result<PhysFrame, LoadError> {
+    let physical_memory_offset = mapper.phys_offset();
+    unsafe {
+        unshare_page_tables(
+            mapper.level_4_table(),
+            kernel_level_4_table,
+            &[page.p4_index(), page.p3_index(), page.p2_index()],
+            physical_memory_offset,
+            frame_allocator,
+        )?;
+    }
+
+    let frame = memory::allocate_zeroed_frame(frame_allocator, physical_memory_offset)
+        .ok_or(LoadError::FrameAllocationFailed)?;
+    // The new table isn't active, so there is nothing to flush
+    unsafe { mapper.map_to(page, frame, flags, frame_allocator)? }.ignore();
+
+    Ok(frame)
+}
+
+/// Copy the part of the segment's file data that falls within `page` into
+/// `frame`, for a segment loaded at `base + p_vaddr`. Bytes of the page past
+/// `p_filesz` are left as they are, which is zero for freshly allocated
+/// frames.
+fn copy_file_data(
+    header: &Elf64ProgramHeader,
+    base: u64,
+    file_data: &[u8],
+    page: Page,
+    frame: PhysFrame,
+    physical_memory_offset: VirtAddr,
+) {
+    let segment_start = base + header.p_vaddr();
+    let page_start = page.start_address().as_u64();
+    let data_start = segment_start.max(page_start);
+    let data_end = (segment_start + header.p_filesz()).min(page_start + PAGE_SIZE);
+    if data_start >= data_end {
+        return;
+    }
+
+    let source =
+        &file_data[(data_start - segment_start) as usize..][..(data_end - data_start) as usize];
+    let destination =
+        physical_memory_offset + frame.start_address().as_u64() + (data_start - page_start);
+    unsafe {
+        ptr::copy_nonoverlapping(
+            source.as_ptr(),
+            destination.as_mut_ptr::<u8>(),
+            source.len(),
+        )
+    };
+}
+
+/// Give `table` its own copy of every page table on the path described by
+/// `indexes` that it still shares with `kernel_table`, so that mapping a page
+/// at the end of that path doesn't modify the kernel's page tables.
+///
+/// This function is unsafe because the caller must guarantee that the
+/// complete physical memory is mapped at `physical_memory_offset` and that
+/// both tables are valid page table hierarchies.
+unsafe fn unshare_page_tables(
+    table: &mut PageTable,
+    kernel_table: &PageTable,
+    indexes: &[PageTableIndex],
+    physical_memory_offset: VirtAddr,
+    frame_allocator: &mut impl FrameAllocator<Size4KiB>,
+) -> Result<(), LoadError> {
+    let (index, remaining_indexes) = match indexes.split_first() {
+        Some(split) => split,
+        None => return Ok(()),
+    };
+    let entry: &mut PageTableEntry = &mut table[*index];
+    let kernel_entry = &kernel_table[*index];
+    if entry.is_unused()
+        || kernel_entry.is_unused()
+        || kernel_entry.flags().contains(PageTableFlags::HUGE_PAGE)
+    {
+        // Either a table map_to will create for us, or a huge page map_to
+        // will refuse to map over
+        return Ok(());
+    }
+
+    let kernel_next_table = &*(physical_memory_offset + kernel_entry.addr().as_u64()).as_ptr();
+    if entry.addr() == kernel_entry.addr() {
+        let frame = memory::allocate_zeroed_frame(frame_allocator, physical_memory_offset)
+            .ok_or(LoadError::FrameAllocationFailed)?;
+        let next_table = memory::page_table_at(frame, physical_memory_offset);
+        *next_table = PageTable::clone(kernel_next_table);
+        entry.set_frame(frame, entry.flags());
+    }
+
+    let next_table = &mut *(physical_memory_offset + entry.addr().as_u64()).as_mut_ptr();
+    unshare_page_tables(
+        next_table,
+        kernel_next_table,
+        remaining_indexes,
+        physical_memory_offset,
+        frame_allocator,
+    )
+}
@@ -0,0 +1,635 @@
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::{mem, ops::Range, ptr};
+use x86_64::{
+    structures::paging::{
+        mapper::TranslateResult, page::PageRangeInclusive, FrameAllocator, FrameDeallocator,
+        Mapper, OffsetPageTable, Page, PageTableFlags, Size4KiB, Translate,
+    },
+    VirtAddr,
+};
+
+use super::{
+    elf64::{
+        sections::{SectionHeader, SectionHeaderFlags, SectionHeaderType},
+        symbols::{SymbolBinding, SymbolIterator, SymbolSectionIndex},
+        Elf64File,
+    },
+    loader::{LoadError, LoadedSegment},
+    relocate::{self, RelocationError, RelocationTarget},
+    validate::Finding,
+    ElfType, InstructionSet,
+};
+use crate::memory;
+
+const PAGE_SIZE: u64 = 4096;
+
+/// Modules are mapped within this range of the kernel's address space. It
+/// lies within 2GiB of the kernel's code, so that modules built with the
+/// default small code model can call into the kernel and be addressed with
+/// 32-bit relocations.
+pub(super) const MODULE_AREA_START: u64 = 0x4000_0000;
+pub(super) const MODULE_AREA_END: u64 = 0x8000_0000;
+
+/// The kernel symbols modules can link against, by name.
+#[derive(Debug, Default)]
+pub struct KernelSymbolTable {
+    symbols: BTreeMap<&'static str, u64>,
+}
+
+impl KernelSymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Export `address` under `name`, replacing any earlier export of the
+    /// same name. Functions must use the C calling convention.
+    pub fn export(&mut self, name: &'static str, address: u64) {
+        self.symbols.insert(name, address);
+    }
+
+    pub fn address(&self, name: &str) -> Option<u64> {
+        self.symbols.get(name).copied()
+    }
+}
+
+/// A relocatable file mapped into the kernel's address space and linked
+/// against the kernel, ready to be initialized.
+#[derive(Debug)]
+pub struct LoadedModule {
+    pages: PageRangeInclusive,
+    /// The pages of the module's code, read-only data and writable data, in
+    /// that order. Groups without sections are left out.
+    regions: Vec<LoadedSegment>,
+    /// Address of every allocated section, by section index.
+    section_addresses: Vec<Option<u64>>,
+    init: Option<VirtAddr>,
+    exit: Option<VirtAddr>,
+    /// Whether `initialize` ran `module_init` successfully, so that
+    /// `unload_module` must run `module_exit`.
+    initialized: bool,
+}
+
+impl LoadedModule {
+    pub fn pages(&self) -> PageRangeInclusive {
+        self.pages
+    }
+
+    pub fn regions(&self) -> &[LoadedSegment] {
+        &self.regions
+    }
+
+    /// The address the section at `index` was loaded at, if it was loaded.
+    pub fn section_address(&self, index: u16) -> Option<VirtAddr> {
+        self.section_addresses
+            .get(index as usize)
+            .copied()
+            .flatten()
+            .map(VirtAddr::new)
+    }
+
+    /// Address of the module's `module_init` function, if it has one.
+    pub fn init_function(&self) -> Option<VirtAddr> {
+        self.init
+    }
+
+    /// Address of the module's `module_exit` function, if it has one.
+    pub fn exit_function(&self) -> Option<VirtAddr> {
+        self.exit
+    }
+}
+
+/// Load a relocatable file as a kernel module.
+///
+/// The file's `ALLOC` sections are laid out in fresh pages of the kernel's
+/// address space, mapped through `kernel_mapper` within the module area. Code,
+/// read-only data and writable data each start on a new page, so that each
+/// gets the page permissions of its sections. Undefined symbols are resolved
+/// against `kernel_symbols`, and undefined weak symbols it doesn't export
+/// resolve to 0. The file's `RelA` and `Rel` sections are then applied to the
+/// sections they relocate.
+///
+/// If loading fails after the module's pages were mapped, they are unmapped
+/// again and their frames are given back to `frame_allocator`.
+pub fn load_module(
+    elf_file: &Elf64File,
+    kernel_symbols: &KernelSymbolTable,
+    kernel_mapper: &mut OffsetPageTable,
+    frame_allocator: &mut (impl FrameAllocator<Size4KiB> + FrameDeallocator<Size4KiB>),
+) -> Result<LoadedModule, LoadError> {
+    if *elf_file.elf_type() != ElfType::Relocatable {
+        return Err(LoadError::NotRelocatable);
+    }
+    if *elf_file.instruction_set() != InstructionSet::X86_64 {
+        return Err(LoadError::Invalid(Finding::WrongInstructionSet));
+    }
+
+    let layout = ModuleLayout::new(elf_file)?;
+    let pages = free_pages(kernel_mapper, layout.page_count())?;
+    let start = pages.start.start_address().as_u64();
+
+    // The pages stay writable until the module is relocated
+    let physical_memory_offset = kernel_mapper.phys_offset();
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+    for (mapped, page) in pages.enumerate() {
+        let mapping = memory::allocate_zeroed_frame(frame_allocator, physical_memory_offset)
+            .ok_or(LoadError::FrameAllocationFailed)
+            .and_then(|frame| unsafe {
+                kernel_mapper
+                    .map_to(page, frame, flags, frame_allocator)
+                    .map_err(|error| {
+                        frame_allocator.deallocate_frame(frame);
+                        LoadError::from(error)
+                    })
+            });
+        match mapping {
+            Ok(flush) => flush.flush(),
+            Err(error) => {
+                unmap_pages(
+                    kernel_mapper,
+                    pages.into_iter().take(mapped),
+                    frame_allocator,
+                );
+                return Err(error);
+            }
+        }
+    }
+
+    let section_addresses: Vec<Option<u64>> = layout
+        .section_offsets
+        .iter()
+        .map(|offset| offset.map(|offset| start + offset))
+        .collect();
+    let mut module = LoadedModule {
+        pages,
+        regions: Vec::new(),
+        section_addresses,
+        init: None,
+        exit: None,
+        initialized: false,
+    };
+
+    if let Err(error) = link(elf_file, &mut module, kernel_symbols) {
+        unmap_pages(kernel_mapper, pages, frame_allocator);
+        return Err(error);
+    }
+
+    module.regions = protect_regions(kernel_mapper, pages, &layout.regions, frame_allocator)?;
+
+    Ok(module)
+}
+
+/// Call the module's `module_init` function, if it has one. A nonzero return
+/// value is reported as `ModuleInitFailed`, and the module should then be
+/// unloaded.
+///
+/// This function is unsafe because it runs the module's code with the
+/// kernel's privileges, so the caller must trust the module.
+pub unsafe fn initialize(module: &mut LoadedModule) -> Result<(), LoadError> {
+    if let Some(init) = module.init {
+        let init: extern "C" fn() -> i32 = mem::transmute(init.as_u64());
+        match init() {
+            0 => {}
+            code => return Err(LoadError::ModuleInitFailed(code)),
+        }
+    }
+    module.initialized = true;
+
+    Ok(())
+}
+
+/// Unload a module, after calling its `module_exit` function if `initialize`
+/// succeeded for it. The module's pages are unmapped and their frames are
+/// given to `frame_deallocator`.
+///
+/// This function is unsafe because it runs the module's code with the
+/// kernel's privileges, and because the caller must guarantee that nothing
+/// still uses the module's code or data, e.g. function pointers the module
+/// registered with the kernel.
+pub unsafe fn unload_module(
+    module: LoadedModule,
+    kernel_mapper: &mut OffsetPageTable,
+    frame_deallocator: &mut impl FrameDeallocator<Size4KiB>,
+) -> Result<(), LoadError> {
+    if module.initialized {
+        if let Some(exit) = module.exit {
+            let exit: extern "C" fn() = mem::transmute(exit.as_u64());
+            exit();
+        }
+    }
+
+    for page in module.pages {
+        let (frame, flush) = kernel_mapper
+            .unmap(page)
+            .map_err(|_| LoadError::PageNotMapped(page))?;
+        flush.flush();
+        frame_deallocator.deallocate_frame(frame);
+    }
+
+    Ok(())
+}
+
+/// Copy the module's sections into its pages, apply its relocations, and find
+/// its entry points.
+fn link(
+    elf_file: &Elf64File,
+    module: &mut LoadedModule,
+    kernel_symbols: &KernelSymbolTable,
+) -> Result<(), LoadError> {
+    for (index, section) in elf_file.section_headers()?.enumerate() {
+        let section = section?;
+        let address = match module.section_addresses[index] {
+            Some(address) => address,
+            None => continue,
+        };
+        // NoBits sections keep the zeroes of their fresh frames
+        if *section.header_type() != SectionHeaderType::NoBits {
+            let data = elf_file.section_data(&section)?;
+            unsafe { ptr::copy_nonoverlapping(data.as_ptr(), address as *mut u8, data.len()) };
+        }
+    }
+
+    let mut memory = ModuleMemory {
+        start: module.pages.start.start_address().as_u64(),
+        end: module.pages.end.start_address().as_u64() + PAGE_SIZE,
+    };
+    for section in elf_file.section_headers()? {
+        let section = section?;
+        let (target_address, symbols) = match section.header_type() {
+            SectionHeaderType::RelA | SectionHeaderType::Rel => {
+                match relocated_section_address(module, &section) {
+                    Some(address) => (address, elf_file.linked_symbols(&section)?),
+                    None => continue,
+                }
+            }
+            _ => continue,
+        };
+        let resolve_symbol =
+            |index| resolve(&symbols, &module.section_addresses, kernel_symbols, index);
+
+        if *section.header_type() == SectionHeaderType::RelA {
+            for relocation in elf_file.rela_entries(&section)? {
+                relocate::apply_rela(&relocation?, target_address, &mut memory, resolve_symbol)?;
+            }
+        } else {
+            for relocation in elf_file.rel_entries(&section)? {
+                relocate::apply_rel(&relocation?, target_address, &mut memory, resolve_symbol)?;
+            }
+        }
+    }
+
+    module.init = entry_point(elf_file, module, "module_init")?;
+    module.exit = entry_point(elf_file, module, "module_exit")?;
+
+    Ok(())
+}
+
+/// The address of the section a relocation section applies to, or `None` if
+/// that section wasn't loaded, e.g. because it holds debug information.
+fn relocated_section_address(module: &LoadedModule, section: &SectionHeader) -> Option<u64> {
+    let index = section.info()?;
+    module
+        .section_addresses
+        .get(index as usize)
+        .copied()
+        .flatten()
+}
+
+fn resolve(
+    symbols: &SymbolIterator,
+    section_addresses: &[Option<u64>],
+    kernel_symbols: &KernelSymbolTable,
+    index: u32,
+) -> Option<u64> {
+    let symbol = symbols.symbol(index).ok()?;
+    match symbol.section_index() {
+        SymbolSectionIndex::Undefined => match kernel_symbols.address(symbol.name()) {
+            Some(address) => Some(address),
+            None if symbol.binding() == SymbolBinding::Weak => Some(0),
+            None => None,
+        },
+        SymbolSectionIndex::Absolute => Some(symbol.value()),
+        SymbolSectionIndex::Index(section) => section_addresses
+            .get(section as usize)
+            .copied()
+            .flatten()
+            .map(|address| address.wrapping_add(symbol.value())),
+        // Common symbols need space the module didn't allocate
+        SymbolSectionIndex::Common | SymbolSectionIndex::Reserved(_) => None,
+    }
+}
+
+/// The address of the function `name` defines, if the module defines it in
+/// one of its loaded sections.
+fn entry_point(
+    elf_file: &Elf64File,
+    module: &LoadedModule,
+    name: &str,
+) -> Result<Option<VirtAddr>, LoadError> {
+    let symbol = match elf_file.lookup_symbol(name)? {
+        Some(symbol) => symbol,
+        None => return Ok(None),
+    };
+
+    match symbol.section_index() {
+        SymbolSectionIndex::Index(index) => Ok(module
+            .section_address(index as u16)
+            .map(|address| address + symbol.value())),
+        _ => Ok(None),
+    }
+}
+
+/// Find `page_count` consecutive unmapped pages in the module area.
+fn free_pages(mapper: &OffsetPageTable, page_count: u64) -> Result<PageRangeInclusive, LoadError> {
+    let area_end = Page::containing_address(VirtAddr::new(MODULE_AREA_END));
+    let mut start = Page::<Size4KiB>::containing_address(VirtAddr::new(MODULE_AREA_START));
+
+    'search: while start + page_count <= area_end {
+        for page in Page::range(start, start + page_count) {
+            if !matches!(
+                mapper.translate(page.start_address()),
+                TranslateResult::NotMapped
+            ) {
+                start = page + 1;
+                continue 'search;
+            }
+        }
+
+        return Ok(Page::range_inclusive(start, start + page_count - 1));
+    }
+
+    Err(LoadError::NoFreeAddressRange)
+}
+
+/// Give the pages of each of the module's `regions`, given as offsets from the
+/// start of `pages`, their final flags. If that fails, all of `pages` are
+/// unmapped and their frames are given to `frame_deallocator`.
+fn protect_regions(
+    kernel_mapper: &mut OffsetPageTable,
+    pages: PageRangeInclusive,
+    regions: &[(Range<u64>, PageTableFlags)],
+    frame_deallocator: &mut impl FrameDeallocator<Size4KiB>,
+) -> Result<Vec<LoadedSegment>, LoadError> {
+    let start = pages.start.start_address().as_u64();
+    let mut loaded_regions = Vec::new();
+    for (range, flags) in regions.iter() {
+        let region = LoadedSegment::new(
+            Page::range_inclusive(
+                Page::containing_address(VirtAddr::new(start + range.start)),
+                Page::containing_address(VirtAddr::new(start + range.end - 1)),
+            ),
+            *flags,
+        );
+        for page in region.pages() {
+            match unsafe { kernel_mapper.update_flags(page, *flags) } {
+                Ok(flush) => flush.flush(),
+                Err(_) => {
+                    unmap_pages(kernel_mapper, pages, frame_deallocator);
+                    return Err(LoadError::PageNotMapped(page));
+                }
+            }
+        }
+        loaded_regions.push(region);
+    }
+
+    Ok(loaded_regions)
+}
+
+/// Unmap the module's `pages` after loading it failed, and give their frames
+/// to `frame_deallocator`.
+fn unmap_pages(
+    mapper: &mut OffsetPageTable,
+    pages: impl IntoIterator<Item = Page>,
+    frame_deallocator: &mut impl FrameDeallocator<Size4KiB>,
+) {
+    for page in pages {
+        if let Ok((frame, flush)) = mapper.unmap(page) {
+            flush.flush();
+            // Nothing else knows about the module's frames yet
+            unsafe { frame_deallocator.deallocate_frame(frame) };
+        }
+    }
+}
+
+/// Where the module's allocated sections go, as offsets from the start of its
+/// pages.
+struct ModuleLayout {
+    /// Offset of every allocated section, by section index.
+    section_offsets: Vec<Option<u64>>,
+    /// The byte ranges of the code, read-only data and writable data, and the
+    /// flags their pages are mapped with. Each starts on a new page.
+    regions: Vec<(Range<u64>, PageTableFlags)>,
+    size: u64,
+}
+
+impl ModuleLayout {
+    fn new(elf_file: &Elf64File) -> Result<Self, LoadError> {
+        let mut sections = Vec::new();
+        for section in elf_file.section_headers()? {
+            sections.push(section?);
+        }
+
+        let mut layout = Self {
+            section_offsets: vec![None; sections.len()],
+            regions: Vec::new(),
+            size: 0,
+        };
+        let groups = [
+            PageTableFlags::PRESENT,
+            PageTableFlags::PRESENT | PageTableFlags::NO_EXECUTE,
+            PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE,
+        ];
+        for group_flags in groups.iter() {
+            let start = align_up(layout.size, PAGE_SIZE)?;
+            layout.size = start;
+
+            for (index, section) in sections.iter().enumerate() {
+                if !section.flags().contains(SectionHeaderFlags::ALLOC)
+                    || page_flags(index as u16, section)? != *group_flags
+                {
+                    continue;
+                }
+
+                let offset = align_up(layout.size, section.address_alignment().max(1))?;
+                layout.section_offsets[index] = Some(offset);
+                layout.size = offset
+                    .checked_add(section.section_size())
+                    .ok_or(LoadError::NoFreeAddressRange)?;
+            }
+
+            if layout.size > start {
+                layout.regions.push((start..layout.size, *group_flags));
+            }
+        }
+
+        Ok(layout)
+    }
+
+    fn page_count(&self) -> u64 {
+        // Even a module without sections gets a page, so that it has an address
+        self.size.div_ceil(PAGE_SIZE).max(1)
+    }
+}
+
+/// The flags of the pages an allocated section is loaded into.
+fn page_flags(index: u16, section: &SectionHeader) -> Result<PageTableFlags, LoadError> {
+    let section_flags = section.flags();
+    if section_flags.contains(SectionHeaderFlags::TLS)
+        || section_flags
+            .contains(SectionHeaderFlags::WRITE | SectionHeaderFlags::EXECUTABLE_INSTRUCTIONS)
+        || section.address_alignment() > PAGE_SIZE
+    {
+        return Err(LoadError::UnsupportedSection(index));
+    }
+
+    let mut flags = PageTableFlags::PRESENT;
+    if section_flags.contains(SectionHeaderFlags::WRITE) {
+        flags |= PageTableFlags::WRITABLE;
+    }
+    if !section_flags.contains(SectionHeaderFlags::EXECUTABLE_INSTRUCTIONS) {
+        flags |= PageTableFlags::NO_EXECUTE;
+    }
+
+    Ok(flags)
+}
+
+fn align_up(value: u64, alignment: u64) -> Result<u64, LoadError> {
+    value
+        .checked_add(alignment - 1)
+        .map(|value| value / alignment * alignment)
+        .filter(|&value| value <= MODULE_AREA_END - MODULE_AREA_START)
+        .ok_or(LoadError::NoFreeAddressRange)
+}
+
+/// The pages of a module being linked, accessed through the kernel's own
+/// mapping of them.
+struct ModuleMemory {
+    start: u64,
+    end: u64,
+}
+
+impl ModuleMemory {
+    fn check(&self, address: u64, length: usize) -> Result<*mut u8, RelocationError> {
+        match address.checked_add(length as u64) {
+            Some(end) if address >= self.start && end <= self.end => Ok(address as *mut u8),
+            _ => Err(RelocationError::UnmappedAddress(address)),
+        }
+    }
+}
+
+impl RelocationTarget for ModuleMemory {
+    fn read(&mut self, address: u64, bytes: &mut [u8]) -> Result<(), RelocationError> {
+        let source = self.check(address, bytes.len())?;
+        unsafe { ptr::copy_nonoverlapping(source, bytes.as_mut_ptr(), bytes.len()) };
+        Ok(())
+    }
+
+    fn write(&mut self, address: u64, bytes: &[u8]) -> Result<(), RelocationError> {
+        let destination = self.check(address, bytes.len())?;
+        unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), destination, bytes.len()) };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::tests::{allocator, page_table};
+    use bootloader::bootinfo::MemoryRegionType;
+
+    #[test_case]
+    fn lay_out_kernel_module() {
+        let file_bytes = include_bytes!("../../elf/src/test_files/kernel_module.elf64");
+        let elf_file =
+            Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+        let layout = ModuleLayout::new(&elf_file).expect("Failed to lay out module");
+
+        // .text, .data, .bss and .rodata, each group on its own page
+        let mut expected_offsets = vec![None; 12];
+        expected_offsets[1] = Some(0);
+        expected_offsets[6] = Some(0x1000);
+        expected_offsets[3] = Some(0x2000);
+        // .bss is aligned to 32 bytes
+        expected_offsets[5] = Some(0x2020);
+        assert_eq!(expected_offsets, layout.section_offsets);
+        assert_eq!(
+            vec![
+                (0..0x80, PageTableFlags::PRESENT),
+                (
+                    0x1000..0x1007,
+                    PageTableFlags::PRESENT | PageTableFlags::NO_EXECUTE
+                ),
+                (
+                    0x2000..0x2040,
+                    PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE
+                ),
+            ],
+            layout.regions
+        );
+        assert_eq!(3, layout.page_count());
+    }
+
+    #[test_case]
+    fn reject_unsupported_module_sections() {
+        let mut file_bytes =
+            include_bytes!("../../elf/src/test_files/kernel_module.elf64").to_vec();
+        // Section headers start at 0x428. Make .text (the 2nd) writable.
+        file_bytes[0x428 + 64 + 8] |= 1;
+        let elf_file =
+            Elf64File::from_bytes(&file_bytes).expect("The file should be correctly parsed");
+        assert!(matches!(
+            ModuleLayout::new(&elf_file),
+            Err(LoadError::UnsupportedSection(1))
+        ));
+    }
+
+    #[test_case]
+    fn export_kernel_symbols() {
+        let mut symbols = KernelSymbolTable::new();
+        symbols.export("kernel_record", 0x20_1000);
+        symbols.export("kernel_counter", 0x20_2000);
+        symbols.export("kernel_record", 0x20_3000);
+
+        assert_eq!(Some(0x20_3000), symbols.address("kernel_record"));
+        assert_eq!(Some(0x20_2000), symbols.address("kernel_counter"));
+        assert_eq!(None, symbols.address("kernel"));
+    }
+
+    #[test_case]
+    fn unmap_module_pages_when_protecting_them_fails() {
+        let mut frame_allocator = allocator(&[(0, 16, MemoryRegionType::Usable)]);
+        let mut mapper = page_table(&mut frame_allocator);
+        let start = Page::containing_address(VirtAddr::new(MODULE_AREA_START));
+        let pages = Page::range_inclusive(start, start + 1);
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+        let mut frames = Vec::new();
+        for page in pages {
+            let frame = frame_allocator.allocate_frame().unwrap();
+            unsafe { mapper.map_to(page, frame, flags, &mut frame_allocator) }
+                .unwrap()
+                .ignore();
+            frames.push(frame);
+        }
+
+        // The last region's second page was never mapped
+        let regions = [
+            (0..0x80, PageTableFlags::PRESENT),
+            (
+                0x1000..0x2040,
+                PageTableFlags::PRESENT | PageTableFlags::NO_EXECUTE,
+            ),
+        ];
+        assert!(matches!(
+            protect_regions(&mut mapper, pages, &regions, &mut frame_allocator),
+            Err(LoadError::PageNotMapped(page)) if page == start + 2
+        ));
+        for page in pages {
+            assert!(matches!(
+                mapper.translate(page.start_address()),
+                TranslateResult::NotMapped
+            ));
+        }
+        // Their frames were deallocated, and are reused last one first
+        assert_eq!(frames.pop(), frame_allocator.allocate_frame());
+        assert_eq!(frames.pop(), frame_allocator.allocate_frame());
+    }
+}
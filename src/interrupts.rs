@@ -3,7 +3,11 @@ use pic8259::ChainedPics;
 use spin;
 use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
 
-use crate::{gdt, print, println};
+use crate::{
+    backtrace,
+    elf::core_dump::{self, Registers, Signal},
+    gdt, print, println, serial,
+};
 
 // Start PIC_1 at interrupt 32 because 0-31 are reserved for CPU exceptions
 pub const PIC_1_OFFSET: u8 = 32;
@@ -37,6 +41,7 @@ lazy_static! {
         // Set CPU exception handlers
         idt.breakpoint.set_handler_fn(breakpoint_handler);
         idt.page_fault.set_handler_fn(page_fault_handler);
+        idt.general_protection_fault.set_handler_fn(general_protection_fault_handler);
         unsafe {
             idt.double_fault.set_handler_fn(double_fault_handler)
                 .set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
@@ -70,9 +75,37 @@ extern "x86-interrupt" fn page_fault_handler(
     println!("Accessed Address: {:?}", Cr2::read());
     println!("Error Code: {:?}", error_code);
     println!("{:#?}", stack_frame);
+    dump_user_core(&stack_frame);
+    // The handler's frame holds the frame pointer of the interrupted code
+    let frame_pointer = unsafe { *(backtrace::frame_pointer() as *const u64) };
+    backtrace::print(
+        Some(stack_frame.instruction_pointer.as_u64()),
+        frame_pointer,
+    );
     crate::hlt_loop();
 }
 
+extern "x86-interrupt" fn general_protection_fault_handler(
+    stack_frame: InterruptStackFrame,
+    error_code: u64,
+) {
+    println!("EXCEPTION: GENERAL PROTECTION FAULT");
+    println!("Error Code: {:#x}", error_code);
+    println!("{:#?}", stack_frame);
+    dump_user_core(&stack_frame);
+    crate::hlt_loop();
+}
+
+/// Write a core file to the serial port if the fault happened in a user
+/// process, so that it can be opened with `gdb` on the host. Page faults and
+/// general protection faults both end a process with `SIGSEGV`.
+fn dump_user_core(stack_frame: &InterruptStackFrame) {
+    let registers = Registers::from(stack_frame);
+    if let Some(core) = core_dump::user_fault_core(registers, Signal::Segmentation) {
+        serial::write_dump("core", &core);
+    }
+}
+
 extern "x86-interrupt" fn double_fault_handler(
     stack_frame: InterruptStackFrame,
     _error_code: u64,
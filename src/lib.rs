@@ -15,11 +15,13 @@ use bootloader::BootInfo;
 use core::panic::PanicInfo;
 
 pub mod allocator;
+pub mod backtrace;
 pub mod elf;
 pub mod gdt;
 pub mod interrupts;
 pub mod memory;
 pub mod qemu;
+pub mod random;
 pub mod serial;
 pub mod task;
 pub mod vga_buffer;
@@ -63,6 +65,7 @@ pub fn init(boot_info: &'static BootInfo) {
     x86_64::instructions::interrupts::enable();
 
     init_heap(&boot_info);
+    backtrace::init(boot_info);
 }
 
 fn init_heap(boot_info: &'static BootInfo) {
@@ -71,9 +74,10 @@ fn init_heap(boot_info: &'static BootInfo) {
 
     let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
     let mut mem_mapper = unsafe { memory::init(phys_mem_offset) };
-    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
-    allocator::init_heap(&mut mem_mapper, &mut frame_allocator)
-        .expect("Heap initialization failed");
+    let frame_allocator =
+        unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map, phys_mem_offset) };
+    memory::init_frame_allocator(frame_allocator);
+    allocator::init_heap(&mut mem_mapper).expect("Heap initialization failed");
 }
 
 /// Loop over a HLT instruction to use less power while waiting for the next
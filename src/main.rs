@@ -15,7 +15,10 @@ extern crate rlibc;
 #[cfg(not(test))]
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
+    use my_rust_os::backtrace;
+
     println!("{}", info);
+    backtrace::print(None, backtrace::frame_pointer());
     my_rust_os::hlt_loop();
 }
 
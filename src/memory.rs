@@ -1,9 +1,20 @@
 use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use core::ptr;
+use spin::Mutex;
 use x86_64::{
-    structures::paging::{FrameAllocator, OffsetPageTable, PageTable, PhysFrame, Size4KiB},
+    structures::paging::{
+        FrameAllocator, FrameDeallocator, OffsetPageTable, PageSize, PageTable, PageTableFlags,
+        PhysFrame, Size2MiB, Size4KiB,
+    },
     PhysAddr, VirtAddr,
 };
 
+pub mod address_space;
+pub mod buddy;
+pub mod mapping;
+
+use buddy::BuddyAllocator;
+
 /// Initialize a new OffsetPageTable.
 ///
 /// This function is unsafe because the caller must guarantee that the
@@ -26,17 +37,111 @@ unsafe fn active_level_4_table(physical_memory_offset: VirtAddr) -> &'static mut
 
     let (level_4_table_frame, _) = Cr3::read();
 
-    let phys = level_4_table_frame.start_address();
+    page_table_at(level_4_table_frame, physical_memory_offset)
+}
+
+/// Returns a mutable reference to the page table stored in `frame`.
+///
+/// This function is unsafe because the caller must guarantee that the
+/// complete physical memory is mapped to virtual memory at the passed
+/// `physical_memory_offset`, and that `frame` holds a page table that is not
+/// referenced anywhere else (aliasing `&mut` references is undefined
+/// behavior).
+pub unsafe fn page_table_at(
+    frame: PhysFrame,
+    physical_memory_offset: VirtAddr,
+) -> &'static mut PageTable {
+    let phys = frame.start_address();
     let virt = physical_memory_offset + phys.as_u64();
     let page_table_ptr: *mut PageTable = virt.as_mut_ptr();
 
     &mut *page_table_ptr // unsafe
 }
 
-/// A FrameAllocator that returns usable frames from the bootloader's memory map.
+/// Allocate a frame from `frame_allocator` and zero it through the physical
+/// memory mapping at `physical_memory_offset`, e.g. for a new page table.
+pub fn allocate_zeroed_frame(
+    frame_allocator: &mut impl FrameAllocator<Size4KiB>,
+    physical_memory_offset: VirtAddr,
+) -> Option<PhysFrame> {
+    let frame = frame_allocator.allocate_frame()?;
+    let frame_ptr: *mut u8 = (physical_memory_offset + frame.start_address().as_u64()).as_mut_ptr();
+    unsafe { ptr::write_bytes(frame_ptr, 0, Size4KiB::SIZE as usize) };
+
+    Some(frame)
+}
+
+/// Deallocate the page table at `level` in `frame`, together with the page
+/// tables and frames its entries point to, except for those it shares with
+/// `kernel_table`, the kernel's page table at the same place if it has one.
+///
+/// This function is unsafe because the caller must guarantee that the
+/// complete physical memory is mapped at `physical_memory_offset`, that both
+/// tables are valid page table hierarchies, and that the page table isn't
+/// used anymore.
+pub unsafe fn deallocate_page_table(
+    frame: PhysFrame,
+    kernel_table: Option<&PageTable>,
+    level: u8,
+    physical_memory_offset: VirtAddr,
+    frame_deallocator: &mut impl FrameDeallocator<Size4KiB>,
+) {
+    let table = page_table_at(frame, physical_memory_offset);
+    for (index, entry) in table.iter().enumerate() {
+        if !entry.flags().contains(PageTableFlags::PRESENT) {
+            continue;
+        }
+        let kernel_entry = kernel_table
+            .map(|kernel_table| &kernel_table[index])
+            .filter(|kernel_entry| kernel_entry.flags().contains(PageTableFlags::PRESENT));
+        if kernel_entry.is_some_and(|kernel_entry| kernel_entry.addr() == entry.addr()) {
+            continue;
+        }
+
+        // Only 4 KiB pages are mapped outside the kernel's page tables, and
+        // huge pages of the kernel are always shared
+        let entry_frame = PhysFrame::containing_address(entry.addr());
+        if level > 1 {
+            let kernel_next_table = kernel_entry
+                .filter(|kernel_entry| !kernel_entry.flags().contains(PageTableFlags::HUGE_PAGE))
+                .map(|kernel_entry| {
+                    &*(physical_memory_offset + kernel_entry.addr().as_u64()).as_ptr()
+                });
+            deallocate_page_table(
+                entry_frame,
+                kernel_next_table,
+                level - 1,
+                physical_memory_offset,
+                frame_deallocator,
+            );
+        } else {
+            frame_deallocator.deallocate_frame(entry_frame);
+        }
+    }
+    frame_deallocator.deallocate_frame(frame);
+}
+
+/// Marks the end of the list of freed frames, which frame 0 may be part of.
+const NO_FRAME: u64 = u64::MAX;
+
+/// A FrameAllocator that returns usable frames from the bootloader's memory
+/// map, and reuses frames that were deallocated before taking new ones.
+///
+/// Frames that were never allocated are taken from the usable regions in
+/// order. Deallocated frames form a linked list, where each frame stores the
+/// physical address of the next one in its first 8 bytes, written through the
+/// physical memory mapping. This makes both allocating and deallocating a
+/// frame constant time, apart from skipping regions that aren't usable once.
 pub struct BootInfoFrameAllocator {
     memory_map: &'static MemoryMap,
-    next: usize,
+    physical_memory_offset: VirtAddr,
+    /// Index of the memory map region that new frames are taken from.
+    region: usize,
+    /// Address of the next frame in that region that was never allocated, or
+    /// 0 if none were taken from it yet.
+    next: u64,
+    /// The most recently deallocated frame, at the head of the free list.
+    free_list: Option<PhysFrame>,
 }
 
 impl BootInfoFrameAllocator {
@@ -44,33 +149,282 @@ impl BootInfoFrameAllocator {
     ///
     /// This function is unsafe because the caller must guarantee that the passed
     /// memory map is valid. The main requirement is that all frames that are marked
-    /// as `USABLE` in it are really unused.
-    pub unsafe fn init(memory_map: &'static MemoryMap) -> Self {
+    /// as `USABLE` in it are really unused. The complete physical memory must also
+    /// be mapped to virtual memory at the passed `physical_memory_offset`.
+    pub unsafe fn init(memory_map: &'static MemoryMap, physical_memory_offset: VirtAddr) -> Self {
         BootInfoFrameAllocator {
             memory_map,
+            physical_memory_offset,
+            region: 0,
             next: 0,
+            free_list: None,
+        }
+    }
+
+    /// Returns a frame that was never allocated before, moving on to the next
+    /// usable region once the current one is used up.
+    fn allocate_unused_frame(&mut self) -> Option<PhysFrame> {
+        while let Some(region) = self.memory_map.get(self.region) {
+            if region.region_type == MemoryRegionType::Usable {
+                let address = self.next.max(region.range.start_addr());
+                if address < region.range.end_addr() {
+                    self.next = address + Size4KiB::SIZE;
+                    return Some(PhysFrame::containing_address(PhysAddr::new(address)));
+                }
+            }
+            self.region += 1;
+            self.next = 0;
+        }
+        None
+    }
+
+    /// Put the frames from `start` up to `end`, which were never allocated, on
+    /// the free list.
+    fn deallocate_unused_frames(&mut self, start: u64, end: u64) {
+        // In reverse, so that they are allocated in order again
+        let mut address = end;
+        while address > start {
+            address -= Size4KiB::SIZE;
+            let frame = PhysFrame::containing_address(PhysAddr::new(address));
+            // The memory map says the frame is unused
+            unsafe { self.deallocate_frame(frame) };
         }
     }
 
-    /// Returns an iterator over the usable frames specified in the memory map.
-    fn usable_frames(&self) -> impl Iterator<Item = PhysFrame> {
-        // get usable regions from memory map
-        let regions = self.memory_map.iter();
-        let usable_regions =
-            regions.filter(|region| region.region_type == MemoryRegionType::Usable);
-        // map each region to its address range
-        let addr_ranges =
-            usable_regions.map(|region| region.range.start_addr()..region.range.end_addr());
-        // transform to an iterator over frame start addresses
-        let frame_addresses = addr_ranges.flat_map(|region| region.step_by(4096));
-        frame_addresses.map(|addr| PhysFrame::containing_address(PhysAddr::new(addr)))
+    /// Returns a pointer to the link to the next free frame, which is stored at
+    /// the start of `frame`.
+    fn free_list_link(&self, frame: PhysFrame) -> *mut u64 {
+        (self.physical_memory_offset + frame.start_address().as_u64()).as_mut_ptr()
     }
 }
 
 unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
     fn allocate_frame(&mut self) -> Option<PhysFrame> {
-        let frame = self.usable_frames().nth(self.next);
-        self.next += 1;
-        frame
+        match self.free_list {
+            Some(frame) => {
+                // Frames only get on the free list through `deallocate_frame`,
+                // which wrote the link
+                let next = unsafe { self.free_list_link(frame).read() };
+                self.free_list = match next {
+                    NO_FRAME => None,
+                    address => Some(PhysFrame::containing_address(PhysAddr::new(address))),
+                };
+                Some(frame)
+            }
+            None => self.allocate_unused_frame(),
+        }
+    }
+}
+
+impl FrameDeallocator<Size4KiB> for BootInfoFrameAllocator {
+    /// Return `frame` to the allocator.
+    ///
+    /// This function is unsafe because the caller must guarantee that `frame`
+    /// was allocated by this allocator, isn't used or deallocated anymore, and
+    /// isn't mapped anywhere else it could be written through.
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame) {
+        let next = self
+            .free_list
+            .map_or(NO_FRAME, |next| next.start_address().as_u64());
+        self.free_list_link(frame).write(next);
+        self.free_list = Some(frame);
+    }
+}
+
+unsafe impl FrameAllocator<Size2MiB> for BootInfoFrameAllocator {
+    /// Returns the first 2 MiB aligned run of frames that were never allocated.
+    /// The frames skipped to get to it are deallocated, so they are still handed
+    /// out as 4 KiB frames.
+    fn allocate_frame(&mut self) -> Option<PhysFrame<Size2MiB>> {
+        while let Some(region) = self.memory_map.get(self.region) {
+            if region.region_type == MemoryRegionType::Usable {
+                let next = self.next.max(region.range.start_addr());
+                let start = x86_64::align_up(next, Size2MiB::SIZE);
+                let end = region.range.end_addr();
+                if start + Size2MiB::SIZE <= end {
+                    self.deallocate_unused_frames(next, start);
+                    self.next = start + Size2MiB::SIZE;
+                    return Some(PhysFrame::containing_address(PhysAddr::new(start)));
+                }
+                self.deallocate_unused_frames(next, end);
+            }
+            self.region += 1;
+            self.next = 0;
+        }
+        None
+    }
+}
+
+/// The frame allocator that `GlobalFrameAllocator` hands out frames from.
+static FRAME_ALLOCATOR: Mutex<Option<BuddyAllocator>> = Mutex::new(None);
+
+/// Hand the frames of `frame_allocator` that are still free to a buddy
+/// allocator, which `GlobalFrameAllocator` then hands out frames from. This
+/// way the heap can take frames from it as it grows, and 2 MiB frames that
+/// were split up are merged again once their frames are deallocated.
+pub fn init_frame_allocator(frame_allocator: BootInfoFrameAllocator) {
+    *FRAME_ALLOCATOR.lock() = Some(BuddyAllocator::from(frame_allocator));
+}
+
+/// A FrameAllocator that hands out frames from the buddy allocator set up by
+/// `init_frame_allocator`, which it locks for each call only. It can be used
+/// while the heap grows, since growing takes the lock only while it maps pages.
+///
+/// Panics if `init_frame_allocator` wasn't called yet.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GlobalFrameAllocator;
+
+impl GlobalFrameAllocator {
+    /// Returns the number of free bytes of physical memory.
+    pub fn free_memory() -> u64 {
+        Self::with_allocator(|frame_allocator| frame_allocator.free_memory())
+    }
+
+    fn with_allocator<T>(f: impl FnOnce(&mut BuddyAllocator) -> T) -> T {
+        let mut frame_allocator = FRAME_ALLOCATOR.lock();
+        f(frame_allocator
+            .as_mut()
+            .expect("The frame allocator is not initialized"))
+    }
+}
+
+unsafe impl FrameAllocator<Size4KiB> for GlobalFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame<Size4KiB>> {
+        Self::with_allocator(|frame_allocator| frame_allocator.allocate_frame())
+    }
+}
+
+unsafe impl FrameAllocator<Size2MiB> for GlobalFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame<Size2MiB>> {
+        Self::with_allocator(|frame_allocator| frame_allocator.allocate_frame())
+    }
+}
+
+impl FrameDeallocator<Size4KiB> for GlobalFrameAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame<Size4KiB>) {
+        Self::with_allocator(|frame_allocator| frame_allocator.deallocate_frame(frame))
+    }
+}
+
+impl FrameDeallocator<Size2MiB> for GlobalFrameAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame<Size2MiB>) {
+        Self::with_allocator(|frame_allocator| frame_allocator.deallocate_frame(frame))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloc::{boxed::Box, vec::Vec};
+    use bootloader::bootinfo::{FrameRange, MemoryRegion};
+    use core::ptr;
+
+    /// Number of frames of fake physical memory the tests allocate from.
+    const FRAMES: usize = 16;
+
+    /// Fake physical memory backing frames 0 to `FRAMES`, shared by the tests
+    /// since the heap is too small for each to have its own.
+    #[repr(align(4096))]
+    struct PhysicalMemory([u8; FRAMES * 4096]);
+
+    static mut PHYSICAL_MEMORY: PhysicalMemory = PhysicalMemory([0; FRAMES * 4096]);
+
+    /// Create an allocator for a memory map with the passed regions, given as
+    /// frame numbers. Frames past `FRAMES` have no memory behind them, so only
+    /// ones that are never written to, like 2 MiB frames, may be allocated there.
+    pub(crate) fn allocator(regions: &[(u64, u64, MemoryRegionType)]) -> BootInfoFrameAllocator {
+        let mut memory_map = MemoryMap::new();
+        for &(start, end, region_type) in regions {
+            memory_map.add_region(MemoryRegion {
+                range: FrameRange::new(start * Size4KiB::SIZE, end * Size4KiB::SIZE),
+                region_type,
+            });
+        }
+
+        let memory = unsafe { ptr::addr_of!(PHYSICAL_MEMORY) };
+        unsafe {
+            BootInfoFrameAllocator::init(
+                Box::leak(Box::new(memory_map)),
+                VirtAddr::from_ptr(memory),
+            )
+        }
+    }
+
+    /// Create an empty page table in a frame from `frame_allocator`.
+    pub(crate) fn page_table(
+        frame_allocator: &mut BootInfoFrameAllocator,
+    ) -> OffsetPageTable<'static> {
+        let frame = frame_allocator.allocate_frame().unwrap();
+        let physical_memory_offset = frame_allocator.physical_memory_offset;
+        unsafe {
+            let level_4_table = page_table_at(frame, physical_memory_offset);
+            level_4_table.zero();
+            OffsetPageTable::new(level_4_table, physical_memory_offset)
+        }
+    }
+
+    fn frame(number: u64) -> PhysFrame {
+        PhysFrame::containing_address(PhysAddr::new(number * Size4KiB::SIZE))
+    }
+
+    #[test_case]
+    fn allocate_frames_of_usable_regions() {
+        let mut allocator = allocator(&[
+            (0, 2, MemoryRegionType::Usable),
+            (2, 3, MemoryRegionType::Reserved),
+            (3, 3, MemoryRegionType::Usable),
+            (5, 7, MemoryRegionType::Usable),
+            (7, 8, MemoryRegionType::Kernel),
+        ]);
+        let frames: Vec<_> = core::iter::from_fn(|| allocator.allocate_frame()).collect();
+        assert_eq!(frames, [frame(0), frame(1), frame(5), frame(6)]);
+        assert_eq!(allocator.allocate_frame(), None::<PhysFrame>);
+    }
+
+    #[test_case]
+    fn reuse_deallocated_frames() {
+        let mut allocator = allocator(&[(0, 4, MemoryRegionType::Usable)]);
+        let first = allocator.allocate_frame().unwrap();
+        let second = allocator.allocate_frame().unwrap();
+        unsafe {
+            allocator.deallocate_frame(first);
+            allocator.deallocate_frame(second);
+        }
+
+        assert_eq!(allocator.allocate_frame(), Some(second));
+        assert_eq!(allocator.allocate_frame(), Some(first));
+        assert_eq!(allocator.allocate_frame(), Some(frame(2)));
+    }
+
+    #[test_case]
+    fn reuse_deallocated_frames_once_memory_is_used_up() {
+        let mut allocator = allocator(&[(1, 4, MemoryRegionType::Usable)]);
+        let frames: Vec<_> = core::iter::from_fn(|| allocator.allocate_frame()).collect();
+        assert_eq!(frames.len(), 3);
+
+        for &frame in &frames {
+            unsafe { allocator.deallocate_frame(frame) };
+        }
+        for &frame in frames.iter().rev() {
+            assert_eq!(allocator.allocate_frame(), Some(frame));
+        }
+        assert_eq!(allocator.allocate_frame(), None::<PhysFrame>);
+    }
+
+    #[test_case]
+    fn allocate_2mib_frames() {
+        let mut allocator = allocator(&[
+            (0, 4, MemoryRegionType::Usable),
+            (512, 1024, MemoryRegionType::Usable),
+        ]);
+        assert_eq!(allocator.allocate_frame(), Some(frame(0)));
+        let huge_frame: Option<PhysFrame<Size2MiB>> = allocator.allocate_frame();
+        assert_eq!(huge_frame.unwrap().start_address().as_u64(), 512 * 4096);
+        let huge_frame: Option<PhysFrame<Size2MiB>> = allocator.allocate_frame();
+        assert_eq!(huge_frame, None);
+
+        // The frames skipped in the first region are still there
+        let frames: Vec<_> = core::iter::from_fn(|| allocator.allocate_frame()).collect();
+        assert_eq!(frames, [frame(1), frame(2), frame(3)]);
     }
 }
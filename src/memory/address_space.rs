@@ -0,0 +1,411 @@
+//! Address spaces for user programs, each with its own level 4 page table.
+
+use super::{allocate_zeroed_frame, deallocate_page_table, page_table_at, GlobalFrameAllocator};
+use x86_64::{
+    registers::control::Cr3,
+    structures::paging::{
+        mapper::{MapToError, UnmapError},
+        page::PageRange,
+        FrameAllocator, FrameDeallocator, Mapper, OffsetPageTable, Page, PageTable, PageTableFlags,
+        PhysFrame, Size4KiB, Translate,
+    },
+    PhysAddr, VirtAddr,
+};
+
+const ENTRY_COUNT: usize = 512;
+
+#[derive(Debug)]
+pub enum AddressSpaceError {
+    FrameAllocationFailed,
+    /// The page is in the part of the address space that is shared with the
+    /// kernel.
+    KernelPage(Page),
+    Mapping(MapToError<Size4KiB>),
+    Unmapping(UnmapError),
+}
+
+impl From<MapToError<Size4KiB>> for AddressSpaceError {
+    fn from(error: MapToError<Size4KiB>) -> Self {
+        Self::Mapping(error)
+    }
+}
+
+impl From<UnmapError> for AddressSpaceError {
+    fn from(error: UnmapError) -> Self {
+        Self::Unmapping(error)
+    }
+}
+
+/// An address space with its own level 4 page table, which shares the
+/// kernel's mappings and has a user part that is private to it.
+///
+/// The kernel's part is made of the level 4 entries the kernel's page table
+/// used when the address space was created. They are copied, so both tables
+/// share the page tables below them, and the kernel's mappings look the same
+/// in every address space. The remaining entries are the user part, where
+/// pages can be mapped with `map_user_range`. The page tables and frames of
+/// the user part belong to the address space, and are deallocated with it.
+///
+/// Images loaded with `elf::loader::load` have their own page tables instead,
+/// since executables are mapped at fixed addresses in level 4 entries the
+/// kernel uses.
+pub struct AddressSpace<A = GlobalFrameAllocator>
+where
+    A: FrameAllocator<Size4KiB> + FrameDeallocator<Size4KiB>,
+{
+    level_4_frame: PhysFrame,
+    /// The level 4 table the kernel's mappings were copied from, which is
+    /// switched back to by `deactivate`.
+    kernel_level_4_frame: PhysFrame,
+    /// Which level 4 entries are shared with the kernel.
+    kernel_entries: [bool; ENTRY_COUNT],
+    physical_memory_offset: VirtAddr,
+    frame_allocator: A,
+}
+
+impl AddressSpace {
+    /// Create an address space with the kernel's mappings from
+    /// `kernel_mapper`, whose frames come from `GlobalFrameAllocator`.
+    pub fn new(kernel_mapper: &mut OffsetPageTable) -> Result<Self, AddressSpaceError> {
+        Self::new_in(kernel_mapper, GlobalFrameAllocator)
+    }
+}
+
+impl<A> AddressSpace<A>
+where
+    A: FrameAllocator<Size4KiB> + FrameDeallocator<Size4KiB>,
+{
+    /// Create an address space with the kernel's mappings from
+    /// `kernel_mapper` and an empty user part, which allocates its page tables
+    /// and frames from `frame_allocator`.
+    pub fn new_in(
+        kernel_mapper: &mut OffsetPageTable,
+        mut frame_allocator: A,
+    ) -> Result<Self, AddressSpaceError> {
+        let physical_memory_offset = kernel_mapper.phys_offset();
+        let kernel_level_4_table = kernel_mapper.level_4_table();
+        let kernel_level_4_address = kernel_level_4_table as *const PageTable as u64;
+        let kernel_level_4_frame = PhysFrame::containing_address(PhysAddr::new(
+            kernel_level_4_address - physical_memory_offset.as_u64(),
+        ));
+
+        let level_4_frame = allocate_zeroed_frame(&mut frame_allocator, physical_memory_offset)
+            .ok_or(AddressSpaceError::FrameAllocationFailed)?;
+        let level_4_table = unsafe { page_table_at(level_4_frame, physical_memory_offset) };
+        let mut kernel_entries = [false; ENTRY_COUNT];
+        for (index, kernel_entry) in kernel_level_4_table.iter().enumerate() {
+            if !kernel_entry.is_unused() {
+                level_4_table[index] = kernel_entry.clone();
+                kernel_entries[index] = true;
+            }
+        }
+
+        Ok(AddressSpace {
+            level_4_frame,
+            kernel_level_4_frame,
+            kernel_entries,
+            physical_memory_offset,
+            frame_allocator,
+        })
+    }
+
+    /// The frame holding the level 4 page table, which is written to CR3 by
+    /// `activate`.
+    pub fn level_4_frame(&self) -> PhysFrame {
+        self.level_4_frame
+    }
+
+    /// Returns whether `page` is in the user part of the address space.
+    pub fn is_user_page(&self, page: Page) -> bool {
+        !self.kernel_entries[usize::from(page.p4_index())]
+    }
+
+    /// Map each of `pages` to a new zeroed frame. The pages are mapped with
+    /// `flags`, plus `PRESENT` and `USER_ACCESSIBLE`.
+    ///
+    /// Fails without mapping anything if a page is in the kernel's part.
+    /// Pages that were mapped before a later page failed stay mapped.
+    pub fn map_user_range(
+        &mut self,
+        pages: PageRange,
+        flags: PageTableFlags,
+    ) -> Result<(), AddressSpaceError> {
+        self.check_user_range(pages)?;
+
+        let flags = flags | PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+        // The page tables only add restrictions, so they allow anything that
+        // the pages below them may allow
+        let table_flags =
+            PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE;
+        let mut mapper = unsafe { self.mapper() };
+        for page in pages {
+            let frame = allocate_zeroed_frame(&mut self.frame_allocator, mapper.phys_offset())
+                .ok_or(AddressSpaceError::FrameAllocationFailed)?;
+            let result = unsafe {
+                mapper.map_to_with_table_flags(
+                    page,
+                    frame,
+                    flags,
+                    table_flags,
+                    &mut self.frame_allocator,
+                )
+            };
+            match result {
+                // Flushing only matters if the address space is active, and
+                // doesn't hurt otherwise
+                Ok(flush) => flush.flush(),
+                Err(error) => {
+                    unsafe { self.frame_allocator.deallocate_frame(frame) };
+                    return Err(error.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Unmap each of `pages` and deallocate the frames they were mapped to.
+    /// The page tables stay until the address space is dropped.
+    ///
+    /// Fails without unmapping anything if a page is in the kernel's part.
+    /// Pages before one that isn't mapped are still unmapped.
+    pub fn unmap_user_range(&mut self, pages: PageRange) -> Result<(), AddressSpaceError> {
+        self.check_user_range(pages)?;
+
+        let mut mapper = unsafe { self.mapper() };
+        for page in pages {
+            let (frame, flush) = mapper.unmap(page)?;
+            flush.flush();
+            // The frame was allocated by `map_user_range` and is no longer
+            // mapped
+            unsafe { self.frame_allocator.deallocate_frame(frame) };
+        }
+        Ok(())
+    }
+
+    /// Returns the physical address that `address` is mapped to, if any.
+    pub fn translate_addr(&self, address: VirtAddr) -> Option<PhysAddr> {
+        unsafe { self.mapper() }.translate_addr(address)
+    }
+
+    /// Returns whether the address space's level 4 table is in CR3.
+    pub fn is_active(&self) -> bool {
+        Cr3::read().0 == self.level_4_frame
+    }
+
+    /// Switch to the address space by writing its level 4 table to CR3, which
+    /// flushes the TLB of all but global pages.
+    ///
+    /// This function is unsafe because the caller must guarantee that nothing
+    /// that is still used is only mapped in the user part of the address space
+    /// that is active now.
+    pub unsafe fn activate(&self) {
+        let (_, flags) = Cr3::read();
+        Cr3::write(self.level_4_frame, flags);
+    }
+
+    /// Switch back to the kernel's page table that the address space was
+    /// created from, if the address space is active.
+    pub fn deactivate(&self) {
+        let (frame, flags) = Cr3::read();
+        if frame == self.level_4_frame {
+            // The kernel's mappings are the same in both tables
+            unsafe { Cr3::write(self.kernel_level_4_frame, flags) };
+        }
+    }
+
+    /// Fail if any of `pages` is in the kernel's part of the address space.
+    fn check_user_range(&self, pages: PageRange) -> Result<(), AddressSpaceError> {
+        match pages.into_iter().find(|&page| !self.is_user_page(page)) {
+            Some(page) => Err(AddressSpaceError::KernelPage(page)),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns a mapper for the address space's page table.
+    ///
+    /// This function is unsafe because the caller must not create a second
+    /// mapper while the first one is still used.
+    unsafe fn mapper(&self) -> OffsetPageTable<'static> {
+        let level_4_table = page_table_at(self.level_4_frame, self.physical_memory_offset);
+        OffsetPageTable::new(level_4_table, self.physical_memory_offset)
+    }
+}
+
+impl<A> Drop for AddressSpace<A>
+where
+    A: FrameAllocator<Size4KiB> + FrameDeallocator<Size4KiB>,
+{
+    /// Deallocate the page tables and frames of the user part, and the level 4
+    /// table. Switches back to the kernel's page table first if the address
+    /// space is active.
+    fn drop(&mut self) {
+        self.deactivate();
+
+        let level_4_table =
+            unsafe { page_table_at(self.level_4_frame, self.physical_memory_offset) };
+        for index in 0..ENTRY_COUNT {
+            let entry = &level_4_table[index];
+            if self.kernel_entries[index] || entry.is_unused() {
+                continue;
+            }
+            let frame = PhysFrame::containing_address(entry.addr());
+            // The user part shares no page tables with the kernel
+            unsafe {
+                deallocate_page_table(
+                    frame,
+                    None,
+                    3,
+                    self.physical_memory_offset,
+                    &mut self.frame_allocator,
+                )
+            };
+        }
+        unsafe { self.frame_allocator.deallocate_frame(self.level_4_frame) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{
+        tests::{allocator, page_table},
+        BootInfoFrameAllocator,
+    };
+    use alloc::vec::Vec;
+    use bootloader::bootinfo::MemoryRegionType;
+    use x86_64::structures::paging::mapper::TranslateResult;
+
+    /// Lends a frame allocator to an address space, so that the test can check
+    /// which frames it gave back after dropping it.
+    struct Frames<'a>(&'a mut BootInfoFrameAllocator);
+
+    unsafe impl FrameAllocator<Size4KiB> for Frames<'_> {
+        fn allocate_frame(&mut self) -> Option<PhysFrame> {
+            self.0.allocate_frame()
+        }
+    }
+
+    impl FrameDeallocator<Size4KiB> for Frames<'_> {
+        unsafe fn deallocate_frame(&mut self, frame: PhysFrame) {
+            self.0.deallocate_frame(frame)
+        }
+    }
+
+    /// A page in the first level 4 entry, which the kernel uses.
+    const KERNEL_PAGE: u64 = 0x1000;
+    /// A page in the second level 4 entry, which is left to user programs.
+    const USER_PAGE: u64 = 0x80_0000_0000;
+    /// The frame `KERNEL_PAGE` is mapped to, which is never written to.
+    const KERNEL_FRAME: u64 = 0x100_0000;
+
+    fn page(address: u64) -> Page {
+        Page::containing_address(VirtAddr::new(address))
+    }
+
+    /// Create a kernel page table in 4 frames from `frame_allocator`, with
+    /// `KERNEL_PAGE` mapped.
+    fn kernel_page_table(frame_allocator: &mut BootInfoFrameAllocator) -> OffsetPageTable<'static> {
+        let mut mapper = page_table(frame_allocator);
+        let frame = PhysFrame::containing_address(PhysAddr::new(KERNEL_FRAME));
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+        unsafe { mapper.map_to(page(KERNEL_PAGE), frame, flags, frame_allocator) }
+            .unwrap()
+            .ignore();
+        mapper
+    }
+
+    #[test_case]
+    fn share_kernel_entries() {
+        let mut frame_allocator = allocator(&[(0, 16, MemoryRegionType::Usable)]);
+        let mut kernel_mapper = kernel_page_table(&mut frame_allocator);
+        let mut space = AddressSpace::new_in(&mut kernel_mapper, Frames(&mut frame_allocator))
+            .expect("Failed to create address space");
+
+        assert!(!space.is_user_page(page(KERNEL_PAGE)));
+        assert!(space.is_user_page(page(USER_PAGE)));
+        assert_eq!(
+            space.translate_addr(VirtAddr::new(KERNEL_PAGE + 8)),
+            Some(PhysAddr::new(KERNEL_FRAME + 8))
+        );
+        assert_eq!(space.translate_addr(VirtAddr::new(USER_PAGE)), None);
+
+        let pages = Page::range(page(0), page(KERNEL_PAGE));
+        assert!(matches!(
+            space.map_user_range(pages, PageTableFlags::WRITABLE),
+            Err(AddressSpaceError::KernelPage(kernel_page)) if kernel_page == page(0)
+        ));
+        assert_eq!(space.translate_addr(VirtAddr::new(0)), None);
+    }
+
+    #[test_case]
+    fn map_and_unmap_user_range() {
+        let mut frame_allocator = allocator(&[(0, 16, MemoryRegionType::Usable)]);
+        let mut kernel_mapper = kernel_page_table(&mut frame_allocator);
+        let mut space = AddressSpace::new_in(&mut kernel_mapper, Frames(&mut frame_allocator))
+            .expect("Failed to create address space");
+        let pages = Page::range(page(USER_PAGE), page(USER_PAGE + 0x2000));
+        space
+            .map_user_range(pages, PageTableFlags::WRITABLE)
+            .expect("Failed to map user range");
+
+        let mapper = unsafe { space.mapper() };
+        let frame = match mapper.translate(VirtAddr::new(USER_PAGE + 0x1000)) {
+            TranslateResult::Mapped { frame, flags, .. } => {
+                let expected_flags = PageTableFlags::PRESENT
+                    | PageTableFlags::WRITABLE
+                    | PageTableFlags::USER_ACCESSIBLE;
+                assert_eq!(flags & expected_flags, expected_flags);
+                frame.start_address()
+            }
+            _ => panic!("The page should be mapped"),
+        };
+        // The kernel's page table doesn't see the user pages
+        assert_eq!(kernel_mapper.translate_addr(VirtAddr::new(USER_PAGE)), None);
+
+        space
+            .unmap_user_range(pages)
+            .expect("Failed to unmap user range");
+        assert_eq!(space.translate_addr(VirtAddr::new(USER_PAGE)), None);
+        assert!(matches!(
+            space.unmap_user_range(pages),
+            Err(AddressSpaceError::Unmapping(UnmapError::PageNotMapped))
+        ));
+
+        // The frame of the last page is the first to be reused
+        let last_page = Page::range(page(USER_PAGE + 0x1000), page(USER_PAGE + 0x2000));
+        space
+            .map_user_range(last_page, PageTableFlags::empty())
+            .expect("Failed to map user range");
+        assert_eq!(
+            space.translate_addr(VirtAddr::new(USER_PAGE + 0x1000)),
+            Some(frame)
+        );
+    }
+
+    #[test_case]
+    fn drop_deallocates_user_page_tables_and_frames() {
+        let mut frame_allocator = allocator(&[(0, 16, MemoryRegionType::Usable)]);
+        let mut kernel_mapper = kernel_page_table(&mut frame_allocator);
+        let mut space = AddressSpace::new_in(&mut kernel_mapper, Frames(&mut frame_allocator))
+            .expect("Failed to create address space");
+        let pages = Page::range(page(USER_PAGE), page(USER_PAGE + 0x3000));
+        space
+            .map_user_range(pages, PageTableFlags::WRITABLE)
+            .expect("Failed to map user range");
+        // A second level 3 table
+        let pages = Page::range(page(2 * USER_PAGE), page(2 * USER_PAGE + 0x1000));
+        space
+            .map_user_range(pages, PageTableFlags::WRITABLE)
+            .expect("Failed to map user range");
+        drop(space);
+
+        // Only the kernel's 4 page tables are still allocated
+        let frames: Vec<PhysFrame> =
+            core::iter::from_fn(|| frame_allocator.allocate_frame()).collect();
+        assert_eq!(frames.len(), 12);
+        assert_eq!(
+            kernel_mapper.translate_addr(VirtAddr::new(KERNEL_PAGE)),
+            Some(PhysAddr::new(KERNEL_FRAME))
+        );
+    }
+}
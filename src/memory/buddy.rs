@@ -0,0 +1,573 @@
+//! A buddy system allocator for physically contiguous runs of frames.
+//!
+//! Memory is handed out in blocks of `2^order` frames that are aligned to
+//! their size, so an order 9 block is a 2 MiB frame that can back a huge page.
+//! Allocating a block splits a larger free block in halves until one has the
+//! requested order, and deallocating it merges it with its buddy, the other
+//! half it was split from, for as long as that is free too.
+//!
+//! The free blocks of each zone and order form a doubly linked list through
+//! their first 16 bytes, like the free list of `BootInfoFrameAllocator`, so
+//! the allocator needs no heap and can back it. A bitmap per order records
+//! which blocks are free, so finding out whether a block's buddy is free and
+//! taking it off its free list are constant time. The bitmaps are stored in
+//! frames that are taken from the usable memory when the allocator is created.
+
+use super::BootInfoFrameAllocator;
+use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use core::ptr;
+use x86_64::{
+    align_down, align_up,
+    structures::paging::{
+        FrameAllocator, FrameDeallocator, PageSize, PhysFrame, Size2MiB, Size4KiB,
+    },
+    PhysAddr, VirtAddr,
+};
+
+/// The order of the largest blocks, which are 4 MiB.
+pub const MAX_ORDER: usize = 10;
+
+const ORDERS: usize = MAX_ORDER + 1;
+const ZONES: usize = 3;
+
+/// Marks the end of a free list, which the block at address 0 may be part of.
+const NO_BLOCK: u64 = u64::MAX;
+
+/// Ranges of physical memory that devices with limited addressing can reach.
+///
+/// Allocating from a zone takes memory from it or, once it is used up, from
+/// the zones below it, so that memory only some devices can use is left for
+/// them as long as possible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Zone {
+    /// Memory below 16 MiB, for legacy ISA DMA.
+    Dma = 0,
+    /// Memory below 4 GiB, for devices with 32-bit DMA addresses.
+    Dma32 = 1,
+    /// All physical memory.
+    Normal = 2,
+}
+
+impl Zone {
+    /// Returns the zone that `address` is in. No block crosses a zone boundary,
+    /// since they are aligned to the size of the largest blocks.
+    fn containing(address: u64) -> Zone {
+        if address < 16 << 20 {
+            Zone::Dma
+        } else if address < 4 << 30 {
+            Zone::Dma32
+        } else {
+            Zone::Normal
+        }
+    }
+}
+
+/// The size in bytes of a block of `order`.
+fn block_size(order: usize) -> u64 {
+    Size4KiB::SIZE << order
+}
+
+/// The order of the blocks that back frames of size `S`.
+fn order_of<S: PageSize>() -> usize {
+    (S::SIZE / Size4KiB::SIZE).trailing_zeros() as usize
+}
+
+/// The size in bytes of the bitmap of `order` for `size` bytes of memory.
+fn bitmap_size(size: u64, order: usize) -> u64 {
+    let blocks = size / block_size(order);
+    blocks.div_ceil(u64::BITS as u64) * 8
+}
+
+/// Returns the start and end addresses of the usable regions of `memory_map`.
+fn usable_ranges(memory_map: &MemoryMap) -> impl Iterator<Item = (u64, u64)> + Clone + '_ {
+    memory_map
+        .iter()
+        .filter(|region| region.region_type == MemoryRegionType::Usable)
+        .map(|region| (region.range.start_addr(), region.range.end_addr()))
+}
+
+/// A physical frame allocator that hands out blocks of `2^order` frames.
+pub struct BuddyAllocator {
+    physical_memory_offset: VirtAddr,
+    /// Start address of the first free block of each zone and order, or
+    /// `NO_BLOCK`.
+    free_lists: [[u64; ORDERS]; ZONES],
+    /// The number of blocks on each free list.
+    free_counts: [[usize; ORDERS]; ZONES],
+    /// Start address of the memory the bitmaps cover, which is aligned to the
+    /// size of the largest blocks.
+    start: u64,
+    /// Physical address of the bitmap of each order, which has a bit for every
+    /// block of that order that is set while the block is free.
+    bitmaps: [u64; ORDERS],
+}
+
+impl BuddyAllocator {
+    /// Create an allocator for the usable regions of the passed memory map.
+    ///
+    /// This function is unsafe because the caller must guarantee that the passed
+    /// memory map is valid, and that all frames that are marked as `USABLE` in it
+    /// are really unused. The complete physical memory must also be mapped to
+    /// virtual memory at the passed `physical_memory_offset`.
+    pub unsafe fn init(memory_map: &MemoryMap, physical_memory_offset: VirtAddr) -> Self {
+        BuddyAllocator::with_free_ranges(
+            memory_map,
+            physical_memory_offset,
+            usable_ranges(memory_map),
+        )
+    }
+
+    /// Create an allocator for the usable regions of `memory_map`, with the
+    /// frames from the start up to the end of each of `free_ranges` as free
+    /// memory. The bitmaps are stored at the end of the last range they fit in.
+    ///
+    /// This function is unsafe for the same reasons as `init`, and because the
+    /// caller must guarantee that `free_ranges` are unused parts of the usable
+    /// regions.
+    unsafe fn with_free_ranges(
+        memory_map: &MemoryMap,
+        physical_memory_offset: VirtAddr,
+        free_ranges: impl Iterator<Item = (u64, u64)> + Clone,
+    ) -> Self {
+        let (start, end) = usable_ranges(memory_map).fold((u64::MAX, 0), |(start, end), range| {
+            (start.min(range.0), end.max(range.1))
+        });
+        let start = align_down(start.min(end), block_size(MAX_ORDER));
+        let end = align_up(end, block_size(MAX_ORDER));
+
+        let size = (0..ORDERS)
+            .map(|order| bitmap_size(end - start, order))
+            .sum();
+        let bitmaps_end = free_ranges
+            .clone()
+            .filter(|(start, end)| end - start >= align_up(size, Size4KiB::SIZE))
+            .last()
+            .map(|(_, end)| end)
+            .expect("No usable memory for the frame allocator's bitmaps");
+        let bitmaps_start = bitmaps_end - align_up(size, Size4KiB::SIZE);
+
+        let mut allocator = BuddyAllocator {
+            physical_memory_offset,
+            free_lists: [[NO_BLOCK; ORDERS]; ZONES],
+            free_counts: [[0; ORDERS]; ZONES],
+            start,
+            bitmaps: [0; ORDERS],
+        };
+        let mut bitmap = bitmaps_start;
+        for order in 0..ORDERS {
+            allocator.bitmaps[order] = bitmap;
+            bitmap += bitmap_size(end - start, order);
+        }
+        ptr::write_bytes(
+            allocator.virtual_address(bitmaps_start).as_mut_ptr::<u8>(),
+            0,
+            size as usize,
+        );
+
+        for (start, end) in free_ranges {
+            let end = if end == bitmaps_end {
+                bitmaps_start
+            } else {
+                end
+            };
+            allocator.add_free_range(start, end);
+        }
+        allocator
+    }
+
+    /// Allocate a block of `2^order` frames, aligned to its size, from `zone`
+    /// or the zones below it. Returns its first frame.
+    pub fn allocate(&mut self, order: usize, zone: Zone) -> Option<PhysFrame> {
+        if order > MAX_ORDER {
+            return None;
+        }
+
+        for zone in (Zone::Dma as usize..=zone as usize).rev() {
+            let block = (order..ORDERS)
+                .find_map(|larger_order| Some((larger_order, self.pop(zone, larger_order)?)));
+            if let Some((larger_order, address)) = block {
+                // Split the block, keeping the lower half and freeing the upper
+                // one, until it has the requested order
+                for split_order in (order..larger_order).rev() {
+                    self.push(zone, split_order, address + block_size(split_order));
+                }
+                return Some(PhysFrame::containing_address(PhysAddr::new(address)));
+            }
+        }
+        None
+    }
+
+    /// Return the block of `2^order` frames starting at `frame` to the
+    /// allocator.
+    ///
+    /// This function is unsafe because the caller must guarantee that the block
+    /// was allocated with the same `order`, and isn't used or deallocated
+    /// anymore.
+    pub unsafe fn deallocate(&mut self, frame: PhysFrame, order: usize) {
+        assert!(order <= MAX_ORDER, "Block order {} is too large", order);
+        self.free_block(frame.start_address().as_u64(), order);
+    }
+
+    /// Returns the number of free blocks of `order` in all zones, which is 0
+    /// for orders above `MAX_ORDER`.
+    pub fn free_blocks(&self, order: usize) -> usize {
+        self.free_counts
+            .iter()
+            .map(|zone| zone.get(order).copied().unwrap_or(0))
+            .sum()
+    }
+
+    /// Returns the number of free blocks of `order` in `zone`, not counting the
+    /// zones below it.
+    pub fn free_blocks_in_zone(&self, zone: Zone, order: usize) -> usize {
+        self.free_counts[zone as usize]
+            .get(order)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of free bytes in all zones.
+    pub fn free_memory(&self) -> u64 {
+        (0..ORDERS)
+            .map(|order| self.free_blocks(order) as u64 * block_size(order))
+            .sum()
+    }
+
+    /// Add the frames from `start` up to `end` as free blocks, using the
+    /// largest ones their alignment allows.
+    fn add_free_range(&mut self, start: u64, end: u64) {
+        let mut address = start;
+        while address < end {
+            let alignment = (address / Size4KiB::SIZE).trailing_zeros() as usize;
+            let mut order = alignment.min(MAX_ORDER);
+            while address + block_size(order) > end {
+                order -= 1;
+            }
+            self.free_block(address, order);
+            address += block_size(order);
+        }
+    }
+
+    /// Add a free block, merging it with its buddy for as long as that is free.
+    fn free_block(&mut self, mut address: u64, mut order: usize) {
+        let zone = Zone::containing(address) as usize;
+        while order < MAX_ORDER {
+            let buddy = address ^ block_size(order);
+            if !self.is_free(order, buddy) {
+                break;
+            }
+            self.remove(zone, order, buddy);
+            address = address.min(buddy);
+            order += 1;
+        }
+        self.push(zone, order, address);
+    }
+
+    /// Put the free block at `address` at the head of its free list.
+    fn push(&mut self, zone: usize, order: usize, address: u64) {
+        let next = self.free_lists[zone][order];
+        // The block is free, so its memory can hold the links
+        unsafe {
+            self.links(address).write([next, NO_BLOCK]);
+            if next != NO_BLOCK {
+                (*self.links(next))[1] = address;
+            }
+        }
+        self.free_lists[zone][order] = address;
+        self.free_counts[zone][order] += 1;
+        self.set_free(order, address, true);
+    }
+
+    /// Take the block at the head of a free list off it.
+    fn pop(&mut self, zone: usize, order: usize) -> Option<u64> {
+        let address = match self.free_lists[zone][order] {
+            NO_BLOCK => return None,
+            address => address,
+        };
+        self.remove(zone, order, address);
+        Some(address)
+    }
+
+    /// Take the free block at `address` off its free list.
+    fn remove(&mut self, zone: usize, order: usize, address: u64) {
+        // Blocks only get on a free list through `push`, which wrote the links
+        unsafe {
+            let [next, previous] = self.links(address).read();
+            match previous {
+                NO_BLOCK => self.free_lists[zone][order] = next,
+                previous => (*self.links(previous))[0] = next,
+            }
+            if next != NO_BLOCK {
+                (*self.links(next))[1] = previous;
+            }
+        }
+        self.free_counts[zone][order] -= 1;
+        self.set_free(order, address, false);
+    }
+
+    /// Returns whether the block of `order` at `address` is on a free list.
+    fn is_free(&self, order: usize, address: u64) -> bool {
+        let (word, bit) = self.bitmap_bit(order, address);
+        unsafe { word.read() & bit != 0 }
+    }
+
+    fn set_free(&mut self, order: usize, address: u64, free: bool) {
+        let (word, bit) = self.bitmap_bit(order, address);
+        unsafe {
+            match free {
+                true => *word |= bit,
+                false => *word &= !bit,
+            }
+        }
+    }
+
+    /// Returns a pointer to the word of the bitmap of `order` that holds the
+    /// bit of the block at `address`, and a mask of that bit.
+    fn bitmap_bit(&self, order: usize, address: u64) -> (*mut u64, u64) {
+        let index = (address - self.start) / block_size(order);
+        let word = self.bitmaps[order] + index / u64::BITS as u64 * 8;
+        (
+            self.virtual_address(word).as_mut_ptr(),
+            1 << (index % u64::BITS as u64),
+        )
+    }
+
+    /// Returns a pointer to the links of the free block at `address`, the
+    /// addresses of the next and the previous block on its free list, which
+    /// are stored at the start of the block.
+    fn links(&self, address: u64) -> *mut [u64; 2] {
+        self.virtual_address(address).as_mut_ptr()
+    }
+
+    fn virtual_address(&self, address: u64) -> VirtAddr {
+        // The offset may have wrapped around, e.g. for the tests' fake memory
+        let offset = self.physical_memory_offset.as_u64();
+        VirtAddr::new(offset.wrapping_add(address))
+    }
+}
+
+impl From<BootInfoFrameAllocator> for BuddyAllocator {
+    /// Take over the frames that `frame_allocator` hasn't handed out, or that
+    /// were deallocated again, e.g. after it was used to map the heap.
+    fn from(mut frame_allocator: BootInfoFrameAllocator) -> Self {
+        let (first_region, next) = (frame_allocator.region, frame_allocator.next);
+        let memory_map = frame_allocator.memory_map;
+        let free_ranges = memory_map
+            .iter()
+            .enumerate()
+            .skip(first_region)
+            .filter(|(_, region)| region.region_type == MemoryRegionType::Usable)
+            .map(move |(index, region)| {
+                let start = if index == first_region {
+                    next.max(region.range.start_addr())
+                } else {
+                    region.range.start_addr()
+                };
+                (start, region.range.end_addr())
+            });
+        // `frame_allocator` was created with the same guarantees as `init` needs
+        let mut allocator = unsafe {
+            BuddyAllocator::with_free_ranges(
+                memory_map,
+                frame_allocator.physical_memory_offset,
+                free_ranges,
+            )
+        };
+
+        // Last, so that the most recently used frames are handed out first
+        while frame_allocator.free_list.is_some() {
+            let frame: PhysFrame = frame_allocator.allocate_frame().unwrap();
+            allocator.free_block(frame.start_address().as_u64(), 0);
+        }
+        allocator
+    }
+}
+
+unsafe impl FrameAllocator<Size4KiB> for BuddyAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame<Size4KiB>> {
+        self.allocate(order_of::<Size4KiB>(), Zone::Normal)
+    }
+}
+
+impl FrameDeallocator<Size4KiB> for BuddyAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame<Size4KiB>) {
+        self.deallocate(frame, order_of::<Size4KiB>());
+    }
+}
+
+unsafe impl FrameAllocator<Size2MiB> for BuddyAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame<Size2MiB>> {
+        let frame = self.allocate(order_of::<Size2MiB>(), Zone::Normal)?;
+        Some(PhysFrame::from_start_address(frame.start_address()).unwrap())
+    }
+}
+
+impl FrameDeallocator<Size2MiB> for BuddyAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame<Size2MiB>) {
+        let frame = PhysFrame::containing_address(frame.start_address());
+        self.deallocate(frame, order_of::<Size2MiB>());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bootloader::bootinfo::{FrameRange, MemoryRegion};
+
+    const MIB: u64 = 1 << 20;
+    const GIB: u64 = 1 << 30;
+    /// The allocators' bitmaps fit in a frame, which is taken from the end of
+    /// the last region.
+    const BITMAPS: u64 = 4096;
+    /// Size of the fake physical memory the tests' free lists and bitmaps are
+    /// stored in.
+    const MEMORY_SIZE: u64 = 8 * MIB + BITMAPS;
+
+    #[repr(align(4096))]
+    struct PhysicalMemory([u8; MEMORY_SIZE as usize]);
+
+    static mut PHYSICAL_MEMORY: PhysicalMemory = PhysicalMemory([0; MEMORY_SIZE as usize]);
+
+    /// Create an allocator for the passed usable regions, which must lie within
+    /// `MEMORY_SIZE` from `base`. The fake physical memory stands in for that
+    /// range.
+    fn allocator(base: u64, regions: &[(u64, u64)]) -> BuddyAllocator {
+        let mut memory_map = MemoryMap::new();
+        for &(start, end) in regions {
+            assert!(start >= base && end <= base + MEMORY_SIZE);
+            memory_map.add_region(MemoryRegion {
+                range: FrameRange::new(start, end),
+                region_type: MemoryRegionType::Usable,
+            });
+        }
+
+        // The offset wraps around if `base` lies above the fake memory
+        let memory = unsafe { ptr::addr_of!(PHYSICAL_MEMORY) } as u64;
+        unsafe { BuddyAllocator::init(&memory_map, VirtAddr::new(memory.wrapping_sub(base))) }
+    }
+
+    fn free_blocks(allocator: &BuddyAllocator) -> [usize; ORDERS] {
+        let mut free_blocks = [0; ORDERS];
+        for (order, count) in free_blocks.iter_mut().enumerate() {
+            *count = allocator.free_blocks(order);
+        }
+        free_blocks
+    }
+
+    #[test_case]
+    fn split_regions_into_aligned_blocks() {
+        let allocator = allocator(MIB, &[(MIB, 9 * MIB + BITMAPS)]);
+        assert_eq!(free_blocks(&allocator), [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1]);
+        assert_eq!(allocator.free_memory(), 8 * MIB);
+    }
+
+    #[test_case]
+    fn split_blocks_and_merge_buddies() {
+        let mut allocator = allocator(0, &[(0, 4 * MIB + BITMAPS)]);
+        let frame = allocator.allocate(0, Zone::Normal).unwrap();
+        assert_eq!(frame.start_address().as_u64(), 0);
+        assert_eq!(free_blocks(&allocator), [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0]);
+
+        let next = allocator.allocate(0, Zone::Normal).unwrap();
+        assert_eq!(next.start_address().as_u64(), 4096);
+
+        unsafe {
+            allocator.deallocate(frame, 0);
+            allocator.deallocate(next, 0);
+        }
+        assert_eq!(free_blocks(&allocator), [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test_case]
+    fn merge_buddy_from_middle_of_free_list() {
+        let mut allocator = allocator(0, &[(0, 4 * MIB + BITMAPS)]);
+        let frames: [PhysFrame; 6] = [(); 6].map(|_| allocator.allocate(0, Zone::Normal).unwrap());
+        let start = |frame: Option<PhysFrame>| frame.map(|frame| frame.start_address().as_u64());
+
+        // The free list of order 0 is frames 4, 2 and 0, so freeing frame 3
+        // takes its buddy, frame 2, from the middle of it
+        unsafe {
+            allocator.deallocate(frames[0], 0);
+            allocator.deallocate(frames[2], 0);
+            allocator.deallocate(frames[4], 0);
+            allocator.deallocate(frames[3], 0);
+        }
+        assert_eq!(allocator.free_blocks(0), 2);
+        assert_eq!(allocator.free_blocks(1), 2);
+        assert_eq!(start(allocator.allocate(0, Zone::Normal)), Some(4 * 4096));
+        assert_eq!(start(allocator.allocate(0, Zone::Normal)), Some(0));
+        assert_eq!(start(allocator.allocate(1, Zone::Normal)), Some(2 * 4096));
+    }
+
+    #[test_case]
+    fn allocate_aligned_huge_frames() {
+        let mut allocator = allocator(0, &[(MIB, 8 * MIB + BITMAPS)]);
+        let first: PhysFrame<Size2MiB> = allocator.allocate_frame().unwrap();
+        let second: PhysFrame<Size2MiB> = allocator.allocate_frame().unwrap();
+        let third: PhysFrame<Size2MiB> = allocator.allocate_frame().unwrap();
+        assert_eq!(first.start_address().as_u64(), 2 * MIB);
+        assert_eq!(second.start_address().as_u64(), 4 * MIB);
+        assert_eq!(third.start_address().as_u64(), 6 * MIB);
+        assert_eq!(
+            FrameAllocator::<Size2MiB>::allocate_frame(&mut allocator),
+            None
+        );
+        assert_eq!(allocator.free_memory(), MIB);
+
+        unsafe { allocator.deallocate_frame(second) };
+        assert_eq!(allocator.free_blocks(order_of::<Size2MiB>()), 1);
+    }
+
+    #[test_case]
+    fn allocate_from_zones() {
+        let mut allocator = allocator(12 * MIB, &[(12 * MIB, 20 * MIB + BITMAPS)]);
+        assert_eq!(allocator.free_blocks_in_zone(Zone::Dma, MAX_ORDER), 1);
+        assert_eq!(allocator.free_blocks_in_zone(Zone::Dma32, MAX_ORDER), 1);
+
+        let start = |frame: Option<PhysFrame>| frame.map(|frame| frame.start_address().as_u64());
+        assert_eq!(
+            start(allocator.allocate(MAX_ORDER, Zone::Normal)),
+            Some(16 * MIB)
+        );
+        assert_eq!(
+            start(allocator.allocate(MAX_ORDER, Zone::Dma32)),
+            Some(12 * MIB)
+        );
+        assert_eq!(start(allocator.allocate(0, Zone::Dma)), None);
+        assert_eq!(start(allocator.allocate(MAX_ORDER + 1, Zone::Normal)), None);
+    }
+
+    #[test_case]
+    fn allocate_from_normal_zone() {
+        let mut allocator = allocator(4 * GIB, &[(4 * GIB, 4 * GIB + 4 * MIB + BITMAPS)]);
+        assert_eq!(allocator.free_blocks_in_zone(Zone::Normal, MAX_ORDER), 1);
+
+        let start = |frame: Option<PhysFrame>| frame.map(|frame| frame.start_address().as_u64());
+        assert_eq!(start(allocator.allocate(0, Zone::Dma32)), None);
+        assert_eq!(start(allocator.allocate(0, Zone::Normal)), Some(4 * GIB));
+    }
+
+    #[test_case]
+    fn count_no_free_blocks_above_max_order() {
+        let allocator = allocator(0, &[(0, 8 * MIB + BITMAPS)]);
+        assert_eq!(allocator.free_blocks(MAX_ORDER), 2);
+        assert_eq!(allocator.free_blocks(MAX_ORDER + 1), 0);
+        assert_eq!(allocator.free_blocks_in_zone(Zone::Dma, MAX_ORDER + 1), 0);
+    }
+
+    #[test_case]
+    fn take_over_frames_of_boot_info_frame_allocator() {
+        let mut frame_allocator =
+            crate::memory::tests::allocator(&[(0, 9, MemoryRegionType::Usable)]);
+        let frames = [
+            frame_allocator.allocate_frame().unwrap(),
+            frame_allocator.allocate_frame().unwrap(),
+            frame_allocator.allocate_frame().unwrap(),
+        ];
+        unsafe { frame_allocator.deallocate_frame(frames[1]) };
+
+        let mut allocator = BuddyAllocator::from(frame_allocator);
+        assert_eq!(free_blocks(&allocator), [2, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(allocator.allocate(0, Zone::Normal), Some(frames[1]));
+    }
+}
@@ -0,0 +1,369 @@
+//! Mapping ranges of memory with the largest pages that fit, which takes
+//! fewer TLB entries and page tables than mapping them with 4 KiB pages.
+
+use core::arch::x86_64::__cpuid;
+use x86_64::{
+    structures::paging::{
+        mapper::MapToError, FrameAllocator, FrameDeallocator, Mapper, Page, PageSize,
+        PageTableFlags, PhysFrame, Size1GiB, Size2MiB, Size4KiB,
+    },
+    PhysAddr, VirtAddr,
+};
+
+/// CPUID leaf with the highest supported extended leaf in `eax`.
+const CPUID_MAX_EXTENDED_LEAF: u32 = 0x8000_0000;
+/// CPUID leaf with the extended processor feature flags.
+const CPUID_EXTENDED_FEATURES: u32 = 0x8000_0001;
+/// The `Page1GB` bit in `edx` of the extended feature flags.
+const PAGE_1GIB_SUPPORT: u32 = 1 << 26;
+
+/// Returns whether the CPU can map 1 GiB pages. 2 MiB pages are always
+/// available in long mode, but 1 GiB pages are optional (QEMU's default CPU
+/// lacks them, for one).
+pub fn supports_1gib_pages() -> bool {
+    let max_extended_leaf = unsafe { __cpuid(CPUID_MAX_EXTENDED_LEAF) }.eax;
+    max_extended_leaf >= CPUID_EXTENDED_FEATURES
+        && unsafe { __cpuid(CPUID_EXTENDED_FEATURES) }.edx & PAGE_1GIB_SUPPORT != 0
+}
+
+/// Map `size` bytes of physical memory at `physical_start` to `start`, e.g. for
+/// a device's registers or a window onto all physical memory.
+///
+/// Uses 1 GiB pages where both addresses are aligned to them, the CPU supports
+/// them and the rest of the range fills one, then 2 MiB pages under the same
+/// conditions, and 4 KiB pages otherwise. `start`, `physical_start` and `size`
+/// are expected to be 4 KiB aligned.
+///
+/// This function is unsafe because the caller must guarantee that mapping the
+/// physical memory doesn't alias memory that is used in ways that the new
+/// mapping could break, e.g. frames owned by a frame allocator.
+pub unsafe fn map_physical_range<M, A>(
+    mapper: &mut M,
+    start: VirtAddr,
+    physical_start: PhysAddr,
+    size: u64,
+    flags: PageTableFlags,
+    frame_allocator: &mut A,
+) -> Result<(), MapToError<Size4KiB>>
+where
+    M: Mapper<Size4KiB> + Mapper<Size2MiB> + Mapper<Size1GiB>,
+    A: FrameAllocator<Size4KiB>,
+{
+    let mut offset = 0;
+    while offset < size {
+        let address = start + offset;
+        let physical_address = physical_start + offset;
+        let fits = |page_size| {
+            address.is_aligned(page_size)
+                && physical_address.is_aligned(page_size)
+                && size - offset >= page_size
+        };
+
+        offset += if fits(Size1GiB::SIZE) && supports_1gib_pages() {
+            map_page::<Size1GiB, _, _>(mapper, address, physical_address, flags, frame_allocator)?
+        } else if fits(Size2MiB::SIZE) {
+            map_page::<Size2MiB, _, _>(mapper, address, physical_address, flags, frame_allocator)?
+        } else {
+            map_page::<Size4KiB, _, _>(mapper, address, physical_address, flags, frame_allocator)?
+        };
+    }
+    Ok(())
+}
+
+/// Map the pages from `start` up to `start + size` to newly allocated frames,
+/// e.g. for the heap.
+///
+/// Uses 2 MiB pages where `start` is aligned to them, the rest of the range fills
+/// one and the frame allocator has a 2 MiB frame left, and 4 KiB pages otherwise.
+/// The frames are not zeroed.
+///
+/// Returns the number of bytes mapped from `start`, which is less than `size`
+/// if the frame allocator ran out. If mapping a page fails otherwise, its frame
+/// is deallocated again, while the pages mapped before it stay mapped.
+pub fn map_allocated_range<M, A>(
+    mapper: &mut M,
+    start: VirtAddr,
+    size: u64,
+    flags: PageTableFlags,
+    frame_allocator: &mut A,
+) -> Result<u64, MapToError<Size4KiB>>
+where
+    M: Mapper<Size4KiB> + Mapper<Size2MiB>,
+    A: FrameAllocator<Size4KiB>
+        + FrameAllocator<Size2MiB>
+        + FrameDeallocator<Size4KiB>
+        + FrameDeallocator<Size2MiB>,
+{
+    let end = (start + size).align_up(Size4KiB::SIZE);
+    let mut address = start.align_down(Size4KiB::SIZE);
+    while address < end {
+        let mapped = if address.is_aligned(Size2MiB::SIZE) && end - address >= Size2MiB::SIZE {
+            match FrameAllocator::<Size2MiB>::allocate_frame(frame_allocator) {
+                Some(frame) => map_allocated_page(mapper, address, frame, flags, frame_allocator),
+                None => map_allocated_4kib_page(mapper, address, flags, frame_allocator),
+            }
+        } else {
+            map_allocated_4kib_page(mapper, address, flags, frame_allocator)
+        };
+
+        match mapped {
+            Ok(page_size) => address += page_size,
+            Err(MapToError::FrameAllocationFailed) => break,
+            Err(error) => return Err(error),
+        }
+    }
+    // The pages can start before `start` and end after `start + size`
+    Ok(address.as_u64().saturating_sub(start.as_u64()).min(size))
+}
+
+/// Map the 4 KiB page at `address` to a newly allocated frame.
+fn map_allocated_4kib_page<M, A>(
+    mapper: &mut M,
+    address: VirtAddr,
+    flags: PageTableFlags,
+    frame_allocator: &mut A,
+) -> Result<u64, MapToError<Size4KiB>>
+where
+    M: Mapper<Size4KiB>,
+    A: FrameAllocator<Size4KiB> + FrameDeallocator<Size4KiB>,
+{
+    let frame: PhysFrame<Size4KiB> = frame_allocator
+        .allocate_frame()
+        .ok_or(MapToError::FrameAllocationFailed)?;
+    map_allocated_page(mapper, address, frame, flags, frame_allocator)
+}
+
+/// Map the page of size `S` at `address` to `frame`, which was just allocated
+/// from `frame_allocator`, or give the frame back if that fails. Returns the
+/// size of the page.
+fn map_allocated_page<S, M, A>(
+    mapper: &mut M,
+    address: VirtAddr,
+    frame: PhysFrame<S>,
+    flags: PageTableFlags,
+    frame_allocator: &mut A,
+) -> Result<u64, MapToError<Size4KiB>>
+where
+    S: PageSize,
+    M: Mapper<S>,
+    A: FrameAllocator<Size4KiB> + FrameDeallocator<S>,
+{
+    // The frame was just allocated, so nothing else can use it
+    let result = unsafe {
+        map_page(
+            mapper,
+            address,
+            frame.start_address(),
+            flags,
+            frame_allocator,
+        )
+    };
+    if result.is_err() {
+        // The frame isn't mapped, since mapping it failed
+        unsafe { frame_allocator.deallocate_frame(frame) };
+    }
+    result
+}
+
+/// Map the page of size `S` at `address` to the frame at `physical_address`.
+/// Returns the size of the page.
+///
+/// This function is unsafe for the same reasons as `Mapper::map_to`.
+unsafe fn map_page<S, M, A>(
+    mapper: &mut M,
+    address: VirtAddr,
+    physical_address: PhysAddr,
+    flags: PageTableFlags,
+    frame_allocator: &mut A,
+) -> Result<u64, MapToError<Size4KiB>>
+where
+    S: PageSize,
+    M: Mapper<S>,
+    A: FrameAllocator<Size4KiB>,
+{
+    let page = Page::<S>::containing_address(address);
+    let frame = PhysFrame::<S>::containing_address(physical_address);
+    match mapper.map_to(page, frame, flags, frame_allocator) {
+        Ok(flush) => flush.flush(),
+        Err(MapToError::FrameAllocationFailed) => return Err(MapToError::FrameAllocationFailed),
+        Err(MapToError::ParentEntryHugePage) => return Err(MapToError::ParentEntryHugePage),
+        Err(MapToError::PageAlreadyMapped(frame)) => {
+            let frame = PhysFrame::containing_address(frame.start_address());
+            return Err(MapToError::PageAlreadyMapped(frame));
+        }
+    }
+    Ok(S::SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{
+        tests::{allocator, page_table},
+        BootInfoFrameAllocator,
+    };
+    use bootloader::bootinfo::MemoryRegionType;
+    use x86_64::structures::paging::mapper::{Translate, TranslateResult};
+
+    const MIB: u64 = 1 << 20;
+    const GIB: u64 = 1 << 30;
+
+    /// Returns the size and start address of the frame that `address` is
+    /// mapped to, if any.
+    fn mapped_frame(mapper: &impl Translate, address: u64) -> Option<(u64, u64)> {
+        match mapper.translate(VirtAddr::new(address)) {
+            TranslateResult::Mapped { frame, .. } => {
+                Some((frame.size(), frame.start_address().as_u64()))
+            }
+            _ => None,
+        }
+    }
+
+    #[test_case]
+    fn map_physical_range_with_largest_pages() {
+        let mut frame_allocator = allocator(&[(0, 16, MemoryRegionType::Usable)]);
+        let mut mapper = page_table(&mut frame_allocator);
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+        let start = 5 * GIB;
+        let size = GIB + 2 * MIB + 4096;
+        unsafe {
+            map_physical_range(
+                &mut mapper,
+                VirtAddr::new(start),
+                PhysAddr::new(GIB),
+                size,
+                flags,
+                &mut frame_allocator,
+            )
+            .unwrap();
+        }
+
+        let mapped = |address| mapped_frame(&mapper, address);
+        let first_page_size = match supports_1gib_pages() {
+            true => Size1GiB::SIZE,
+            false => Size2MiB::SIZE,
+        };
+        assert_eq!(mapped(start), Some((first_page_size, GIB)));
+        assert_eq!(
+            mapped(start + GIB - 2 * MIB),
+            Some((first_page_size, 2 * GIB - first_page_size))
+        );
+        assert_eq!(mapped(start + GIB), Some((Size2MiB::SIZE, 2 * GIB)));
+        assert_eq!(
+            mapped(start + GIB + 2 * MIB),
+            Some((Size4KiB::SIZE, 2 * GIB + 2 * MIB))
+        );
+        assert_eq!(mapped(start + size), None);
+    }
+
+    /// Hands out the frames of a `BootInfoFrameAllocator`, which can't take
+    /// back 2 MiB frames, and counts the ones it gets back instead.
+    struct CountingAllocator {
+        frames: BootInfoFrameAllocator,
+        deallocated_huge_frames: usize,
+    }
+
+    unsafe impl FrameAllocator<Size4KiB> for CountingAllocator {
+        fn allocate_frame(&mut self) -> Option<PhysFrame<Size4KiB>> {
+            self.frames.allocate_frame()
+        }
+    }
+
+    unsafe impl FrameAllocator<Size2MiB> for CountingAllocator {
+        fn allocate_frame(&mut self) -> Option<PhysFrame<Size2MiB>> {
+            self.frames.allocate_frame()
+        }
+    }
+
+    impl FrameDeallocator<Size4KiB> for CountingAllocator {
+        unsafe fn deallocate_frame(&mut self, frame: PhysFrame<Size4KiB>) {
+            self.frames.deallocate_frame(frame)
+        }
+    }
+
+    impl FrameDeallocator<Size2MiB> for CountingAllocator {
+        unsafe fn deallocate_frame(&mut self, _frame: PhysFrame<Size2MiB>) {
+            self.deallocated_huge_frames += 1;
+        }
+    }
+
+    fn counting_allocator() -> CountingAllocator {
+        CountingAllocator {
+            frames: allocator(&[
+                (0, 16, MemoryRegionType::Usable),
+                (512, 1024, MemoryRegionType::Usable),
+            ]),
+            deallocated_huge_frames: 0,
+        }
+    }
+
+    #[test_case]
+    fn map_allocated_range_with_huge_pages() {
+        let mut frame_allocator = counting_allocator();
+        let mut mapper = page_table(&mut frame_allocator.frames);
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+        let start = 0x_4444_4440_0000 - 4096;
+        let size = 2 * MIB + 2 * 4096;
+        let mapped = map_allocated_range(
+            &mut mapper,
+            VirtAddr::new(start),
+            size,
+            flags,
+            &mut frame_allocator,
+        )
+        .unwrap();
+        assert_eq!(mapped, size);
+
+        let page_size = |address| mapped_frame(&mapper, address).map(|(size, _)| size);
+        assert_eq!(page_size(start), Some(Size4KiB::SIZE));
+        assert_eq!(
+            mapped_frame(&mapper, start + 4096),
+            Some((Size2MiB::SIZE, 2 * MIB))
+        );
+        assert_eq!(page_size(start + 4096 + 2 * MIB), Some(Size4KiB::SIZE));
+        assert_eq!(page_size(start + size), None);
+
+        // Without another 2 MiB frame, 4 KiB frames are used until they run out
+        let start = 0x_4444_4480_0000;
+        let mapped = map_allocated_range(
+            &mut mapper,
+            VirtAddr::new(start),
+            2 * MIB,
+            flags,
+            &mut frame_allocator,
+        )
+        .unwrap();
+        assert!(mapped > 0 && mapped < 2 * MIB);
+        let (page_size, _) = mapped_frame(&mapper, start).unwrap();
+        assert_eq!(page_size, Size4KiB::SIZE);
+        assert!(mapped_frame(&mapper, start + mapped - 4096).is_some());
+        assert_eq!(mapped_frame(&mapper, start + mapped), None);
+    }
+
+    #[test_case]
+    fn deallocate_frame_that_failed_to_map() {
+        let mut frame_allocator = counting_allocator();
+        let mut mapper = page_table(&mut frame_allocator.frames);
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+        // A 4 KiB page in the way of the 2 MiB page
+        let start = 0x_4444_4440_0000;
+        map_allocated_range(
+            &mut mapper,
+            VirtAddr::new(start + 4096),
+            4096,
+            flags,
+            &mut frame_allocator,
+        )
+        .unwrap();
+
+        let result = map_allocated_range(
+            &mut mapper,
+            VirtAddr::new(start),
+            2 * MIB,
+            flags,
+            &mut frame_allocator,
+        );
+        assert!(matches!(result, Err(MapToError::PageAlreadyMapped(_))));
+        assert_eq!(frame_allocator.deallocated_huge_frames, 1);
+    }
+}
@@ -0,0 +1,49 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::instructions::random::RdRand;
+
+/// Number of times to retry RDRAND before giving up on it. Intel recommends
+/// 10 retries, after which the hardware can be assumed to be broken.
+const RDRAND_RETRIES: usize = 10;
+
+/// State of the fallback generator. Advanced on every fallback so that two
+/// calls made within the same timestamp counter tick still differ.
+static FALLBACK_STATE: AtomicU64 = AtomicU64::new(0x9E37_79B9_7F4A_7C15);
+
+/// Returns a random u64 from the RDRAND instruction.
+///
+/// Falls back to mixing the timestamp counter into a counter-based generator
+/// if the CPU doesn't support RDRAND (like QEMU's default CPU) or it keeps
+/// failing. The fallback is not suitable for cryptography, but it's good
+/// enough to make addresses hard to guess.
+pub fn random_u64() -> u64 {
+    if let Some(rdrand) = RdRand::new() {
+        for _ in 0..RDRAND_RETRIES {
+            if let Some(value) = rdrand.get_u64() {
+                return value;
+            }
+        }
+    }
+
+    let timestamp = unsafe { core::arch::x86_64::_rdtsc() };
+    let state = FALLBACK_STATE.fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed);
+    split_mix_64(state ^ timestamp)
+}
+
+/// The SplitMix64 finalizer, which spreads every input bit over the output.
+fn split_mix_64(value: u64) -> u64 {
+    let mut value = value;
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn random_values_differ() {
+        let first = random_u64();
+        assert!((0..8).any(|_| random_u64() != first));
+    }
+}
@@ -1,3 +1,4 @@
+use core::fmt;
 use lazy_static::lazy_static;
 use spin::Mutex;
 use uart_16550::SerialPort;
@@ -39,3 +40,28 @@ macro_rules! serial_println {
     ($fmt:expr, $($arg:tt)*) => ($crate::serial_print!(
         concat!($fmt, "\n"), $($arg)*));
 }
+
+/// Writes `bytes` to the host as hex lines between `-----BEGIN <label>-----`
+/// and `-----END <label>-----` markers, so that binary dumps such as core
+/// files can share the serial stream with text output. The host can recover
+/// them from the QEMU serial log with
+/// `sed -n '/^-----BEGIN core/,/^-----END core/{//!p}' serial.log | xxd -r -p > core`.
+pub fn write_dump(label: &str, bytes: &[u8]) {
+    use x86_64::instructions::interrupts;
+
+    interrupts::without_interrupts(|| {
+        format_dump(&mut *SERIAL1.lock(), label, bytes).expect("Printing to serial failed");
+    });
+}
+
+/// Writes `bytes` to `writer` as `write_dump` writes them to the host.
+pub fn format_dump(writer: &mut impl fmt::Write, label: &str, bytes: &[u8]) -> fmt::Result {
+    writeln!(writer, "-----BEGIN {}-----", label)?;
+    for line in bytes.chunks(32) {
+        for byte in line {
+            write!(writer, "{:02x}", byte)?;
+        }
+        writeln!(writer)?;
+    }
+    writeln!(writer, "-----END {}-----", label)
+}
@@ -0,0 +1,154 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(my_rust_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use bootloader::{entry_point, BootInfo};
+use core::panic::PanicInfo;
+use my_rust_os::memory::{
+    self,
+    address_space::{AddressSpace, AddressSpaceError},
+    BootInfoFrameAllocator, GlobalFrameAllocator,
+};
+use spin::Mutex;
+use x86_64::{
+    registers::control::Cr3,
+    structures::paging::{page::PageRange, OffsetPageTable, Page, PageTableFlags, Translate},
+    VirtAddr,
+};
+
+static MEMORY: Mutex<Option<OffsetPageTable<'static>>> = Mutex::new(None);
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use my_rust_os::allocator;
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mem_mapper = unsafe { memory::init(phys_mem_offset) };
+    let frame_allocator =
+        unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map, phys_mem_offset) };
+    memory::init_frame_allocator(frame_allocator);
+    allocator::init_heap(&mut mem_mapper).expect("Heap initialization failed");
+    *MEMORY.lock() = Some(mem_mapper);
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    my_rust_os::test_panic_handler(&info)
+}
+
+fn new_address_space() -> AddressSpace {
+    let mut memory_state = MEMORY.lock();
+    let mapper = memory_state.as_mut().unwrap();
+    AddressSpace::new(mapper).expect("Failed to create address space")
+}
+
+fn kernel_translate(address: VirtAddr) -> Option<u64> {
+    let memory_state = MEMORY.lock();
+    let mapper = memory_state.as_ref().unwrap();
+    mapper.translate_addr(address).map(|phys| phys.as_u64())
+}
+
+/// Returns `count` pages at the start of the first level 4 entry that the
+/// kernel doesn't use.
+fn user_pages(space: &AddressSpace, count: u64) -> PageRange {
+    let start = (1..512)
+        .map(|index| Page::containing_address(VirtAddr::new(index << 39)))
+        .find(|&page| space.is_user_page(page))
+        .expect("The kernel uses every level 4 entry");
+    Page::range(start, start + count)
+}
+
+#[test_case]
+fn activate_address_space() {
+    let mut space = new_address_space();
+    let pages = user_pages(&space, 2);
+    space
+        .map_user_range(pages, PageTableFlags::WRITABLE)
+        .expect("Failed to map user range");
+    let address = (pages.start + 1).start_address();
+    let kernel_value = Box::new(42u64);
+    assert!(!space.is_active());
+
+    unsafe { space.activate() };
+    assert!(space.is_active());
+    let value = address.as_mut_ptr::<u64>();
+    // The pages are zeroed, and the kernel's memory is still there
+    assert_eq!(0, unsafe { *value });
+    unsafe { *value = 0x1234 };
+    assert_eq!(42, *kernel_value);
+    space.deactivate();
+
+    assert!(!space.is_active());
+    assert_eq!(None, kernel_translate(address));
+    assert!(space.translate_addr(address).is_some());
+
+    unsafe { space.activate() };
+    assert_eq!(0x1234, unsafe { *value });
+    // Dropping an active address space switches back to the kernel's table
+    let level_4_frame = space.level_4_frame();
+    drop(space);
+    assert_ne!(level_4_frame, Cr3::read().0);
+}
+
+#[test_case]
+fn address_spaces_are_isolated() {
+    let mut first = new_address_space();
+    let mut second = new_address_space();
+    let pages = user_pages(&first, 1);
+    let value = pages.start.start_address().as_mut_ptr::<u64>();
+    for (space, written) in [(&mut first, 1), (&mut second, 2)] {
+        space
+            .map_user_range(pages, PageTableFlags::WRITABLE)
+            .expect("Failed to map user range");
+        unsafe {
+            space.activate();
+            *value = written;
+        }
+        space.deactivate();
+    }
+
+    for (space, written) in [(&first, 1), (&second, 2)] {
+        unsafe { space.activate() };
+        assert_eq!(written, unsafe { *value });
+        space.deactivate();
+    }
+}
+
+#[test_case]
+fn reject_kernel_pages() {
+    let mut space = new_address_space();
+    let kernel_page = Page::containing_address(VirtAddr::from_ptr(&MEMORY));
+    assert!(!space.is_user_page(kernel_page));
+    assert!(matches!(
+        space.map_user_range(Page::range(kernel_page, kernel_page + 1), PageTableFlags::WRITABLE),
+        Err(AddressSpaceError::KernelPage(page)) if page == kernel_page
+    ));
+    assert!(matches!(
+        space.unmap_user_range(Page::range(kernel_page, kernel_page + 1)),
+        Err(AddressSpaceError::KernelPage(page)) if page == kernel_page
+    ));
+}
+
+#[test_case]
+fn drop_deallocates_frames() {
+    let free_memory = GlobalFrameAllocator::free_memory();
+    let mut space = new_address_space();
+    let pages = user_pages(&space, 4);
+    space
+        .map_user_range(pages, PageTableFlags::WRITABLE)
+        .expect("Failed to map user range");
+    // The level 4 table, 3 more page tables and the 4 pages
+    assert_eq!(free_memory - 8 * 4096, GlobalFrameAllocator::free_memory());
+
+    drop(space);
+    assert_eq!(free_memory, GlobalFrameAllocator::free_memory());
+}
@@ -0,0 +1,602 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(my_rust_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+use bootloader::{entry_point, BootInfo};
+use core::panic::PanicInfo;
+use my_rust_os::elf::{
+    core_dump::{self, CrashedProcess, Registers, Signal, UserProcess, NT_PRSTATUS},
+    elf64::Elf64File,
+    loader::{self, LoadError, LoadedImage},
+    notes::X86IsaLevels,
+    relocate::RelocationError,
+    validate::Finding,
+    ElfType, ProgramHeaderFlags, ProgramSegmentType,
+};
+use my_rust_os::memory::{self, BootInfoFrameAllocator, GlobalFrameAllocator};
+use my_rust_os::serial;
+use spin::Mutex;
+use x86_64::{
+    registers::model_specific::FsBase,
+    structures::paging::{mapper::TranslateResult, OffsetPageTable, PageTableFlags, Translate},
+    VirtAddr,
+};
+
+static MEMORY: Mutex<Option<(OffsetPageTable<'static>, GlobalFrameAllocator)>> = Mutex::new(None);
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use my_rust_os::allocator;
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mem_mapper = unsafe { memory::init(phys_mem_offset) };
+    let frame_allocator =
+        unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map, phys_mem_offset) };
+    memory::init_frame_allocator(frame_allocator);
+    allocator::init_heap(&mut mem_mapper).expect("Heap initialization failed");
+    *MEMORY.lock() = Some((mem_mapper, GlobalFrameAllocator));
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    my_rust_os::test_panic_handler(&info)
+}
+
+fn load(file_bytes: &[u8]) -> Result<LoadedImage, LoadError> {
+    let elf_file = Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+    let mut memory_state = MEMORY.lock();
+    let (mapper, frame_allocator) = memory_state.as_mut().unwrap();
+    loader::load(&elf_file, mapper, frame_allocator)
+}
+
+/// Read a byte of the image through its own page table.
+fn read_image_byte(image: &LoadedImage, address: u64) -> Option<u8> {
+    let mut memory_state = MEMORY.lock();
+    let (mapper, _) = memory_state.as_mut().unwrap();
+    let phys_offset = mapper.phys_offset();
+    let image_mapper = unsafe {
+        OffsetPageTable::new(
+            memory::page_table_at(image.level_4_frame(), phys_offset),
+            phys_offset,
+        )
+    };
+
+    image_mapper
+        .translate_addr(VirtAddr::new(address))
+        .map(|phys| unsafe { *(phys_offset + phys.as_u64()).as_ptr::<u8>() })
+}
+
+/// Returns the flags the page containing `address` is mapped with in the
+/// image's own page table.
+fn image_page_flags(image: &LoadedImage, address: u64) -> Option<PageTableFlags> {
+    let mut memory_state = MEMORY.lock();
+    let (mapper, _) = memory_state.as_mut().unwrap();
+    let phys_offset = mapper.phys_offset();
+    let image_mapper = unsafe {
+        OffsetPageTable::new(
+            memory::page_table_at(image.level_4_frame(), phys_offset),
+            phys_offset,
+        )
+    };
+
+    match image_mapper.translate(VirtAddr::new(address)) {
+        TranslateResult::Mapped { flags, .. } => Some(flags),
+        _ => None,
+    }
+}
+
+/// Read a little endian u64 of the image through its own page table.
+fn read_image_u64(image: &LoadedImage, address: u64) -> Option<u64> {
+    let mut bytes = [0; 8];
+    for (offset, byte) in bytes.iter_mut().enumerate() {
+        *byte = read_image_byte(image, address + offset as u64)?;
+    }
+    Some(u64::from_le_bytes(bytes))
+}
+
+fn relocate(
+    image: &LoadedImage,
+    file_bytes: &[u8],
+    resolve_symbol: impl FnMut(&str) -> Option<u64>,
+) -> Result<(), LoadError> {
+    let elf_file = Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+    let phys_offset = MEMORY.lock().as_ref().unwrap().0.phys_offset();
+    loader::relocate(image, &elf_file, phys_offset, resolve_symbol)
+}
+
+#[test_case]
+fn reject_relocatable_file() {
+    let file_bytes = include_bytes!("../elf/src/test_files/main_ret0.elf64");
+    assert!(matches!(load(file_bytes), Err(LoadError::NotExecutable)));
+}
+
+#[test_case]
+fn load_hello_world_segments() {
+    let file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64");
+    let image = load(file_bytes).expect("Failed to load hello_world");
+
+    assert_eq!(VirtAddr::new(0x401040), image.entry_point());
+
+    let user_read_only =
+        PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE | PageTableFlags::NO_EXECUTE;
+    let expected = [
+        (0x400000, 0x400000, user_read_only),
+        (
+            0x401000,
+            0x401000,
+            PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE,
+        ),
+        (0x402000, 0x402000, user_read_only),
+        (
+            0x403000,
+            0x404000,
+            user_read_only | PageTableFlags::WRITABLE,
+        ),
+    ];
+    assert_eq!(expected.len(), image.segments().len());
+
+    for ((start, end, flags), segment) in expected.iter().zip(image.segments()) {
+        assert_eq!(VirtAddr::new(*start), segment.pages().start.start_address());
+        assert_eq!(VirtAddr::new(*end), segment.pages().end.start_address());
+        assert_eq!(*flags, segment.flags());
+    }
+}
+
+#[test_case]
+fn load_hello_world_contents() {
+    let file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64");
+    let image = load(file_bytes).expect("Failed to load hello_world");
+
+    // The ELF header is part of the first Load segment
+    for (offset, byte) in file_bytes[..0x40].iter().enumerate() {
+        assert_eq!(
+            Some(*byte),
+            read_image_byte(&image, 0x400000 + offset as u64)
+        );
+    }
+
+    // First bytes of _start in .text
+    for (offset, byte) in file_bytes[0x1040..0x1050].iter().enumerate() {
+        assert_eq!(
+            Some(*byte),
+            read_image_byte(&image, 0x401040 + offset as u64)
+        );
+    }
+
+    // .data is the last initialized part of the writable segment, and .bss
+    // follows it with zeroes up to p_memsz and the rest of the page
+    for (offset, byte) in file_bytes[0x3020..0x3030].iter().enumerate() {
+        assert_eq!(
+            Some(*byte),
+            read_image_byte(&image, 0x404020 + offset as u64)
+        );
+    }
+    for address in 0x404030..0x405000 {
+        assert_eq!(Some(0), read_image_byte(&image, address));
+    }
+    assert_eq!(None, read_image_byte(&image, 0x405000));
+}
+
+#[test_case]
+fn load_hello_world_stack() {
+    let file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64");
+    let image = load(file_bytes).expect("Failed to load hello_world");
+
+    let expected_flags = PageTableFlags::PRESENT
+        | PageTableFlags::USER_ACCESSIBLE
+        | PageTableFlags::WRITABLE
+        | PageTableFlags::NO_EXECUTE;
+    assert_eq!(VirtAddr::new(0x7FFF_FFFF_F000), image.stack_top());
+    assert_eq!(expected_flags, image.stack().flags());
+    assert_eq!(
+        Some(expected_flags),
+        image_page_flags(&image, 0x7FFF_FFFF_EFF8)
+    );
+    assert_eq!(Some(0), read_image_byte(&image, 0x7FFF_FFFF_EFFF));
+    assert_eq!(None, read_image_byte(&image, 0x7FFF_FFFF_F000));
+}
+
+#[test_case]
+fn load_executable_stack_on_request() {
+    // Set PF_X in the flags of the GnuStack program header, the 10th
+    let mut file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64").to_vec();
+    file_bytes[64 + 9 * 56 + 4] |= 1;
+    let image = load(&file_bytes).expect("Failed to load hello_world");
+
+    assert!(!image.stack().flags().contains(PageTableFlags::NO_EXECUTE));
+}
+
+#[test_case]
+fn protect_hello_world_relro() {
+    let file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64");
+    let image = load(file_bytes).expect("Failed to load hello_world");
+
+    // RELRO covers 0x403E00 to 0x404000, which ends on a page boundary
+    let relro_pages = image.relro_pages().expect("Expected RELRO pages");
+    assert_eq!(VirtAddr::new(0x403000), relro_pages.start.start_address());
+    assert_eq!(VirtAddr::new(0x404000), relro_pages.end.start_address());
+    assert_eq!(
+        Some(
+            PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE | PageTableFlags::NO_EXECUTE
+        ),
+        image_page_flags(&image, 0x403E00)
+    );
+    assert!(image_page_flags(&image, 0x404000)
+        .unwrap()
+        .contains(PageTableFlags::WRITABLE));
+}
+
+#[test_case]
+fn load_does_not_modify_kernel_page_table() {
+    let file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64");
+    let image = load(file_bytes).expect("Failed to load hello_world");
+
+    let memory_state = MEMORY.lock();
+    let (mapper, _) = memory_state.as_ref().unwrap();
+    for segment in image.segments() {
+        for page in segment.pages() {
+            assert_eq!(None, mapper.translate_addr(page.start_address()));
+        }
+    }
+}
+
+#[test_case]
+fn relocate_hello_world() {
+    let file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64");
+    let image = load(file_bytes).expect("Failed to load hello_world");
+
+    relocate(&image, file_bytes, |name| match name {
+        "puts" => Some(0x7000_1000),
+        "__libc_start_main" => Some(0x7000_2000),
+        _ => None,
+    })
+    .expect("Failed to relocate hello_world");
+
+    // GOT entries of .rela.dyn, the last of which is for the weak
+    // __gmon_start__ that nothing defines
+    assert_eq!(Some(0x7000_2000), read_image_u64(&image, 0x403FF0));
+    assert_eq!(Some(0), read_image_u64(&image, 0x403FF8));
+    // PLT GOT entry of .rela.plt
+    assert_eq!(Some(0x7000_1000), read_image_u64(&image, 0x404018));
+}
+
+#[test_case]
+fn relocate_hello_world_unresolved_symbol() {
+    let file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64");
+    let image = load(file_bytes).expect("Failed to load hello_world");
+
+    assert!(matches!(
+        relocate(&image, file_bytes, |_| None),
+        Err(LoadError::Relocation(RelocationError::UnresolvedSymbol(2)))
+    ));
+}
+
+#[test_case]
+fn load_static_pie_at_random_base() {
+    let file_bytes = include_bytes!("../elf/src/test_files/static_pie.elf64");
+    let image = load(file_bytes).expect("Failed to load static_pie");
+
+    let base = image.base();
+    assert_ne!(0, base);
+    assert_eq!(0, base % 0x1000);
+    assert_eq!(VirtAddr::new(base + 0x1000), image.entry_point());
+    assert_eq!(4, image.segments().len());
+    assert_eq!(
+        VirtAddr::new(base),
+        image.segments()[0].pages().start.start_address()
+    );
+
+    // `pointer` in .data was relocated to point at `value`
+    assert_eq!(Some(42), read_image_u64(&image, base + 0x4000));
+    assert_eq!(Some(base + 0x4000), read_image_u64(&image, base + 0x4008));
+}
+
+#[test_case]
+fn load_static_pie_at_different_bases() {
+    let file_bytes = include_bytes!("../elf/src/test_files/static_pie.elf64");
+    let first = load(file_bytes).expect("Failed to load static_pie");
+
+    assert!((0..4).any(|_| load(file_bytes).unwrap().base() != first.base()));
+}
+
+#[test_case]
+fn reject_writable_and_executable_segment() {
+    // Make the text segment, the 4th program header, writable
+    let mut file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64").to_vec();
+    file_bytes[64 + 3 * 56 + 4] |= 2;
+    assert!(matches!(
+        load(&file_bytes),
+        Err(LoadError::Invalid(Finding::WritableAndExecutable(3)))
+    ));
+}
+
+#[test_case]
+fn reject_executable_and_writable_segments_sharing_a_page() {
+    // Move the text segment, the 4th program header, and the entry point onto
+    // the page where the data segment at 0x403E00 starts
+    let mut file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64").to_vec();
+    file_bytes[64 + 3 * 56 + 16..][..8].copy_from_slice(&0x403000u64.to_le_bytes());
+    file_bytes[24..32].copy_from_slice(&0x403040u64.to_le_bytes());
+    assert!(matches!(
+        load(&file_bytes),
+        Err(LoadError::Invalid(Finding::ConflictingPagePermissions(
+            3, 5
+        )))
+    ));
+}
+
+#[test_case]
+fn reject_dynamically_linked_shared_file() {
+    // hello_world has an interpreter, so as a Shared file it needs a dynamic
+    // linker
+    let mut file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64").to_vec();
+    file_bytes[16] = 3;
+    assert!(matches!(
+        load(&file_bytes),
+        Err(LoadError::InterpreterRequired)
+    ));
+}
+
+#[test_case]
+fn reject_file_needing_unsupported_isa_level() {
+    let file_bytes = include_bytes!("../elf/src/test_files/static_pie_v3.elf64");
+    assert!(matches!(
+        load(file_bytes),
+        Err(LoadError::UnsupportedIsaLevels(levels)) if levels == X86IsaLevels::V3
+    ));
+}
+
+#[test_case]
+fn reject_segments_outside_user_ranges() {
+    // Move the read-only data segment, the 5th program header
+    let load_at = |address: u64, memory_size: u64| {
+        let mut file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64").to_vec();
+        let header = 64 + 4 * 56;
+        file_bytes[header + 16..][..8].copy_from_slice(&address.to_le_bytes());
+        file_bytes[header + 40..][..8].copy_from_slice(&memory_size.to_le_bytes());
+        load(&file_bytes)
+    };
+
+    // The kernel heap, the module area, the stack and the upper half
+    for address in [
+        0x4444_4444_2000,
+        0x4000_2000,
+        0x7FFF_FFFF_E000,
+        0xFFFF_8000_0000_2000,
+    ] {
+        assert!(matches!(
+            load_at(address, 0x150),
+            Err(LoadError::ReservedAddress(reserved)) if reserved == address
+        ));
+    }
+    // Segments ending right below the module area are fine
+    assert!(load_at(0x3FFF_E000, 0x2000).is_ok());
+    assert!(matches!(
+        load_at(0x3FFF_E000, 0x2001),
+        Err(LoadError::ReservedAddress(0x3FFF_E000))
+    ));
+    assert!(matches!(
+        load_at(0xFFFF_FFFF_FFFF_F000, 0x2000),
+        Err(LoadError::InvalidSegmentAddress(0xFFFF_FFFF_FFFF_F000))
+    ));
+}
+
+#[test_case]
+fn unload_deallocates_frames() {
+    let file_bytes = include_bytes!("../elf/src/test_files/tls.elf64");
+    let free_memory = GlobalFrameAllocator::free_memory();
+    let mut image = load(file_bytes).expect("Failed to load tls");
+
+    let mut memory_state = MEMORY.lock();
+    let (mapper, frame_allocator) = memory_state.as_mut().unwrap();
+    loader::allocate_tls_block(&mut image, mapper, frame_allocator)
+        .expect("Failed to allocate TLS block");
+    assert!(GlobalFrameAllocator::free_memory() < free_memory);
+
+    unsafe { loader::unload(image, mapper, frame_allocator) };
+    assert_eq!(free_memory, GlobalFrameAllocator::free_memory());
+}
+
+#[test_case]
+fn load_tls_block_of_initial_thread() {
+    let file_bytes = include_bytes!("../elf/src/test_files/tls.elf64");
+    let image = load(file_bytes).expect("Failed to load tls");
+    let block = image.tls_block().expect("Expected a TLS block");
+    let thread_pointer = block.thread_pointer().as_u64();
+
+    assert_eq!(0, thread_pointer % 0x20);
+    assert_eq!(
+        PageTableFlags::PRESENT
+            | PageTableFlags::USER_ACCESSIBLE
+            | PageTableFlags::WRITABLE
+            | PageTableFlags::NO_EXECUTE,
+        image_page_flags(&image, thread_pointer).unwrap()
+    );
+    // _start reads `counter` at %fs:-0x40 and `scratch` at %fs:-0x20, and
+    // %fs:0 holds the thread pointer itself
+    assert_eq!(Some(7), read_image_u64(&image, thread_pointer - 0x40));
+    for offset in (0x8..=0x38).step_by(8) {
+        assert_eq!(Some(0), read_image_u64(&image, thread_pointer - offset));
+    }
+    assert_eq!(Some(thread_pointer), read_image_u64(&image, thread_pointer));
+}
+
+#[test_case]
+fn allocate_tls_blocks_for_more_threads() {
+    let file_bytes = include_bytes!("../elf/src/test_files/tls.elf64");
+    let mut image = load(file_bytes).expect("Failed to load tls");
+
+    let block = {
+        let mut memory_state = MEMORY.lock();
+        let (mapper, frame_allocator) = memory_state.as_mut().unwrap();
+        loader::allocate_tls_block(&mut image, mapper, frame_allocator)
+            .expect("Failed to allocate TLS block")
+            .expect("Expected a TLS block")
+    };
+    let thread_pointer = block.thread_pointer().as_u64();
+
+    assert_ne!(
+        image.tls_block().unwrap().thread_pointer(),
+        block.thread_pointer()
+    );
+    assert_eq!(Some(7), read_image_u64(&image, thread_pointer - 0x40));
+    assert_eq!(Some(thread_pointer), read_image_u64(&image, thread_pointer));
+
+    block.activate();
+    assert_eq!(block.thread_pointer(), FsBase::read());
+    FsBase::write(VirtAddr::zero());
+}
+
+#[test_case]
+fn load_without_tls() {
+    let file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64");
+    let mut image = load(file_bytes).expect("Failed to load hello_world");
+    assert_eq!(None, image.tls_block());
+
+    let mut memory_state = MEMORY.lock();
+    let (mapper, frame_allocator) = memory_state.as_mut().unwrap();
+    assert!(matches!(
+        loader::allocate_tls_block(&mut image, mapper, frame_allocator),
+        Ok(None)
+    ));
+}
+
+#[test_case]
+fn write_hello_world_core_dump() {
+    let file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64");
+    let image = load(file_bytes).expect("Failed to load hello_world");
+    let process = CrashedProcess {
+        pid: 1,
+        name: "hello_world",
+        signal: Signal::Segmentation,
+        registers: Registers {
+            rip: 0x401040,
+            rsp: 0x7FFF_FFFF_EFF8,
+            ..Registers::default()
+        },
+    };
+    let phys_offset = MEMORY.lock().as_ref().unwrap().0.phys_offset();
+    let core_bytes = core_dump::core_dump(&image, phys_offset, &process);
+
+    let core_file =
+        Elf64File::from_bytes(&core_bytes).expect("The file should be correctly parsed");
+    assert_eq!(&ElfType::Core, core_file.elf_type());
+    let headers: Vec<_> = core_file
+        .program_headers()
+        .expect("Expected program headers")
+        .map(|header| header.expect("Failed to parse program header"))
+        .collect();
+
+    let status = core_file
+        .segment_notes(&headers[0])
+        .expect("Expected notes")
+        .map(|note| note.expect("Failed to parse note"))
+        .find(|note| note.note_type() == NT_PRSTATUS)
+        .expect("Expected an NT_PRSTATUS note");
+    // rip is the 17th register
+    assert_eq!(
+        &0x401040u64.to_le_bytes(),
+        &status.descriptor()[112 + 16 * 8..][..8]
+    );
+
+    // .text is dumped as it was loaded
+    let text = headers
+        .iter()
+        .find(|header| header.p_vaddr() == 0x401000)
+        .expect("Expected a segment for .text");
+    assert_eq!(&ProgramSegmentType::Load, text.segment_type());
+    assert_eq!(&ProgramHeaderFlags::new(true, false, true), text.flags());
+    assert_eq!(
+        Ok(&file_bytes[0x1040..0x1050]),
+        core_file.virtual_address_data(0x401040, 0x10)
+    );
+
+    // The stack is writable and ends right below the stack top
+    let stack = headers
+        .iter()
+        .find(|header| header.p_vaddr() + header.p_memsz() == 0x7FFF_FFFF_F000)
+        .expect("Expected a segment for the stack");
+    assert_eq!(&ProgramHeaderFlags::new(false, true, true), stack.flags());
+    assert_eq!(16 * 4096, stack.p_memsz());
+}
+
+#[test_case]
+fn dump_core_of_faulting_user_process() {
+    let file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64");
+    let image = load(file_bytes).expect("Failed to load hello_world");
+    let phys_offset = MEMORY.lock().as_ref().unwrap().0.phys_offset();
+    core_dump::set_user_process(Some(UserProcess {
+        pid: 7,
+        name: "hello_world",
+        image,
+        physical_memory_offset: phys_offset,
+    }));
+
+    // A user code segment selector, with privilege level 3
+    let registers = Registers {
+        rip: 0x401040,
+        cs: 0x1B,
+        rsp: 0x7FFF_FFFF_EFF8,
+        ..Registers::default()
+    };
+    // Faults of the kernel aren't the process's
+    assert_eq!(
+        None,
+        core_dump::user_fault_core(
+            Registers {
+                cs: 0x8,
+                ..registers
+            },
+            Signal::Segmentation
+        )
+    );
+    let core_bytes =
+        core_dump::user_fault_core(registers, Signal::Segmentation).expect("Expected a core file");
+
+    let mut dump = String::new();
+    serial::format_dump(&mut dump, "core", &core_bytes).expect("Failed to format the dump");
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(Some(&"-----BEGIN core-----"), lines.first());
+    assert_eq!(Some(&"-----END core-----"), lines.last());
+    let written: Vec<u8> = lines[1..lines.len() - 1]
+        .iter()
+        .flat_map(|line| {
+            (0..line.len())
+                .step_by(2)
+                .map(move |index| u8::from_str_radix(&line[index..index + 2], 16).unwrap())
+        })
+        .collect();
+    assert_eq!(core_bytes, written);
+
+    let core_file = Elf64File::from_bytes(&written).expect("The file should be correctly parsed");
+    assert_eq!(&ElfType::Core, core_file.elf_type());
+    let notes_header = core_file
+        .program_headers()
+        .expect("Expected program headers")
+        .next()
+        .expect("Expected a Note segment")
+        .expect("Failed to parse program header");
+    let status = core_file
+        .segment_notes(&notes_header)
+        .expect("Expected notes")
+        .map(|note| note.expect("Failed to parse note"))
+        .find(|note| note.note_type() == NT_PRSTATUS)
+        .expect("Expected an NT_PRSTATUS note");
+    // si_signo is first, and pr_pid at offset 32
+    assert_eq!(&11u32.to_le_bytes(), &status.descriptor()[..4]);
+    assert_eq!(&7u32.to_le_bytes(), &status.descriptor()[32..36]);
+
+    let process = core_dump::set_user_process(None).expect("Expected the user process");
+    let mut memory_state = MEMORY.lock();
+    let (mapper, frame_allocator) = memory_state.as_mut().unwrap();
+    unsafe { loader::unload(process.image, mapper, frame_allocator) };
+}
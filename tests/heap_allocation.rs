@@ -6,7 +6,7 @@
 
 extern crate alloc;
 
-use alloc::{boxed::Box, vec::Vec};
+use alloc::{boxed::Box, vec, vec::Vec};
 use bootloader::{entry_point, BootInfo};
 use core::panic::PanicInfo;
 use my_rust_os::allocator::HEAP_SIZE;
@@ -20,9 +20,10 @@ fn main(boot_info: &'static BootInfo) -> ! {
 
     let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
     let mut mem_mapper = unsafe { memory::init(phys_mem_offset) };
-    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
-    allocator::init_heap(&mut mem_mapper, &mut frame_allocator)
-        .expect("Heap initialization failed");
+    let frame_allocator =
+        unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map, phys_mem_offset) };
+    memory::init_frame_allocator(frame_allocator);
+    allocator::init_heap(&mut mem_mapper).expect("Heap initialization failed");
 
     test_main();
     loop {}
@@ -106,3 +107,34 @@ fn fragment_heap_then_large_alloc() {
     // find a large enough region and this allocation will fail
     let _vec: Vec<u8> = Vec::with_capacity(HEAP_SIZE / 2 as usize);
 }
+
+#[test_case]
+fn allocate_past_initial_heap_size() {
+    let size = 4 * HEAP_SIZE;
+    let mut vec: Vec<u8> = Vec::with_capacity(size);
+    for i in 0..size {
+        vec.push(i as u8);
+    }
+
+    for i in 0..size {
+        assert_eq!(i as u8, vec[i]);
+    }
+}
+
+#[test_case]
+fn many_long_lived_large_allocations() {
+    // 4 MiB in all, which also needs page tables and a 2 MiB page or two
+    let vecs: Vec<Vec<u64>> = (0..64).map(|i| vec![i; 8192]).collect();
+
+    for (i, vec) in vecs.iter().enumerate() {
+        assert!(vec.iter().all(|&value| value == i as u64));
+    }
+}
+
+#[test_case]
+fn grown_heap_reuses_memory() {
+    for _ in 0..16 {
+        let vec: Vec<u8> = Vec::with_capacity(2 * HEAP_SIZE);
+        assert!(vec.capacity() >= 2 * HEAP_SIZE);
+    }
+}
@@ -0,0 +1,221 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(my_rust_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use bootloader::{entry_point, BootInfo};
+use core::{
+    panic::PanicInfo,
+    ptr,
+    sync::atomic::{AtomicI64, Ordering},
+};
+use my_rust_os::elf::{
+    elf64::Elf64File,
+    loader::LoadError,
+    module::{self, KernelSymbolTable, LoadedModule},
+    relocate::RelocationError,
+};
+use my_rust_os::memory::{self, BootInfoFrameAllocator, GlobalFrameAllocator};
+use spin::Mutex;
+use x86_64::{
+    structures::paging::{
+        mapper::TranslateResult, FrameDeallocator, OffsetPageTable, PageTableFlags, PhysFrame,
+        Size4KiB, Translate,
+    },
+    VirtAddr,
+};
+
+static MEMORY: Mutex<Option<(OffsetPageTable<'static>, GlobalFrameAllocator)>> = Mutex::new(None);
+
+/// The last value the module passed to `kernel_record`.
+static RECORDED: AtomicI64 = AtomicI64::new(0);
+/// Updated by the module directly, through its relocations.
+static mut KERNEL_COUNTER: i64 = 0;
+
+extern "C" fn kernel_record(value: i64) {
+    RECORDED.store(value, Ordering::SeqCst);
+}
+
+/// Counts the frames it is given instead of reusing them.
+struct CountingDeallocator(usize);
+
+impl FrameDeallocator<Size4KiB> for CountingDeallocator {
+    unsafe fn deallocate_frame(&mut self, _frame: PhysFrame) {
+        self.0 += 1;
+    }
+}
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use my_rust_os::allocator;
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mem_mapper = unsafe { memory::init(phys_mem_offset) };
+    let frame_allocator =
+        unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map, phys_mem_offset) };
+    memory::init_frame_allocator(frame_allocator);
+    allocator::init_heap(&mut mem_mapper).expect("Heap initialization failed");
+    *MEMORY.lock() = Some((mem_mapper, GlobalFrameAllocator));
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    my_rust_os::test_panic_handler(&info)
+}
+
+fn kernel_symbols() -> KernelSymbolTable {
+    let mut symbols = KernelSymbolTable::new();
+    symbols.export("kernel_record", kernel_record as extern "C" fn(i64) as u64);
+    symbols.export("kernel_counter", unsafe {
+        ptr::addr_of!(KERNEL_COUNTER) as u64
+    });
+    symbols
+}
+
+fn load(file_bytes: &[u8], symbols: &KernelSymbolTable) -> Result<LoadedModule, LoadError> {
+    let elf_file = Elf64File::from_bytes(file_bytes).expect("The file should be correctly parsed");
+    let mut memory_state = MEMORY.lock();
+    let (mapper, frame_allocator) = memory_state.as_mut().unwrap();
+    module::load_module(&elf_file, symbols, mapper, frame_allocator)
+}
+
+fn unload(module: LoadedModule) -> usize {
+    let mut memory_state = MEMORY.lock();
+    let (mapper, _) = memory_state.as_mut().unwrap();
+    let mut deallocator = CountingDeallocator(0);
+    unsafe { module::unload_module(module, mapper, &mut deallocator) }
+        .expect("Failed to unload module");
+    deallocator.0
+}
+
+fn page_flags(address: VirtAddr) -> Option<PageTableFlags> {
+    let memory_state = MEMORY.lock();
+    let (mapper, _) = memory_state.as_ref().unwrap();
+    match mapper.translate(address) {
+        TranslateResult::Mapped { flags, .. } => Some(flags),
+        _ => None,
+    }
+}
+
+#[test_case]
+fn load_kernel_module_sections() {
+    let file_bytes = include_bytes!("../elf/src/test_files/kernel_module.elf64");
+    let module = load(file_bytes, &kernel_symbols()).expect("Failed to load module");
+
+    let text = module.section_address(1).expect(".text should be loaded");
+    let data = module.section_address(3).expect(".data should be loaded");
+    let bss = module.section_address(5).expect(".bss should be loaded");
+    let rodata = module.section_address(6).expect(".rodata should be loaded");
+    assert_eq!(None, module.section_address(7));
+    assert_eq!(module.pages().start.start_address(), text);
+    assert_eq!(Some(text + 0x20u64), module.init_function());
+    assert_eq!(Some(text + 0x60u64), module.exit_function());
+
+    assert_eq!(b"module\0", unsafe { &*rodata.as_ptr::<[u8; 7]>() });
+    // `pointer` holds the address of `initialized`, right after it
+    assert_eq!(data.as_u64() + 8, unsafe { *data.as_ptr::<u64>() });
+    assert_eq!(0x1234, unsafe { *(data + 8u64).as_ptr::<i64>() });
+    assert_eq!([0; 4], unsafe { *bss.as_ptr::<[i64; 4]>() });
+
+    let flags = page_flags(text).unwrap();
+    assert!(!flags.contains(PageTableFlags::WRITABLE));
+    assert!(!flags.contains(PageTableFlags::NO_EXECUTE));
+    assert!(!flags.contains(PageTableFlags::USER_ACCESSIBLE));
+    let flags = page_flags(rodata).unwrap();
+    assert!(!flags.contains(PageTableFlags::WRITABLE));
+    assert!(flags.contains(PageTableFlags::NO_EXECUTE));
+    let flags = page_flags(bss).unwrap();
+    assert!(flags.contains(PageTableFlags::WRITABLE));
+    assert!(flags.contains(PageTableFlags::NO_EXECUTE));
+
+    assert_eq!(3, unload(module));
+}
+
+#[test_case]
+fn initialize_and_unload_kernel_module() {
+    let file_bytes = include_bytes!("../elf/src/test_files/kernel_module.elf64");
+    let mut module = load(file_bytes, &kernel_symbols()).expect("Failed to load module");
+    let text = module.pages().start.start_address();
+    unsafe { KERNEL_COUNTER = 1 };
+
+    unsafe { module::initialize(&mut module) }.expect("module_init failed");
+    // `initialized` plus `zeroed[2]`, which module_init set to 'm'
+    assert_eq!(0x1234 + 0x6D, RECORDED.load(Ordering::SeqCst));
+    assert_eq!(0x1235, unsafe { KERNEL_COUNTER });
+
+    assert_eq!(3, unload(module));
+    assert_eq!(-1, RECORDED.load(Ordering::SeqCst));
+    assert_eq!(0, unsafe { KERNEL_COUNTER });
+    assert_eq!(None, page_flags(text));
+}
+
+#[test_case]
+fn unload_uninitialized_kernel_module() {
+    let file_bytes = include_bytes!("../elf/src/test_files/kernel_module.elf64");
+    let module = load(file_bytes, &kernel_symbols()).expect("Failed to load module");
+    RECORDED.store(0, Ordering::SeqCst);
+
+    // module_exit only runs after module_init succeeded
+    assert_eq!(3, unload(module));
+    assert_eq!(0, RECORDED.load(Ordering::SeqCst));
+}
+
+#[test_case]
+fn load_kernel_modules_side_by_side() {
+    let file_bytes = include_bytes!("../elf/src/test_files/kernel_module.elf64");
+    let first = load(file_bytes, &kernel_symbols()).expect("Failed to load module");
+    let second = load(file_bytes, &kernel_symbols()).expect("Failed to load module");
+    assert!(first.pages().end < second.pages().start);
+
+    unload(first);
+    unload(second);
+}
+
+#[test_case]
+fn reject_kernel_module_with_unresolved_symbol() {
+    let file_bytes = include_bytes!("../elf/src/test_files/kernel_module.elf64");
+    let module = load(file_bytes, &kernel_symbols()).expect("Failed to load module");
+    let start = module.pages().start;
+    unload(module);
+
+    let mut symbols = KernelSymbolTable::new();
+    symbols.export("kernel_record", kernel_record as extern "C" fn(i64) as u64);
+    // kernel_counter is symbol 10
+    assert!(matches!(
+        load(file_bytes, &symbols),
+        Err(LoadError::Relocation(RelocationError::UnresolvedSymbol(10)))
+    ));
+
+    // The pages of the failed module were unmapped again
+    let module = load(file_bytes, &kernel_symbols()).expect("Failed to load module");
+    assert_eq!(start, module.pages().start);
+    unload(module);
+}
+
+#[test_case]
+fn failed_kernel_module_load_deallocates_frames() {
+    let file_bytes = include_bytes!("../elf/src/test_files/kernel_module.elf64");
+    // Load a module first, so that the page tables of the module area exist
+    let module = load(file_bytes, &kernel_symbols()).expect("Failed to load module");
+    unload(module);
+
+    let free_memory = GlobalFrameAllocator::free_memory();
+    assert!(load(file_bytes, &KernelSymbolTable::new()).is_err());
+    assert_eq!(free_memory, GlobalFrameAllocator::free_memory());
+}
+
+#[test_case]
+fn reject_executable_as_kernel_module() {
+    let file_bytes = include_bytes!("../elf/src/test_files/hello_world.elf64");
+    assert!(matches!(
+        load(file_bytes, &kernel_symbols()),
+        Err(LoadError::NotRelocatable)
+    ));
+}
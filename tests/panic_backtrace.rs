@@ -0,0 +1,81 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use bootloader::{entry_point, BootInfo};
+use core::panic::PanicInfo;
+use my_rust_os::backtrace;
+use my_rust_os::elf::symbolize::demangle;
+use my_rust_os::qemu::{exit_qemu, QemuExitCode};
+use my_rust_os::{serial_print, serial_println};
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use my_rust_os::allocator;
+    use my_rust_os::memory::{self, BootInfoFrameAllocator};
+    use x86_64::VirtAddr;
+
+    serial_print!("panic_backtrace::nested_panic...\t");
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mem_mapper = unsafe { memory::init(phys_mem_offset) };
+    let frame_allocator =
+        unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map, phys_mem_offset) };
+    memory::init_frame_allocator(frame_allocator);
+    allocator::init_heap(&mut mem_mapper).expect("Heap initialization failed");
+    backtrace::init(boot_info);
+
+    outer();
+
+    serial_println!("[test did not panic]");
+    exit_qemu(QemuExitCode::Failed);
+    loop {}
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    let names: Vec<String> = backtrace::frames(backtrace::frame_pointer())
+        .filter_map(|address| backtrace::resolve(address - 1))
+        .map(|(function, _)| demangle(function.name()).to_string())
+        .collect();
+
+    // The panicking functions appear innermost first, below the panic machinery
+    let position = |name: &str| names.iter().position(|found| found == name);
+    let inner = position("panic_backtrace::inner");
+    let middle = position("panic_backtrace::middle");
+    let outer = position("panic_backtrace::outer");
+    let main = position("panic_backtrace::main");
+    if inner.is_some() && inner < middle && middle < outer && outer < main {
+        serial_println!("[ok]");
+        exit_qemu(QemuExitCode::Success);
+    } else {
+        serial_println!("[failed]\n");
+        serial_println!("Error: unexpected backtrace {:#?}\n", names);
+        exit_qemu(QemuExitCode::Failed);
+    }
+    loop {}
+}
+
+#[inline(never)]
+fn outer() {
+    middle();
+    // Prevent tail call optimization, so that every function keeps its frame
+    volatile::Volatile::new(0).read();
+}
+
+#[inline(never)]
+fn middle() {
+    inner();
+    volatile::Volatile::new(0).read();
+}
+
+#[inline(never)]
+fn inner() {
+    panic!("nested panic");
+}